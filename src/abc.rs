@@ -0,0 +1,67 @@
+//! Module with the ABC notation import and export of the score
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+mod abc_reader;
+mod abc_writer;
+
+pub use abc_reader::AbcReader;
+pub use abc_writer::AbcWriter;
+
+use crate::notation::{Accidental, Clef, KeySignature, KeySignatureType, NoteName, Rational};
+
+/// Letters of the note names in the middle octave.
+const LETTERS: [char; 7] = ['C', 'D', 'E', 'F', 'G', 'A', 'B'];
+
+/// Mode suffix of the ABC key.
+fn mode(signature_type: KeySignatureType) -> &'static str {
+    match signature_type {
+        KeySignatureType::Maj => "",
+        KeySignatureType::Min => "m",
+        KeySignatureType::Dorian => "dor",
+        KeySignatureType::Phrygian => "phr",
+        KeySignatureType::Lydian => "lyd",
+        KeySignatureType::Mixolydian => "mix",
+        KeySignatureType::Locrian => "loc",
+    }
+}
+
+/// Value of the `K:` field, e.g. `F#m` or `Bbdor`.
+fn key(key: &KeySignature) -> String {
+    let accidental = match key.ext.accidental {
+        Some(Accidental::Sharp) => "#",
+        Some(Accidental::Flat) => "b",
+        Some(Accidental::DoubleSharp) => "##",
+        Some(Accidental::DoubleFlat) => "bb",
+        None => "",
+    };
+
+    format!(
+        "{}{accidental}{}",
+        LETTERS[key.ext.note_name.value() as usize],
+        mode(key.signature_type)
+    )
+}
+
+/// Name of the clef used by the `clef=` modifier.
+fn clef(clef: Clef) -> &'static str {
+    match clef {
+        Clef::Treble => "treble",
+        Clef::Bass => "bass",
+    }
+}
+
+/// Note name of the letter in any case.
+fn note_name(letter: char) -> Option<NoteName> {
+    let position = LETTERS
+        .iter()
+        .position(|l| *l == letter.to_ascii_uppercase())?;
+
+    NoteName::from_value(position as u8)
+}
+
+/// Fraction written in the `L:` and `Q:` fields, e.g. `1/4`.
+fn fraction(value: Rational) -> String {
+    format!("{}/{}", value.numer(), value.denom())
+}
@@ -0,0 +1,993 @@
+//! ABC reader definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    abc::{clef, mode, note_name},
+    error::{AppError, Result},
+    notation::{
+        Accidental, Chord, Clef, Duration, ExtNoteName, KeySignature, KeySignatureType, Note,
+        NoteLength, NoteName, Octave, Pitch, Rational, Score, ScoreInfo, Stave, Symbol, Tempo,
+        TimeSignature, Tuplet, MAX_DOTS,
+    },
+};
+
+/// Characters of a single music line.
+struct Chars {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Chars {
+    fn new(line: &str) -> Self {
+        Self {
+            chars: line.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let eaten = self.peek() == Some(c);
+        if eaten {
+            self.pos += 1;
+        }
+        eaten
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+
+        self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    }
+
+    /// Skip everything up to and including the closing character.
+    fn skip_to(&mut self, closing: char) -> bool {
+        while let Some(c) = self.next() {
+            if c == closing {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Text up to the closing character, which is skipped.
+    fn text_to(&mut self, closing: char) -> Option<String> {
+        let start = self.pos;
+        self.skip_to(closing)
+            .then(|| self.chars[start..self.pos - 1].iter().collect())
+    }
+}
+
+/// Reader of a single voice, which becomes a stave.
+#[derive(Debug)]
+struct VoiceReader {
+    id: Option<String>,
+    stave: Stave,
+    key: KeySignature,
+    time: TimeSignature,
+    /// Unit note length.
+    unit: Rational,
+    /// Accidentals of the current bar, which apply to the rest of it.
+    bar_alterations: HashMap<(NoteName, Octave), i8>,
+    /// Tuplet of the following notes and the number of notes left in it.
+    tuplet: Option<(Tuplet, u32)>,
+    /// Length change of the next note by a broken rhythm.
+    broken: Rational,
+    /// Start of the repeated section.
+    repeat_start: usize,
+    /// Start of the first ending, which is skipped when repeating.
+    ending_start: Option<usize>,
+}
+
+impl VoiceReader {
+    fn new(id: Option<String>, header: &Header, clef: Clef) -> Self {
+        let symbols = vec![
+            Symbol::Clef(clef),
+            Symbol::KeySignature(header.key),
+            Symbol::TimeSignature(header.time),
+        ];
+
+        Self {
+            id,
+            repeat_start: symbols.len(),
+            stave: Stave { symbols },
+            key: header.key,
+            time: header.time,
+            unit: header.unit(),
+            bar_alterations: HashMap::new(),
+            tuplet: None,
+            broken: Rational::from(1),
+            ending_start: None,
+        }
+    }
+
+    fn has_notes(&self) -> bool {
+        self.stave.symbols.iter().any(|s| s.duration().is_some())
+    }
+
+    /// End the bar, which may end or start a repeat.
+    fn bar(&mut self, end_repeat: bool, start_repeat: bool) {
+        self.bar_alterations.clear();
+
+        let len = self.stave.symbols.len();
+        if end_repeat {
+            let end = self.ending_start.take().unwrap_or(len);
+            let repeated = self.stave.symbols[self.repeat_start..end].to_vec();
+            self.stave.symbols.extend(repeated);
+        }
+        if end_repeat || start_repeat {
+            self.repeat_start = self.stave.symbols.len();
+        }
+    }
+
+    /// Ending of the repeated section with the given number.
+    fn ending(&mut self, number: u32) {
+        if number == 1 {
+            self.ending_start = Some(self.stave.symbols.len());
+        }
+    }
+}
+
+/// Fields of the tune header, which the voices start with.
+#[derive(Debug)]
+struct Header {
+    key: KeySignature,
+    time: TimeSignature,
+    clef: Clef,
+    unit: Option<Rational>,
+    /// Voices declared in the header with their clefs.
+    voices: Vec<(String, Clef)>,
+}
+
+impl Header {
+    /// Unit note length, which defaults to 1/16 in short meters.
+    fn unit(&self) -> Rational {
+        self.unit
+            .unwrap_or(match self.time.bar_length() < Rational::new(3, 4) {
+                true => Rational::new(1, 16),
+                false => Rational::new(1, 8),
+            })
+    }
+}
+
+/// Reader of a single tune.
+struct TuneReader {
+    /// Line of the source being read.
+    line: usize,
+    header: Header,
+    info: ScoreInfo,
+    tempo: Option<u32>,
+    /// Voices of the tune body. Empty while the header is read.
+    voices: Vec<VoiceReader>,
+    current: usize,
+}
+
+impl TuneReader {
+    fn new() -> Self {
+        Self {
+            line: 0,
+            header: Header {
+                key: KeySignature {
+                    ext: ExtNoteName {
+                        note_name: NoteName::C,
+                        accidental: None,
+                    },
+                    signature_type: KeySignatureType::Maj,
+                },
+                time: TimeSignature::c(),
+                clef: Clef::Treble,
+                unit: None,
+                voices: vec![],
+            },
+            info: ScoreInfo::default(),
+            tempo: None,
+            voices: vec![],
+            current: 0,
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> Box<dyn std::error::Error> {
+        AppError::Abc(format!("line {}: {}", self.line, message.into())).into()
+    }
+
+    fn in_body(&self) -> bool {
+        !self.voices.is_empty()
+    }
+
+    fn voice(&mut self) -> &mut VoiceReader {
+        &mut self.voices[self.current]
+    }
+
+    /// Read the lines of the tune. Returns `false` at the end of the tune.
+    fn line(&mut self, line: &str) -> Result<bool> {
+        // Comments and stylesheet directives are not part of the music.
+        let line = line.split('%').next().unwrap_or_default().trim_end();
+
+        let mut chars = line.chars();
+        match (chars.next(), chars.next()) {
+            (None, _) => Ok(!self.in_body()),
+            (Some('X'), Some(':')) if self.in_body() => Ok(false),
+            (Some(name), Some(':')) if name.is_ascii_alphabetic() => {
+                self.field(name, line[2..].trim())?;
+                Ok(true)
+            }
+            _ if !self.in_body() => Err(self.error("Tune header must end with the K: field"))?,
+            _ => {
+                self.music(&mut Chars::new(line))?;
+                Ok(true)
+            }
+        }
+    }
+
+    fn field(&mut self, name: char, value: &str) -> Result<()> {
+        match name {
+            'T' => self.info.name = self.info.name.take().or(Some(value.to_owned())),
+            'C' => self.info.author = self.info.author.take().or(Some(value.to_owned())),
+            'Z' => self.info.transcriber = self.info.transcriber.take().or(Some(value.to_owned())),
+            'M' => {
+                let time = self.meter(value)?;
+                match self.in_body() {
+                    true => {
+                        self.voice().time = time;
+                        self.voice().stave.symbols.push(Symbol::TimeSignature(time));
+                    }
+                    false => self.header.time = time,
+                }
+            }
+            'L' => {
+                let unit = self.fraction(value)?;
+                match self.in_body() {
+                    true => self.voice().unit = unit,
+                    false => self.header.unit = Some(unit),
+                }
+            }
+            'Q' => {
+                let Some(tempo) = self.tempo(value)? else {
+                    return Ok(());
+                };
+                match self.in_body() {
+                    true => self.voice().stave.symbols.push(Symbol::Tempo(tempo)),
+                    false => self.tempo = Some(tempo.quarters_per_minute()),
+                }
+            }
+            'V' => self.voice_field(value)?,
+            'K' => self.key_field(value)?,
+            // Other fields, e.g. lyrics or notes, don't change the music.
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn fraction(&self, value: &str) -> Result<Rational> {
+        let fraction = value.split_once('/').and_then(|(numer, denom)| {
+            let (numer, denom) = (numer.trim().parse().ok()?, denom.trim().parse().ok()?);
+            (numer > 0 && denom > 0).then(|| Rational::new(numer, denom))
+        });
+
+        fraction.ok_or_else(|| self.error(format!("Invalid fraction '{value}'")))
+    }
+
+    fn meter(&self, value: &str) -> Result<TimeSignature> {
+        let (beat_count, denom) = match value {
+            "C" => (4, 4),
+            "C|" => (2, 2),
+            value => {
+                let parsed = value.split_once('/').and_then(|(numer, denom)| {
+                    Some((numer.trim().parse().ok()?, denom.trim().parse().ok()?))
+                });
+                parsed.ok_or_else(|| self.error(format!("Unsupported meter '{value}'")))?
+            }
+        };
+        let unsupported = || self.error(format!("Unsupported meter '{value}'"));
+        if beat_count == 0 || denom == 0 {
+            Err(unsupported())?;
+        }
+        let single_beat_note = NoteLength::ALL
+            .into_iter()
+            .find(|l| l.value() == Rational::new(1, denom))
+            .ok_or_else(unsupported)?
+            .into();
+
+        Ok(TimeSignature {
+            beat_count,
+            single_beat_note,
+        })
+    }
+
+    fn tempo(&self, value: &str) -> Result<Option<Tempo>> {
+        // Text of the tempo, e.g. "Allegro", is only printed.
+        let value: String = value.split('"').step_by(2).collect();
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(None);
+        }
+
+        let (beat, speed) = match value.split_once('=') {
+            Some((beats, speed)) => {
+                let beat = beats
+                    .split_whitespace()
+                    .map(|b| self.fraction(b))
+                    .sum::<Result<Rational>>()?;
+                (beat, speed.trim())
+            }
+            // Obsolete tempo in the unit note lengths.
+            None => (self.unit(), value),
+        };
+
+        let invalid = || self.error(format!("Unsupported tempo '{value}'"));
+        Ok(Some(Tempo {
+            note_length: Duration::from_value(beat).ok_or_else(invalid)?,
+            speed: speed.parse().map_err(|_| invalid())?,
+        }))
+    }
+
+    fn unit(&self) -> Rational {
+        match self.in_body() {
+            true => self.voices[self.current].unit,
+            false => self.header.unit(),
+        }
+    }
+
+    /// Clef given by the `clef=` modifier or the clef name.
+    fn clef(&self, modifier: &str) -> Result<Option<Clef>> {
+        let name = modifier.strip_prefix("clef=").unwrap_or(modifier);
+        let clef = [Clef::Treble, Clef::Bass]
+            .into_iter()
+            .find(|c| self::clef(*c) == name);
+
+        match clef {
+            Some(clef) => Ok(Some(clef)),
+            None if modifier.starts_with("clef=") => {
+                Err(self.error(format!("Unsupported clef '{name}'")))?
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn voice_field(&mut self, value: &str) -> Result<()> {
+        let mut tokens = value.split_whitespace();
+        let id = tokens
+            .next()
+            .ok_or_else(|| self.error("Voice has no identifier"))?
+            .to_owned();
+        let mut clef = None;
+        for token in tokens {
+            clef = clef.or(self.clef(token)?);
+        }
+
+        if !self.in_body() {
+            self.header.voices.push((id, clef.unwrap_or(Clef::Treble)));
+            return Ok(());
+        }
+
+        self.current = match self.voices.iter().position(|v| v.id.as_ref() == Some(&id)) {
+            Some(current) => current,
+            // Voice field before any music names the implicit first voice.
+            None if self.voices.len() == 1
+                && self.voices[0].id.is_none()
+                && !self.voices[0].has_notes() =>
+            {
+                self.voices[0].id = Some(id);
+                0
+            }
+            None => {
+                let declared = self.header.voices.iter().find(|(v, _)| *v == id);
+                let voice_clef = clef.or(declared.map(|(_, c)| *c));
+                let voice_clef = voice_clef.unwrap_or(self.header.clef);
+                self.voices
+                    .push(VoiceReader::new(Some(id), &self.header, voice_clef));
+                self.current = self.voices.len() - 1;
+                return Ok(());
+            }
+        };
+
+        if let Some(clef) = clef {
+            self.voice().stave.symbols.push(Symbol::Clef(clef));
+        }
+
+        Ok(())
+    }
+
+    fn key_field(&mut self, value: &str) -> Result<()> {
+        let mut key = None;
+        let mut clef = None;
+        let mut tokens = value.split_whitespace().peekable();
+        while let Some(token) = tokens.next() {
+            if let Some(c) = self.clef(token)? {
+                clef = Some(c);
+                continue;
+            }
+            if token.contains('=') || key.is_some() {
+                Err(self.error(format!("Key modifier '{token}' is not supported")))?;
+            }
+
+            // Mode may be written separately, e.g. `A minor`.
+            let mut name = token.to_owned();
+            if let Some(mode) =
+                tokens.next_if(|t| !t.contains('=') && matches!(self.clef(t), Ok(None)))
+            {
+                if !mode.starts_with(['^', '_', '=']) && mode.is_ascii() {
+                    name.push_str(mode);
+                } else {
+                    Err(self.error("Explicit key accidentals are not supported"))?;
+                }
+            }
+            key = Some(self.key(&name)?);
+        }
+
+        if !self.in_body() {
+            self.header.key = key.unwrap_or(self.header.key);
+            self.header.clef = clef.unwrap_or(self.header.clef);
+            self.voices = match self.header.voices.as_slice() {
+                [] => vec![VoiceReader::new(None, &self.header, self.header.clef)],
+                voices => voices
+                    .iter()
+                    .map(|(id, clef)| VoiceReader::new(Some(id.clone()), &self.header, *clef))
+                    .collect(),
+            };
+            return Ok(());
+        }
+
+        if let Some(clef) = clef {
+            self.voice().stave.symbols.push(Symbol::Clef(clef));
+        }
+        if let Some(key) = key {
+            self.voice().key = key;
+            self.voice().stave.symbols.push(Symbol::KeySignature(key));
+        }
+
+        Ok(())
+    }
+
+    /// Key with the tonic and the mode, e.g. `F#m` or `Bbdorian`.
+    fn key(&self, name: &str) -> Result<KeySignature> {
+        let unsupported = || self.error(format!("Unsupported key '{name}'"));
+        if name == "none" {
+            return Ok(self.header.key);
+        }
+
+        let mut chars = name.chars();
+        let note_name = chars
+            .next()
+            .filter(char::is_ascii_uppercase)
+            .and_then(note_name)
+            .ok_or_else(unsupported)?;
+        let rest = chars.as_str();
+        let (accidental, rest) = match rest.strip_prefix('#') {
+            Some(rest) => (Some(Accidental::Sharp), rest),
+            None => match rest.strip_prefix('b') {
+                Some(rest) => (Some(Accidental::Flat), rest),
+                None => (None, rest),
+            },
+        };
+
+        let rest = rest.to_ascii_lowercase();
+        let signature_type = match rest.get(..3).unwrap_or(&rest) {
+            "" | "maj" | "ion" => KeySignatureType::Maj,
+            "m" | "min" | "aeo" => KeySignatureType::Min,
+            mode_name => KeySignatureType::ALL
+                .into_iter()
+                .find(|t| !mode(*t).is_empty() && mode(*t) == mode_name)
+                .ok_or_else(unsupported)?,
+        };
+
+        Ok(KeySignature {
+            ext: ExtNoteName {
+                note_name,
+                accidental,
+            },
+            signature_type,
+        })
+    }
+
+    fn music(&mut self, chars: &mut Chars) -> Result<()> {
+        while let Some(c) = chars.peek() {
+            match c {
+                ' ' | '\t' | '`' | ')' | 'y' | '\\' => chars.pos += 1,
+                // Decorations, annotations and grace notes take no time.
+                '.' | '~' | 'H' | 'L' | 'M' | 'O' | 'P' | 'S' | 'T' | 'u' | 'v' => chars.pos += 1,
+                '!' | '+' | '"' | '{' => {
+                    chars.pos += 1;
+                    let closing = if c == '{' { '}' } else { c };
+                    if !chars.skip_to(closing) {
+                        Err(self.error(format!("Missing closing '{closing}'")))?;
+                    }
+                }
+                '(' if chars.peek_at(1).is_some_and(|c| c.is_ascii_digit()) => {
+                    self.tuplet(chars)?
+                }
+                '(' => chars.pos += 1,
+                '|' | ':' => self.bar(chars)?,
+                '[' => match chars.peek_at(1) {
+                    Some('|') => self.bar(chars)?,
+                    Some(c) if c.is_ascii_digit() => {
+                        chars.pos += 1;
+                        let number = chars.number().unwrap_or(1);
+                        self.voice().ending(number);
+                    }
+                    Some(c) if c.is_ascii_alphabetic() && chars.peek_at(2) == Some(':') => {
+                        chars.pos += 1;
+                        let field = chars
+                            .text_to(']')
+                            .ok_or_else(|| self.error("Missing closing ']'"))?;
+                        let (name, value) = field.split_at(2);
+                        self.field(name.chars().next().unwrap(), value.trim())?;
+                    }
+                    _ => self.element(chars)?,
+                },
+                'Z' | 'X' => {
+                    chars.pos += 1;
+                    let bars = chars.number().unwrap_or(1);
+                    let rests = Duration::split(self.voice().time.bar_length());
+                    for _ in 0..bars {
+                        let rests = rests.iter().copied().map(Symbol::Rest);
+                        self.voice().stave.symbols.extend(rests);
+                    }
+                }
+                '&' => Err(self.error("Voice overlays are not supported"))?,
+                '^' | '_' | '=' | 'z' | 'x' => self.element(chars)?,
+                c if note_name(c).is_some() => self.element(chars)?,
+                c => Err(self.error(format!("Unexpected character '{c}'")))?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn bar(&mut self, chars: &mut Chars) -> Result<()> {
+        let mut bar = String::new();
+        if chars.eat('[') {
+            bar.push('[');
+        }
+        while let Some(c) = chars.peek() {
+            match c {
+                '|' | ':' => bar.push(c),
+                ']' if bar.contains('|') => bar.push(c),
+                _ => break,
+            }
+            chars.pos += 1;
+        }
+
+        self.voice().bar(bar.starts_with(':'), bar.ends_with(':'));
+        if let Some(number) = chars.number() {
+            self.voice().ending(number);
+        }
+
+        Ok(())
+    }
+
+    fn tuplet(&mut self, chars: &mut Chars) -> Result<()> {
+        chars.pos += 1;
+        let actual = chars.number().unwrap_or_default();
+        let normal = match chars.eat(':') {
+            true => chars.number(),
+            false => None,
+        };
+        let notes = match chars.eat(':') {
+            true => chars.number(),
+            false => None,
+        };
+
+        let compound = self.voice().time.beat_count % 3 == 0 && self.voice().time.beat_count > 3;
+        let normal = match (normal, actual) {
+            (Some(normal), _) => normal,
+            (None, 2 | 4 | 8) => 3,
+            (None, 3 | 6) => 2,
+            (None, _) if compound => 3,
+            (None, _) => 2,
+        };
+        let tuplet = u8::try_from(actual)
+            .ok()
+            .zip(u8::try_from(normal).ok())
+            .and_then(|(actual, normal)| Tuplet::new(actual, normal))
+            .ok_or_else(|| self.error(format!("Unsupported tuplet {actual}:{normal}")))?;
+
+        let notes = notes.unwrap_or(actual);
+        self.voice().tuplet = Some((tuplet, notes));
+
+        Ok(())
+    }
+
+    /// Pitch of the note with the accidentals of the key and the bar.
+    fn pitch(&mut self, chars: &mut Chars) -> Result<Pitch> {
+        let mut alteration = None;
+        loop {
+            let change = match chars.peek() {
+                Some('^') => 1,
+                Some('_') => -1,
+                Some('=') => 0,
+                _ => break,
+            };
+            alteration = Some(alteration.unwrap_or(0) + change);
+            chars.pos += 1;
+        }
+
+        let letter = chars.next().unwrap_or(' ');
+        let note_name = note_name(letter)
+            .ok_or_else(|| self.error(format!("Expected a note, found '{letter}'")))?;
+        let mut octave = match letter.is_ascii_uppercase() {
+            true => 4,
+            false => 5,
+        };
+        loop {
+            match chars.peek() {
+                Some('\'') => octave += 1,
+                Some(',') => octave -= 1,
+                _ => break,
+            }
+            chars.pos += 1;
+        }
+        let octave = u8::try_from(octave)
+            .ok()
+            .and_then(Octave::from_value)
+            .ok_or_else(|| self.error(format!("Octave of the note '{letter}' is too high")))?;
+
+        let voice = self.voice();
+        let alteration = match alteration {
+            Some(alteration) => {
+                voice
+                    .bar_alterations
+                    .insert((note_name, octave), alteration);
+                alteration
+            }
+            None => match voice.bar_alterations.get(&(note_name, octave)) {
+                Some(alteration) => *alteration,
+                None => voice.key.alteration(note_name),
+            },
+        };
+        let accidental = Accidental::from_alteration(alteration)
+            .ok_or_else(|| self.error(format!("Unsupported alteration {alteration}")))?;
+
+        Ok(Pitch::new(note_name, octave, accidental))
+    }
+
+    /// Length multiplier of the unit note length, e.g. `3`, `/`, or `3/2`.
+    fn length(&self, chars: &mut Chars) -> Result<Rational> {
+        let numer = chars.number().unwrap_or(1);
+        let mut slashes = 0;
+        while chars.eat('/') {
+            slashes += 1;
+        }
+        let denom = match (slashes, chars.number()) {
+            (0, _) => 1,
+            (1, Some(denom)) => denom,
+            (slashes, None) if slashes < 8 => 1 << slashes,
+            _ => 0,
+        };
+        if numer == 0 || denom == 0 {
+            Err(self.error("Invalid note length"))?;
+        }
+
+        Ok(Rational::new(numer, denom))
+    }
+
+    /// Read the note, chord or rest.
+    fn element(&mut self, chars: &mut Chars) -> Result<()> {
+        let mut pitches = vec![];
+        let mut tie = false;
+        let mut length = Rational::from(1);
+        match chars.peek() {
+            Some('z' | 'x') => chars.pos += 1,
+            Some('[') => {
+                chars.pos += 1;
+                while !chars.eat(']') {
+                    if chars.peek().is_none() {
+                        Err(self.error("Missing closing ']'"))?;
+                    }
+                    pitches.push(self.pitch(chars)?);
+                    // Length of the chord is given by its first note.
+                    let note_length = self.length(chars)?;
+                    if pitches.len() == 1 {
+                        length = note_length;
+                    }
+                    tie |= chars.eat('-');
+                }
+            }
+            _ => pitches.push(self.pitch(chars)?),
+        }
+        length *= self.length(chars)?;
+        tie |= chars.eat('-') && !pitches.is_empty();
+
+        // Broken rhythm takes the length from the next note to this one.
+        let start = chars.pos;
+        while chars.eat(' ') {}
+        let mut broken = Rational::from(1);
+        let mut dots = 0;
+        while let Some(sign @ ('>' | '<')) = chars.peek() {
+            chars.pos += 1;
+            dots += 1;
+            if dots > MAX_DOTS {
+                Err(self.error("Broken rhythm is too long"))?;
+            }
+            broken = match sign {
+                '>' => Duration::dotted(Rational::from(1), dots),
+                _ => Rational::new(1, 1 << dots),
+            };
+        }
+        if dots == 0 {
+            chars.pos = start;
+        }
+
+        let voice = self.voice();
+        let value = length * voice.unit * voice.broken * broken;
+        voice.broken = match broken.cmp(&Rational::from(1)) {
+            std::cmp::Ordering::Greater => Rational::new(1, 1 << dots),
+            std::cmp::Ordering::Less => Duration::dotted(Rational::from(1), dots),
+            std::cmp::Ordering::Equal => Rational::from(1),
+        };
+        let tuplet = match voice.tuplet.take() {
+            Some((tuplet, notes)) if notes > 1 => {
+                voice.tuplet = Some((tuplet, notes - 1));
+                Some(tuplet)
+            }
+            Some((tuplet, _)) => Some(tuplet),
+            None => None,
+        };
+
+        self.push(pitches, value, tuplet, tie)
+    }
+
+    /// Push the element with the written value to the stave.
+    fn push(
+        &mut self,
+        pitches: Vec<Pitch>,
+        value: Rational,
+        tuplet: Option<Tuplet>,
+        tie: bool,
+    ) -> Result<()> {
+        let durations = match Duration::from_value(value) {
+            Some(duration) => vec![duration],
+            None => Duration::split(value),
+        };
+        if durations.iter().map(|d| d.value()).sum::<Rational>() != value {
+            Err(self.error(format!("Unsupported note length {value}")))?;
+        }
+
+        let count = durations.len();
+        for (i, duration) in durations.into_iter().enumerate() {
+            let duration = Duration { tuplet, ..duration };
+            let tie = tie || i + 1 < count;
+            let symbol = match pitches.as_slice() {
+                [] => Symbol::Rest(duration),
+                [pitch] => Symbol::Note(Note {
+                    pitch: pitch.clone(),
+                    duration,
+                    tie,
+                }),
+                pitches => Symbol::Chord(Chord {
+                    pitches: pitches.to_vec(),
+                    duration,
+                    tie,
+                }),
+            };
+            self.voice().stave.symbols.push(symbol);
+        }
+
+        Ok(())
+    }
+
+    fn score(self) -> Result<Score> {
+        if !self.in_body() {
+            Err(self.error("Tune has no K: field"))?;
+        }
+
+        Ok(Score {
+            staves: self.voices.into_iter().map(|v| v.stave).collect(),
+            info: self.info,
+            tempo: self.tempo.unwrap_or(Score::default().tempo),
+            spelling: None,
+        })
+    }
+}
+
+/// Reader of ABC tunes. Only the first tune of the source is read and each of its
+/// voices becomes a stave. Repeats are written out.
+///
+/// # EXAMPLE
+/// ```
+/// # use music_sheet_gen::{abc::*, notation::*};
+///
+/// let abc = "X:1\nT:Scale\nM:4/4\nL:1/8\nK:G\nGABc defg|]\n";
+///
+/// let score = AbcReader.read(abc).unwrap();
+///
+/// assert_eq!(score.info.name.as_deref(), Some("Scale"));
+/// assert_eq!(score.staves[0].symbols.len(), 11);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AbcReader;
+
+impl AbcReader {
+    /// Read the first tune of the ABC source.
+    pub fn read(&self, source: &str) -> Result<Score> {
+        let lines: Vec<&str> = source.lines().collect();
+        // File header before the first tune is skipped.
+        let start = lines
+            .iter()
+            .position(|l| l.starts_with("X:"))
+            .unwrap_or_default();
+
+        let mut reader = TuneReader::new();
+        for (i, line) in lines.iter().enumerate().skip(start) {
+            reader.line = i + 1;
+            if !reader.line(line)? {
+                break;
+            }
+        }
+
+        reader.score()
+    }
+
+    /// Read the first tune of the file at the given path.
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<Score> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|e| AppError::build_path(path, &e))?;
+
+        self.read(&source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abc::AbcWriter;
+
+    fn symbols_kinds(stave: &Stave) -> String {
+        stave
+            .symbols
+            .iter()
+            .map(|s| match s {
+                Symbol::Clef(_) => 'c',
+                Symbol::TimeSignature(_) => 't',
+                Symbol::KeySignature(_) => 'k',
+                Symbol::Chord(_) => 'C',
+                Symbol::Note(_) => 'n',
+                Symbol::Rest(_) => 'r',
+                Symbol::Tempo(_) => 'T',
+            })
+            .collect()
+    }
+
+    fn pitch(symbol: &Symbol) -> &Pitch {
+        match symbol {
+            Symbol::Note(note) => &note.pitch,
+            s => panic!("Expected a note, found {s:?}"),
+        }
+    }
+
+    #[test]
+    fn read_repeats_and_rhythms() {
+        // Arrange
+        let abc = "%abc-2.1\n\nX:1\nT:Tune\nC:Trad\nM:6/8\nL:1/8\nQ:3/8=60\nK:D\n\
+                   |:A>B c2 d|[1 e3 z3:|[2 f3 (3gab z||\n\nX:2\nK:C\nC|\n";
+
+        // Act
+        let score = AbcReader.read(abc).unwrap();
+
+        // Assert
+        let symbols = &score.staves[0].symbols;
+        assert_eq!(score.info.name.as_deref(), Some("Tune"));
+        assert_eq!(score.info.author.as_deref(), Some("Trad"));
+        assert_eq!(score.tempo, 90);
+        assert_eq!(score.staves.len(), 1);
+        assert_eq!(symbols_kinds(&score.staves[0]), "cktnnnnnrnnnnnnnnr");
+        assert_eq!(
+            symbols[3].duration(),
+            Some(&Duration::new(NoteLength::L8, 1, None))
+        );
+        assert_eq!(symbols[4].duration(), Some(&NoteLength::L16.into()));
+        assert_eq!(
+            pitch(&symbols[5]),
+            &Pitch::new(NoteName::C, Octave::O5, Some(Accidental::Sharp))
+        );
+        assert_eq!(pitch(&symbols[9]), pitch(&symbols[3]));
+        assert_eq!(
+            symbols[14].duration(),
+            Some(&Duration::new(NoteLength::L8, 0, Some(Tuplet::triplet())))
+        );
+    }
+
+    #[test]
+    fn read_voices_chords_and_accidentals() {
+        // Arrange
+        let abc = "X:1\nM:C\nL:1/4\nV:1 clef=treble\nV:2 clef=bass\nK:F\n\
+                   V:1\n^c c =B B | B4 |]\nV:2\n[C,G,]2- [C,G,]2 | Z |]\n";
+
+        // Act
+        let score = AbcReader.read(abc).unwrap();
+
+        // Assert
+        let (upper, lower) = (&score.staves[0].symbols, &score.staves[1].symbols);
+        assert_eq!(symbols_kinds(&score.staves[0]), "cktnnnnn");
+        assert_eq!(symbols_kinds(&score.staves[1]), "cktCCr");
+        assert_eq!(pitch(&upper[4]).accidental(), Some(Accidental::Sharp));
+        assert_eq!(pitch(&upper[6]).accidental(), None);
+        assert_eq!(pitch(&upper[7]).accidental(), Some(Accidental::Flat));
+        assert!(matches!(lower[0], Symbol::Clef(Clef::Bass)));
+        assert!(matches!(&lower[3], Symbol::Chord(c) if c.tie && c.pitches.len() == 2));
+        assert_eq!(lower[5].duration(), Some(&NoteLength::L1.into()));
+    }
+
+    #[test]
+    fn read_written_score() {
+        // Arrange
+        let abc = "X:1\nT:Round trip\nM:3/4\nL:1/8\nQ:1/4=80\nV:1\nV:2 clef=bass\nK:Bb\n\
+                   V:1\nB2 (3cd_e f2 | [K:E] ^^F6- | F2 z4 | [M:2/4] G4 |]\n\
+                   V:2\n[B,,D,]6 | z6 | [K:clef=treble] z4 |]\n";
+        let score = AbcReader.read(abc).unwrap();
+
+        // Act
+        let written = AbcWriter.write(&score).unwrap();
+        let read = AbcReader.read(&written).unwrap();
+
+        // Assert
+        assert_eq!(read.info, score.info);
+        assert_eq!(read.tempo, 80);
+        for (read, stave) in read.staves.iter().zip(&score.staves) {
+            assert_eq!(read.symbols.len(), stave.symbols.len());
+            for (r, s) in read.symbols.iter().zip(&stave.symbols) {
+                assert_eq!(format!("{r:?}"), format!("{s:?}"));
+            }
+        }
+    }
+
+    #[test]
+    fn unsupported_tunes() {
+        for abc in [
+            "X:1\nT:No key\n",
+            "X:1\nT:Body\nC D|\n",
+            "X:1\nK:C\nC&D|\n",
+            "X:1\nM:2+3/8\nK:C\nC|\n",
+            "X:1\nK:Hp\nC|\n",
+            "X:1\nK:C clef=alto\nC|\n",
+            "X:1\nK:C\nC5/3|\n",
+            "X:1\nK:C\nC?|\n",
+            "X:1\nK:C\n\"unclosed C|\n",
+        ] {
+            assert!(AbcReader.read(abc).is_err(), "{abc}");
+        }
+    }
+
+    #[test]
+    fn zero_meter() {
+        for meter in ["3/0", "0/4"] {
+            // Arrange
+            let abc = format!("X:1\nM:{meter}\nK:C\nC");
+
+            // Act
+            let err = AbcReader.read(&abc).unwrap_err();
+
+            // Assert
+            assert!(
+                err.to_string()
+                    .contains(&format!("Unsupported meter '{meter}'")),
+                "{err}"
+            );
+        }
+    }
+}
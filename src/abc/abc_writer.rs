@@ -0,0 +1,489 @@
+//! ABC writer definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use std::{collections::HashMap, fmt::Write, path::Path};
+
+use crate::{
+    abc::{clef, fraction, key, LETTERS},
+    error::{AppError, Result},
+    notation::{
+        Accidental, Clef, Duration, ExtNoteName, KeySignature, KeySignatureType, NoteName, Octave,
+        Pitch, Rational, Score, Stave, Symbol, Tempo, TimeSignature,
+    },
+};
+
+/// Bars written on a single line of the tune.
+const BARS_PER_LINE: u32 = 4;
+
+/// Value of the `M:` field.
+fn meter(time: &TimeSignature) -> String {
+    format!("{}/{}", time.numerator(), time.denominator())
+}
+
+/// Value of the `Q:` field.
+fn tempo(tempo: &Tempo) -> String {
+    format!("{}={}", fraction(tempo.note_length.value()), tempo.speed)
+}
+
+/// Key, time and clef, which the stave sets before its first note.
+fn initial_signatures(stave: &Stave) -> (Option<KeySignature>, Option<TimeSignature>, Clef) {
+    let (mut key, mut time, mut clef) = (None, None, Clef::Treble);
+    for symbol in stave.symbols.iter().take_while(|s| s.duration().is_none()) {
+        match symbol {
+            Symbol::KeySignature(k) => key = key.or(Some(*k)),
+            Symbol::TimeSignature(t) => time = time.or(Some(*t)),
+            Symbol::Clef(c) => clef = *c,
+            _ => {}
+        }
+    }
+
+    (key, time, clef)
+}
+
+/// Writer of a single voice, which splits the stave into bars.
+struct VoiceWriter {
+    out: String,
+    /// Unit note length of the tune.
+    unit: Rational,
+    key: KeySignature,
+    time: TimeSignature,
+    clef: Clef,
+    /// Position in the current bar.
+    position: Rational,
+    bars: u32,
+    /// Accidentals written in the current bar, which apply to the rest of it.
+    bar_alterations: HashMap<(NoteName, Octave), i8>,
+    /// Notes left in the current tuplet group.
+    tuplet_notes: usize,
+}
+
+impl VoiceWriter {
+    fn new(unit: Rational, key: KeySignature, time: TimeSignature, clef: Clef) -> Self {
+        Self {
+            out: String::new(),
+            unit,
+            key,
+            time,
+            clef,
+            position: Rational::from(0),
+            bars: 0,
+            bar_alterations: HashMap::new(),
+            tuplet_notes: 0,
+        }
+    }
+
+    fn finish(self) -> String {
+        // The last bar line is the final one.
+        let mut out = self.out.trim_end_matches([' ', '|', '\n']).to_owned();
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        out.push_str("|]\n");
+
+        out
+    }
+
+    /// Write the bar line, if the current bar is full.
+    fn bar_line(&mut self) {
+        while self.position >= self.time.bar_length() {
+            self.position -= self.time.bar_length();
+            self.bars += 1;
+            self.bar_alterations.clear();
+            self.out.push_str(" |");
+            if self.bars % BARS_PER_LINE == 0 {
+                self.out.push('\n');
+            }
+        }
+    }
+
+    /// Separate the next element from the previous one.
+    fn space(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push(' ');
+        }
+    }
+
+    /// Write the pitch with the accidental, which the key and the bar don't imply.
+    fn pitch(&mut self, pitch: &Pitch) {
+        let alteration = Accidental::alteration(pitch.accidental());
+        let position = (pitch.note_name(), pitch.octave);
+        let implied = match self.bar_alterations.get(&position) {
+            Some(alteration) => *alteration,
+            None => self.key.alteration(pitch.note_name()),
+        };
+        if alteration != implied {
+            self.out.push_str(match alteration {
+                2 => "^^",
+                1 => "^",
+                -1 => "_",
+                -2 => "__",
+                _ => "=",
+            });
+            self.bar_alterations.insert(position, alteration);
+        }
+
+        let letter = LETTERS[pitch.note_name().value() as usize];
+        match pitch.octave.value() {
+            octave @ 5.. => {
+                self.out.push(letter.to_ascii_lowercase());
+                self.out.push_str(&"'".repeat(octave as usize - 5));
+            }
+            octave => {
+                self.out.push(letter);
+                self.out.push_str(&",".repeat(4 - octave as usize));
+            }
+        }
+    }
+
+    /// Write the length of the written duration relative to the unit note length.
+    fn length(&mut self, duration: &Duration) {
+        let written = Duration::new(duration.base, duration.dots, None).value() / self.unit;
+        let _ = match (*written.numer(), *written.denom()) {
+            (1, 1) => Ok(()),
+            (1, 2) => write!(self.out, "/"),
+            (1, denom) => write!(self.out, "/{denom}"),
+            (numer, 1) => write!(self.out, "{numer}"),
+            (numer, denom) => write!(self.out, "{numer}/{denom}"),
+        };
+    }
+
+    /// Start the tuplet group with the notes following in the stave.
+    fn tuplet(&mut self, following: &[Symbol]) {
+        let Some(tuplet) = following.first().and_then(|s| s.duration()?.tuplet) else {
+            return;
+        };
+        if self.tuplet_notes > 0 {
+            self.tuplet_notes -= 1;
+            return;
+        }
+
+        let notes = following
+            .iter()
+            .take_while(|s| s.duration().is_some_and(|d| d.tuplet == Some(tuplet)))
+            .count();
+        self.space();
+        match (tuplet.actual(), tuplet.normal(), notes) {
+            (3, 2, 3) => self.out.push_str("(3"),
+            (actual, normal, notes) => {
+                let _ = write!(self.out, "({actual}:{normal}:{notes}");
+            }
+        }
+        self.tuplet_notes = notes - 1;
+    }
+
+    /// Write the note, chord or rest (no pitches).
+    fn notes(&mut self, pitches: &[Pitch], duration: Duration, tie: bool) {
+        let pieces = duration.split_bars(self.position, self.time.bar_length());
+        for (i, piece) in pieces.iter().enumerate() {
+            // Notes of the tuplet group are beamed together.
+            if duration.tuplet.is_none() {
+                self.space();
+            }
+
+            match pitches {
+                [] => self.out.push('z'),
+                [pitch] => self.pitch(pitch),
+                pitches => {
+                    self.out.push('[');
+                    pitches.iter().for_each(|p| self.pitch(p));
+                    self.out.push(']');
+                }
+            }
+            self.length(piece);
+            if !pitches.is_empty() && (tie || i + 1 < pieces.len()) {
+                self.out.push('-');
+            }
+
+            self.position += piece.value();
+            self.bar_line();
+        }
+    }
+
+    fn voice(mut self, stave: &Stave) -> String {
+        for (i, symbol) in stave.symbols.iter().enumerate() {
+            match symbol {
+                Symbol::Clef(c) if *c != self.clef => {
+                    self.clef = *c;
+                    self.space();
+                    let _ = write!(self.out, "[K:clef={}]", clef(*c));
+                }
+                Symbol::KeySignature(k) if *k != self.key => {
+                    self.key = *k;
+                    self.space();
+                    let _ = write!(self.out, "[K:{}]", key(k));
+                }
+                Symbol::TimeSignature(t)
+                    if (t.beat_count, t.single_beat_note)
+                        != (self.time.beat_count, self.time.single_beat_note) =>
+                {
+                    self.time = *t;
+                    self.space();
+                    let _ = write!(self.out, "[M:{}]", meter(t));
+                }
+                Symbol::Tempo(t) => {
+                    self.space();
+                    let _ = write!(self.out, "[Q:{}]", tempo(t));
+                }
+                Symbol::Note(note) => {
+                    self.tuplet(&stave.symbols[i..]);
+                    self.notes(std::slice::from_ref(&note.pitch), note.duration, note.tie);
+                }
+                Symbol::Chord(chord) => {
+                    self.tuplet(&stave.symbols[i..]);
+                    self.notes(&chord.pitches, chord.duration, chord.tie);
+                }
+                Symbol::Rest(duration) => {
+                    self.tuplet(&stave.symbols[i..]);
+                    self.notes(&[], *duration, false);
+                }
+                _ => {}
+            }
+        }
+
+        self.finish()
+    }
+}
+
+/// Writer of the score into a single ABC tune. Each stave is written as a voice.
+///
+/// # EXAMPLE
+/// ```
+/// # use music_sheet_gen::{abc::*, notation::*};
+///
+/// let score = Score {
+///     staves: vec![Stave {
+///         symbols: vec![
+///             Symbol::Clef(Clef::Treble),
+///             Symbol::Note(Note {
+///                 pitch: Pitch::new(NoteName::C, Octave::O5, None),
+///                 duration: NoteLength::L4.into(),
+///                 tie: false,
+///             }),
+///         ],
+///     }],
+///     ..Default::default()
+/// };
+///
+/// let abc = AbcWriter.write(&score).unwrap();
+///
+/// assert!(abc.ends_with("K:C\nc2 |]\n"));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AbcWriter;
+
+impl AbcWriter {
+    /// Unit note length of the written tunes.
+    const UNIT: Rational = Rational::new_raw(1, 8);
+
+    /// Write the score into the ABC tune.
+    pub fn write(&self, score: &Score) -> Result<String> {
+        if score.staves.is_empty() {
+            Err(AppError::Abc("Score has no staves".to_owned()))?;
+        }
+        // Bars of no length would never be filled.
+        let empty_bars =
+            score.staves.iter().flat_map(|s| &s.symbols).any(
+                |s| matches!(s, Symbol::TimeSignature(t) if t.bar_length() == Rational::from(0)),
+            );
+        if empty_bars {
+            Err(AppError::Abc("Time signature has no beats".to_owned()))?;
+        }
+
+        let mut out = String::from("X:1\n");
+        for (field, value) in [
+            ('T', &score.info.name),
+            ('C', &score.info.author),
+            ('Z', &score.info.transcriber),
+        ] {
+            if let Some(value) = value {
+                let _ = writeln!(out, "{field}:{}", value.replace('\n', " "));
+            }
+        }
+
+        let (first_key, first_time, first_clef) = initial_signatures(&score.staves[0]);
+        let key = first_key.unwrap_or(KeySignature {
+            ext: ExtNoteName {
+                note_name: NoteName::C,
+                accidental: None,
+            },
+            signature_type: KeySignatureType::Maj,
+        });
+        let time = first_time.unwrap_or(TimeSignature::c());
+        let _ = writeln!(out, "M:{}", meter(&time));
+        let _ = writeln!(out, "L:{}", fraction(Self::UNIT));
+        let _ = writeln!(out, "Q:1/4={}", score.tempo);
+
+        let voices = score.staves.len() > 1;
+        if voices {
+            for (i, stave) in score.staves.iter().enumerate() {
+                let (_, _, stave_clef) = initial_signatures(stave);
+                let _ = writeln!(out, "V:{} clef={}", i + 1, clef(stave_clef));
+            }
+        }
+        match (voices, first_clef) {
+            (false, Clef::Bass) => {
+                let _ = writeln!(out, "K:{} clef=bass", self::key(&key));
+            }
+            _ => {
+                let _ = writeln!(out, "K:{}", self::key(&key));
+            }
+        }
+
+        for (i, stave) in score.staves.iter().enumerate() {
+            if voices {
+                let _ = writeln!(out, "V:{}", i + 1);
+            }
+            let (_, _, stave_clef) = initial_signatures(stave);
+            out.push_str(&VoiceWriter::new(Self::UNIT, key, time, stave_clef).voice(stave));
+        }
+
+        Ok(out)
+    }
+
+    /// Write the score into the file at the given path.
+    pub fn save(&self, score: &Score, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.write(score)?).map_err(|e| AppError::build_path(path, &e))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{Chord, Note, NoteLength, Tuplet};
+
+    fn note(note_name: NoteName, octave: Octave, accidental: Option<Accidental>) -> Pitch {
+        Pitch::new(note_name, octave, accidental)
+    }
+
+    #[test]
+    fn write_header_and_bars() {
+        // Arrange
+        let score = Score {
+            staves: vec![Stave {
+                symbols: vec![
+                    Symbol::Clef(Clef::Treble),
+                    Symbol::KeySignature(
+                        KeySignature::from_fifths(1, KeySignatureType::Min).unwrap(),
+                    ),
+                    Symbol::TimeSignature(TimeSignature {
+                        beat_count: 3,
+                        single_beat_note: NoteLength::L4.into(),
+                    }),
+                    Symbol::Note(Note {
+                        pitch: note(NoteName::F, Octave::O4, Some(Accidental::Sharp)),
+                        duration: NoteLength::L2.into(),
+                        tie: false,
+                    }),
+                    Symbol::Note(Note {
+                        pitch: note(NoteName::F, Octave::O4, None),
+                        duration: NoteLength::L2.into(),
+                        tie: false,
+                    }),
+                    Symbol::Note(Note {
+                        pitch: note(NoteName::F, Octave::O4, None),
+                        duration: NoteLength::L8.into(),
+                        tie: false,
+                    }),
+                    Symbol::Rest(Duration::new(NoteLength::L4, 1, None)),
+                ],
+            }],
+            info: crate::notation::ScoreInfo {
+                name: Some("Waltz".to_owned()),
+                author: Some("Anon".to_owned()),
+                transcriber: None,
+            },
+            tempo: 120,
+            spelling: None,
+        };
+
+        // Act
+        let abc = AbcWriter.write(&score).unwrap();
+
+        // Assert
+        assert_eq!(
+            abc,
+            "X:1\nT:Waltz\nC:Anon\nM:3/4\nL:1/8\nQ:1/4=120\nK:Em\nF4 =F2- | =F2 F z3 |]\n"
+        );
+    }
+
+    #[test]
+    fn write_voices_chords_and_tuplets() {
+        // Arrange
+        let triplet = Duration::new(NoteLength::L8, 0, Some(Tuplet::triplet()));
+        let score = Score {
+            staves: vec![
+                Stave {
+                    symbols: vec![
+                        Symbol::TimeSignature(TimeSignature::c()),
+                        Symbol::Note(Note {
+                            pitch: note(NoteName::C, Octave::O6, None),
+                            duration: triplet,
+                            tie: false,
+                        }),
+                        Symbol::Note(Note {
+                            pitch: note(NoteName::B, Octave::O5, Some(Accidental::Flat)),
+                            duration: triplet,
+                            tie: false,
+                        }),
+                        Symbol::Rest(triplet),
+                        Symbol::Rest(Duration::new(NoteLength::L2, 1, None)),
+                    ],
+                },
+                Stave {
+                    symbols: vec![
+                        Symbol::Clef(Clef::Bass),
+                        Symbol::Chord(Chord {
+                            pitches: vec![
+                                note(NoteName::C, Octave::O3, None),
+                                note(NoteName::G, Octave::O2, None),
+                            ],
+                            duration: NoteLength::L1.into(),
+                            tie: true,
+                        }),
+                    ],
+                },
+            ],
+            ..Default::default()
+        };
+
+        // Act
+        let abc = AbcWriter.write(&score).unwrap();
+
+        // Assert
+        assert!(abc.contains("V:1 clef=treble\nV:2 clef=bass\nK:C\n"));
+        assert!(abc.contains("V:1\n(3c'_bz z6 |]\n"));
+        assert!(abc.contains("V:2\n[C,G,,]8- |]\n"));
+    }
+
+    #[test]
+    fn write_zero_beats() {
+        // Arrange
+        let score = Score {
+            staves: vec![Stave {
+                symbols: vec![
+                    Symbol::TimeSignature(TimeSignature {
+                        beat_count: 0,
+                        single_beat_note: NoteLength::L4.into(),
+                    }),
+                    Symbol::Note(Note {
+                        pitch: note(NoteName::C, Octave::O4, None),
+                        duration: NoteLength::L4.into(),
+                        tie: false,
+                    }),
+                ],
+            }],
+            ..Default::default()
+        };
+
+        // Act
+        let written = AbcWriter.write(&score);
+
+        // Assert
+        assert!(written.is_err());
+    }
+}
@@ -29,6 +29,21 @@ pub enum AppError {
     #[error("Lilypond translation failed: {0}")]
     Lily(String),
 
+    #[error("Lilypond source is invalid at line {0}: {1}")]
+    LilyParse(usize, String),
+
+    #[error("MIDI writing failed: {0}")]
+    Midi(String),
+
+    #[error("MusicXML conversion failed: {0}")]
+    MusicXml(String),
+
+    #[error("ABC conversion failed: {0}")]
+    Abc(String),
+
+    #[error("SoundFont is invalid: {0}")]
+    SoundFont(String),
+
     #[error("Fluidsynth translation failed: {0}")]
     Fluidsynth(String),
 
@@ -47,6 +62,18 @@ pub enum AppError {
     #[error("Score is missing a time signature.")]
     MissingTimeSignature,
 
+    #[error("Duration cannot be shorter than 1/128 note.")]
+    DurationTooShort,
+
+    #[error("Invalid interval: {0}")]
+    Interval(String),
+
+    #[error("Pitch {0} cannot be transposed.")]
+    Transposition(String),
+
+    #[error("Scale pattern '{0}' is invalid: {1}")]
+    ScalePattern(String, String),
+
     #[error("Error: {0}")]
     Other(String),
 }
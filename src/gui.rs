@@ -23,6 +23,7 @@ pub enum TabType {
     ScoreVisualizer,
     InterpretParameters,
     ControlPanel,
+    Diagnostics,
 }
 
 impl TabType {
@@ -34,6 +35,7 @@ impl TabType {
             TabType::ScoreVisualizer => "Score visualizer",
             TabType::InterpretParameters => "Interpret parameters",
             TabType::ControlPanel => "Control panel",
+            TabType::Diagnostics => "Diagnostics",
         }
     }
 }
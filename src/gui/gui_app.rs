@@ -8,19 +8,34 @@
 use std::{
     collections::HashMap,
     ffi::OsStr,
-    fs::File,
     path::{Path, PathBuf},
     rc::Rc,
 };
 
 use crate::{
-    error::Result,
-    gui::{toast, windows::*},
-    lsystem::{interpret::MusicIntInfo, CSSLRuleSet, CSSLSystem},
+    abc::{AbcReader, AbcWriter},
+    error::{AppError, Result},
+    gui::{
+        toast,
+        utils::{
+            self, AsyncResult, Bundle, BundleFormat, MidiSource, OutputFormat, SynthBackend,
+            ToAsyncResult,
+        },
+        windows::*,
+    },
+    harmony::Harmonizer,
+    lily::{LilyLanguage, LilyLayout, LilyParser},
+    lsystem::{interpret::MusicIntInfo, CSSLRuleSet, CSSLSystem, LSystem, RuleSpan},
+    midi::MidiWriter,
+    musicxml::{MusicXmlReader, MusicXmlWriter},
+    notation::{Score, ScoreInfo, Stave, Transposition},
     sanitizer::LilySanitizer,
+    synth::{AudioWriter, RenderSettings},
+    Arguments,
 };
 use egui_dock::{DockArea, DockState, TabViewer};
 use egui_file_dialog::FileDialog;
+use poll_promise::Promise;
 
 /// Holds the shared state of GUI application, which is passed
 /// between the windows.
@@ -30,21 +45,89 @@ pub struct GuiAppState {
     pub rules: CSSLRuleSet,
     /// Currently edited axiom
     pub axiom: String,
+    /// Seed of the random generator of the L-system.
+    #[serde(default = "rand::random")]
+    pub seed: u64,
     /// Currently edited interpreter parameters
     pub music_int_info: MusicIntInfo,
     /// Currently edited lily sanitizer (max line bars etc.)
     pub lily_sanitizer: LilySanitizer,
+    /// Whether to add an accompaniment stave to the generated melody.
+    #[serde(default)]
+    pub harmonize: bool,
+    /// Currently edited accompaniment parameters
+    #[serde(default)]
+    pub harmonizer: Harmonizer,
+    /// Transposition applied to the generated score.
+    #[serde(default)]
+    pub transposition: Transposition,
+    /// Title and authors printed on the score.
+    #[serde(default)]
+    pub score_info: ScoreInfo,
+    /// Language of the note names in the generated Lilypond source.
+    #[serde(default)]
+    pub lily_language: LilyLanguage,
+    /// Whether to write the Lilypond source in the relative octave mode.
+    #[serde(default)]
+    pub lily_relative: bool,
+    /// Paper and layout of the engraved score.
+    #[serde(default)]
+    pub lily_layout: LilyLayout,
+    /// Format of the engraved pages.
+    #[serde(default)]
+    pub lily_format: OutputFormat,
+    /// Source of the MIDI file synthesized into the score audio.
+    #[serde(default)]
+    pub midi_source: MidiSource,
+    /// Synthesizer rendering the MIDI into the score audio.
+    #[serde(default)]
+    pub synth_backend: SynthBackend,
+    /// Sample rate, gain and effects of the synthesized audio.
+    #[serde(default)]
+    pub audio_render: RenderSettings,
+    /// Format of the exported audio.
+    #[serde(default)]
+    pub audio_writer: AudioWriter,
+    /// Format of the exported project bundle.
+    #[serde(default)]
+    pub bundle_format: BundleFormat,
 
     /// Currently used L-system for generation
     pub l_system: CSSLSystem,
     /// Used rules in all iterations.
     pub used_rules_history: Vec<Vec<Rc<crate::lsystem::CSSLRule>>>,
+    /// Rules used in the last step along with the ranges of the word they wrote.
+    #[serde(default)]
+    pub last_rule_spans: Vec<RuleSpan<crate::lsystem::CSSLRule>>,
 
     /// Currently displayed score image path.
     pub score_images: Option<Vec<PathBuf>>,
+    /// PDF of the currently displayed score, if it was generated.
+    #[serde(default)]
+    pub score_pdf: Option<PathBuf>,
     /// Currently played audio path.
     pub score_audio: Option<PathBuf>,
 
+    /// Lilypond errors and warnings of the last refresh.
+    #[serde(skip)]
+    pub diagnostics: Vec<ScoreDiagnostic>,
+    /// Diagnostic selected in the diagnostics window.
+    #[serde(skip)]
+    pub selected_diagnostic: Option<usize>,
+    /// Characters of the current word highlighted in the statistics.
+    #[serde(skip)]
+    pub highlighted_word: Vec<usize>,
+
+    /// Currently displayed score after the transposition and sanitization.
+    #[serde(skip)]
+    pub score: Option<Score>,
+    /// Lilypond source of the currently displayed score.
+    #[serde(skip)]
+    pub score_lily: Option<String>,
+    /// Score imported from a file, which is displayed instead of the generated one.
+    #[serde(skip)]
+    pub imported_score: Option<Score>,
+
     /// Flag used to trigger repaint and re-interpration.
     #[serde(skip)]
     #[serde(default = "default_dirty")]
@@ -58,16 +141,19 @@ fn default_dirty() -> bool {
 impl GuiAppState {
     /// Reset the currently used L-system
     pub fn reset(&mut self) {
-        self.l_system = CSSLSystem::new(self.axiom.clone(), self.rules.clone());
+        self.l_system = CSSLSystem::new(self.axiom.clone(), self.rules.clone(), self.seed);
         self.used_rules_history.clear();
+        self.last_rule_spans.clear();
+        self.highlighted_word.clear();
         self.score_images = None;
+        self.score_pdf = None;
         self.score_audio = None;
         self.dirty = true;
     }
 
     /// Apply changes to L-system.
     pub fn apply_changes(&mut self) -> Result<()> {
-        self.l_system = CSSLSystem::new(self.axiom.clone(), self.rules.clone());
+        self.l_system = CSSLSystem::new(self.axiom.clone(), self.rules.clone(), self.seed);
 
         Ok(())
     }
@@ -95,17 +181,40 @@ impl Default for GuiAppState {
         ])
         .unwrap();
         let axiom = "F++++F--F++F".to_owned();
+        let seed = rand::random();
 
         Self {
-            l_system: CSSLSystem::new(axiom.clone(), rules.clone()),
+            l_system: CSSLSystem::new(axiom.clone(), rules.clone(), seed),
             rules,
             axiom,
+            seed,
             dirty: true,
             music_int_info: MusicIntInfo::default(),
             used_rules_history: Vec::default(),
+            last_rule_spans: Vec::default(),
             score_images: None,
+            score_pdf: None,
             score_audio: None,
             lily_sanitizer: LilySanitizer::default(),
+            harmonize: false,
+            harmonizer: Harmonizer::default(),
+            transposition: Transposition::default(),
+            score_info: ScoreInfo::default(),
+            lily_language: LilyLanguage::default(),
+            lily_relative: false,
+            lily_layout: LilyLayout::default(),
+            lily_format: OutputFormat::default(),
+            midi_source: MidiSource::default(),
+            synth_backend: SynthBackend::default(),
+            audio_render: RenderSettings::default(),
+            audio_writer: AudioWriter::default(),
+            bundle_format: BundleFormat::default(),
+            diagnostics: Vec::default(),
+            selected_diagnostic: None,
+            highlighted_word: Vec::default(),
+            score: None,
+            score_lily: None,
+            imported_score: None,
         }
     }
 }
@@ -134,8 +243,9 @@ fn create_tabs(app_state: &GuiAppState) -> HashMap<&'static str, Box<dyn Dockabl
         Box::new(GrammarEdit::new(app_state)),
         Box::new(ScoreVisualizer::default()),
         Box::new(ControlPanel::new(app_state)),
-        Box::new(InterpretParameteres {}),
+        Box::new(InterpretParameteres::new(app_state)),
         Box::new(Statistics {}),
+        Box::new(Diagnostics {}),
     ];
 
     tabs.into_iter().map(|tab| (tab.name(), tab)).collect()
@@ -169,6 +279,19 @@ impl Default for GuiAppDocked {
     }
 }
 
+/// Action performed with the file picked in the file dialog.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum FileAction {
+    #[default]
+    Export,
+    ExportMusicXml,
+    ImportLilypond,
+    ImportMusicXml,
+    ExportAbc,
+    ImportAbc,
+    ExportAudio,
+}
+
 /// Tab type used for TabViewer is the DockableWindow::name(), which is a string.
 type Tab = String;
 
@@ -183,6 +306,14 @@ pub struct GuiApp {
 
     #[serde(skip)]
     file_dialog: FileDialog,
+    #[serde(skip)]
+    file_action: FileAction,
+    /// SoundFont passed on the command line, which renders the exported audio.
+    #[serde(skip)]
+    sf_path: Option<PathBuf>,
+    /// Audio export running in the background, which returns the path of the written file.
+    #[serde(skip)]
+    audio_export_promise: Option<Promise<AsyncResult<PathBuf>>>,
 }
 
 impl Default for GuiApp {
@@ -195,46 +326,264 @@ impl Default for GuiApp {
             dock_state,
             app_docked,
             file_dialog: FileDialog::new(),
+            file_action: FileAction::default(),
+            sf_path: Arguments::new().ok().and_then(|a| a.sound_font_path),
+            audio_export_promise: None,
         }
     }
 }
 
 impl GuiApp {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        // SVG pages of the score are loaded by egui.
+        egui_extras::install_image_loaders(&cc.egui_ctx);
+
         // Load previous app state (if any).
         if let Some(storage) = cc.storage {
-            return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+            let mut app: Self = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+
+            // Tabs added since the state was saved are missing from the dock.
+            for &name in app.app_docked.tabs.keys() {
+                if app.dock_state.find_tab(&name.to_owned()).is_none() {
+                    app.dock_state.push_to_focused_leaf(name.to_owned());
+                }
+            }
+
+            return app;
         }
 
         Default::default()
     }
 
-    /// Export current score to TAR archive to the given path.
+    /// Export the project bundle with the score, its sources and the grammar, which
+    /// generated it, in the selected bundle format to the given path.
+    ///
+    /// The seed reproduces the run, when the L-system is stepped without retries. The rules
+    /// used in each iteration are recorded too.
     pub fn export(&self, path: &Path) -> crate::error::Result<()> {
-        let path = if path.extension().unwrap_or(OsStr::new("")) != "tar" {
-            path.with_added_extension("tar")
-        } else {
-            path.to_path_buf()
+        let app_state = &self.app_docked.app_state;
+        let title = app_state.score_info.name.clone().unwrap_or_default();
+        let seed = *app_state.l_system.state().seed();
+        let mut bundle = Bundle::new(title);
+        bundle.set_seed(seed);
+
+        if let Some(lily) = app_state.score_lily.as_ref() {
+            bundle.add("score.ly", "Lilypond source of the score", lily.as_str());
+
+            // PDF is engraved for any preview format, the cached output is reused.
+            let output = utils::lilypond(lily, "score", OutputFormat::Pdf)?;
+            if let Some(pdf_path) = output.pdf_path() {
+                bundle.add_path(pdf_path, "Engraved score")?;
+            }
+        }
+        if let Some(score) = app_state.score.as_ref() {
+            let midi = MidiWriter::default().write(score)?;
+            bundle.add("score.midi", "MIDI of the score", midi);
+        }
+        for page_path in app_state.score_images.as_ref().unwrap_or(&vec![]) {
+            bundle.add_path(page_path, "Engraved page of the score")?;
+        }
+        if let Some(audio_path) = app_state.score_audio.as_ref() {
+            bundle.add_path(audio_path, "Synthesized audio of the score")?;
+        }
+
+        let grammar: Vec<String> = std::iter::once(app_state.axiom.clone())
+            .chain(app_state.rules.css_rules().iter().map(|r| r.to_string()))
+            .collect();
+        bundle.add(
+            "grammar.txt",
+            "Axiom on the first line followed by the rules",
+            grammar.join("\n") + "\n",
+        );
+        bundle.add(
+            "seed.txt",
+            "Seed of the random generator of the L-system",
+            format!("{seed}\n"),
+        );
+        let pretty = ron::ser::PrettyConfig::default;
+        bundle.add(
+            "interpret.ron",
+            "Parameters of the music interpretation",
+            ron::ser::to_string_pretty(&app_state.music_int_info, pretty())?,
+        );
+        let history: Vec<Vec<String>> = app_state
+            .used_rules_history
+            .iter()
+            .map(|rules| rules.iter().map(|r| r.to_string()).collect())
+            .collect();
+        bundle.add(
+            "history.ron",
+            "Rules used in each iteration of the L-system",
+            ron::ser::to_string_pretty(&history, pretty())?,
+        );
+
+        bundle.save(path, app_state.bundle_format)?;
+
+        Ok(())
+    }
+
+    /// Export current score to MusicXML file to the given path.
+    pub fn export_musicxml(&self, path: &Path) -> crate::error::Result<()> {
+        let path = match path.extension().and_then(OsStr::to_str) {
+            Some("musicxml" | "xml") => path.to_path_buf(),
+            _ => path.with_added_extension("musicxml"),
+        };
+
+        let score = self
+            .app_docked
+            .app_state
+            .score
+            .as_ref()
+            .ok_or(AppError::MusicXml("No score was generated yet".to_owned()))?;
+
+        MusicXmlWriter.save(score, path)
+    }
+
+    /// Export current score to ABC file to the given path.
+    pub fn export_abc(&self, path: &Path) -> crate::error::Result<()> {
+        let path = match path.extension().and_then(OsStr::to_str) {
+            Some("abc") => path.to_path_buf(),
+            _ => path.with_added_extension("abc"),
         };
 
-        let file = File::create(path)?;
-        let mut builder = tar::Builder::new(file);
-        for p in self
+        let score = self
             .app_docked
             .app_state
-            .score_images
+            .score
             .as_ref()
-            .unwrap_or(&vec![])
-        {
-            builder.append_path_with_name(p, p.file_name().unwrap())?;
+            .ok_or(AppError::Abc("No score was generated yet".to_owned()))?;
+
+        AbcWriter.save(score, path)
+    }
+
+    /// Start the export of the audio of the current score with the audio settings to the
+    /// given path. The audio is rendered in the background, see [`Self::poll_audio_export`].
+    pub fn export_audio(&mut self, path: &Path) -> crate::error::Result<()> {
+        if self.audio_export_promise.is_some() {
+            Err(AppError::Audio(
+                "Audio export is already running".to_owned(),
+            ))?;
         }
 
-        if let Some(audio_path) = self.app_docked.app_state.score_audio.as_ref() {
-            builder.append_path_with_name(audio_path, audio_path.file_name().unwrap())?;
+        let app_state = &self.app_docked.app_state;
+        let extension = app_state.audio_writer.format.extension();
+        let path = match path.extension().and_then(OsStr::to_str) {
+            Some(e) if e == extension => path.to_path_buf(),
+            _ => path.with_added_extension(extension),
+        };
+
+        let score = app_state
+            .score
+            .as_ref()
+            .ok_or(AppError::Audio("No score was generated yet".to_owned()))?;
+        let midi = MidiWriter::default().write(score)?;
+        let backend = app_state.synth_backend;
+        let sf_path = self.sf_path.clone();
+        let settings = app_state.audio_render;
+        let writer = app_state.audio_writer;
+
+        self.audio_export_promise = Some(Promise::spawn_thread("export_audio", move || {
+            let frames = utils::render_audio(backend, sf_path.as_deref(), &midi, &settings)
+                .into_async_result()?;
+            writer
+                .save(&frames, settings.sample_rate, &path)
+                .into_async_result()?;
+
+            AsyncResult::Ok(path)
+        }));
+
+        Ok(())
+    }
+
+    /// Notify about the finished audio export.
+    fn poll_audio_export(&mut self, ctx: &egui::Context) {
+        let Some(promise) = self.audio_export_promise.take() else {
+            return;
+        };
+
+        match promise.try_take() {
+            Ok(AsyncResult::Ok(path)) => {
+                toast::show_success(format!("Audio exported to: {}", path.display()).as_str());
+            }
+            Ok(AsyncResult::Err(e)) => toast::show_error(format!("Export failed: {e}").as_str()),
+            Err(promise) => {
+                self.audio_export_promise = Some(promise);
+                ctx.request_repaint();
+            }
         }
+    }
+
+    /// Import the score from the Lilypond source at the given path.
+    pub fn import_lilypond(&mut self, path: &Path) -> crate::error::Result<()> {
+        let source = std::fs::read_to_string(path)?;
+        self.import(LilyParser::parse(&source)?);
+
+        Ok(())
+    }
+
+    /// Import the score from the MusicXML file at the given path.
+    pub fn import_musicxml(&mut self, path: &Path) -> crate::error::Result<()> {
+        self.import(MusicXmlReader.load(path)?);
+
+        Ok(())
+    }
+
+    /// Import the score from the first tune of the ABC file at the given path.
+    pub fn import_abc(&mut self, path: &Path) -> crate::error::Result<()> {
+        self.import(AbcReader.load(path)?);
 
         Ok(())
     }
+
+    /// Show the imported score instead of the generated one.
+    fn import(&mut self, mut score: Score) {
+        score
+            .staves
+            .iter_mut()
+            .for_each(Stave::insert_missing_signatures);
+
+        let app_state = &mut self.app_docked.app_state;
+        app_state.imported_score = Some(score);
+        app_state.dirty = true;
+    }
+
+    /// Perform the action with the file picked in the file dialog.
+    fn file_picked(&mut self, path: &Path) {
+        let (result, done, failed) = match self.file_action {
+            FileAction::Export => (self.export(path), "File exported to", "Export failed"),
+            FileAction::ExportMusicXml => (
+                self.export_musicxml(path),
+                "File exported to",
+                "Export failed",
+            ),
+            FileAction::ImportLilypond => (
+                self.import_lilypond(path),
+                "Score imported from",
+                "Import failed",
+            ),
+            FileAction::ImportMusicXml => (
+                self.import_musicxml(path),
+                "Score imported from",
+                "Import failed",
+            ),
+            FileAction::ExportAbc => (self.export_abc(path), "File exported to", "Export failed"),
+            FileAction::ImportAbc => (
+                self.import_abc(path),
+                "Score imported from",
+                "Import failed",
+            ),
+            FileAction::ExportAudio => (
+                self.export_audio(path),
+                "Exporting audio to",
+                "Export failed",
+            ),
+        };
+
+        match result {
+            Ok(_) => toast::show_success(format!("{done}: {}", path.display()).as_str()),
+            Err(e) => toast::show_error(format!("{failed}: {e}").as_str()),
+        }
+    }
 }
 
 impl eframe::App for GuiApp {
@@ -249,8 +598,33 @@ impl eframe::App for GuiApp {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
                     if ui.button("Export..").clicked() {
+                        self.file_action = FileAction::Export;
+                        self.file_dialog.save_file();
+                    };
+                    if ui.button("Export MusicXML..").clicked() {
+                        self.file_action = FileAction::ExportMusicXml;
                         self.file_dialog.save_file();
                     };
+                    if ui.button("Export ABC..").clicked() {
+                        self.file_action = FileAction::ExportAbc;
+                        self.file_dialog.save_file();
+                    };
+                    if ui.button("Export audio..").clicked() {
+                        self.file_action = FileAction::ExportAudio;
+                        self.file_dialog.save_file();
+                    };
+                    if ui.button("Import LilyPond..").clicked() {
+                        self.file_action = FileAction::ImportLilypond;
+                        self.file_dialog.pick_file();
+                    };
+                    if ui.button("Import MusicXML..").clicked() {
+                        self.file_action = FileAction::ImportMusicXml;
+                        self.file_dialog.pick_file();
+                    };
+                    if ui.button("Import ABC..").clicked() {
+                        self.file_action = FileAction::ImportAbc;
+                        self.file_dialog.pick_file();
+                    };
                     ui.separator();
                     if ui.button("Quit").clicked() {
                         ctx.send_viewport_cmd(egui::ViewportCommand::Close);
@@ -272,21 +646,13 @@ impl eframe::App for GuiApp {
                 .show(ctx, &mut self.app_docked);
 
             // Show all toasts.
+            self.poll_audio_export(ctx);
             toast::TOASTS.lock().show(ctx);
 
             // Show active dialog if any.
             self.file_dialog.update(ctx);
             if let Some(path) = self.file_dialog.take_picked() {
-                match self.export(&path) {
-                    Ok(_) => {
-                        toast::show_success(
-                            format!("File exported to: {}", path.display()).as_str(),
-                        );
-                    }
-                    Err(e) => {
-                        toast::show_error(format!("Export failed: {e}").as_str());
-                    }
-                };
+                self.file_picked(&path);
             }
         });
     }
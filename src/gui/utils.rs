@@ -3,10 +3,14 @@
 //! ### Author
 //! Jakub Kloub (xkloub03), VUT FIT
 
+mod bundle;
+mod render_cache;
 mod texture;
+pub use bundle::{Bundle, BundleFormat};
 use once_cell::sync::Lazy;
 use poll_promise::Promise;
 use regex::Regex;
+pub use render_cache::RenderCache;
 pub use texture::InMemoryTexture;
 pub use texture::Texture;
 
@@ -15,52 +19,119 @@ use derive_getters::Getters;
 use crate::{
     error::{AppError, Result},
     ext::CapturedStr,
-    gui::DIR_NAME,
+    midi::MidiReader,
+    synth::{Instrument, RenderSettings, SoundFont, Synthesizer},
 };
+use parking_lot::Mutex;
 use std::{
     cmp::Ordering,
     fs::File,
     io::Write,
     path::{Path, PathBuf},
     process::Command,
+    sync::Arc,
 };
 
+/// Format of the pages engraved by Lilypond.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum OutputFormat {
+    /// Raster pages with the given resolution.
+    Png { dpi: u16 },
+    /// Vector pages, which stay sharp at any zoom.
+    Svg,
+    /// PDF document. Pages are also rendered as PNG for the preview.
+    Pdf,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::Png {
+            dpi: Self::default_dpi(),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Default resolution of Lilypond PNG output.
+    pub fn default_dpi() -> u16 {
+        101
+    }
+
+    /// Extension of the generated page files.
+    pub fn page_extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Svg => "svg",
+            _ => "png",
+        }
+    }
+
+    fn lilypond_args(&self) -> Vec<String> {
+        match self {
+            OutputFormat::Png { dpi } => vec!["--png".to_owned(), format!("-dresolution={dpi}")],
+            OutputFormat::Svg => vec!["-dbackend=svg".to_owned()],
+            OutputFormat::Pdf => vec!["--pdf".to_owned(), "--png".to_owned()],
+        }
+    }
+}
+
+/// Source of the MIDI file synthesized into the score audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum MidiSource {
+    /// MIDI written directly from the score, which doesn't wait for the engraving.
+    #[default]
+    Native,
+    /// MIDI generated by Lilypond along with the pages.
+    Lilypond,
+}
+
 #[derive(Debug, Clone, Getters)]
 pub struct LilyOutput {
     pages: Vec<PathBuf>,
-    preview_path: PathBuf,
+    pdf_path: Option<PathBuf>,
     midi_path: PathBuf,
+    format: OutputFormat,
+    /// Messages printed by Lilypond, including warnings.
+    log: String,
 }
 
-pub fn lilypond(lily_str: &str, filename: &str) -> Result<LilyOutput> {
-    let cache_dir = directories::BaseDirs::new()
-        .ok_or(AppError::Other("Failed to create BaseDirs".to_string()))?
-        .cache_dir()
-        .join(DIR_NAME);
+/// File with the Lilypond output kept in the cache entry.
+const LOG_FILE: &str = "lilypond.log";
 
-    if cache_dir.exists() {
-        std::fs::remove_dir_all(&cache_dir)?;
-    }
-    std::fs::create_dir_all(&cache_dir)?;
+/// Engrave the Lilypond source, reusing the cached output of the same source and format.
+pub fn lilypond(lily_str: &str, filename: &str, format: OutputFormat) -> Result<LilyOutput> {
+    let cache = RenderCache::new()?;
+    let key = RenderCache::key(&[
+        lily_str.as_bytes(),
+        filename.as_bytes(),
+        format!("{format:?}").as_bytes(),
+    ]);
+
+    let dir = cache.entry(&key, |dir| {
+        run_lilypond(dir, lily_str, filename, format)?;
+        lily_output(dir, filename, format).map(|_| ())
+    })?;
 
-    let input_path = cache_dir.join("input.ily");
-    let output_preview_path = cache_dir.join(format!("{filename}.preview.png"));
-    let output_midi_path = cache_dir.join(format!("{filename}.midi"));
+    lily_output(&dir, filename, format)
+}
 
+fn run_lilypond(dir: &Path, lily_str: &str, filename: &str, format: OutputFormat) -> Result<()> {
+    let input_path = dir.join("input.ily");
     let mut input_file =
         File::create(&input_path).map_err(|e| AppError::build_path(&input_path, &e))?;
     input_file.write_all(lily_str.as_bytes())?;
 
     let mut cmd = Command::new("lilypond");
-    cmd.current_dir(&cache_dir).args([
-        "--png",
-        "-dpreview",
+    cmd.current_dir(dir).args(format.lilypond_args()).args([
         "-o",
         filename,
         input_path.display().to_string().as_str(),
     ]);
     let output = cmd.output()?;
 
+    // Warnings of the successful run are read along with the cached output.
+    let log_path = dir.join(LOG_FILE);
+    std::fs::write(&log_path, &output.stderr).map_err(|e| AppError::build_path(&log_path, &e))?;
+
     if !output.status.success() {
         Err(AppError::Lily(format!(
             "Exit code: {0}, Stderr: \n{1}",
@@ -69,12 +140,20 @@ pub fn lilypond(lily_str: &str, filename: &str) -> Result<LilyOutput> {
         )))?;
     }
 
-    let mut pages: Vec<PathBuf> = std::fs::read_dir(&cache_dir)?
+    Ok(())
+}
+
+/// Collect the files generated by Lilypond in the directory.
+fn lily_output(dir: &Path, filename: &str, format: OutputFormat) -> Result<LilyOutput> {
+    let output_pdf_path = dir.join(format!("{filename}.pdf"));
+    let output_midi_path = dir.join(format!("{filename}.midi"));
+
+    let extension = format!(".{}", format.page_extension());
+    let mut pages: Vec<PathBuf> = std::fs::read_dir(dir)?
         .filter_map(|entry| {
             let path = entry.ok()?.path();
             let fname = path.file_name()?.to_str()?;
-            if fname.starts_with(filename) && fname.ends_with(".png") && !fname.contains("preview")
-            {
+            if fname.starts_with(filename) && fname.ends_with(&extension) {
                 Some(path)
             } else {
                 None
@@ -84,90 +163,230 @@ pub fn lilypond(lily_str: &str, filename: &str) -> Result<LilyOutput> {
 
     pages.sort_by(page_num_asc);
 
-    if !output_midi_path.exists() || !output_preview_path.exists() || pages.is_empty() {
+    let pdf_path = (format == OutputFormat::Pdf).then_some(output_pdf_path);
+    let pdf_missing = pdf_path.as_ref().is_some_and(|p| !p.exists());
+    if !output_midi_path.exists() || pdf_missing || pages.is_empty() {
         Err(AppError::Lily("Not all files were generated.".to_string()))?;
     }
 
     Ok(LilyOutput {
         pages,
-        preview_path: output_preview_path,
+        pdf_path,
         midi_path: output_midi_path,
+        format,
+        log: std::fs::read_to_string(dir.join(LOG_FILE)).unwrap_or_default(),
     })
 }
 
+/// Order pages by their number. Single page without a number comes first.
 #[allow(clippy::ptr_arg)]
 fn page_num_asc(a: &PathBuf, b: &PathBuf) -> Ordering {
-    static PAGE_REG: Lazy<Regex> = Lazy::new(|| Regex::new(r"^.*?(\d+)\.png$").unwrap());
+    static PAGE_REG: Lazy<Regex> = Lazy::new(|| Regex::new(r"^.*?-(?:page)?(\d+)\.\w+$").unwrap());
 
-    let af = a.file_name().unwrap().to_str().unwrap();
-    let bf = b.file_name().unwrap().to_str().unwrap();
+    let page_num = |path: &PathBuf| -> Option<i32> {
+        let fname = path.file_name()?.to_str()?;
+        PAGE_REG.captures(fname)?.captured_str(1).ok()?.parse().ok()
+    };
 
-    if !af.contains("page") {
-        return Ordering::Less;
-    }
+    page_num(a).cmp(&page_num(b))
+}
 
-    if !bf.contains("page") {
-        return Ordering::Greater;
-    }
+/// Store the MIDI file in the cache and return its path.
+pub fn midi_file(midi: &[u8], filename: &str) -> Result<PathBuf> {
+    let filename_midi = format!("{filename}.midi");
+    let key = RenderCache::key(&[midi, filename_midi.as_bytes()]);
 
-    match (PAGE_REG.captures(af), PAGE_REG.captures(bf)) {
-        (Some(ac), Some(bc)) => {
-            let pa: i32 = ac.captured_str(1).unwrap().parse().unwrap();
-            let pb: i32 = bc.captured_str(1).unwrap().parse().unwrap();
-            pa.cmp(&pb)
-        }
-        _ => Ordering::Equal,
-    }
+    let dir = RenderCache::new()?.entry(&key, |dir| {
+        let path = dir.join(&filename_midi);
+        std::fs::write(&path, midi).map_err(|e| AppError::build_path(&path, &e).into())
+    })?;
+
+    Ok(dir.join(filename_midi))
 }
 
 #[derive(Debug, Clone, Getters)]
-pub struct FluidsynthOutput {
+pub struct SynthOutput {
     wav_path: PathBuf,
 }
 
-pub fn fluidsynth(
-    sf_path: impl AsRef<Path>,
+/// Synthesizer rendering the MIDI file into audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum SynthBackend {
+    /// Synthesizer of this program, which doesn't need any external program.
+    #[default]
+    Builtin,
+    /// External `fluidsynth` program.
+    Fluidsynth,
+}
+
+/// SoundFont with the path it was loaded from.
+type LoadedSoundFont = (PathBuf, Arc<SoundFont>);
+
+/// Last loaded SoundFont, which is reused by the following renders.
+static SOUND_FONT: Lazy<Mutex<Option<LoadedSoundFont>>> = Lazy::new(Mutex::default);
+
+/// SoundFont at the given path, loaded only if it differs from the last loaded one.
+fn sound_font(sf_path: &Path) -> Result<Arc<SoundFont>> {
+    let mut loaded = SOUND_FONT.lock();
+    if let Some((path, sound_font)) = loaded.as_ref() {
+        if path == sf_path {
+            return Ok(sound_font.clone());
+        }
+    }
+
+    let sound_font = Arc::new(SoundFont::load(sf_path)?);
+    *loaded = Some((sf_path.to_owned(), sound_font.clone()));
+
+    Ok(sound_font)
+}
+
+/// Synthesize the MIDI file, reusing the cached audio of the same MIDI, sound font,
+/// synthesizer and settings. Without the sound font, the MIDI is rendered by the additive
+/// synthesizer.
+pub fn synthesize(
+    backend: SynthBackend,
+    sf_path: Option<&Path>,
     midi_path: impl AsRef<Path>,
     filename: &str,
-) -> Result<FluidsynthOutput> {
-    let cache_dir = directories::BaseDirs::new()
-        .ok_or(AppError::Other("Failed to create BaseDirs".to_string()))?
-        .cache_dir()
-        .join(DIR_NAME);
-
-    std::fs::create_dir_all(&cache_dir)?;
+    settings: &RenderSettings,
+) -> Result<SynthOutput> {
+    let midi_path = midi_path.as_ref();
+
+    let midi = std::fs::read(midi_path).map_err(|e| AppError::build_path(midi_path, &e))?;
+    let mut instrument = b"Additive".to_vec();
+    if let Some(sf_path) = sf_path {
+        let sf_meta = std::fs::metadata(sf_path).map_err(|e| AppError::build_path(sf_path, &e))?;
+        let sf_modified = sf_meta
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+
+        instrument = format!("{backend:?}").into_bytes();
+        instrument.extend(sf_path.as_os_str().as_encoded_bytes());
+        instrument.extend(sf_meta.len().to_le_bytes());
+        instrument.extend(sf_modified.as_nanos().to_le_bytes());
+    }
+    let key = RenderCache::key(&[
+        &midi,
+        &instrument,
+        filename.as_bytes(),
+        format!("{settings:?}").as_bytes(),
+    ]);
 
     let filename_wav = format!("{filename}.wav");
-    let wav_path = cache_dir.join(&filename_wav);
+    let dir = RenderCache::new()?.entry(&key, |dir| match (backend, sf_path) {
+        (SynthBackend::Fluidsynth, Some(sf_path)) => {
+            run_fluidsynth(dir, sf_path, midi_path, &filename_wav, settings)
+        }
+        _ => builtin_synthesizer(sf_path, &midi, settings)?.save_wav(dir.join(&filename_wav)),
+    })?;
+
+    Ok(SynthOutput {
+        wav_path: dir.join(filename_wav),
+    })
+}
+
+/// Synthesizer of the backend, which renders the MIDI progressively in this process, so its
+/// audio can be streamed. FluidSynth only writes the WAV file, so there is none for it.
+pub fn synthesizer(
+    backend: SynthBackend,
+    sf_path: Option<&Path>,
+    midi: &[u8],
+    settings: &RenderSettings,
+) -> Result<Option<Synthesizer>> {
+    match (backend, sf_path) {
+        (SynthBackend::Fluidsynth, Some(_)) => Ok(None),
+        _ => builtin_synthesizer(sf_path, midi, settings).map(Some),
+    }
+}
+
+/// Built-in synthesizer of the MIDI with the sound font, or the additive synthesizer without
+/// it.
+fn builtin_synthesizer(
+    sf_path: Option<&Path>,
+    midi: &[u8],
+    settings: &RenderSettings,
+) -> Result<Synthesizer> {
+    let notes = MidiReader.read(midi)?;
+    Ok(match sf_path {
+        Some(sf_path) => Synthesizer::with_settings(sound_font(sf_path)?, notes, *settings),
+        None => Synthesizer::with_settings(Instrument::Additive, notes, *settings),
+    })
+}
 
+/// Render the MIDI into the stereo frames with the render settings. Without the sound font,
+/// the MIDI is rendered by the additive synthesizer.
+pub fn render_audio(
+    backend: SynthBackend,
+    sf_path: Option<&Path>,
+    midi: &[u8],
+    settings: &RenderSettings,
+) -> Result<Vec<[f32; 2]>> {
+    let sf_path = match (backend, sf_path) {
+        (SynthBackend::Fluidsynth, Some(sf_path)) => sf_path,
+        _ => return Ok(builtin_synthesizer(sf_path, midi, settings)?.frames()),
+    };
+
+    let midi_path = midi_file(midi, "export")?;
+    let output = synthesize(backend, Some(sf_path), midi_path, "export", settings)?;
+
+    let wav_path = output.wav_path();
+    let wav = File::open(wav_path).map_err(|e| AppError::build_path(wav_path, &e))?;
+    let decoder = rodio::Decoder::new_wav(std::io::BufReader::new(wav))
+        .map_err(|e| AppError::Audio(e.to_string()))?;
+    let samples: Vec<f32> = decoder.map(|s| s as f32 / 32768.0).collect();
+
+    Ok(samples.chunks_exact(2).map(|s| [s[0], s[1]]).collect())
+}
+
+/// Gain of FluidSynth, which matches the gain of the built-in synthesizer at 0 dB.
+const FLUIDSYNTH_GAIN: f32 = 0.2;
+
+fn run_fluidsynth(
+    dir: &Path,
+    sf_path: &Path,
+    midi_path: &Path,
+    filename_wav: &str,
+    settings: &RenderSettings,
+) -> Result<()> {
+    let switch = |on: bool| if on { "1" } else { "0" };
     let output = Command::new("fluidsynth")
-        .current_dir(cache_dir)
+        .current_dir(dir)
         .args([
             "-ni",
-            sf_path.as_ref().to_string_lossy().to_string().as_str(),
-            midi_path.as_ref().to_string_lossy().to_string().as_str(),
+            sf_path.to_string_lossy().to_string().as_str(),
+            midi_path.to_string_lossy().to_string().as_str(),
             "-F",
-            filename_wav.as_str(),
+            filename_wav,
             "-r",
-            "44100",
+            settings.sample_rate.to_string().as_str(),
+            "-g",
+            (FLUIDSYNTH_GAIN * 10f32.powf(settings.gain / 20.0))
+                .to_string()
+                .as_str(),
+            "-R",
+            switch(settings.reverb),
+            "-C",
+            switch(settings.chorus),
         ])
         .output()?;
 
     if !output.status.success() {
-        Err(AppError::Lily(format!(
-            "Exit code: {0}, Stdout: \n{1}",
-            output.status.code().unwrap(),
-            String::from_utf8(output.stdout).unwrap()
+        // Status without the exit code names the signal, which terminated the process.
+        Err(AppError::Fluidsynth(format!(
+            "{0}, Stdout: \n{1}",
+            output.status,
+            String::from_utf8_lossy(&output.stdout)
         )))?;
     }
 
-    if !wav_path.exists() {
+    if !dir.join(filename_wav).exists() {
         Err(AppError::Fluidsynth(
             "WAV file was not generated.".to_string(),
         ))?;
     }
 
-    Ok(FluidsynthOutput { wav_path })
+    Ok(())
 }
 
 pub fn section_name(ui: &mut egui::Ui, name: &str) {
@@ -229,22 +448,63 @@ impl<T, R: ToString> ToAsyncResult<T> for std::result::Result<T, R> {
     }
 }
 
-pub fn lilypond_async(lily_str: String, filename: String) -> Promise<AsyncResult<LilyOutput>> {
+pub fn lilypond_async(
+    lily_str: String,
+    filename: String,
+    format: OutputFormat,
+) -> Promise<AsyncResult<LilyOutput>> {
     poll_promise::Promise::spawn_thread("lilypond_command", move || {
-        lilypond(lily_str.as_ref(), filename.as_ref()).into()
+        lilypond(lily_str.as_ref(), filename.as_ref(), format).into()
     })
 }
 
-pub fn fluidsynth_async(
-    sf_path: &impl AsRef<Path>,
+pub fn synth_async(
+    backend: SynthBackend,
+    sf_path: Option<&Path>,
     midi_path: &impl AsRef<Path>,
     filename: &str,
-) -> Promise<AsyncResult<FluidsynthOutput>> {
-    let sf_path = sf_path.as_ref().to_path_buf();
+    settings: RenderSettings,
+) -> Promise<AsyncResult<SynthOutput>> {
+    let sf_path = sf_path.map(Path::to_path_buf);
     let midi_path = midi_path.as_ref().to_path_buf();
     let filename = filename.to_owned();
 
-    poll_promise::Promise::spawn_thread("lilypond_command", move || {
-        fluidsynth(sf_path, midi_path, filename.as_ref()).into()
+    poll_promise::Promise::spawn_thread("synth_command", move || {
+        synthesize(
+            backend,
+            sf_path.as_deref(),
+            midi_path,
+            filename.as_ref(),
+            &settings,
+        )
+        .into()
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_order() {
+        // Arrange
+        let mut pages: Vec<PathBuf> = ["score-page10.png", "score-page2.png", "score.png"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        let mut svg_pages: Vec<PathBuf> = ["score-3.svg", "score-1.svg", "score-2.svg"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+
+        // Act
+        pages.sort_by(page_num_asc);
+        svg_pages.sort_by(page_num_asc);
+
+        // Assert
+        assert_eq!(pages[0], PathBuf::from("score.png"));
+        assert_eq!(pages[2], PathBuf::from("score-page10.png"));
+        assert_eq!(svg_pages[0], PathBuf::from("score-1.svg"));
+        assert_eq!(svg_pages[2], PathBuf::from("score-3.svg"));
+    }
+}
@@ -0,0 +1,414 @@
+//! Export bundle definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use std::{
+    ffi::OsStr,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{write::DeflateEncoder, Compression};
+
+use crate::error::{AppError, Result};
+
+/// Name of the manifest file, which is the first file of the bundle.
+const MANIFEST_NAME: &str = "manifest.ron";
+
+/// Format, in which the bundle is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum BundleFormat {
+    #[default]
+    Tar,
+    /// Deflate compressed ZIP archive.
+    Zip,
+    /// Plain directory with the files of the bundle.
+    Directory,
+}
+
+impl BundleFormat {
+    /// Extension of the archive, the directory has none.
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            BundleFormat::Tar => Some("tar"),
+            BundleFormat::Zip => Some("zip"),
+            BundleFormat::Directory => None,
+        }
+    }
+}
+
+/// File of the bundle described in the manifest.
+#[derive(Debug, Clone, serde::Serialize)]
+struct ManifestFile {
+    name: String,
+    description: String,
+    size: usize,
+}
+
+/// Manifest listing the files of the bundle.
+#[derive(Debug, Clone, serde::Serialize)]
+struct Manifest {
+    application: &'static str,
+    version: &'static str,
+    title: String,
+    /// Creation time in seconds since the Unix epoch.
+    created: u64,
+    /// Seed of the L-system, which generated the bundled score.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    files: Vec<ManifestFile>,
+}
+
+/// File added to the bundle.
+#[derive(Debug, Clone)]
+struct BundleFile {
+    name: String,
+    description: String,
+    data: Vec<u8>,
+}
+
+/// Collection of the exported files with a manifest, which is written as an archive or
+/// a directory.
+///
+/// ```
+/// use music_sheet_gen::gui::utils::{Bundle, BundleFormat};
+///
+/// let mut bundle = Bundle::new("Song");
+/// bundle.add("grammar.txt", "Grammar", "F\nF -> FF % 1");
+///
+/// let dir = std::env::temp_dir().join(format!("bundle-doc-{}", std::process::id()));
+/// let path = bundle.save(&dir, BundleFormat::Zip).unwrap();
+/// assert_eq!(path, dir.with_extension("zip"));
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    title: String,
+    created: SystemTime,
+    seed: Option<u64>,
+    files: Vec<BundleFile>,
+}
+
+impl Bundle {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            created: SystemTime::now(),
+            seed: None,
+            files: vec![],
+        }
+    }
+
+    /// Record the seed of the L-system in the manifest.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    /// Add the data as a file with the name and its description for the manifest.
+    pub fn add(
+        &mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        data: impl Into<Vec<u8>>,
+    ) {
+        self.files.push(BundleFile {
+            name: name.into(),
+            description: description.into(),
+            data: data.into(),
+        });
+    }
+
+    /// Add the file at the path under its file name.
+    pub fn add_path(&mut self, path: &Path, description: impl Into<String>) -> Result<()> {
+        let name = path.file_name().ok_or(AppError::Path(
+            path.display().to_string(),
+            "No file name".to_owned(),
+        ))?;
+        let data = std::fs::read(path).map_err(|e| AppError::build_path(path, &e))?;
+        self.add(name.to_string_lossy(), description, data);
+
+        Ok(())
+    }
+
+    /// RON manifest of the bundle.
+    pub fn manifest(&self) -> Result<String> {
+        let manifest = Manifest {
+            application: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            title: self.title.clone(),
+            created: self
+                .created
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            seed: self.seed,
+            files: self
+                .files
+                .iter()
+                .map(|f| ManifestFile {
+                    name: f.name.clone(),
+                    description: f.description.clone(),
+                    size: f.data.len(),
+                })
+                .collect(),
+        };
+
+        Ok(ron::ser::to_string_pretty(
+            &manifest,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    /// Write the bundle in the format to the path, which gets the extension of the archive,
+    /// and return the written path.
+    pub fn save(&self, path: impl AsRef<Path>, format: BundleFormat) -> Result<PathBuf> {
+        let path = path.as_ref();
+        let path = match format.extension() {
+            Some(e) if path.extension() != Some(OsStr::new(e)) => path.with_added_extension(e),
+            _ => path.to_path_buf(),
+        };
+
+        let manifest = self.manifest()?;
+        let files: Vec<(&str, &[u8])> = std::iter::once((MANIFEST_NAME, manifest.as_bytes()))
+            .chain(self.files.iter().map(|f| (f.name.as_str(), &f.data[..])))
+            .collect();
+
+        match format {
+            BundleFormat::Tar => {
+                let file =
+                    std::fs::File::create(&path).map_err(|e| AppError::build_path(&path, &e))?;
+                self.write_tar(file, &files)?;
+            }
+            BundleFormat::Zip => {
+                let zip = self.zip(&files)?;
+                std::fs::write(&path, zip).map_err(|e| AppError::build_path(&path, &e))?;
+            }
+            BundleFormat::Directory => {
+                std::fs::create_dir_all(&path).map_err(|e| AppError::build_path(&path, &e))?;
+                for (name, data) in files {
+                    let file_path = path.join(name);
+                    std::fs::write(&file_path, data)
+                        .map_err(|e| AppError::build_path(&file_path, &e))?;
+                }
+            }
+        }
+
+        Ok(path)
+    }
+
+    fn write_tar(&self, out: impl Write, files: &[(&str, &[u8])]) -> Result<()> {
+        let mtime = self
+            .created
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let mut builder = tar::Builder::new(out);
+        for (name, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(mtime);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *data)?;
+        }
+        builder.finish()?;
+
+        Ok(())
+    }
+
+    /// ZIP archive of the files, which are deflated, unless it makes them bigger.
+    fn zip(&self, files: &[(&str, &[u8])]) -> Result<Vec<u8>> {
+        let (time, date) = dos_date_time(self.created);
+        let mut out = vec![];
+        let mut central = vec![];
+
+        for (name, data) in files {
+            let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+            encoder.write_all(data)?;
+            let deflated = encoder.finish()?;
+            let (method, compressed): (u16, &[u8]) = match deflated.len() < data.len() {
+                true => (8, &deflated),
+                false => (0, data),
+            };
+            let offset = out.len() as u32;
+
+            // Fields shared by the local and the central header from the version needed.
+            let mut common = vec![];
+            common.extend(20u16.to_le_bytes());
+            // Names are in UTF-8.
+            common.extend(0x0800u16.to_le_bytes());
+            common.extend(method.to_le_bytes());
+            common.extend(time.to_le_bytes());
+            common.extend(date.to_le_bytes());
+            common.extend(crc32fast::hash(data).to_le_bytes());
+            common.extend((compressed.len() as u32).to_le_bytes());
+            common.extend((data.len() as u32).to_le_bytes());
+            common.extend((name.len() as u16).to_le_bytes());
+            // No extra field.
+            common.extend(0u16.to_le_bytes());
+
+            out.extend(0x04034B50u32.to_le_bytes());
+            out.extend(&common);
+            out.extend(name.as_bytes());
+            out.extend(compressed);
+
+            central.extend(0x02014B50u32.to_le_bytes());
+            // Version made by.
+            central.extend(20u16.to_le_bytes());
+            central.extend(&common);
+            // No comment, first disk and no attributes.
+            central.extend([0; 10]);
+            central.extend(offset.to_le_bytes());
+            central.extend(name.as_bytes());
+        }
+
+        let central_offset = out.len() as u32;
+        out.extend(&central);
+
+        out.extend(0x06054B50u32.to_le_bytes());
+        // Number of this disk and of the disk with the central directory.
+        out.extend([0; 4]);
+        out.extend((files.len() as u16).to_le_bytes());
+        out.extend((files.len() as u16).to_le_bytes());
+        out.extend((central.len() as u32).to_le_bytes());
+        out.extend(central_offset.to_le_bytes());
+        // No comment.
+        out.extend(0u16.to_le_bytes());
+
+        Ok(out)
+    }
+}
+
+/// MS-DOS time and date of the UTC time used by ZIP.
+fn dos_date_time(time: SystemTime) -> (u16, u16) {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // Civil date from the days since the epoch by Howard Hinnant's algorithm.
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as u64;
+
+    let time = ((secs / 3600) << 11) | ((secs % 3600 / 60) << 5) | ((secs % 60) / 2);
+    let date = (year.saturating_sub(1980) << 9) | (month << 5) | day;
+
+    (time as u16, date as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("bundle-test-{name}-{}", std::process::id()))
+    }
+
+    fn bundle() -> Bundle {
+        let mut bundle = Bundle::new("Test");
+        bundle.add("score.ly", "Lilypond source", "{ c'4 }");
+        bundle.add("grammar.txt", "Grammar", "F\n".repeat(100));
+        bundle.set_seed(42);
+        bundle
+    }
+
+    #[test]
+    fn dos_dates() {
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(1709210096);
+
+        // 2024-02-29 12:34:56
+        assert_eq!(
+            dos_date_time(time),
+            ((12 << 11) | (34 << 5) | 28, (44 << 9) | (2 << 5) | 29)
+        );
+    }
+
+    #[test]
+    fn save_tar() {
+        // Arrange
+        let path = temp_path("tar");
+
+        // Act
+        let path = bundle().save(path, BundleFormat::Tar).unwrap();
+
+        // Assert
+        let file = std::fs::File::open(&path).unwrap();
+        let mut archive = tar::Archive::new(file);
+        let names: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().display().to_string())
+            .collect();
+        assert_eq!(names, ["manifest.ron", "score.ly", "grammar.txt"]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn save_zip() {
+        // Arrange
+        let path = temp_path("zip");
+
+        // Act
+        let path = bundle().save(path, BundleFormat::Zip).unwrap();
+
+        // Assert
+        let zip = std::fs::read(&path).unwrap();
+        let u16_at = |i: usize| u16::from_le_bytes([zip[i], zip[i + 1]]) as usize;
+        let u32_at = |i: usize| u32::from_le_bytes(zip[i..i + 4].try_into().unwrap()) as usize;
+        let end = zip.len() - 22;
+        assert_eq!(u32_at(end), 0x06054B50);
+        assert_eq!(u16_at(end + 10), 3);
+
+        // Second entry of the central directory is stored.
+        let first = u32_at(end + 16);
+        let second = first + 46 + u16_at(first + 28);
+        assert_eq!(&zip[second + 46..second + 54], b"score.ly");
+        assert_eq!(u16_at(second + 10), 0);
+        let local = u32_at(second + 42);
+        assert_eq!(&zip[local + 38..local + 45], b"{ c'4 }");
+        assert_eq!(u32_at(second + 16), crc32fast::hash(b"{ c'4 }") as usize);
+
+        // Third one is deflated.
+        let third = second + 46 + 8;
+        assert_eq!(u16_at(third + 10), 8);
+        assert!(u32_at(third + 20) < u32_at(third + 24));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn save_directory() {
+        // Arrange
+        let path = temp_path("dir");
+
+        // Act
+        let saved = bundle().save(&path, BundleFormat::Directory).unwrap();
+
+        // Assert
+        assert_eq!(saved, path);
+        let manifest = std::fs::read_to_string(path.join("manifest.ron")).unwrap();
+        assert!(manifest.contains("title: \"Test\""));
+        assert!(manifest.contains("seed: Some(42)"));
+        assert!(manifest.contains("name: \"grammar.txt\""));
+        assert!(manifest.contains("size: 200"));
+        assert_eq!(
+            std::fs::read_to_string(path.join("score.ly")).unwrap(),
+            "{ c'4 }"
+        );
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
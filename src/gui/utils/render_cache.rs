@@ -0,0 +1,219 @@
+//! Render cache definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use crate::{
+    error::{AppError, Result},
+    gui::DIR_NAME,
+};
+use std::{
+    fs::File,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::SystemTime,
+};
+
+/// Default limit of the total size of the cached files.
+pub const MAX_CACHE_SIZE: u64 = 256 * 1024 * 1024;
+
+/// File, whose modification time marks the last use of the entry.
+const USED_MARKER: &str = ".used";
+
+/// Directory of rendered outputs, where each entry is a directory named by a hash of its inputs.
+///
+/// Entries are built in a temporary directory and then renamed, so concurrent renders never
+/// see incomplete entries. When the cache grows over its size limit, the least recently
+/// used entries are removed.
+#[derive(Debug, Clone)]
+pub struct RenderCache {
+    root: PathBuf,
+    max_size: u64,
+}
+
+impl RenderCache {
+    /// Cache in the user cache directory.
+    pub fn new() -> Result<Self> {
+        let root = directories::BaseDirs::new()
+            .ok_or(AppError::Other("Failed to create BaseDirs".to_string()))?
+            .cache_dir()
+            .join(DIR_NAME);
+
+        Ok(Self::with_root(root, MAX_CACHE_SIZE))
+    }
+
+    pub fn with_root(root: PathBuf, max_size: u64) -> Self {
+        Self { root, max_size }
+    }
+
+    /// Key of the entry created from the given inputs.
+    pub fn key(inputs: &[&[u8]]) -> String {
+        let mut hasher = DefaultHasher::new();
+        inputs.hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// Directory of the entry with the given key. The entry is built by `build` in an empty
+    /// directory if it isn't cached yet.
+    pub fn entry(&self, key: &str, build: impl FnOnce(&Path) -> Result<()>) -> Result<PathBuf> {
+        let path = self.root.join(key);
+        if path.is_dir() {
+            Self::touch(&path)?;
+            return Ok(path);
+        }
+
+        static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let tmp = self.root.join(format!(
+            "{key}.tmp-{}-{}",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&tmp).map_err(|e| AppError::build_path(&tmp, &e))?;
+
+        if let Err(e) = build(&tmp) {
+            std::fs::remove_dir_all(&tmp)?;
+            return Err(e);
+        }
+
+        Self::touch(&tmp)?;
+        if std::fs::rename(&tmp, &path).is_err() {
+            // The same entry was finished by another render in the meantime.
+            std::fs::remove_dir_all(&tmp)?;
+        }
+
+        self.evict(&path)?;
+
+        Ok(path)
+    }
+
+    /// Mark the entry as just used.
+    fn touch(entry: &Path) -> Result<()> {
+        let marker = entry.join(USED_MARKER);
+        File::create(&marker).map_err(|e| AppError::build_path(&marker, &e))?;
+
+        Ok(())
+    }
+
+    /// Remove the least recently used entries until the cache fits into its size limit.
+    fn evict(&self, keep: &Path) -> Result<()> {
+        let mut entries = vec![];
+        for entry in std::fs::read_dir(&self.root)? {
+            let path = entry?.path();
+            if !path.is_dir() || path == keep || path.to_string_lossy().contains(".tmp-") {
+                continue;
+            }
+
+            let used = std::fs::metadata(path.join(USED_MARKER))
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            entries.push((used, dir_size(&path)?, path));
+        }
+
+        let mut size: u64 = dir_size(keep)? + entries.iter().map(|(_, s, _)| s).sum::<u64>();
+        entries.sort_by_key(|(used, _, _)| *used);
+
+        for (_, entry_size, path) in entries {
+            if size <= self.max_size {
+                break;
+            }
+
+            std::fs::remove_dir_all(&path)?;
+            size -= entry_size;
+        }
+
+        Ok(())
+    }
+}
+
+/// Total size of the files in the directory.
+fn dir_size(dir: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += match metadata.is_dir() {
+            true => dir_size(&entry.path())?,
+            false => metadata.len(),
+        };
+    }
+
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache(name: &str, max_size: u64) -> RenderCache {
+        let root =
+            std::env::temp_dir().join(format!("{DIR_NAME}-test-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        RenderCache::with_root(root, max_size)
+    }
+
+    fn write_entry(size: usize) -> impl FnOnce(&Path) -> Result<()> {
+        move |dir| {
+            std::fs::write(dir.join("out"), vec![0u8; size])?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn entry_is_reused() {
+        // Arrange
+        let cache = temp_cache("reuse", MAX_CACHE_SIZE);
+        let key = RenderCache::key(&[b"source", b"format"]);
+
+        // Act
+        let first = cache.entry(&key, write_entry(10)).unwrap();
+        let second = cache
+            .entry(&key, |_| Err(AppError::Other("Rebuilt".to_owned()))?)
+            .unwrap();
+
+        // Assert
+        assert_eq!(first, second);
+        assert!(first.join("out").exists());
+        assert_ne!(key, RenderCache::key(&[b"source", b"other format"]));
+
+        std::fs::remove_dir_all(&cache.root).unwrap();
+    }
+
+    #[test]
+    fn failed_build_is_not_cached() {
+        // Arrange
+        let cache = temp_cache("failed", MAX_CACHE_SIZE);
+
+        // Act
+        let result = cache.entry("key", |_| Err(AppError::Other("Failed".to_owned()))?);
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_dir(&cache.root).unwrap().count(), 0);
+
+        std::fs::remove_dir_all(&cache.root).unwrap();
+    }
+
+    #[test]
+    fn least_recently_used_evicted() {
+        // Arrange
+        let cache = temp_cache("evict", 250);
+        let a = cache.entry("a", write_entry(100)).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let b = cache.entry("b", write_entry(100)).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        cache.entry("a", write_entry(100)).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+
+        // Act
+        let c = cache.entry("c", write_entry(100)).unwrap();
+
+        // Assert
+        assert!(a.exists());
+        assert!(!b.exists());
+        assert!(c.exists());
+
+        std::fs::remove_dir_all(&cache.root).unwrap();
+    }
+}
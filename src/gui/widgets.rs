@@ -5,6 +5,7 @@
 
 mod audio_player;
 mod axiom_edit;
+mod duration_selector;
 mod length_selector;
 mod note_name_selector;
 mod octave_selector;
@@ -13,6 +14,7 @@ mod rule_sums;
 
 pub use audio_player::AudioPlayer;
 pub use axiom_edit::AxiomEdit;
+pub use duration_selector::DurationSelector;
 pub use length_selector::LengthSelector;
 pub use note_name_selector::NoteNameSelector;
 pub use octave_selector::OctaveSelector;
@@ -0,0 +1,80 @@
+//! Duration selector widget definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use crate::{
+    gui::widgets::LengthSelector,
+    notation::{Duration, Tuplet, MAX_DOTS},
+};
+
+/// Tuplets offered in the selector, the ratios can be further edited.
+const TUPLET_PRESETS: [(u8, u8); 4] = [(3, 2), (5, 4), (6, 4), (7, 8)];
+
+pub struct DurationSelector<'a> {
+    duration: &'a mut Duration,
+}
+
+impl<'a> DurationSelector<'a> {
+    pub fn new(duration: &'a mut Duration) -> Self {
+        Self { duration }
+    }
+
+    fn show_dots(&mut self, ui: &mut egui::Ui) {
+        egui::Frame::canvas(ui.style()).show(ui, |ui| {
+            for dots in 0..=MAX_DOTS {
+                ui.selectable_value(&mut self.duration.dots, dots, dots.to_string());
+            }
+        });
+    }
+
+    fn show_tuplet(&mut self, ui: &mut egui::Ui) {
+        let tuplet = &mut self.duration.tuplet;
+        let text = match tuplet {
+            Some(t) => format!("{}:{}", t.actual(), t.normal()),
+            None => "None".to_string(),
+        };
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt(ui.id().with("combo:tuplet"))
+                .selected_text(text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(tuplet, None, "None");
+                    for (actual, normal) in TUPLET_PRESETS {
+                        let t = Tuplet::new(actual, normal);
+                        ui.selectable_value(tuplet, t, format!("{actual}:{normal}"));
+                    }
+                });
+
+            if let Some(t) = tuplet {
+                let (mut actual, mut normal) = (t.actual(), t.normal());
+                ui.add(egui::DragValue::new(&mut actual).range(2..=32));
+                ui.label(":");
+                ui.add(egui::DragValue::new(&mut normal).range(1..=32));
+                *t = Tuplet::new(actual, normal).unwrap_or(*t);
+            }
+        });
+    }
+}
+
+impl<'a> egui::Widget for DurationSelector<'a> {
+    fn ui(mut self, ui: &mut egui::Ui) -> egui::Response {
+        egui::Grid::new(ui.unique_id())
+            .num_columns(2)
+            .spacing([10.0, 4.0])
+            .min_col_width(0.0)
+            .show(ui, |ui| {
+                ui.label("Base");
+                ui.add(LengthSelector::new(&mut self.duration.base));
+                ui.end_row();
+
+                ui.label("Dots");
+                self.show_dots(ui);
+                ui.end_row();
+
+                ui.label("Tuplet");
+                self.show_tuplet(ui);
+            })
+            .response
+    }
+}
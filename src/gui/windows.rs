@@ -4,6 +4,7 @@
 //! Jakub Kloub (xkloub03), VUT FIT
 
 mod control_panel;
+mod diagnostics;
 mod grammar_edit;
 mod interpret_parameters;
 mod logger;
@@ -11,6 +12,7 @@ mod score_visualizer;
 mod statistics;
 
 pub use control_panel::ControlPanel;
+pub use diagnostics::{written_by, Diagnostics, ScoreDiagnostic};
 pub use grammar_edit::GrammarEdit;
 pub use interpret_parameters::InterpretParameteres;
 pub use logger::Logger;
@@ -25,7 +25,11 @@ impl ControlPanel {
     pub fn step(&mut self, app_state: &mut GuiAppState, n: usize) {
         (0..n).for_each(|_| {
             self.prev_word = app_state.l_system.state().word().clone();
-            app_state.used_rules_history.push(app_state.l_system.step());
+            let rule_spans = app_state.l_system.step_traced();
+            app_state
+                .used_rules_history
+                .push(rule_spans.iter().map(|(_, r)| r.clone()).collect());
+            app_state.last_rule_spans = rule_spans;
         });
     }
 
@@ -37,6 +41,7 @@ impl ControlPanel {
             .set_word(self.prev_word.clone())
             .set_iter_num(iter_num - 1);
         app_state.used_rules_history.pop();
+        app_state.last_rule_spans.clear();
     }
 
     pub fn retry_step(&mut self, app_state: &mut GuiAppState) {
@@ -0,0 +1,154 @@
+//! Diagnostics window definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use crate::{
+    gui::{windows::DockableWindow, GuiAppState, TabType},
+    lily::{LilyDiagnostic, Severity, SourceMap, SymbolLocation},
+};
+
+/// Lilypond diagnostic mapped back to the score and the L-system word.
+#[derive(Debug, Clone)]
+pub struct ScoreDiagnostic {
+    pub diagnostic: LilyDiagnostic,
+    /// Symbol written at the position of the diagnostic.
+    pub location: Option<SymbolLocation>,
+    /// Lilypond source of the symbol.
+    pub symbol_source: Option<String>,
+    /// Indices of the L-system word characters, which wrote the symbol.
+    pub word_indices: Vec<usize>,
+}
+
+impl ScoreDiagnostic {
+    /// Parse the Lilypond output and map its diagnostics to the symbols.
+    ///
+    /// `word_indices` hold the word character of each symbol of the first stave.
+    pub fn map_all(
+        output: &str,
+        source_map: &SourceMap,
+        word_indices: &[Option<usize>],
+    ) -> Vec<Self> {
+        LilyDiagnostic::parse(output)
+            .into_iter()
+            .map(|diagnostic| {
+                let location = diagnostic
+                    .position
+                    .and_then(|p| source_map.locate(p))
+                    .cloned();
+                let symbol_source = location
+                    .as_ref()
+                    .and_then(|l| source_map.symbol_source(l))
+                    .map(str::to_owned);
+                let word_indices = location
+                    .as_ref()
+                    .map(|l| written_by(l, word_indices))
+                    .unwrap_or_default();
+
+                Self {
+                    diagnostic,
+                    location,
+                    symbol_source,
+                    word_indices,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Indices of the L-system word characters, which wrote the symbol.
+///
+/// `word_indices` hold the word character of each symbol of the first stave.
+pub fn written_by(location: &SymbolLocation, word_indices: &[Option<usize>]) -> Vec<usize> {
+    if location.stave != 0 {
+        return vec![];
+    }
+
+    location
+        .score_symbols
+        .clone()
+        .filter_map(|i| word_indices.get(i).copied().flatten())
+        .collect()
+}
+
+#[derive(Debug, Default)]
+pub struct Diagnostics;
+
+impl Diagnostics {
+    fn show_diagnostic(
+        ui: &mut egui::Ui,
+        app_state: &mut GuiAppState,
+        i: usize,
+        diagnostic: &ScoreDiagnostic,
+    ) {
+        let (severity_color, position) = (
+            match diagnostic.diagnostic.severity {
+                Severity::Error => ui.visuals().error_fg_color,
+                Severity::Warning => ui.visuals().warn_fg_color,
+            },
+            diagnostic
+                .diagnostic
+                .position
+                .map(|p| format!("{}:{}", p.line, p.column))
+                .unwrap_or_default(),
+        );
+
+        ui.colored_label(severity_color, diagnostic.diagnostic.severity.to_string());
+        ui.label(position);
+
+        let selected = app_state.selected_diagnostic == Some(i);
+        if ui
+            .selectable_label(selected, &diagnostic.diagnostic.message)
+            .clicked()
+        {
+            app_state.selected_diagnostic = if selected { None } else { Some(i) };
+            app_state.highlighted_word = match selected {
+                true => vec![],
+                false => diagnostic.word_indices.clone(),
+            };
+        }
+
+        match (&diagnostic.location, &diagnostic.symbol_source) {
+            (Some(location), Some(source)) => {
+                ui.monospace(source).on_hover_text(format!(
+                    "Stave {}, symbol {}, L-system characters: {:?}",
+                    location.stave + 1,
+                    location.symbol + 1,
+                    diagnostic.word_indices
+                ));
+            }
+            _ => {
+                ui.label("");
+            }
+        }
+        ui.end_row();
+    }
+}
+
+impl DockableWindow for Diagnostics {
+    fn name(&self) -> &'static str {
+        TabType::Diagnostics.as_str()
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui, app_state: &mut GuiAppState) {
+        if app_state.diagnostics.is_empty() {
+            ui.centered_and_justified(|ui| {
+                ui.label("No Lilypond errors or warnings");
+            });
+            return;
+        }
+
+        let diagnostics = app_state.diagnostics.clone();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("diagnostics")
+                .num_columns(4)
+                .striped(true)
+                .spacing([20.0, 4.0])
+                .show(ui, |ui| {
+                    for (i, diagnostic) in diagnostics.iter().enumerate() {
+                        Self::show_diagnostic(ui, app_state, i, diagnostic);
+                    }
+                });
+        });
+    }
+}
@@ -19,6 +19,7 @@ use crate::{
 pub struct GrammarEdit {
     rule_edit_state: RuleEditState,
     axiom: String,
+    seed: u64,
 }
 
 impl GrammarEdit {
@@ -26,12 +27,14 @@ impl GrammarEdit {
         Self {
             rule_edit_state: RuleEditState::new().with_rules(&app_state.rules),
             axiom: app_state.axiom.clone(),
+            seed: app_state.seed,
         }
     }
 
     fn apply(&mut self, app_state: &mut GuiAppState) -> Result<()> {
         self.rule_edit_state.check()?;
         app_state.rules = CSSLRuleSet::new(self.rule_edit_state.rules.clone());
+        app_state.seed = self.seed;
         app_state.apply_changes()?;
         app_state.axiom = self.axiom.clone();
 
@@ -71,6 +74,15 @@ impl View for GrammarEdit {
                     ui.add(AxiomEdit::new(&mut self.axiom));
                 });
                 ui.end_row();
+
+                ui.label("Seed");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut self.seed));
+                    if ui.button("Random").clicked() {
+                        self.seed = rand::random();
+                    }
+                });
+                ui.end_row();
             });
 
         ui.separator();
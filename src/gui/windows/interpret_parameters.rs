@@ -6,20 +6,145 @@
 use crate::{
     gui::{
         gui_app::GuiAppState,
-        toast, utils,
-        widgets::{LengthSelector, NoteNameSelector, OctaveSelector},
+        utils::{self, BundleFormat, MidiSource, OutputFormat, SynthBackend},
+        widgets::{DurationSelector, LengthSelector, NoteNameSelector, OctaveSelector},
         windows::DockableWindow,
         TabType,
     },
-    lsystem::interpret::ScaleType,
-    notation::KeySignatureType,
+    harmony::{AccompanimentPattern, HarmonicRhythm},
+    lily::{LilyLanguage, LilyLayout, Orientation, PaperSize},
+    lsystem::interpret::{CustomScale, MusicIntInfo, ScaleType, Spelling},
+    notation::{KeySignatureType, Octave, ScoreInfo},
+    synth::{AudioFormat, BitDepth},
 };
 
 #[derive(Debug, Default)]
-pub struct InterpretParameteres;
+pub struct InterpretParameteres {
+    /// Currently edited steps of the custom scale.
+    pattern_text: String,
+    /// Error of the last parsed `pattern_text`.
+    pattern_error: Option<String>,
+}
 
 impl InterpretParameteres {
+    pub fn new(app_state: &GuiAppState) -> Self {
+        Self {
+            pattern_text: app_state.music_int_info.custom_scale.pattern(),
+            pattern_error: None,
+        }
+    }
+
+    fn show_custom_scale(&mut self, ui: &mut egui::Ui, info: &mut MusicIntInfo) {
+        ui.label("Steps");
+        ui.vertical(|ui| {
+            let response = ui.text_edit_singleline(&mut self.pattern_text);
+            if response.changed() {
+                let spelling = info.custom_scale.spelling;
+                match CustomScale::parse_steps(&self.pattern_text)
+                    .and_then(|steps| CustomScale::new(steps, spelling))
+                {
+                    Ok(scale) => {
+                        info.custom_scale = scale;
+                        self.pattern_error = None;
+                    }
+                    Err(e) => self.pattern_error = Some(e.to_string()),
+                }
+            } else if !response.has_focus() && self.pattern_error.is_none() {
+                // Scale could have been changed by loading a different state.
+                self.pattern_text = info.custom_scale.pattern();
+            }
+
+            if let Some(err) = &self.pattern_error {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+            }
+        });
+        ui.end_row();
+
+        ui.label("Spelling");
+        ui.horizontal(|ui| {
+            let spelling = &mut info.custom_scale.spelling;
+            ui.selectable_value(spelling, Spelling::Key, "Key");
+            ui.selectable_value(spelling, Spelling::Sharps, "Sharps");
+            ui.selectable_value(spelling, Spelling::Flats, "Flats");
+        });
+        ui.end_row();
+
+        ui.label("Preview");
+        let notes = info
+            .custom_scale
+            .notes(info.key_signature, Octave::O4)
+            .iter()
+            .map(|p| p.ext.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        ui.label(notes);
+        ui.end_row();
+    }
+
+    /// Text edit of an optional value, where empty text means no value.
+    fn optional_text_edit(ui: &mut egui::Ui, value: &mut Option<String>) {
+        let mut text = value.clone().unwrap_or_default();
+        if ui.text_edit_singleline(&mut text).changed() {
+            *value = Some(text).filter(|t| !t.trim().is_empty());
+        }
+    }
+
+    fn show_score_info(&mut self, ui: &mut egui::Ui, info: &mut ScoreInfo) {
+        utils::section_name(ui, "Score info");
+
+        ui.label("Title");
+        Self::optional_text_edit(ui, &mut info.name);
+        ui.end_row();
+
+        ui.label("Composer");
+        Self::optional_text_edit(ui, &mut info.author);
+        ui.end_row();
+
+        ui.label("Arranger");
+        Self::optional_text_edit(ui, &mut info.transcriber);
+        ui.end_row();
+    }
+
+    fn show_layout(&mut self, ui: &mut egui::Ui, layout: &mut LilyLayout) {
+        ui.label("Paper size");
+        ui.horizontal(|ui| {
+            let paper_size = &mut layout.paper_size;
+            egui::ComboBox::from_id_salt("combo:paper_size")
+                .selected_text(paper_size.name())
+                .show_ui(ui, |ui| {
+                    for p in PaperSize::ALL {
+                        ui.selectable_value(paper_size, p, p.name());
+                    }
+                });
+
+            let orientation = &mut layout.orientation;
+            ui.selectable_value(orientation, Orientation::Portrait, "Portrait");
+            ui.selectable_value(orientation, Orientation::Landscape, "Landscape");
+        });
+        ui.end_row();
+
+        ui.label("Staff size");
+        ui.add(egui::Slider::new(&mut layout.staff_size, 11..=26));
+        ui.end_row();
+
+        ui.label("Ragged last line");
+        ui.checkbox(&mut layout.ragged_last, "");
+        ui.end_row();
+
+        ui.label("Systems per page");
+        ui.horizontal(|ui| {
+            let mut fixed = layout.systems_per_page.is_some();
+            ui.checkbox(&mut fixed, "");
+            let mut count = layout.systems_per_page.unwrap_or(5);
+            ui.add_enabled(fixed, egui::Slider::new(&mut count, 1..=12));
+            layout.systems_per_page = fixed.then_some(count);
+        });
+        ui.end_row();
+    }
+
     fn show_grid_contents(&mut self, ui: &mut egui::Ui, app_state: &mut GuiAppState) {
+        self.show_score_info(ui, &mut app_state.score_info);
+
         let info = &mut app_state.music_int_info;
 
         utils::section_name(ui, "Key");
@@ -39,31 +164,33 @@ impl InterpretParameteres {
         ui.label("Type");
         ui.horizontal(|ui| {
             let key_type = &mut info.key_signature.signature_type;
-            ui.selectable_value(key_type, KeySignatureType::Maj, "Major");
-            ui.selectable_value(key_type, KeySignatureType::Min, "Minor");
-
-            if info.scale_type == ScaleType::JazzLike && *key_type == KeySignatureType::Min {
-                toast::show_info(&"Minor + Jazz-like isn't currently supported.");
-                info.scale_type = ScaleType::Basic;
-            }
+            egui::ComboBox::from_id_salt("combo:key_type")
+                .selected_text(key_type.name())
+                .show_ui(ui, |ui| {
+                    for t in KeySignatureType::ALL {
+                        ui.selectable_value(key_type, t, t.name());
+                    }
+                });
         });
         ui.end_row();
 
         ui.label("Scale type");
         ui.horizontal(|ui| {
             let scale_type = &mut info.scale_type;
-            ui.selectable_value(scale_type, ScaleType::Basic, "Basic");
-            ui.selectable_value(scale_type, ScaleType::JazzLike, "Jazz-like");
-
-            if *scale_type == ScaleType::JazzLike
-                && info.key_signature.signature_type == KeySignatureType::Min
-            {
-                toast::show_info(&"Minor + Jazz-like isn't currently supported.");
-                info.key_signature.signature_type = KeySignatureType::Maj;
-            }
+            egui::ComboBox::from_id_salt("combo:scale_type")
+                .selected_text(scale_type.name())
+                .show_ui(ui, |ui| {
+                    for t in ScaleType::ALL {
+                        ui.selectable_value(scale_type, t, t.name());
+                    }
+                });
         });
         ui.end_row();
 
+        if info.scale_type == ScaleType::Custom {
+            self.show_custom_scale(ui, info);
+        }
+
         utils::section_name(ui, "First note");
 
         ui.label("Pitch note");
@@ -75,7 +202,7 @@ impl InterpretParameteres {
         ui.end_row();
 
         ui.label("Length");
-        ui.add(LengthSelector::new(&mut info.first_note.duration));
+        ui.add(DurationSelector::new(&mut info.first_note.duration));
         ui.end_row();
 
         utils::section_name(ui, "Time signature");
@@ -89,20 +216,153 @@ impl InterpretParameteres {
 
         ui.label("Denom");
         ui.add(LengthSelector::new(
-            &mut info.time_signature.single_beat_note,
+            &mut info.time_signature.single_beat_note.base,
         ));
         ui.end_row();
 
         utils::section_name(ui, "Tempo");
 
         ui.label("Note length");
-        ui.add(LengthSelector::new(&mut info.tempo.note_length));
+        ui.add(DurationSelector::new(&mut info.tempo.note_length));
         ui.end_row();
 
         ui.label("Speed");
         ui.add(egui::Slider::new(&mut info.tempo.speed, 60..=220));
         ui.end_row();
 
+        utils::section_name(ui, "Accompaniment");
+
+        ui.label("Enabled");
+        ui.checkbox(&mut app_state.harmonize, "");
+        ui.end_row();
+
+        ui.label("Pattern");
+        ui.horizontal(|ui| {
+            let pattern = &mut app_state.harmonizer.pattern;
+            ui.selectable_value(pattern, AccompanimentPattern::Block, "Block");
+            ui.selectable_value(pattern, AccompanimentPattern::Alberti, "Alberti");
+            ui.selectable_value(pattern, AccompanimentPattern::Arpeggio, "Arpeggio");
+        });
+        ui.end_row();
+
+        ui.label("Chord change");
+        ui.horizontal(|ui| {
+            let rhythm = &mut app_state.harmonizer.rhythm;
+            ui.selectable_value(rhythm, HarmonicRhythm::Bar, "Bar");
+            ui.selectable_value(rhythm, HarmonicRhythm::HalfBar, "Half bar");
+        });
+        ui.end_row();
+
+        utils::section_name(ui, "Lilypond output");
+
+        ui.label("Note names");
+        let language = &mut app_state.lily_language;
+        egui::ComboBox::from_id_salt("combo:lily_language")
+            .selected_text(language.name())
+            .show_ui(ui, |ui| {
+                for l in LilyLanguage::ALL {
+                    ui.selectable_value(language, l, l.name());
+                }
+            });
+        ui.end_row();
+
+        ui.label("Relative octaves");
+        ui.checkbox(&mut app_state.lily_relative, "");
+        ui.end_row();
+
+        ui.label("Format");
+        ui.horizontal(|ui| {
+            let format = &mut app_state.lily_format;
+            let dpi = match format {
+                OutputFormat::Png { dpi } => *dpi,
+                _ => OutputFormat::default_dpi(),
+            };
+            ui.selectable_value(format, OutputFormat::Png { dpi }, "PNG");
+            ui.selectable_value(format, OutputFormat::Svg, "SVG");
+            ui.selectable_value(format, OutputFormat::Pdf, "PDF");
+
+            if let OutputFormat::Png { dpi } = format {
+                ui.add(egui::Slider::new(dpi, 50..=300).suffix(" dpi"));
+            }
+        });
+        ui.end_row();
+
+        utils::section_name(ui, "Audio");
+
+        ui.label("MIDI source");
+        ui.horizontal(|ui| {
+            let source = &mut app_state.midi_source;
+            ui.selectable_value(source, MidiSource::Native, "Score");
+            ui.selectable_value(source, MidiSource::Lilypond, "Lilypond");
+        });
+        ui.end_row();
+
+        ui.label("Synthesizer")
+            .on_hover_text("Without a SoundFont, the additive piano synthesizer is used.");
+        ui.horizontal(|ui| {
+            let backend = &mut app_state.synth_backend;
+            ui.selectable_value(backend, SynthBackend::Builtin, "Built-in");
+            ui.selectable_value(backend, SynthBackend::Fluidsynth, "FluidSynth");
+        });
+        ui.end_row();
+
+        let render = &mut app_state.audio_render;
+        ui.label("Sample rate");
+        ui.horizontal(|ui| {
+            for rate in [22050, 44100, 48000, 96000] {
+                let text = format!("{} kHz", rate as f32 / 1000.0);
+                ui.selectable_value(&mut render.sample_rate, rate, text);
+            }
+        });
+        ui.end_row();
+
+        ui.label("Gain");
+        ui.add(egui::Slider::new(&mut render.gain, -24.0..=12.0).suffix(" dB"));
+        ui.end_row();
+
+        ui.label("Effects");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut render.reverb, "Reverb");
+            ui.checkbox(&mut render.chorus, "Chorus");
+        });
+        ui.end_row();
+
+        let writer = &mut app_state.audio_writer;
+        ui.label("Export format");
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut writer.format, AudioFormat::Wav, "WAV");
+            ui.selectable_value(&mut writer.format, AudioFormat::Flac, "FLAC");
+            ui.selectable_value(&mut writer.format, AudioFormat::OggVorbis, "OGG Vorbis");
+        });
+        ui.end_row();
+
+        ui.label("Bit depth");
+        ui.add_enabled_ui(writer.format != AudioFormat::OggVorbis, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut writer.bit_depth, BitDepth::Int16, "16-bit");
+                ui.selectable_value(&mut writer.bit_depth, BitDepth::Int24, "24-bit");
+                ui.selectable_value(&mut writer.bit_depth, BitDepth::Float32, "32-bit float")
+                    .on_hover_text("FLAC stores the floating point samples as 24-bit.");
+            });
+        });
+        ui.end_row();
+
+        ui.label("Normalize");
+        ui.checkbox(&mut writer.normalize, "");
+        ui.end_row();
+
+        utils::section_name(ui, "Export");
+
+        ui.label("Bundle format")
+            .on_hover_text("Format of the project bundle written by File > Export.");
+        ui.horizontal(|ui| {
+            let format = &mut app_state.bundle_format;
+            ui.selectable_value(format, BundleFormat::Tar, "TAR");
+            ui.selectable_value(format, BundleFormat::Zip, "ZIP");
+            ui.selectable_value(format, BundleFormat::Directory, "Directory");
+        });
+        ui.end_row();
+
         utils::section_name(ui, "Lilypond sanitizer");
 
         ui.label("Max line bars");
@@ -118,6 +378,8 @@ impl InterpretParameteres {
             1..=70,
         ));
         ui.end_row();
+
+        self.show_layout(ui, &mut app_state.lily_layout);
     }
 }
 
@@ -9,18 +9,22 @@ use crate::{
         gui_app::GuiAppState,
         toast,
         utils::{
-            fluidsynth_async, lilypond_async, AsyncResult, InMemoryTexture, Texture, ToAsyncResult,
+            self, lilypond_async, midi_file, synth_async, AsyncResult, InMemoryTexture, MidiSource,
+            OutputFormat, SynthBackend, Texture, ToAsyncResult,
         },
-        widgets::AudioPlayer,
-        windows::DockableWindow,
+        widgets::{AudioPlayer, NoteNameSelector},
+        windows::{written_by, DockableWindow, ScoreDiagnostic},
         TabType,
     },
-    lily::Lilypond,
+    lily::{Lilypond, Severity, SourceMap, SvgLink, SvgLinks},
     lsystem::{
         interpret::{Interpret, MusicInterpret},
         LSystem,
     },
+    midi::MidiWriter,
+    notation::{Interval, Transposition},
     sanitizer::LilySanitizer,
+    synth::{RenderSettings, Synthesizer},
     utils::{AudioController, AudioData},
     Arguments,
 };
@@ -30,17 +34,24 @@ use poll_promise::Promise;
 use std::{
     cmp::{max, min},
     ops::DerefMut,
-    path::PathBuf,
-    sync::Arc,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc},
 };
 
+const ZOOM_STEP: f32 = 1.25;
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 5.0;
+/// Distance in staff spaces, from which a click selects the nearest note.
+const CLICK_DISTANCE: f32 = 1.5;
+/// Offset of the note head center from its origin in staff spaces.
+const NOTE_HEAD_CENTER: f32 = 0.6;
+
 #[derive(PartialEq, Debug)]
 enum ScoreRefreshState {
     Begin,
     LilyCompilation,
     TextureLoading,
-    Fluidsynth,
-    AudioLoading,
+    Synthesis,
     Done,
 }
 
@@ -53,69 +64,171 @@ impl std::fmt::Display for ScoreRefreshState {
                 ScoreRefreshState::Begin => "",
                 ScoreRefreshState::LilyCompilation => "Lilypond compilation",
                 ScoreRefreshState::TextureLoading => "Loading generated pages",
-                ScoreRefreshState::Fluidsynth => "Converting MIDI to audio",
-                ScoreRefreshState::AudioLoading => "Loading audio",
+                ScoreRefreshState::Synthesis => "Converting MIDI to audio",
                 ScoreRefreshState::Done => "Done",
             }
         )
     }
 }
 
-struct RefreshOutput {
-    pages_data: Vec<InMemoryTexture>,
+/// Page loaded in the background.
+enum PageData {
+    Raster(InMemoryTexture),
+    Vector(Vec<u8>, SvgLinks),
+}
+
+/// Page displayed in the visualizer.
+enum Page {
+    Raster(Texture),
+    /// URI and content of the SVG file, which is rasterized by egui in the displayed size,
+    /// and its point-and-click links.
+    Vector(String, egui::load::Bytes, SvgLinks),
+}
+
+impl std::fmt::Debug for Page {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Page::Raster(texture) => f.debug_tuple("Raster").field(texture).finish(),
+            Page::Vector(uri, _, _) => f.debug_tuple("Vector").field(uri).finish(),
+        }
+    }
+}
+
+/// Synthesized audio of the score.
+struct AudioOutput {
     audio_data: AudioData,
-    score_image_paths: Vec<PathBuf>,
     score_audio_path: PathBuf,
 }
 
+struct RefreshOutput {
+    pages_data: Vec<PageData>,
+    /// Audio synthesized from the Lilypond MIDI, if it was requested.
+    audio: Option<AudioOutput>,
+    score_image_paths: Vec<PathBuf>,
+    score_pdf_path: Option<PathBuf>,
+    lily_log: String,
+}
+
+/// Synthesizer and sound font rendering the score audio.
+#[derive(Debug, Clone)]
+struct SynthSettings {
+    backend: SynthBackend,
+    /// SoundFont passed on the command line, otherwise the additive synthesizer is used.
+    sf_path: Option<PathBuf>,
+    render: RenderSettings,
+    /// Receives the synthesizer of the built-in backends, which is played while the audio is
+    /// rendered.
+    stream: mpsc::Sender<Synthesizer>,
+}
+
+fn synthesize(settings: &SynthSettings, midi_path: &Path) -> AsyncResult<AudioOutput> {
+    let sf_path = settings.sf_path.as_deref();
+    let midi = std::fs::read(midi_path).into_async_result()?;
+    let synthesizer = utils::synthesizer(settings.backend, sf_path, &midi, &settings.render)
+        .into_async_result()?;
+    if let Some(synthesizer) = synthesizer {
+        // Receiver is dropped, when the next refresh started meanwhile.
+        let _ = settings.stream.send(synthesizer);
+    }
+
+    let synth_output = synth_async(
+        settings.backend,
+        sf_path,
+        &midi_path,
+        "score",
+        settings.render,
+    )
+    .block_and_take()?;
+    let audio_data = AudioData::load_from(synth_output.wav_path()).into_async_result()?;
+
+    AsyncResult::Ok(AudioOutput {
+        audio_data,
+        score_audio_path: synth_output.wav_path().to_owned(),
+    })
+}
+
+/// Synthesize the MIDI written from the score, independently of the engraving.
+fn audio_async(midi: Vec<u8>, settings: SynthSettings) -> Promise<AsyncResult<AudioOutput>> {
+    poll_promise::Promise::spawn_thread("audio_async", move || {
+        let midi_path = midi_file(&midi, "score").into_async_result()?;
+        synthesize(&settings, &midi_path)
+    })
+}
+
+/// Engrave the score and synthesize the Lilypond MIDI, if `synth` is given.
 fn refresh_async(
     async_state: Arc<Mutex<ScoreRefreshState>>,
     lily_input: String,
-    sf_path: PathBuf,
+    format: OutputFormat,
+    synth: Option<SynthSettings>,
 ) -> Promise<AsyncResult<RefreshOutput>> {
     poll_promise::Promise::spawn_thread("refresh_async", move || {
         *async_state.lock().deref_mut() = ScoreRefreshState::LilyCompilation;
-        let lily_output = lilypond_async(lily_input, "score".to_owned()).block_and_take()?;
+        let lily_output =
+            lilypond_async(lily_input, "score".to_owned(), format).block_and_take()?;
 
         *async_state.lock().deref_mut() = ScoreRefreshState::TextureLoading;
-        let mut textures: Vec<InMemoryTexture> = vec![];
-        for t_res in lily_output.pages().iter().map(InMemoryTexture::load_from) {
-            textures.push(t_res.into_async_result()?);
+        let mut pages: Vec<PageData> = vec![];
+        for path in lily_output.pages().iter() {
+            pages.push(match lily_output.format() {
+                OutputFormat::Svg => {
+                    let svg = std::fs::read(path).into_async_result()?;
+                    let links = SvgLinks::parse(&String::from_utf8_lossy(&svg));
+                    PageData::Vector(svg, links)
+                }
+                _ => PageData::Raster(InMemoryTexture::load_from(path).into_async_result()?),
+            });
         }
 
-        *async_state.lock().deref_mut() = ScoreRefreshState::Fluidsynth;
-        let fluid_output =
-            fluidsynth_async(&sf_path, lily_output.midi_path(), "score").block_and_take()?;
-
-        *async_state.lock().deref_mut() = ScoreRefreshState::AudioLoading;
-        let audio_data = AudioData::load_from(fluid_output.wav_path()).into_async_result()?;
+        let audio = match synth {
+            Some(settings) => {
+                *async_state.lock().deref_mut() = ScoreRefreshState::Synthesis;
+                Some(synthesize(&settings, lily_output.midi_path())?)
+            }
+            None => None,
+        };
 
         *async_state.lock().deref_mut() = ScoreRefreshState::Done;
 
         AsyncResult::Ok(RefreshOutput {
-            pages_data: textures,
-            audio_data,
+            pages_data: pages,
+            audio,
             score_image_paths: lily_output.pages().to_owned(),
-            score_audio_path: fluid_output.wav_path().to_owned(),
+            score_pdf_path: lily_output.pdf_path().to_owned(),
+            lily_log: lily_output.log().to_owned(),
         })
     })
 }
 
 pub struct ScoreVisualizer {
-    pub images: Option<Vec<Texture>>,
+    pages: Option<Vec<Page>>,
+    /// Scale of the displayed page, where 1.0 fits the page into the window.
+    zoom: f32,
+    /// Number of finished refreshes, which keeps the URIs of vector pages unique.
+    refresh_count: usize,
     last_step_num: i32,
     audio_controller: AudioController,
-    sf_path: PathBuf,
+    sf_path: Option<PathBuf>,
+    /// Source of the score being rendered, used to map the Lilypond diagnostics.
+    source_map: SourceMap,
+    /// L-system word character of each symbol of the first stave.
+    word_indices: Vec<Option<usize>>,
 
     selected_image: Option<usize>,
+    /// Clicked note and the index of its page.
+    selected_link: Option<(usize, SvgLink)>,
     refresh_state: Option<Arc<Mutex<ScoreRefreshState>>>,
     refresh_promise: Option<Promise<AsyncResult<RefreshOutput>>>,
+    audio_promise: Option<Promise<AsyncResult<AudioOutput>>>,
+    /// Synthesizer of the audio being rendered.
+    audio_stream: Option<mpsc::Receiver<Synthesizer>>,
 }
 
 impl std::fmt::Debug for ScoreVisualizer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ScoreVisualizer")
-            .field("images", &self.images)
+            .field("pages", &self.pages)
+            .field("zoom", &self.zoom)
             .field("last_step_num", &self.last_step_num)
             .field("audio_controller", &self.audio_controller)
             .field("sf_path", &self.sf_path)
@@ -125,15 +238,21 @@ impl std::fmt::Debug for ScoreVisualizer {
 
 impl Default for ScoreVisualizer {
     fn default() -> Self {
-        let args = Arguments::new().unwrap();
         Self {
-            images: None,
+            pages: None,
+            zoom: 1.0,
+            refresh_count: 0,
             selected_image: None,
+            selected_link: None,
             last_step_num: Default::default(),
             audio_controller: AudioController::new().unwrap(),
-            sf_path: args.sound_font_path,
+            sf_path: Arguments::new().ok().and_then(|a| a.sound_font_path),
+            source_map: SourceMap::default(),
+            word_indices: vec![],
             refresh_state: None,
             refresh_promise: None,
+            audio_promise: None,
+            audio_stream: None,
         }
     }
 }
@@ -144,23 +263,208 @@ impl ScoreVisualizer {
         self.last_step_num = *state.iter_num();
         app_state.dirty = false;
 
-        // Create the score and translate it.
-        let score = MusicInterpret::new(app_state.music_int_info.clone())
-            .translate(state.word())
-            .sanitized()?;
-        let lily_score = Lilypond::from(score).sanitized_with(LilySanitizer::default())?;
+        // Create the score and translate it, unless it was imported.
+        let interpret = MusicInterpret::new(app_state.music_int_info.clone());
+        let mut score = match &app_state.imported_score {
+            Some(imported) => imported.clone(),
+            None => {
+                let mut score = interpret.translate(state.word());
+                score.info = app_state.score_info.clone();
+                score
+            }
+        };
+        if app_state.harmonize {
+            app_state.harmonizer.harmonize(&mut score)?;
+        }
+        score.transpose(&app_state.transposition)?;
+        let score = score.sanitized()?;
+        app_state.score = Some(score.clone());
+        let native_midi = match app_state.midi_source {
+            MidiSource::Native => Some(MidiWriter::default().write(&score)?),
+            MidiSource::Lilypond => None,
+        };
+        let mut lily_score = Lilypond::from(score).sanitized_with(LilySanitizer::default())?;
+        lily_score.language = app_state.lily_language;
+        lily_score.relative = app_state.lily_relative;
+        lily_score.layout = app_state.lily_layout.clone();
+        self.source_map = lily_score.source_map();
+        app_state.score_lily = Some(self.source_map.source().to_owned());
+        self.word_indices = match app_state.imported_score {
+            Some(_) => vec![],
+            None => interpret.word_indices(state.word()),
+        };
+        app_state.diagnostics.clear();
+        app_state.selected_diagnostic = None;
+        app_state.highlighted_word.clear();
 
-        self.images = None;
+        self.pages = None;
         self.selected_image = None;
+        self.selected_link = None;
+        let (stream, audio_stream) = mpsc::channel();
+        self.audio_stream = Some(audio_stream);
+        let synth = SynthSettings {
+            backend: app_state.synth_backend,
+            sf_path: self.sf_path.clone(),
+            render: app_state.audio_render,
+            stream,
+        };
         self.refresh_state = Some(Arc::new(Mutex::new(ScoreRefreshState::Begin)));
         self.refresh_promise = Some(refresh_async(
             self.refresh_state.clone().unwrap(),
-            lily_score.to_string(),
-            self.sf_path.clone(),
+            self.source_map.source().to_owned(),
+            app_state.lily_format,
+            native_midi.is_none().then(|| synth.clone()),
         ));
+        self.audio_promise = native_midi.map(|midi| audio_async(midi, synth));
 
         Ok(())
     }
+
+    /// Point-and-click link of the note clicked in the page, if there is any nearby.
+    fn clicked_link(response: &egui::Response, page: &Page) -> Option<SvgLink> {
+        let Page::Vector(_, _, links) = page else {
+            toast::show_info(&"Switch the output format to SVG to locate the clicked notes.");
+            return None;
+        };
+
+        let pos = response.interact_pointer_pos()? - response.rect.min;
+        let (x, y) = links.to_page((
+            pos.x / response.rect.width(),
+            pos.y / response.rect.height(),
+        ));
+
+        links
+            .nearest((x - NOTE_HEAD_CENTER, y), CLICK_DISTANCE)
+            .cloned()
+    }
+
+    /// Circle the selected note in the displayed page.
+    fn paint_selection(ui: &egui::Ui, response: &egui::Response, links: &SvgLinks, link: &SvgLink) {
+        let (fx, fy) = links.to_fraction((link.x + NOTE_HEAD_CENTER, link.y));
+        let center = response.rect.min + egui::vec2(fx, fy) * response.rect.size();
+        let staff_space = response.rect.width() / links.view_box[2];
+
+        ui.painter().circle_stroke(
+            center,
+            staff_space * CLICK_DISTANCE,
+            ui.visuals().selection.stroke,
+        );
+    }
+
+    /// Highlight the L-system word characters, which wrote the clicked note.
+    fn select_link(&mut self, app_state: &mut GuiAppState, page: usize, link: Option<SvgLink>) {
+        app_state.highlighted_word = link
+            .as_ref()
+            .and_then(|l| self.source_map.locate(l.position))
+            .map(|l| written_by(l, &self.word_indices))
+            .unwrap_or_default();
+        self.selected_link = link.map(|l| (page, l));
+    }
+
+    fn load_audio(&mut self, app_state: &mut GuiAppState, audio: AudioOutput) {
+        self.audio_controller
+            .load_from_data(audio.audio_data)
+            .unwrap();
+        app_state.score_audio = Some(audio.score_audio_path);
+    }
+
+    /// Play the audio while it is synthesized, once its synthesizer is ready.
+    fn poll_audio_stream(&mut self) {
+        let Some(audio_stream) = self.audio_stream.as_ref() else {
+            return;
+        };
+
+        match audio_stream.try_recv() {
+            Ok(synthesizer) => {
+                self.audio_stream = None;
+                if let Err(e) = self.audio_controller.load_stream(synthesizer) {
+                    toast::show_error(format!("Audio streaming failed: {e}").as_str());
+                }
+            }
+            Err(mpsc::TryRecvError::Disconnected) => self.audio_stream = None,
+            Err(mpsc::TryRecvError::Empty) => {}
+        }
+    }
+
+    /// Load the audio synthesized from the native MIDI, once it is done.
+    fn poll_audio(&mut self, app_state: &mut GuiAppState) {
+        let Some(audio_promise) = self.audio_promise.take() else {
+            return;
+        };
+
+        match audio_promise.try_take() {
+            Ok(AsyncResult::Ok(audio)) => self.load_audio(app_state, audio),
+            Ok(AsyncResult::Err(e)) => {
+                toast::show_error(format!("Audio rendering failed: {e}").as_str());
+            }
+            Err(promise) => self.audio_promise = Some(promise),
+        }
+    }
+
+    /// Name of the imported score, which is displayed instead of the generated one.
+    fn show_import(ui: &mut egui::Ui, app_state: &mut GuiAppState) {
+        let Some(imported) = &app_state.imported_score else {
+            return;
+        };
+        let name = imported.info.name.clone();
+
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "Showing imported score {}",
+                name.as_deref().unwrap_or("without title")
+            ));
+            if ui.button("Close import").clicked() {
+                app_state.imported_score = None;
+                app_state.dirty = true;
+            }
+        });
+    }
+
+    /// Controls of the score transposition. Any change re-renders the score.
+    fn show_transposition(&mut self, ui: &mut egui::Ui, app_state: &mut GuiAppState) {
+        let before = app_state.transposition;
+        let transposition = &mut app_state.transposition;
+        let key = app_state.music_int_info.key_signature;
+
+        ui.horizontal(|ui| {
+            ui.label("Transpose");
+
+            let interval = match transposition {
+                Transposition::Up(i) | Transposition::Down(i) => *i,
+                _ => Interval::MAJOR_SECOND,
+            };
+            ui.selectable_value(transposition, Transposition::None, "None");
+            ui.selectable_value(transposition, Transposition::Up(interval), "Up");
+            ui.selectable_value(transposition, Transposition::Down(interval), "Down");
+            if ui
+                .selectable_label(matches!(transposition, Transposition::To(_)), "To key")
+                .clicked()
+                && !matches!(transposition, Transposition::To(_))
+            {
+                *transposition = Transposition::To(key);
+            }
+
+            match transposition {
+                Transposition::Up(interval) | Transposition::Down(interval) => {
+                    egui::ComboBox::from_id_salt("combo:transpose_interval")
+                        .selected_text(interval.to_string())
+                        .show_ui(ui, |ui| {
+                            for i in (1..=12).map(Interval::from_halftones) {
+                                ui.selectable_value(interval, i, i.to_string());
+                            }
+                        });
+                }
+                Transposition::To(key) => {
+                    ui.add(NoteNameSelector::new(&mut key.ext));
+                }
+                Transposition::None => {}
+            }
+        });
+
+        if before != app_state.transposition {
+            app_state.dirty = true;
+        }
+    }
 }
 
 impl DockableWindow for ScoreVisualizer {
@@ -176,6 +480,11 @@ impl DockableWindow for ScoreVisualizer {
             }
         }
 
+        self.show_transposition(ui, app_state);
+        Self::show_import(ui, app_state);
+
+        self.poll_audio_stream();
+        self.poll_audio(app_state);
         if self.audio_controller.is_loaded() {
             ui.add(AudioPlayer::new(&mut self.audio_controller));
         }
@@ -184,23 +493,55 @@ impl DockableWindow for ScoreVisualizer {
             match refresh_promise.try_take() {
                 Ok(refresh_output) => match refresh_output {
                     AsyncResult::Ok(refresh_output) => {
-                        self.images = Some(
+                        self.refresh_count += 1;
+                        self.pages = Some(
                             refresh_output
                                 .pages_data
                                 .into_iter()
-                                .map(|data| Texture::load_from_memory(data, ui.ctx()))
+                                .enumerate()
+                                .map(|(i, data)| match data {
+                                    PageData::Raster(data) => {
+                                        Page::Raster(Texture::load_from_memory(data, ui.ctx()))
+                                    }
+                                    PageData::Vector(data, links) => Page::Vector(
+                                        format!("bytes://score-{}-{i}.svg", self.refresh_count),
+                                        data.into(),
+                                        links,
+                                    ),
+                                })
                                 .collect(),
                         );
                         self.selected_image = Some(0);
-                        self.audio_controller
-                            .load_from_data(refresh_output.audio_data)
-                            .unwrap();
+                        if let Some(audio) = refresh_output.audio {
+                            self.load_audio(app_state, audio);
+                        }
                         app_state.score_images = Some(refresh_output.score_image_paths);
-                        app_state.score_audio = Some(refresh_output.score_audio_path);
+                        app_state.score_pdf = refresh_output.score_pdf_path;
+                        app_state.diagnostics = ScoreDiagnostic::map_all(
+                            &refresh_output.lily_log,
+                            &self.source_map,
+                            &self.word_indices,
+                        );
                         ui.ctx().request_repaint();
                     }
                     AsyncResult::Err(e) => {
-                        toast::show_error(format!("Refresh failed: {e}").as_str());
+                        app_state.diagnostics =
+                            ScoreDiagnostic::map_all(&e, &self.source_map, &self.word_indices);
+                        let errors = app_state
+                            .diagnostics
+                            .iter()
+                            .filter(|d| d.diagnostic.severity == Severity::Error)
+                            .count();
+
+                        if errors > 0 {
+                            log::info!("Lilypond output:\n{e}");
+                            toast::show_error(&format!(
+                                "Refresh failed: Lilypond reported {errors} error(s), see {}.",
+                                TabType::Diagnostics.as_str()
+                            ));
+                        } else {
+                            toast::show_error(format!("Refresh failed: {e}").as_str());
+                        }
                     }
                 },
                 Err(promise) => {
@@ -217,16 +558,42 @@ impl DockableWindow for ScoreVisualizer {
                     self.refresh_promise = Some(promise);
                 }
             };
-        } else if let (Some(images), Some(selected_image)) = (&self.images, self.selected_image) {
+        } else if let (Some(pages), Some(selected_image)) = (&self.pages, self.selected_image) {
+            let mut clicked = None;
             ui.group(|ui| {
-                ui.vertical_centered(|ui| {
-                    ui.set_height(ui.available_height() - 28.0);
-                    let Texture(_, tex) = &images[selected_image];
-                    egui::Image::new(tex).shrink_to_fit().ui(ui);
-                });
+                let size = egui::vec2(ui.available_width(), ui.available_height() - 28.0);
+                egui::ScrollArea::both()
+                    .max_height(size.y)
+                    .auto_shrink(false)
+                    .show(ui, |ui| {
+                        ui.vertical_centered(|ui| {
+                            let page = &pages[selected_image];
+                            let image = match page {
+                                Page::Raster(Texture(_, tex)) => egui::Image::new(tex),
+                                Page::Vector(uri, bytes, _) => {
+                                    egui::Image::from_bytes(uri.clone(), bytes.clone())
+                                }
+                            };
+                            let response = image
+                                .fit_to_exact_size(size * self.zoom)
+                                .sense(egui::Sense::click())
+                                .ui(ui);
+
+                            if response.clicked() {
+                                clicked = Some(Self::clicked_link(&response, page));
+                            }
+                            if let (Page::Vector(_, _, links), Some((page, link))) =
+                                (page, &self.selected_link)
+                            {
+                                if *page == selected_image {
+                                    Self::paint_selection(ui, &response, links, link);
+                                }
+                            }
+                        });
+                    });
                 ui.add_space(5.0);
                 ui.vertical_centered(|ui| {
-                    ui.set_width(100.0);
+                    ui.set_width(220.0);
 
                     ui.horizontal(|ui| {
                         if ui.button("<").clicked() {
@@ -234,15 +601,31 @@ impl DockableWindow for ScoreVisualizer {
                             ui.ctx().request_repaint();
                         }
 
-                        ui.label(format!("{} / {}", selected_image + 1, images.len()));
+                        ui.label(format!("{} / {}", selected_image + 1, pages.len()));
 
                         if ui.button(">").clicked() {
-                            self.selected_image = Some(min(images.len() - 1, selected_image + 1));
+                            self.selected_image = Some(min(pages.len() - 1, selected_image + 1));
                             ui.ctx().request_repaint();
                         }
+
+                        ui.separator();
+
+                        if ui.button("-").clicked() {
+                            self.zoom = (self.zoom / ZOOM_STEP).max(MIN_ZOOM);
+                        }
+                        if ui.button(format!("{:.0} %", self.zoom * 100.0)).clicked() {
+                            self.zoom = 1.0;
+                        }
+                        if ui.button("+").clicked() {
+                            self.zoom = (self.zoom * ZOOM_STEP).min(MAX_ZOOM);
+                        }
                     })
                 })
             });
+
+            if let Some(link) = clicked {
+                self.select_link(app_state, selected_image, link);
+            }
         } else {
             ui.group(|ui| {
                 ui.centered_and_justified(|ui| {
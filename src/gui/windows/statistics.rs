@@ -3,6 +3,8 @@
 //! ### Author
 //! Jakub Kloub (xkloub03), VUT FIT
 
+use egui::text::{LayoutJob, TextFormat};
+
 use crate::{
     gui::{utils, windows::DockableWindow, GuiAppState, TabType},
     lsystem::LSystem,
@@ -20,10 +22,28 @@ impl Statistics {
         ui.strong("Probability");
         ui.end_row();
 
-        for r in app_state.used_rules_history.last().unwrap_or(&vec![]) {
-            ui.label(r.left());
+        for (i, r) in app_state
+            .used_rules_history
+            .last()
+            .unwrap_or(&vec![])
+            .iter()
+            .enumerate()
+        {
+            // Mark the rules, which wrote the highlighted characters of the word.
+            let wrote_highlighted = app_state.last_rule_spans.get(i).is_some_and(|(range, _)| {
+                app_state.highlighted_word.iter().any(|c| range.contains(c))
+            });
+            let highlight = ui.visuals().selection.bg_fill;
+            let side = |side: &str| match wrote_highlighted {
+                true => egui::RichText::new(side)
+                    .strong()
+                    .background_color(highlight),
+                false => egui::RichText::new(side),
+            };
+
+            ui.label(side(r.left()));
             ui.label("->");
-            ui.label(r.right());
+            ui.label(side(r.right()));
             ui.label("%");
             ui.label(format!("{:.2}", r.p()));
             ui.end_row();
@@ -31,6 +51,33 @@ impl Statistics {
     }
 }
 
+impl Statistics {
+    /// Current word with the characters of the selected diagnostic highlighted.
+    fn word_layout(ui: &egui::Ui, word: &str, highlighted: &[usize]) -> LayoutJob {
+        let normal = TextFormat {
+            font_id: egui::TextStyle::Body.resolve(ui.style()),
+            color: ui.visuals().text_color(),
+            ..Default::default()
+        };
+        let marked = TextFormat {
+            background: ui.visuals().selection.bg_fill,
+            ..normal.clone()
+        };
+
+        let mut job = LayoutJob::default();
+        let mut start = 0;
+        // Indices are ascending, as the symbols were written in the order of the word.
+        for &i in highlighted.iter().filter(|&&i| i < word.len()) {
+            job.append(&word[start..i], 0.0, normal.clone());
+            job.append(&word[i..i + 1], 0.0, marked.clone());
+            start = i + 1;
+        }
+        job.append(&word[start..], 0.0, normal);
+
+        job
+    }
+}
+
 impl DockableWindow for Statistics {
     fn name(&self) -> &'static str {
         TabType::Statistics.as_str()
@@ -40,7 +87,9 @@ impl DockableWindow for Statistics {
         utils::section_name(ui, "Current Word");
         ui.group(|ui| {
             ui.set_max_height(50.0);
-            ui.add(egui::Label::new(app_state.l_system.state().word()).wrap());
+            let word = app_state.l_system.state().word();
+            let layout = Self::word_layout(ui, word, &app_state.highlighted_word);
+            ui.add(egui::Label::new(layout).wrap());
         });
 
         utils::section_name(ui, "Last step used rules");
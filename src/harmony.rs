@@ -0,0 +1,30 @@
+//! Harmonization of generated melodies
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+mod diatonic_chord;
+mod harmonizer;
+
+pub use diatonic_chord::DiatonicChord;
+pub use harmonizer::Harmonizer;
+
+/// How often the accompaniment changes its chord.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum HarmonicRhythm {
+    /// One chord per bar.
+    Bar,
+    /// One chord per half of a bar.
+    HalfBar,
+}
+
+/// Way the selected chord is written into the accompaniment stave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum AccompanimentPattern {
+    /// Whole chord sounding for the entire duration.
+    Block,
+    /// Root, fifth, third, fifth.
+    Alberti,
+    /// Root, third, fifth, third.
+    Arpeggio,
+}
@@ -0,0 +1,113 @@
+//! Diatonic chord definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use crate::{
+    lsystem::interpret::{BasicScale, Scale},
+    notation::{KeySignature, Octave, Pitch},
+};
+
+/// Triad built from the notes of a key on one of its degrees.
+#[derive(Debug, Clone)]
+pub struct DiatonicChord {
+    /// Degree of the chord root in the key, where 0 is the tonic.
+    pub degree: u8,
+    /// Root, third and fifth of the chord.
+    pub pitches: [Pitch; 3],
+}
+
+impl DiatonicChord {
+    /// Create triads on all seven degrees of the given key.
+    ///
+    /// Roots of the chords are kept inside of the given octave.
+    pub fn all(key: KeySignature, octave: Octave) -> Vec<Self> {
+        let scale = BasicScale::new(key);
+        let up = |pitch: &Pitch, steps: usize| {
+            let mut p = pitch.clone();
+            (0..steps).for_each(|_| scale.advance(&mut p));
+            p
+        };
+
+        let tonic = Pitch {
+            ext: key.ext,
+            octave,
+        };
+
+        (0..7)
+            .map(|degree| {
+                let mut root = up(&tonic, degree);
+                if root.real_octave() != octave {
+                    root.octave = root.octave.try_prev().unwrap_or(root.octave);
+                }
+
+                Self {
+                    degree: degree as u8,
+                    pitches: [root.clone(), up(&root, 2), up(&root, 4)],
+                }
+            })
+            .collect()
+    }
+
+    pub fn root(&self) -> &Pitch {
+        &self.pitches[0]
+    }
+
+    pub fn third(&self) -> &Pitch {
+        &self.pitches[1]
+    }
+
+    pub fn fifth(&self) -> &Pitch {
+        &self.pitches[2]
+    }
+
+    /// Check if the chord contains a pitch with the given halftone value.
+    pub fn contains(&self, halftone: u8) -> bool {
+        self.pitches.iter().any(|p| p.value_halftone() == halftone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{
+        Accidental::*, ExtNoteName, KeySignatureType::*, NoteName::*, Octave::*,
+    };
+
+    #[test]
+    fn all_a_maj() {
+        // Arrange
+        let a_maj = KeySignature {
+            ext: ExtNoteName {
+                note_name: A,
+                accidental: None,
+            },
+            signature_type: Maj,
+        };
+
+        // Act
+        let chords = DiatonicChord::all(a_maj, O3);
+
+        // Assert
+        assert_eq!(chords.len(), 7);
+        assert_eq!(
+            chords[0].pitches,
+            [
+                Pitch::new(A, O3, None),
+                Pitch::new(C, O4, Some(Sharp)),
+                Pitch::new(E, O4, None)
+            ]
+        );
+        assert_eq!(*chords[2].root(), Pitch::new(C, O3, Some(Sharp)));
+        assert_eq!(
+            chords[4].pitches,
+            [
+                Pitch::new(E, O3, None),
+                Pitch::new(G, O3, Some(Sharp)),
+                Pitch::new(B, O3, None)
+            ]
+        );
+        assert!(chords[3].contains(Pitch::new(F, O1, Some(Sharp)).value_halftone()));
+        assert!(!chords[3].contains(Pitch::new(F, O1, None).value_halftone()));
+    }
+}
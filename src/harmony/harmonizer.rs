@@ -0,0 +1,364 @@
+//! Harmonizer definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use crate::{
+    error::*,
+    harmony::{AccompanimentPattern, DiatonicChord, HarmonicRhythm},
+    notation::{
+        Chord, Clef, Duration, KeySignature, Note, Octave, Pitch, Rational, Score, Stave, Symbol,
+        TimeSignature,
+    },
+};
+
+/// Preference of chords on given degrees, when the melody fits them equally.
+const DEGREE_WEIGHTS: [i32; 7] = [3, 1, 0, 2, 2, 1, -2];
+
+/// Part of the melody harmonized by a single chord.
+#[derive(Debug, Default)]
+struct Segment {
+    /// Length of the segment.
+    length: Rational,
+    /// Halftone values of melody notes and the time they sound in the segment.
+    notes: Vec<(u8, Rational)>,
+    /// Halftone values of notes starting at the beginning of the segment.
+    onset: Vec<u8>,
+}
+
+/// Creates a chordal accompaniment for the melody in the first stave of a score.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct Harmonizer {
+    pub rhythm: HarmonicRhythm,
+    pub pattern: AccompanimentPattern,
+}
+
+impl Default for Harmonizer {
+    fn default() -> Self {
+        Self {
+            rhythm: HarmonicRhythm::Bar,
+            pattern: AccompanimentPattern::Block,
+        }
+    }
+}
+
+impl Harmonizer {
+    pub fn new(rhythm: HarmonicRhythm, pattern: AccompanimentPattern) -> Self {
+        Self { rhythm, pattern }
+    }
+
+    /// Add a bass clef accompaniment stave harmonizing the first stave of the score.
+    pub fn harmonize(&self, score: &mut Score) -> Result<()> {
+        let melody = score.staves.first().ok_or(AppError::Other(
+            "Score has no stave to harmonize.".to_string(),
+        ))?;
+        let key = Self::find_key(melody)?;
+        let time = Self::find_time(melody)?;
+
+        let chords = DiatonicChord::all(key, Octave::O3);
+        let segments = self.segments(melody, &time);
+
+        let mut symbols = vec![
+            Symbol::Clef(Clef::Bass),
+            Symbol::KeySignature(key),
+            Symbol::TimeSignature(time),
+        ];
+        let mut prev_degree = None;
+
+        for (i, segment) in segments.iter().enumerate() {
+            let is_last = i + 1 == segments.len();
+            let chord = Self::select_chord(&chords, segment, prev_degree, is_last);
+
+            symbols.extend(self.write_chord(chord, segment.length, &time));
+            prev_degree = Some(chord.degree);
+        }
+
+        score.staves.push(Stave { symbols });
+
+        Ok(())
+    }
+
+    /// Split the melody into segments, each of which will get its own chord.
+    fn segments(&self, melody: &Stave, time: &TimeSignature) -> Vec<Segment> {
+        let bar_len = time.bar_length();
+        let segment_len = match self.rhythm {
+            HarmonicRhythm::HalfBar => bar_len / 2,
+            HarmonicRhythm::Bar => bar_len,
+        };
+        let seg_start = |i: usize| Rational::from(i as u32) * segment_len;
+
+        let mut segments: Vec<Segment> = vec![];
+        let mut pos = Rational::from(0);
+
+        for sym in melody.symbols.iter() {
+            let (pitches, length) = match sym {
+                Symbol::Note(note) => (std::slice::from_ref(&note.pitch), note.duration),
+                Symbol::Chord(chord) => (chord.pitches.as_slice(), chord.duration),
+                Symbol::Rest(length) => (&[][..], *length),
+                _ => continue,
+            };
+
+            let (start, end) = (pos, pos + length.value());
+            while seg_start(segments.len()) < end {
+                segments.push(Segment::default());
+            }
+
+            for (i, segment) in segments
+                .iter_mut()
+                .enumerate()
+                .skip((start / segment_len).to_integer() as usize)
+            {
+                let seg_start = seg_start(i);
+                let overlap = end.min(seg_start + segment_len) - start.max(seg_start);
+
+                for p in pitches {
+                    segment.notes.push((p.value_halftone(), overlap));
+                    if start == seg_start {
+                        segment.onset.push(p.value_halftone());
+                    }
+                }
+            }
+
+            pos = end;
+        }
+
+        // All segments are full except for the last one, which ends with the melody.
+        let count = segments.len();
+        for (i, segment) in segments.iter_mut().enumerate() {
+            segment.length = if i + 1 == count {
+                pos - seg_start(i)
+            } else {
+                segment_len
+            };
+        }
+
+        segments
+    }
+
+    /// Select the chord fitting the best to the melody notes of the segment.
+    fn select_chord<'a>(
+        chords: &'a [DiatonicChord],
+        segment: &Segment,
+        prev_degree: Option<u8>,
+        is_last: bool,
+    ) -> &'a DiatonicChord {
+        // Ratings are computed in 1/128 notes.
+        let ticks = |value: Rational| (value * 128).round().to_integer() as i32;
+        let len = ticks(segment.length);
+
+        let rate = |chord: &DiatonicChord| {
+            let mut rating = DEGREE_WEIGHTS[chord.degree as usize] * len / 16;
+
+            for &(halftone, overlap) in segment.notes.iter() {
+                let overlap = ticks(overlap);
+                rating += match chord.contains(halftone) {
+                    true if chord.root().value_halftone() == halftone => 3 * overlap,
+                    true => 2 * overlap,
+                    false => -overlap,
+                };
+            }
+
+            if segment.onset.iter().any(|&h| chord.contains(h)) {
+                rating += len / 4;
+            }
+            if prev_degree == Some(chord.degree) {
+                rating -= len / 8;
+            }
+            if is_last && chord.degree == 0 {
+                rating += 4 * len;
+            }
+
+            rating
+        };
+
+        chords
+            .iter()
+            .fold((&chords[0], i32::MIN), |best, chord| {
+                let rating = rate(chord);
+                if rating > best.1 {
+                    (chord, rating)
+                } else {
+                    best
+                }
+            })
+            .0
+    }
+
+    /// Write the chord in the selected pattern, so that it takes the given length.
+    fn write_chord(
+        &self,
+        chord: &DiatonicChord,
+        length: Rational,
+        time: &TimeSignature,
+    ) -> Vec<Symbol> {
+        let figure = match self.pattern {
+            AccompanimentPattern::Block => {
+                return Duration::split(length)
+                    .into_iter()
+                    .map(|duration| {
+                        Symbol::Chord(Chord {
+                            pitches: chord.pitches.to_vec(),
+                            duration,
+                            tie: false,
+                        })
+                    })
+                    .collect();
+            }
+            AccompanimentPattern::Alberti => {
+                [chord.root(), chord.fifth(), chord.third(), chord.fifth()]
+            }
+            AccompanimentPattern::Arpeggio => {
+                [chord.root(), chord.third(), chord.fifth(), chord.third()]
+            }
+        };
+
+        let mut unit = time.single_beat_note;
+        // Shortest beat is kept, when it can't be halved.
+        let _ = unit.halve();
+        let count = (length / unit.value()).to_integer();
+        let note = |pitch: &Pitch, duration| {
+            Symbol::Note(Note {
+                pitch: pitch.clone(),
+                duration,
+                tie: false,
+            })
+        };
+
+        (0..count as usize)
+            .map(|i| note(figure[i % figure.len()], unit))
+            .chain(
+                Duration::split(length - unit.value() * count)
+                    .into_iter()
+                    .map(|duration| note(chord.root(), duration)),
+            )
+            .collect()
+    }
+
+    fn find_key(stave: &Stave) -> Result<KeySignature> {
+        stave
+            .symbols
+            .iter()
+            .find_map(|s| match s {
+                Symbol::KeySignature(key) => Some(*key),
+                _ => None,
+            })
+            .ok_or(AppError::StaveKeyNotFound.into())
+    }
+
+    fn find_time(stave: &Stave) -> Result<TimeSignature> {
+        stave
+            .symbols
+            .iter()
+            .find_map(|s| match s {
+                Symbol::TimeSignature(time) => Some(*time),
+                _ => None,
+            })
+            .ok_or(AppError::MissingTimeSignature.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{
+        ExtNoteName, KeySignatureType, NoteLength, NoteLength::*, NoteName::*, Octave::*,
+    };
+
+    fn c_maj_score(notes: &[(crate::notation::NoteName, Octave, NoteLength)]) -> Score {
+        let key = KeySignature {
+            ext: ExtNoteName {
+                note_name: C,
+                accidental: None,
+            },
+            signature_type: KeySignatureType::Maj,
+        };
+
+        Score {
+            staves: vec![Stave {
+                symbols: [
+                    Symbol::Clef(Clef::Treble),
+                    Symbol::KeySignature(key),
+                    Symbol::TimeSignature(TimeSignature::c()),
+                ]
+                .into_iter()
+                .chain(notes.iter().map(|&(n, o, d)| {
+                    Symbol::Note(Note {
+                        pitch: Pitch::new(n, o, None),
+                        duration: d.into(),
+                        tie: false,
+                    })
+                }))
+                .collect(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn accompaniment_roots(score: &Score) -> Vec<Pitch> {
+        score.staves[1]
+            .symbols
+            .iter()
+            .filter_map(|s| match s {
+                Symbol::Chord(c) => Some(c.pitches[0].clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn harmonize_cadence() {
+        // Arrange
+        let mut score = c_maj_score(&[
+            (C, O4, L4),
+            (E, O4, L4),
+            (G, O4, L4),
+            (C, O5, L4),
+            (F, O4, L4),
+            (A, O4, L4),
+            (C, O5, L4),
+            (A, O4, L4),
+            (G, O4, L4),
+            (B, O4, L4),
+            (D, O5, L4),
+            (B, O4, L4),
+            (C, O5, L1),
+        ]);
+
+        // Act
+        Harmonizer::default().harmonize(&mut score).unwrap();
+
+        // Assert
+        assert_eq!(score.staves.len(), 2);
+        assert!(matches!(
+            score.staves[1].symbols[0],
+            Symbol::Clef(Clef::Bass)
+        ));
+        assert_eq!(
+            accompaniment_roots(&score),
+            vec![
+                Pitch::new(C, O3, None),
+                Pitch::new(F, O3, None),
+                Pitch::new(G, O3, None),
+                Pitch::new(C, O3, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn harmonize_keeps_length() {
+        // Arrange
+        let mut score = c_maj_score(&[(E, O4, L2), (G, O4, L4), (D, O4, L8), (C, O4, L2)]);
+        let harmonizer = Harmonizer::new(HarmonicRhythm::HalfBar, AccompanimentPattern::Alberti);
+
+        // Act
+        harmonizer.harmonize(&mut score).unwrap();
+
+        // Assert
+        let length: Rational = score.staves[1]
+            .symbols
+            .iter()
+            .filter_map(|s| s.duration().map(Duration::value))
+            .sum();
+        assert_eq!(length, Rational::new(11, 8));
+    }
+}
@@ -8,31 +8,37 @@
 use crate::error::{AppError, Result};
 use std::{env, path::PathBuf};
 
+pub mod abc;
 pub mod error;
 pub mod ext;
 pub mod gui;
+pub mod harmony;
 pub mod lily;
 pub mod lsystem;
+pub mod midi;
+pub mod musicxml;
 pub mod notation;
 pub mod sanitizer;
+pub mod synth;
 pub mod utils;
 
 pub struct Arguments {
-    pub sound_font_path: PathBuf,
+    /// SoundFont of the synthesized audio. Without it, the additive synthesizer is used.
+    pub sound_font_path: Option<PathBuf>,
 }
 
 impl Arguments {
     pub fn new() -> Result<Self> {
         let args: Vec<String> = env::args().collect();
 
-        if args.len() != 2 {
+        if args.len() > 2 {
             Err(AppError::Argument(
                 "Invalid number of arguemnts".to_string(),
             ))?;
         }
 
         let args = Self {
-            sound_font_path: PathBuf::from(args[1].clone()),
+            sound_font_path: args.get(1).map(PathBuf::from),
         };
 
         Ok(args)
@@ -40,6 +46,6 @@ impl Arguments {
 
     pub fn help() {
         let args: Vec<String> = env::args().collect();
-        eprintln!("USAGE: {} <path to sf2 virtual piano>", args[0]);
+        eprintln!("USAGE: {} [path to sf2 virtual piano]", args[0]);
     }
 }
@@ -3,21 +3,37 @@
 //! ### Author
 //! Jakub Kloub (xkloub03), VUT FIT
 
+mod lily_chord;
+mod lily_diagnostic;
+mod lily_header;
+mod lily_language;
+mod lily_layout;
 mod lily_note;
+mod lily_parser;
 mod lily_stave;
+mod lily_svg;
 mod lily_symbol;
+mod lily_tuplet;
 mod lilypond;
 
 use std::fmt::Display;
 
+pub use lily_chord::LilyChord;
+pub use lily_diagnostic::{LilyDiagnostic, Severity, SourceMap, SourcePosition, SymbolLocation};
+pub use lily_header::LilyHeader;
+pub use lily_language::{InLanguage, LilyLanguage, LilyWrite};
+pub use lily_layout::{LilyLayout, Orientation, PaperSize};
 pub use lily_note::LilyNote;
+pub use lily_parser::LilyParser;
 pub use lily_stave::LilyStave;
+pub use lily_svg::{SvgLink, SvgLinks};
 pub use lily_symbol::LilySymbol;
+pub use lily_tuplet::LilyTuplet;
 pub use lilypond::Lilypond;
 
 use crate::notation::{
-    Accidental, Clef, ExtNoteName, KeySignature, KeySignatureType, NoteLength, NoteName, Octave,
-    Pitch, Tempo, TimeSignature,
+    Accidental, Clef, Duration, ExtNoteName, KeySignature, KeySignatureType, NoteLength, NoteName,
+    Octave, Pitch, Rational, Tempo, TimeSignature, Tuplet,
 };
 
 #[derive(Debug, Clone)]
@@ -51,13 +67,13 @@ impl Display for LilyClef {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[rustfmt::skip]
 pub enum LilyNoteName {
-    Ces, C, Cis,
-    Des, D, Dis,
-    Es, E, Eis,
-    Fes, F, Fis,
-    Ges, G, Gis,
-    As, A, Ais,
-    Hes, H, His,
+    Ceses, Ces, C, Cis, Cisis,
+    Deses, Des, D, Dis, Disis,
+    Eses, Es, E, Eis, Eisis,
+    Feses, Fes, F, Fis, Fisis,
+    Geses, Ges, G, Gis, Gisis,
+    Asas, As, A, Ais, Aisis,
+    Heses, Hes, H, His, Hisis,
 }
 
 impl From<ExtNoteName> for LilyNoteName {
@@ -67,6 +83,8 @@ impl From<ExtNoteName> for LilyNoteName {
                 return match acc {
                     Accidental::Sharp => note_names[1],
                     Accidental::Flat => note_names[2],
+                    Accidental::DoubleSharp => note_names[3],
+                    Accidental::DoubleFlat => note_names[4],
                 };
             }
 
@@ -76,13 +94,13 @@ impl From<ExtNoteName> for LilyNoteName {
         use LilyNoteName::*;
 
         match ext.note_name {
-            NoteName::C => select(&[C, Cis, Ces]),
-            NoteName::D => select(&[D, Dis, Des]),
-            NoteName::E => select(&[E, Eis, Es]),
-            NoteName::F => select(&[F, Fis, Fes]),
-            NoteName::G => select(&[G, Gis, Ges]),
-            NoteName::A => select(&[A, Ais, As]),
-            NoteName::B => select(&[H, His, Hes]),
+            NoteName::C => select(&[C, Cis, Ces, Cisis, Ceses]),
+            NoteName::D => select(&[D, Dis, Des, Disis, Deses]),
+            NoteName::E => select(&[E, Eis, Es, Eisis, Eses]),
+            NoteName::F => select(&[F, Fis, Fes, Fisis, Feses]),
+            NoteName::G => select(&[G, Gis, Ges, Gisis, Geses]),
+            NoteName::A => select(&[A, Ais, As, Aisis, Asas]),
+            NoteName::B => select(&[H, His, Hes, Hisis, Heses]),
         }
     }
 }
@@ -93,35 +111,122 @@ impl From<Pitch> for LilyNoteName {
     }
 }
 
+impl From<LilyNoteName> for ExtNoteName {
+    fn from(name: LilyNoteName) -> Self {
+        const NOTE_NAMES: [NoteName; 7] = [
+            NoteName::C,
+            NoteName::D,
+            NoteName::E,
+            NoteName::F,
+            NoteName::G,
+            NoteName::A,
+            NoteName::B,
+        ];
+
+        Self {
+            note_name: NOTE_NAMES[name.letter() as usize],
+            accidental: Accidental::from_alteration(name.alteration()).unwrap(),
+        }
+    }
+}
+
+impl LilyNoteName {
+    #[rustfmt::skip]
+    pub const ALL: [LilyNoteName; 35] = {
+        use LilyNoteName::*;
+        [
+            Ceses, Ces, C, Cis, Cisis,
+            Deses, Des, D, Dis, Disis,
+            Eses, Es, E, Eis, Eisis,
+            Feses, Fes, F, Fis, Fisis,
+            Geses, Ges, G, Gis, Gisis,
+            Asas, As, A, Ais, Aisis,
+            Heses, Hes, H, His, Hisis,
+        ]
+    };
+
+    /// Index of the note name without accidental, starting with C.
+    pub fn letter(&self) -> u8 {
+        *self as u8 / 5
+    }
+
+    /// Number of halftones by which the accidental alters the note.
+    pub fn alteration(&self) -> i8 {
+        (*self as u8 % 5) as i8 - 2
+    }
+
+    fn deutsch(&self) -> &'static str {
+        match self {
+            LilyNoteName::Ceses => "ceses",
+            LilyNoteName::Ces => "ces",
+            LilyNoteName::C => "c",
+            LilyNoteName::Cis => "cis",
+            LilyNoteName::Cisis => "cisis",
+            LilyNoteName::Deses => "deses",
+            LilyNoteName::Des => "des",
+            LilyNoteName::D => "d",
+            LilyNoteName::Dis => "dis",
+            LilyNoteName::Disis => "disis",
+            LilyNoteName::Eses => "eses",
+            LilyNoteName::Es => "es",
+            LilyNoteName::E => "e",
+            LilyNoteName::Eis => "eis",
+            LilyNoteName::Eisis => "eisis",
+            LilyNoteName::Feses => "feses",
+            LilyNoteName::Fes => "fes",
+            LilyNoteName::F => "f",
+            LilyNoteName::Fis => "fis",
+            LilyNoteName::Fisis => "fisis",
+            LilyNoteName::Geses => "geses",
+            LilyNoteName::Ges => "ges",
+            LilyNoteName::G => "g",
+            LilyNoteName::Gis => "gis",
+            LilyNoteName::Gisis => "gisis",
+            LilyNoteName::Asas => "asas",
+            LilyNoteName::As => "as",
+            LilyNoteName::A => "a",
+            LilyNoteName::Ais => "ais",
+            LilyNoteName::Aisis => "aisis",
+            LilyNoteName::Heses => "heses",
+            LilyNoteName::Hes => "b",
+            LilyNoteName::H => "h",
+            LilyNoteName::His => "his",
+            LilyNoteName::Hisis => "hisis",
+        }
+    }
+}
+
+impl LilyWrite for LilyNoteName {
+    fn write_lily(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        language: LilyLanguage,
+    ) -> std::fmt::Result {
+        const LETTERS: [&str; 7] = ["c", "d", "e", "f", "g", "a", "b"];
+        const SOLFEGE: [&str; 7] = ["do", "re", "mi", "fa", "sol", "la", "si"];
+
+        let letter = self.letter() as usize;
+        let alteration = (self.alteration() + 2) as usize;
+
+        let (name, suffixes) = match language {
+            LilyLanguage::Deutsch => return write!(f, "{}", self.deutsch()),
+            LilyLanguage::English => (LETTERS[letter], ["ff", "f", "", "s", "ss"]),
+            LilyLanguage::Nederlands => match letter {
+                // Shortened "ees" and "aes"
+                2 | 5 => (LETTERS[letter], ["ses", "s", "", "is", "isis"]),
+                _ => (LETTERS[letter], ["eses", "es", "", "is", "isis"]),
+            },
+            LilyLanguage::Italiano => (SOLFEGE[letter], ["bb", "b", "", "d", "dd"]),
+            LilyLanguage::Espanol => (SOLFEGE[letter], ["bb", "b", "", "s", "ss"]),
+        };
+
+        write!(f, "{name}{}", suffixes[alteration])
+    }
+}
+
 impl Display for LilyNoteName {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                LilyNoteName::Ces => "ces",
-                LilyNoteName::C => "c",
-                LilyNoteName::Cis => "cis",
-                LilyNoteName::Des => "des",
-                LilyNoteName::D => "d",
-                LilyNoteName::Dis => "dis",
-                LilyNoteName::Es => "es",
-                LilyNoteName::E => "e",
-                LilyNoteName::Eis => "eis",
-                LilyNoteName::Fes => "fes",
-                LilyNoteName::F => "f",
-                LilyNoteName::Fis => "fis",
-                LilyNoteName::Ges => "ges",
-                LilyNoteName::G => "g",
-                LilyNoteName::Gis => "gis",
-                LilyNoteName::As => "as",
-                LilyNoteName::A => "a",
-                LilyNoteName::Ais => "ais",
-                LilyNoteName::Hes => "b",
-                LilyNoteName::H => "h",
-                LilyNoteName::His => "his",
-            }
-        )
+        self.write_lily(f, LilyLanguage::default())
     }
 }
 
@@ -129,6 +234,11 @@ impl Display for LilyNoteName {
 pub enum LilyKeyType {
     Major,
     Minor,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Locrian,
 }
 
 impl From<KeySignatureType> for LilyKeyType {
@@ -136,6 +246,11 @@ impl From<KeySignatureType> for LilyKeyType {
         match sig_type {
             KeySignatureType::Maj => LilyKeyType::Major,
             KeySignatureType::Min => LilyKeyType::Minor,
+            KeySignatureType::Dorian => LilyKeyType::Dorian,
+            KeySignatureType::Phrygian => LilyKeyType::Phrygian,
+            KeySignatureType::Lydian => LilyKeyType::Lydian,
+            KeySignatureType::Mixolydian => LilyKeyType::Mixolydian,
+            KeySignatureType::Locrian => LilyKeyType::Locrian,
         }
     }
 }
@@ -148,6 +263,11 @@ impl Display for LilyKeyType {
             match self {
                 LilyKeyType::Major => "\\major",
                 LilyKeyType::Minor => "\\minor",
+                LilyKeyType::Dorian => "\\dorian",
+                LilyKeyType::Phrygian => "\\phrygian",
+                LilyKeyType::Lydian => "\\lydian",
+                LilyKeyType::Mixolydian => "\\mixolydian",
+                LilyKeyType::Locrian => "\\locrian",
             }
         )
     }
@@ -175,17 +295,21 @@ impl From<NoteLength> for LilyNoteLength {
 }
 
 impl LilyNoteLength {
-    pub fn value_128(&self) -> u16 {
-        match self {
-            LilyNoteLength::L1 => 128,
-            LilyNoteLength::L2 => 64,
-            LilyNoteLength::L4 => 32,
-            LilyNoteLength::L8 => 16,
-            LilyNoteLength::L16 => 8,
-            LilyNoteLength::L32 => 4,
-            LilyNoteLength::L64 => 2,
-            LilyNoteLength::L128 => 1,
-        }
+    /// Fraction of the whole note.
+    pub fn value(&self) -> Rational {
+        Rational::new(
+            1,
+            match self {
+                LilyNoteLength::L1 => 1,
+                LilyNoteLength::L2 => 2,
+                LilyNoteLength::L4 => 4,
+                LilyNoteLength::L8 => 8,
+                LilyNoteLength::L16 => 16,
+                LilyNoteLength::L32 => 32,
+                LilyNoteLength::L64 => 64,
+                LilyNoteLength::L128 => 128,
+            },
+        )
     }
 }
 
@@ -208,26 +332,81 @@ impl Display for LilyNoteLength {
     }
 }
 
+/// Written note length with dots. Tuplets are written as `LilyTuplet`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LilyDuration {
+    pub length: LilyNoteLength,
+    pub dots: u8,
+    /// Tuplet of a note outside of a complete `LilyTuplet`, which is written as
+    /// the `*normal/actual` multiplier.
+    pub scale: Option<Tuplet>,
+}
+
+impl From<LilyNoteLength> for LilyDuration {
+    fn from(length: LilyNoteLength) -> Self {
+        Self {
+            length,
+            dots: 0,
+            scale: None,
+        }
+    }
+}
+
+impl From<Duration> for LilyDuration {
+    fn from(duration: Duration) -> Self {
+        Self {
+            length: duration.base.into(),
+            dots: duration.dots,
+            scale: None,
+        }
+    }
+}
+
+impl LilyDuration {
+    /// Fraction of the whole note.
+    pub fn value(&self) -> Rational {
+        let value = Duration::dotted(self.length.value(), self.dots);
+
+        match self.scale {
+            Some(tuplet) => value * tuplet.ratio(),
+            None => value,
+        }
+    }
+}
+
+impl Display for LilyDuration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.length, ".".repeat(self.dots as usize))?;
+        if let Some(tuplet) = self.scale {
+            write!(f, "*{}/{}", tuplet.normal(), tuplet.actual())?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LilyTime {
-    pub nom: u8,
-    pub denom: LilyNoteLength,
+    pub nom: u32,
+    pub denom: u32,
 }
 
 impl LilyTime {
     pub fn c() -> Self {
-        Self {
-            nom: 4,
-            denom: LilyNoteLength::L4,
-        }
+        Self { nom: 4, denom: 4 }
+    }
+
+    /// Length of a single bar as a fraction of the whole note.
+    pub fn bar_length(&self) -> Rational {
+        Rational::new(self.nom, self.denom)
     }
 }
 
 impl From<TimeSignature> for LilyTime {
     fn from(time: TimeSignature) -> Self {
         Self {
-            nom: time.beat_count,
-            denom: time.single_beat_note.into(),
+            nom: time.numerator(),
+            denom: time.denominator(),
         }
     }
 }
@@ -240,7 +419,7 @@ impl Display for LilyTime {
 
 #[derive(Debug, Clone)]
 pub struct LilyTempo {
-    pub note_length: LilyNoteLength,
+    pub note_length: LilyDuration,
     pub speed: u8,
 }
 
@@ -274,9 +453,24 @@ impl From<KeySignature> for LilyKey {
     }
 }
 
+impl LilyWrite for LilyKey {
+    fn write_lily(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        language: LilyLanguage,
+    ) -> std::fmt::Result {
+        write!(
+            f,
+            "\\key {} {}",
+            self.note.in_language(language),
+            self.key_type
+        )
+    }
+}
+
 impl Display for LilyKey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "\\key {} {}", self.note, self.key_type)
+        self.write_lily(f, LilyLanguage::default())
     }
 }
 
@@ -293,6 +487,23 @@ pub enum OctaveRelative {
     Down(u8),
 }
 
+impl OctaveRelative {
+    /// Number of octaves by which the marks move the note up (positive) or down (negative).
+    pub fn offset(&self) -> i32 {
+        match self {
+            OctaveRelative::Up(times) => *times as i32,
+            OctaveRelative::Down(times) => -(*times as i32),
+        }
+    }
+
+    pub fn from_offset(offset: i32) -> Self {
+        match u8::try_from(offset) {
+            Ok(times) => OctaveRelative::Up(times),
+            Err(_) => OctaveRelative::Down(offset.unsigned_abs() as u8),
+        }
+    }
+}
+
 impl From<Octave> for OctaveRelative {
     fn from(octave: Octave) -> Self {
         match octave {
@@ -347,6 +558,20 @@ mod tests {
         assert_eq!(OctaveRelative::Down(5).to_string(), ",,,,,");
     }
 
+    #[test]
+    fn to_string_note_name_languages() {
+        let names = |language| {
+            [LilyNoteName::Fis, LilyNoteName::Hes, LilyNoteName::Asas]
+                .map(|n| n.in_language(language).to_string())
+        };
+
+        assert_eq!(names(LilyLanguage::Deutsch), ["fis", "b", "asas"]);
+        assert_eq!(names(LilyLanguage::English), ["fs", "bf", "aff"]);
+        assert_eq!(names(LilyLanguage::Nederlands), ["fis", "bes", "ases"]);
+        assert_eq!(names(LilyLanguage::Italiano), ["fad", "sib", "labb"]);
+        assert_eq!(names(LilyLanguage::Espanol), ["fas", "sib", "labb"]);
+    }
+
     #[test]
     fn to_string_clef() {
         assert_eq!(LilyClef::Treble.to_string(), "\\clef treble");
@@ -376,23 +601,25 @@ mod tests {
 
     #[test]
     fn to_string_time() {
-        assert_eq!(
-            LilyTime {
-                nom: 3,
-                denom: LilyNoteLength::L4
-            }
-            .to_string(),
-            "\\time 3/4"
-        );
+        assert_eq!(LilyTime { nom: 3, denom: 4 }.to_string(), "\\time 3/4");
 
-        assert_eq!(
-            LilyTime {
-                nom: 6,
-                denom: LilyNoteLength::L8
-            }
-            .to_string(),
-            "\\time 6/8"
-        );
+        assert_eq!(LilyTime { nom: 6, denom: 8 }.to_string(), "\\time 6/8");
+    }
+
+    #[test]
+    fn dotted_time_into_lily() {
+        // Arrange
+        let time = TimeSignature {
+            beat_count: 2,
+            single_beat_note: Duration::new(NoteLength::L4, 1, None),
+        };
+
+        // Act
+        let lily = LilyTime::from(time);
+
+        // Assert
+        assert_eq!(lily.to_string(), "\\time 6/8");
+        assert_eq!(lily.bar_length(), time.bar_length());
     }
 
     #[test]
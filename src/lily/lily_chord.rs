@@ -0,0 +1,92 @@
+//! Lilypond chord definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use crate::{
+    lily::{LilyDuration, LilyLanguage, LilyNoteName, LilyWrite, OctaveRelative},
+    notation::Chord,
+};
+
+#[derive(Debug, Clone)]
+pub struct LilyChord {
+    pub notes: Vec<(LilyNoteName, OctaveRelative)>,
+    pub length: LilyDuration,
+    /// Whether the chord is tied to the following one.
+    pub tie: bool,
+}
+
+impl From<Chord> for LilyChord {
+    fn from(chord: Chord) -> Self {
+        Self {
+            notes: chord
+                .pitches
+                .into_iter()
+                .map(|p| (p.ext.into(), p.octave.into()))
+                .collect(),
+            length: chord.duration.into(),
+            tie: chord.tie,
+        }
+    }
+}
+
+impl LilyWrite for LilyChord {
+    fn write_lily(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        language: LilyLanguage,
+    ) -> std::fmt::Result {
+        let notes = self
+            .notes
+            .iter()
+            .map(|(name, octave)| format!("{}{octave}", name.in_language(language)))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let tie = if self.tie { "~" } else { "" };
+
+        write!(f, "<{notes}>{}{tie}", self.length)
+    }
+}
+
+impl std::fmt::Display for LilyChord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_lily(f, LilyLanguage::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lily::LilyNoteLength;
+
+    #[test]
+    fn to_string() {
+        assert_eq!(
+            LilyChord {
+                notes: vec![
+                    (LilyNoteName::C, OctaveRelative::Up(0)),
+                    (LilyNoteName::Es, OctaveRelative::Up(0)),
+                    (LilyNoteName::G, OctaveRelative::Up(0)),
+                ],
+                length: LilyNoteLength::L2.into(),
+                tie: false,
+            }
+            .to_string(),
+            "<c es g>2"
+        );
+
+        assert_eq!(
+            LilyChord {
+                notes: vec![
+                    (LilyNoteName::H, OctaveRelative::Down(1)),
+                    (LilyNoteName::Dis, OctaveRelative::Up(0)),
+                ],
+                length: LilyNoteLength::L8.into(),
+                tie: false,
+            }
+            .to_string(),
+            "<h, dis>8"
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Lilypond diagnostics and source map definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use std::{fmt::Display, ops::Range};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            }
+        )
+    }
+}
+
+/// Position in the Lilypond source. Line is counted from 1, column from 0 as Lilypond does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourcePosition {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Error or warning reported by Lilypond.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LilyDiagnostic {
+    pub severity: Severity,
+    /// Position of the problem, if Lilypond reported any.
+    pub position: Option<SourcePosition>,
+    pub message: String,
+}
+
+impl LilyDiagnostic {
+    /// Parse all `file:line:column: severity: message` lines of the Lilypond output.
+    ///
+    /// Other lines (progress, excerpts of the source) are ignored.
+    pub fn parse(output: &str) -> Vec<Self> {
+        static REG: Lazy<Regex> = Lazy::new(|| {
+            Regex::new(r"^(?:.*?:(\d+):(\d+): )?((?:fatal |programming )?error|warning): (.*)$")
+                .unwrap()
+        });
+
+        output
+            .lines()
+            .filter_map(|line| {
+                let captures = REG.captures(line.trim_end())?;
+                let position = match (captures.get(1), captures.get(2)) {
+                    (Some(line), Some(column)) => Some(SourcePosition {
+                        line: line.as_str().parse().ok()?,
+                        column: column.as_str().parse().ok()?,
+                    }),
+                    _ => None,
+                };
+                let severity = match &captures[3] {
+                    "warning" => Severity::Warning,
+                    _ => Severity::Error,
+                };
+
+                Some(Self {
+                    severity,
+                    position,
+                    message: captures[4].to_owned(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Display for LilyDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(position) = self.position {
+            write!(f, "{}:{}: ", position.line, position.column)?;
+        }
+
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+/// Symbol of the Lilypond score.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolLocation {
+    /// Index of the stave, which is the same in the `Score` and `Lilypond`.
+    pub stave: usize,
+    /// Index of the `LilySymbol` in the stave.
+    pub symbol: usize,
+    /// Indices of the `Score` symbols the `LilySymbol` was made of.
+    pub score_symbols: Range<usize>,
+}
+
+/// Lilypond source along with the positions of the written stave symbols.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    pub(super) source: String,
+    pub(super) symbols: Vec<(Range<usize>, SymbolLocation)>,
+}
+
+impl SourceMap {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Byte offset of the position in the source.
+    pub fn offset(&self, position: SourcePosition) -> Option<usize> {
+        let line_start = match position.line {
+            0 => return None,
+            1 => 0,
+            n => self.source.match_indices('\n').nth(n - 2)?.0 + 1,
+        };
+        let line = self.source[line_start..].split('\n').next()?;
+
+        // Lilypond counts characters, the source may contain UTF-8 text in the header.
+        let column = match line.char_indices().nth(position.column) {
+            Some((i, _)) => i,
+            None if line.chars().count() == position.column => line.len(),
+            None => return None,
+        };
+
+        Some(line_start + column)
+    }
+
+    /// Symbol written at the position.
+    pub fn locate(&self, position: SourcePosition) -> Option<&SymbolLocation> {
+        let offset = self.offset(position)?;
+
+        self.symbols
+            .iter()
+            .find(|(range, _)| range.contains(&offset))
+            .map(|(_, location)| location)
+    }
+
+    /// Source text of the symbol.
+    pub fn symbol_source(&self, location: &SymbolLocation) -> Option<&str> {
+        self.symbols
+            .iter()
+            .find(|(_, l)| l == location)
+            .map(|(range, _)| &self.source[range.clone()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lily::{lily_stave::tests::stave_d_maj, Lilypond};
+
+    #[test]
+    fn parse_diagnostics() {
+        // Arrange
+        let output = "Processing `/tmp/input.ily'\n\
+            Parsing...\n\
+            /tmp/input.ily:7:21: error: not a note name: x\n\
+            \\new Staff { c4 d4 \n\
+            \x20                   x4 }\n\
+            /tmp/input.ily:8:0: warning: barcheck failed at: 1/4\n\
+            fatal error: failed files: \"/tmp/input.ily\"\n";
+
+        // Act
+        let diagnostics = LilyDiagnostic::parse(output);
+
+        // Assert
+        assert_eq!(
+            diagnostics,
+            vec![
+                LilyDiagnostic {
+                    severity: Severity::Error,
+                    position: Some(SourcePosition {
+                        line: 7,
+                        column: 21
+                    }),
+                    message: "not a note name: x".to_owned(),
+                },
+                LilyDiagnostic {
+                    severity: Severity::Warning,
+                    position: Some(SourcePosition { line: 8, column: 0 }),
+                    message: "barcheck failed at: 1/4".to_owned(),
+                },
+                LilyDiagnostic {
+                    severity: Severity::Error,
+                    position: None,
+                    message: "failed files: \"/tmp/input.ily\"".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn locate_symbols() {
+        // Arrange
+        let (d_maj, _) = stave_d_maj();
+        let lilypond = Lilypond {
+            staves: vec![d_maj.clone(), d_maj.clone()],
+            ..Default::default()
+        };
+
+        // Act
+        let source_map = lilypond.source_map();
+
+        // Assert
+        assert_eq!(source_map.source(), lilypond.to_string());
+        for (range, location) in source_map.symbols.iter() {
+            let stave = &lilypond.staves[location.stave];
+            assert_eq!(
+                &source_map.source()[range.clone()],
+                stave.symbols[location.symbol].to_string()
+            );
+
+            let start = source_map.source()[..range.start].rfind('\n').unwrap() + 1;
+            let position = SourcePosition {
+                line: source_map.source()[..start].matches('\n').count() + 1,
+                column: range.start - start,
+            };
+            assert_eq!(source_map.locate(position), Some(location));
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Lilypond header definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use crate::notation::ScoreInfo;
+
+/// Titles of the score printed above the first stave.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LilyHeader {
+    pub title: Option<String>,
+    pub composer: Option<String>,
+    pub arranger: Option<String>,
+    /// Text at the bottom of the last page. Default Lilypond tagline is hidden if `None`.
+    pub tagline: Option<String>,
+}
+
+impl From<ScoreInfo> for LilyHeader {
+    fn from(info: ScoreInfo) -> Self {
+        Self {
+            title: info.name,
+            composer: info.author,
+            arranger: info.transcriber,
+            tagline: None,
+        }
+    }
+}
+
+/// Quote the text as a Lilypond string.
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl std::fmt::Display for LilyHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = [
+            ("title", &self.title),
+            ("composer", &self.composer),
+            ("arranger", &self.arranger),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some(format!("{name} = {}", quoted(value.as_ref()?))))
+        .chain(std::iter::once(format!(
+            "tagline = {}",
+            self.tagline
+                .as_deref()
+                .map(quoted)
+                .unwrap_or("##f".to_owned())
+        )))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+        write!(f, "\\header{{{fields}}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_string() {
+        // Arrange
+        let header = LilyHeader {
+            title: Some("Song \"One\"".to_owned()),
+            composer: Some("L-system".to_owned()),
+            ..Default::default()
+        };
+
+        // Act && Assert
+        assert_eq!(
+            header.to_string(),
+            "\\header{title = \"Song \\\"One\\\"\" composer = \"L-system\" tagline = ##f}"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+//! Lilypond note name language definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use std::fmt::{Display, Formatter, Result};
+
+/// Language of the note names in the Lilypond source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum LilyLanguage {
+    #[default]
+    Deutsch,
+    English,
+    Nederlands,
+    Italiano,
+    Espanol,
+}
+
+impl LilyLanguage {
+    pub const ALL: [LilyLanguage; 5] = [
+        LilyLanguage::Deutsch,
+        LilyLanguage::English,
+        LilyLanguage::Nederlands,
+        LilyLanguage::Italiano,
+        LilyLanguage::Espanol,
+    ];
+
+    /// Name used in the `\language` command.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LilyLanguage::Deutsch => "deutsch",
+            LilyLanguage::English => "english",
+            LilyLanguage::Nederlands => "nederlands",
+            LilyLanguage::Italiano => "italiano",
+            LilyLanguage::Espanol => "español",
+        }
+    }
+}
+
+/// Lilypond code which contains note names and therefore depends on the language.
+pub trait LilyWrite {
+    fn write_lily(&self, f: &mut Formatter<'_>, language: LilyLanguage) -> Result;
+
+    /// Displayable wrapper writing the code in the given language.
+    fn in_language(&self, language: LilyLanguage) -> InLanguage<'_, Self>
+    where
+        Self: Sized,
+    {
+        InLanguage(self, language)
+    }
+}
+
+/// Lilypond code written in the given note name language.
+pub struct InLanguage<'a, T>(&'a T, LilyLanguage);
+
+impl<T: LilyWrite> Display for InLanguage<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.0.write_lily(f, self.1)
+    }
+}
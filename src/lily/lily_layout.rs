@@ -0,0 +1,128 @@
+//! Lilypond paper and layout definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaperSize {
+    A4,
+    A5,
+    B5,
+    Letter,
+    Legal,
+}
+
+impl PaperSize {
+    pub const ALL: [PaperSize; 5] = [
+        PaperSize::A4,
+        PaperSize::A5,
+        PaperSize::B5,
+        PaperSize::Letter,
+        PaperSize::Legal,
+    ];
+
+    /// Name of the paper size used by Lilypond.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PaperSize::A4 => "a4",
+            PaperSize::A5 => "a5",
+            PaperSize::B5 => "b5",
+            PaperSize::Letter => "letter",
+            PaperSize::Legal => "legal",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+/// Page and system layout of the engraved score.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LilyLayout {
+    pub paper_size: PaperSize,
+    pub orientation: Orientation,
+    /// Height of the stave in points.
+    pub staff_size: u8,
+    /// Whether the last system is left at its natural width instead of filling the line.
+    pub ragged_last: bool,
+    /// Fixed number of systems on each page, chosen by Lilypond if `None`.
+    pub systems_per_page: Option<u8>,
+}
+
+impl Default for LilyLayout {
+    fn default() -> Self {
+        Self {
+            paper_size: PaperSize::A4,
+            orientation: Orientation::Portrait,
+            staff_size: 20,
+            ragged_last: false,
+            systems_per_page: None,
+        }
+    }
+}
+
+impl LilyLayout {
+    /// Top level command setting the staff size.
+    pub fn staff_size(&self) -> String {
+        format!("#(set-global-staff-size {})", self.staff_size)
+    }
+
+    /// The `\paper` block.
+    pub fn paper(&self) -> String {
+        let orientation = match self.orientation {
+            Orientation::Portrait => "",
+            Orientation::Landscape => " 'landscape",
+        };
+        let systems = self
+            .systems_per_page
+            .map(|n| format!(" systems-per-page = {n}"))
+            .unwrap_or_default();
+
+        format!(
+            "\\paper{{#(set-paper-size \"{}\"{orientation}){systems}}}",
+            self.paper_size.name()
+        )
+    }
+
+    /// The `\layout` block of the score.
+    pub fn layout(&self) -> String {
+        match self.ragged_last {
+            true => "\\layout{ragged-last = ##t}".to_owned(),
+            false => "\\layout{}".to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks() {
+        // Arrange
+        let layout = LilyLayout {
+            paper_size: PaperSize::Letter,
+            orientation: Orientation::Landscape,
+            staff_size: 18,
+            ragged_last: true,
+            systems_per_page: Some(4),
+        };
+
+        // Act && Assert
+        assert_eq!(layout.staff_size(), "#(set-global-staff-size 18)");
+        assert_eq!(
+            layout.paper(),
+            "\\paper{#(set-paper-size \"letter\" 'landscape) systems-per-page = 4}"
+        );
+        assert_eq!(layout.layout(), "\\layout{ragged-last = ##t}");
+        assert_eq!(
+            LilyLayout::default().paper(),
+            "\\paper{#(set-paper-size \"a4\")}"
+        );
+    }
+}
@@ -4,7 +4,7 @@
 //! Jakub Kloub (xkloub03), VUT FIT
 
 use crate::{
-    lily::{LilyNoteLength, LilyNoteName, OctaveRelative},
+    lily::{LilyDuration, LilyLanguage, LilyNoteName, LilyWrite, OctaveRelative},
     notation::Note,
 };
 
@@ -12,7 +12,9 @@ use crate::{
 pub struct LilyNote {
     pub note_name: LilyNoteName,
     pub octave_relative: OctaveRelative,
-    pub length: LilyNoteLength,
+    pub length: LilyDuration,
+    /// Whether the note is tied to the following one.
+    pub tie: bool,
 }
 
 impl From<Note> for LilyNote {
@@ -23,23 +25,38 @@ impl From<Note> for LilyNote {
             note_name: note.pitch.into(),
             length: note.duration.into(),
             octave_relative: octave.into(),
+            tie: note.tie,
         }
     }
 }
 
-impl std::fmt::Display for LilyNote {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl LilyWrite for LilyNote {
+    fn write_lily(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        language: LilyLanguage,
+    ) -> std::fmt::Result {
         write!(
             f,
-            "{}{}{}",
-            self.note_name, self.octave_relative, self.length
+            "{}{}{}{}",
+            self.note_name.in_language(language),
+            self.octave_relative,
+            self.length,
+            if self.tie { "~" } else { "" }
         )
     }
 }
 
+impl std::fmt::Display for LilyNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_lily(f, LilyLanguage::default())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lily::LilyNoteLength;
 
     #[test]
     fn to_string() {
@@ -47,7 +64,8 @@ mod tests {
             LilyNote {
                 note_name: LilyNoteName::Des,
                 octave_relative: OctaveRelative::Up(2),
-                length: LilyNoteLength::L8,
+                length: LilyNoteLength::L8.into(),
+                tie: false,
             }
             .to_string(),
             "des''8"
@@ -57,7 +75,8 @@ mod tests {
             LilyNote {
                 note_name: LilyNoteName::C,
                 octave_relative: OctaveRelative::Down(1),
-                length: LilyNoteLength::L1,
+                length: LilyNoteLength::L1.into(),
+                tie: false,
             }
             .to_string(),
             "c,1"
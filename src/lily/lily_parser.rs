@@ -0,0 +1,1329 @@
+//! Lilypond source parser definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use std::collections::HashMap;
+
+use crate::{
+    error::{AppError, Result},
+    lily::{LilyLanguage, LilyNoteName, LilyWrite},
+    notation::{
+        Chord, Clef, Duration, KeySignature, KeySignatureType, Note, NoteLength, Octave, Pitch,
+        Rational, Score, ScoreInfo, Stave, Symbol, Tempo, TimeSignature, Tuplet, MAX_DOTS,
+    },
+};
+
+/// Maximal number of variable expansions, which stops self-referencing variables.
+const MAX_EXPANSIONS: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    OpenBrace,
+    CloseBrace,
+    OpenSimultaneous,
+    CloseSimultaneous,
+    OpenChord,
+    CloseChord,
+    Tie,
+    BarCheck,
+    Equals,
+    Dot,
+    Slash,
+    Star,
+    Dash,
+    Up,
+    Down,
+    /// Octave marks, positive for `'`, negative for `,`.
+    Marks(i32),
+    Number(u32),
+    Word(String),
+    Command(String),
+    Str(String),
+    Scheme,
+    Other(char),
+}
+
+/// Token along with its line in the source.
+type Lexed = (Token, usize);
+
+/// Split the source into tokens, dropping comments and whitespace.
+fn tokenize(source: &str) -> Result<Vec<Lexed>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut i = 0;
+
+    // Advance while the condition holds, counting the lines.
+    let skip_while = |i: &mut usize, line: &mut usize, cond: &dyn Fn(usize) -> bool| {
+        while *i < chars.len() && cond(*i) {
+            if chars[*i] == '\n' {
+                *line += 1;
+            }
+            *i += 1;
+        }
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let start_line = line;
+
+        let token = match c {
+            c if c.is_whitespace() => {
+                skip_while(&mut i, &mut line, &|i| chars[i].is_whitespace());
+                continue;
+            }
+            '%' if next == Some('{') => {
+                i += 2;
+                skip_while(&mut i, &mut line, &|i| {
+                    !(chars[i] == '%' && chars.get(i + 1) == Some(&'}'))
+                });
+                i += 2;
+                continue;
+            }
+            '%' => {
+                skip_while(&mut i, &mut line, &|i| chars[i] != '\n');
+                continue;
+            }
+            '"' => {
+                let mut text = String::new();
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    match chars[i] {
+                        '\\' if i + 1 < chars.len() => {
+                            i += 1;
+                            text.push(if chars[i] == 'n' { '\n' } else { chars[i] });
+                        }
+                        '\n' => {
+                            line += 1;
+                            text.push('\n');
+                        }
+                        c => text.push(c),
+                    }
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    Err(AppError::LilyParse(
+                        start_line,
+                        "Unterminated string".to_owned(),
+                    ))?;
+                }
+                i += 1;
+                tokens.push((Token::Str(text), start_line));
+                continue;
+            }
+            '#' => {
+                i += 1;
+                match chars.get(i) {
+                    Some('(') => {
+                        let mut depth = 0;
+                        let mut in_string = false;
+                        while i < chars.len() {
+                            match chars[i] {
+                                '"' => in_string = !in_string,
+                                '\\' if in_string => i += 1,
+                                '(' if !in_string => depth += 1,
+                                ')' if !in_string => depth -= 1,
+                                '\n' => line += 1,
+                                _ => {}
+                            }
+                            i += 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                    }
+                    Some('"') => {
+                        i += 1;
+                        skip_while(&mut i, &mut line, &|i| chars[i] != '"');
+                        i += 1;
+                    }
+                    _ => skip_while(&mut i, &mut line, &|i| {
+                        !chars[i].is_whitespace() && !"{}<>".contains(chars[i])
+                    }),
+                }
+                tokens.push((Token::Scheme, start_line));
+                continue;
+            }
+            '\\' => {
+                i += 1;
+                let start = i;
+                skip_while(&mut i, &mut line, &|i| chars[i].is_ascii_alphabetic());
+                if i == start && i < chars.len() {
+                    i += 1;
+                }
+                let name: String = chars[start..i].iter().collect();
+                tokens.push((Token::Command(name), start_line));
+                continue;
+            }
+            '\'' | ',' => {
+                let start = i;
+                skip_while(&mut i, &mut line, &|i| chars[i] == c);
+                let count = (i - start) as i32;
+                let marks = if c == '\'' { count } else { -count };
+                tokens.push((Token::Marks(marks), start_line));
+                continue;
+            }
+            c if c.is_ascii_digit() => {
+                let start = i;
+                skip_while(&mut i, &mut line, &|i| chars[i].is_ascii_digit());
+                let text: String = chars[start..i].iter().collect();
+                let number = text.parse().map_err(|_| {
+                    AppError::LilyParse(start_line, format!("Number '{text}' is too big"))
+                })?;
+                tokens.push((Token::Number(number), start_line));
+                continue;
+            }
+            c if c.is_alphabetic() => {
+                let start = i;
+                skip_while(&mut i, &mut line, &|i| chars[i].is_alphabetic());
+                tokens.push((Token::Word(chars[start..i].iter().collect()), start_line));
+                continue;
+            }
+            '<' if next == Some('<') => Token::OpenSimultaneous,
+            '>' if next == Some('>') => Token::CloseSimultaneous,
+            '{' => Token::OpenBrace,
+            '}' => Token::CloseBrace,
+            '<' => Token::OpenChord,
+            '>' => Token::CloseChord,
+            '~' => Token::Tie,
+            '|' => Token::BarCheck,
+            '=' => Token::Equals,
+            '.' => Token::Dot,
+            '/' => Token::Slash,
+            '*' => Token::Star,
+            '-' => Token::Dash,
+            '^' => Token::Up,
+            '_' => Token::Down,
+            c => Token::Other(c),
+        };
+
+        i += match token {
+            Token::OpenSimultaneous | Token::CloseSimultaneous => 2,
+            _ => 1,
+        };
+        tokens.push((token, start_line));
+    }
+
+    Ok(tokens)
+}
+
+/// State of the parsed music, which carries over between the notes.
+#[derive(Debug, Clone)]
+struct MusicState {
+    /// Position of the previous note in the `\relative` mode, counted in note names.
+    relative: Option<i32>,
+    /// Octave offset of the `\fixed` mode.
+    fixed: i32,
+    /// Duration of the previous note, used when the duration is omitted.
+    duration: Duration,
+    /// Tuplet of the enclosing `\tuplet` expression.
+    tuplet: Option<Tuplet>,
+    /// Pitches of the previous chord, repeated by `q`.
+    chord: Vec<Pitch>,
+}
+
+impl Default for MusicState {
+    fn default() -> Self {
+        Self {
+            relative: None,
+            fixed: 0,
+            duration: NoteLength::L4.into(),
+            tuplet: None,
+            chord: vec![],
+        }
+    }
+}
+
+/// Parser of the Lilypond subset written by the backend and common hand-written constructs:
+/// `\relative` and `\fixed` modes, chords, rests, ties, tuplets, variables and
+/// clef, key, time and tempo changes. Staves without a key get the C major key.
+///
+/// # EXAMPLE
+/// ```
+/// # use music_sheet_gen::{lily::LilyParser, notation::*};
+///
+/// let score = LilyParser::parse("\\relative c' { \\time 3/4 c4 e g~ g2. }").unwrap();
+///
+/// assert_eq!(score.staves.len(), 1);
+/// assert_eq!(score.staves[0].symbols.len(), 6);
+/// assert!(score.sanitized().is_ok());
+/// ```
+#[derive(Debug)]
+pub struct LilyParser {
+    tokens: Vec<Lexed>,
+    pos: usize,
+    /// Note names of the current `\language`.
+    note_names: HashMap<String, LilyNoteName>,
+    variables: HashMap<String, Vec<Lexed>>,
+    expansions: usize,
+    state: MusicState,
+    info: ScoreInfo,
+    /// Tempo of the `\midi` block.
+    midi_tempo: Option<u32>,
+    staves: Vec<Stave>,
+}
+
+impl LilyParser {
+    /// Parse the Lilypond source into a score.
+    pub fn parse(source: &str) -> Result<Score> {
+        let mut parser = Self {
+            tokens: tokenize(source)?,
+            pos: 0,
+            note_names: HashMap::new(),
+            variables: HashMap::new(),
+            expansions: 0,
+            state: MusicState::default(),
+            info: ScoreInfo::default(),
+            midi_tempo: None,
+            staves: vec![],
+        };
+        // Default language of Lilypond.
+        parser.set_language(LilyLanguage::Nederlands);
+
+        parser.file()?;
+        parser.into_score()
+    }
+
+    fn into_score(self) -> Result<Score> {
+        if self.staves.is_empty() {
+            Err(AppError::LilyParse(
+                0,
+                "Source contains no music".to_owned(),
+            ))?;
+        }
+
+        let first_tempo = self
+            .staves
+            .iter()
+            .flat_map(|s| s.symbols.iter())
+            .find_map(|s| match s {
+                Symbol::Tempo(tempo) => Some(tempo.quarters_per_minute()),
+                _ => None,
+            });
+        let default = Score::default();
+
+        Ok(Score {
+            tempo: self.midi_tempo.or(first_tempo).unwrap_or(default.tempo),
+            staves: self.staves,
+            info: self.info,
+            spelling: None,
+        })
+    }
+
+    fn set_language(&mut self, language: LilyLanguage) {
+        self.note_names = LilyNoteName::ALL
+            .iter()
+            .map(|&n| (n.in_language(language).to_string(), n))
+            .collect();
+
+        // Long forms accepted by Lilypond along with the shortened ones.
+        if language == LilyLanguage::Nederlands {
+            use LilyNoteName::*;
+            for (name, note) in [("ees", Es), ("eeses", Eses), ("aes", As), ("aeses", Asas)] {
+                self.note_names.insert(name.to_owned(), note);
+            }
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> Box<dyn std::error::Error> {
+        let line = self
+            .tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map_or(0, |(_, line)| *line);
+
+        AppError::LilyParse(line, message.into()).into()
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset).map(|(t, _)| t)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| self.error("Unexpected end of the source"))?;
+        self.pos += 1;
+
+        Ok(token)
+    }
+
+    /// Consume the token if it is the expected one.
+    fn accept(&mut self, expected: &Token) -> bool {
+        let accepted = self.peek() == Some(expected);
+        if accepted {
+            self.pos += 1;
+        }
+
+        accepted
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        if !self.accept(&expected) {
+            Err(self.error(format!("Expected {expected:?}, found {:?}", self.peek())))?;
+        }
+
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<u32> {
+        match self.next()? {
+            Token::Number(n) => Ok(n),
+            t => Err(self.error(format!("Expected a number, found {t:?}"))),
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Str(s) | Token::Word(s) => Ok(s),
+            t => Err(self.error(format!("Expected a string, found {t:?}"))),
+        }
+    }
+
+    /// Skip the braced block along with all nested blocks.
+    fn skip_block(&mut self) -> Result<()> {
+        self.expect(Token::OpenBrace)?;
+
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next()? {
+                Token::OpenBrace => depth += 1,
+                Token::CloseBrace => depth -= 1,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replace the variable reference at the current position with its value.
+    fn expand(&mut self, name: &str) -> Result<bool> {
+        let Some(value) = self.variables.get(name) else {
+            return Ok(false);
+        };
+
+        self.expansions += 1;
+        if self.expansions > MAX_EXPANSIONS {
+            Err(self.error(format!("Variable '{name}' is expanded too many times")))?;
+        }
+
+        let value = value.clone();
+        self.tokens.splice(self.pos..self.pos + 1, value);
+
+        Ok(true)
+    }
+
+    fn file(&mut self) -> Result<()> {
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::Command(c) => match c.as_str() {
+                    "version" => {
+                        self.pos += 1;
+                        self.string()?;
+                    }
+                    "language" => {
+                        self.pos += 1;
+                        let name = self.string()?;
+                        let language = LilyLanguage::ALL
+                            .into_iter()
+                            .find(|l| {
+                                l.name() == name
+                                    || (name == "espanol" && *l == LilyLanguage::Espanol)
+                            })
+                            .ok_or_else(|| self.error(format!("Unsupported language '{name}'")))?;
+                        self.set_language(language);
+                    }
+                    "include" => Err(self.error("Included files are not supported"))?,
+                    "score" => {
+                        self.pos += 1;
+                        self.score_block()?;
+                    }
+                    _ if self.block()? => {}
+                    _ => self.staves()?,
+                },
+                Token::Word(name) if self.peek_at(1) == Some(&Token::Equals) => {
+                    self.pos += 2;
+                    self.assignment(name)?;
+                }
+                Token::Scheme => self.pos += 1,
+                _ => self.staves()?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parse a `\header`, `\paper`, `\layout` or `\midi` block, if there is one.
+    fn block(&mut self) -> Result<bool> {
+        let Some(Token::Command(c)) = self.peek() else {
+            return Ok(false);
+        };
+
+        match c.as_str() {
+            "header" => {
+                self.pos += 1;
+                self.header()?;
+            }
+            "midi" => {
+                self.pos += 1;
+                self.midi()?;
+            }
+            "paper" | "layout" => {
+                self.pos += 1;
+                self.skip_block()?;
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    fn score_block(&mut self) -> Result<()> {
+        self.expect(Token::OpenBrace)?;
+
+        while !self.accept(&Token::CloseBrace) {
+            if !self.block()? {
+                self.staves()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn header(&mut self) -> Result<()> {
+        self.expect(Token::OpenBrace)?;
+
+        while !self.accept(&Token::CloseBrace) {
+            let Token::Word(field) = self.next()? else {
+                Err(self.error("Expected a header field"))?
+            };
+            self.expect(Token::Equals)?;
+
+            let value = match self.peek() {
+                Some(Token::Str(s)) => Some(s.clone()),
+                _ => None,
+            };
+
+            // Skip the value, which may be a markup.
+            self.pos += 1;
+            while !matches!(self.peek(), Some(Token::CloseBrace) | None)
+                && (!matches!(self.peek(), Some(Token::Word(_)))
+                    || self.peek_at(1) != Some(&Token::Equals))
+            {
+                if self.peek() == Some(&Token::OpenBrace) {
+                    self.skip_block()?;
+                } else {
+                    self.pos += 1;
+                }
+            }
+
+            match field.as_str() {
+                "title" => self.info.name = value,
+                "composer" => self.info.author = value,
+                "arranger" => self.info.transcriber = value,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn midi(&mut self) -> Result<()> {
+        self.expect(Token::OpenBrace)?;
+
+        while !self.accept(&Token::CloseBrace) {
+            if self.next()? == Token::Command("tempo".to_owned()) {
+                if let Some(tempo) = self.tempo()? {
+                    self.midi_tempo = Some(tempo.quarters_per_minute());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Store the value of the variable, which is a string, a number or a music expression
+    /// ending with a closing bracket, e.g. `\relative c' { ... }`.
+    fn assignment(&mut self, name: String) -> Result<()> {
+        let start = self.pos;
+
+        if !matches!(
+            self.next()?,
+            Token::Str(_) | Token::Number(_) | Token::Scheme
+        ) {
+            self.pos = start;
+            let mut depth = 0;
+            loop {
+                match self.next()? {
+                    Token::OpenBrace | Token::OpenSimultaneous => depth += 1,
+                    Token::CloseBrace | Token::CloseSimultaneous => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        self.variables
+            .insert(name, self.tokens[start..self.pos].to_vec());
+
+        Ok(())
+    }
+
+    /// Parse an expression, which contains whole staves.
+    fn staves(&mut self) -> Result<()> {
+        match self.peek().cloned() {
+            Some(Token::OpenSimultaneous) => {
+                self.pos += 1;
+                while !self.accept(&Token::CloseSimultaneous) {
+                    self.staves()?;
+                }
+            }
+            Some(Token::Command(c)) if c == "new" || c == "context" => {
+                let context = match self.peek_at(1) {
+                    Some(Token::Word(w)) => w.clone(),
+                    t => Err(self.error(format!("Expected a context name, found {t:?}")))?,
+                };
+                self.pos += 2;
+                self.context_options()?;
+
+                match context.as_str() {
+                    "PianoStaff" | "GrandStaff" | "StaffGroup" | "ChoirStaff" => self.staves()?,
+                    _ => self.stave()?,
+                }
+            }
+            Some(Token::Command(c)) if self.expand(&c)? => self.staves()?,
+            Some(_) => self.stave()?,
+            None => Err(self.error("Unexpected end of the source"))?,
+        }
+
+        Ok(())
+    }
+
+    /// Skip the name and `\with` block of a new context.
+    fn context_options(&mut self) -> Result<()> {
+        if self.accept(&Token::Equals) {
+            self.string()?;
+        }
+        if self.accept(&Token::Command("with".to_owned())) {
+            self.skip_block()?;
+        }
+
+        Ok(())
+    }
+
+    /// Parse a music expression into a new stave, which gets the C major key and 4/4 time
+    /// assumed by Lilypond, if it doesn't set them.
+    fn stave(&mut self) -> Result<()> {
+        let mut symbols = vec![];
+        self.state.relative = None;
+        self.state.fixed = 0;
+        self.music(&mut symbols)?;
+
+        let mut stave = Stave { symbols };
+        stave.insert_missing_signatures();
+        self.staves.push(stave);
+
+        Ok(())
+    }
+
+    /// Parse a single music expression into the symbols of a stave.
+    fn music(&mut self, symbols: &mut Vec<Symbol>) -> Result<()> {
+        match self.next()? {
+            Token::OpenBrace => {
+                while !self.accept(&Token::CloseBrace) {
+                    self.music(symbols)?;
+                }
+            }
+            Token::OpenSimultaneous => {
+                let mut voices = 0;
+                while !self.accept(&Token::CloseSimultaneous) {
+                    let before = symbols.len();
+                    self.music(symbols)?;
+                    if symbols[before..].iter().any(|s| s.duration().is_some()) {
+                        voices += 1;
+                    }
+                }
+                if voices > 1 {
+                    Err(self.error("Multiple voices in one stave are not supported"))?;
+                }
+            }
+            Token::OpenChord => self.chord(symbols)?,
+            Token::Word(word) => self.note(&word, symbols)?,
+            Token::Tie => Self::tie_last(symbols),
+            Token::Command(c) => self.command(&c, symbols)?,
+            Token::BarCheck | Token::Scheme | Token::Str(_) => {}
+            Token::Other('(' | ')' | '[' | ']') => {}
+            t => Err(self.error(format!("Unexpected {t:?} in music")))?,
+        }
+
+        Ok(())
+    }
+
+    fn command(&mut self, command: &str, symbols: &mut Vec<Symbol>) -> Result<()> {
+        match command {
+            "relative" => {
+                let reference = match self.peek() {
+                    Some(Token::Word(w)) if self.note_names.contains_key(w) => {
+                        let pitch = self.absolute_pitch()?;
+                        Self::position(&pitch)
+                    }
+                    // Without the start pitch, the first note is relative to `f`.
+                    _ => 3 * 7 + 3,
+                };
+
+                let outer = (self.state.relative, self.state.fixed);
+                self.state.relative = Some(reference);
+                self.music(symbols)?;
+                (self.state.relative, self.state.fixed) = outer;
+            }
+            "fixed" | "absolute" => {
+                let fixed = match command {
+                    "fixed" => self.absolute_pitch()?.octave.value() as i32 - 3,
+                    _ => 0,
+                };
+
+                let outer = (self.state.relative, self.state.fixed);
+                (self.state.relative, self.state.fixed) = (None, fixed);
+                self.music(symbols)?;
+                (self.state.relative, self.state.fixed) = outer;
+            }
+            "tuplet" | "times" => {
+                let (n, m) = (self.number()?, self.number_after_slash()?);
+                let (actual, normal) = if command == "tuplet" { (n, m) } else { (m, n) };
+                let tuplet = Tuplet::new(
+                    u8::try_from(actual).map_err(|_| self.error("Tuplet is too big"))?,
+                    u8::try_from(normal).map_err(|_| self.error("Tuplet is too big"))?,
+                )
+                .ok_or_else(|| self.error(format!("Unsupported tuplet {n}/{m}")))?;
+                // Optional duration of the tuplet groups.
+                if let Some(Token::Number(_)) = self.peek() {
+                    self.pos += 1;
+                    while self.accept(&Token::Dot) {}
+                }
+
+                let outer = self.state.tuplet;
+                self.state.tuplet = Some(tuplet);
+                self.music(symbols)?;
+                self.state.tuplet = outer;
+            }
+            "new" | "context" => {
+                self.next()?;
+                self.context_options()?;
+                self.music(symbols)?;
+            }
+            "repeat" => {
+                let kind = self.string()?;
+                let times = self.number()?;
+                let times = if kind == "volta" { 1 } else { times };
+
+                let start = self.pos;
+                for _ in 0..times {
+                    self.pos = start;
+                    self.music(symbols)?;
+                }
+                if self.accept(&Token::Command("alternative".to_owned())) {
+                    self.music(symbols)?;
+                }
+            }
+            "clef" => {
+                let clef = self.string()?;
+                symbols.push(Symbol::Clef(match clef.as_str() {
+                    "treble" | "violin" | "G" => Clef::Treble,
+                    "bass" | "F" => Clef::Bass,
+                    _ => Err(self.error(format!("Unsupported clef '{clef}'")))?,
+                }));
+                // Skip octave transposition of the clef, e.g. `G2`.
+                if let Some(Token::Number(_)) = self.peek() {
+                    self.pos += 1;
+                }
+            }
+            "key" => {
+                let pitch = self.absolute_pitch()?;
+                let signature_type = match self.next()? {
+                    Token::Command(mode) => match mode.as_str() {
+                        "major" | "ionian" => KeySignatureType::Maj,
+                        "minor" | "aeolian" => KeySignatureType::Min,
+                        "dorian" => KeySignatureType::Dorian,
+                        "phrygian" => KeySignatureType::Phrygian,
+                        "lydian" => KeySignatureType::Lydian,
+                        "mixolydian" => KeySignatureType::Mixolydian,
+                        "locrian" => KeySignatureType::Locrian,
+                        _ => Err(self.error(format!("Unsupported key mode '{mode}'")))?,
+                    },
+                    t => Err(self.error(format!("Expected a key mode, found {t:?}")))?,
+                };
+
+                symbols.push(Symbol::KeySignature(KeySignature {
+                    ext: pitch.ext,
+                    signature_type,
+                }));
+            }
+            "time" => {
+                let beat_count = self.number()?;
+                let single_beat_note = self.number_after_slash()?;
+                if beat_count == 0 {
+                    Err(self.error("Time signature has no beats"))?;
+                }
+
+                symbols.push(Symbol::TimeSignature(TimeSignature {
+                    beat_count: u8::try_from(beat_count)
+                        .map_err(|_| self.error("Time signature is too big"))?,
+                    single_beat_note: self.note_length(single_beat_note)?.into(),
+                }));
+            }
+            "tempo" => {
+                if let Some(tempo) = self.tempo()? {
+                    symbols.push(Symbol::Tempo(tempo));
+                }
+            }
+            "partial" => {
+                self.number()?;
+                while self.accept(&Token::Dot) {}
+                self.multiplier()?;
+            }
+            "bar" | "mark" | "markup" => {
+                self.next()?;
+            }
+            "override" | "set" | "tweak" => {
+                self.property_path()?;
+                self.accept(&Token::Equals);
+                self.next()?;
+            }
+            "revert" | "unset" | "omit" | "hide" => self.property_path()?,
+            "include" => Err(self.error("Included files are not supported"))?,
+            "\\" => Err(self.error("Multiple voices in one stave are not supported"))?,
+            name if self.expand_previous(name)? => self.music(symbols)?,
+            // Dynamics, articulations, breaks and other commands without arguments.
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Replace the just consumed variable reference with its value.
+    fn expand_previous(&mut self, name: &str) -> Result<bool> {
+        self.pos -= 1;
+        let expanded = self.expand(name)?;
+        if !expanded {
+            self.pos += 1;
+        }
+
+        Ok(expanded)
+    }
+
+    /// Skip the context property, e.g. `Staff.TimeSignature.color`.
+    fn property_path(&mut self) -> Result<()> {
+        match self.next()? {
+            Token::Word(_) => {}
+            t => Err(self.error(format!("Expected a property, found {t:?}")))?,
+        }
+        while self.accept(&Token::Dot) {
+            self.next()?;
+        }
+
+        Ok(())
+    }
+
+    /// Parse the `\tempo` arguments. Tempo given only by a text is skipped.
+    fn tempo(&mut self) -> Result<Option<Tempo>> {
+        if let Some(Token::Str(_)) = self.peek() {
+            self.pos += 1;
+        }
+        let Some(Token::Number(length)) = self.peek().cloned() else {
+            return Ok(None);
+        };
+
+        self.pos += 1;
+        let dots = self.dots()?;
+        self.expect(Token::Equals)?;
+        let speed = self.number()?;
+        // Tempo range, e.g. `4 = 100-120`.
+        if self.accept(&Token::Dash) {
+            self.number()?;
+        }
+
+        Ok(Some(Tempo {
+            note_length: Duration::new(self.note_length(length)?, dots, None),
+            speed: u8::try_from(speed).map_err(|_| self.error("Tempo is too fast"))?,
+        }))
+    }
+
+    fn dots(&mut self) -> Result<u8> {
+        let mut dots = 0;
+        while self.accept(&Token::Dot) {
+            dots += 1;
+            if dots > MAX_DOTS {
+                return Err(self.error(format!("Duration has more than {MAX_DOTS} dots")));
+            }
+        }
+
+        Ok(dots)
+    }
+
+    fn number_after_slash(&mut self) -> Result<u32> {
+        self.expect(Token::Slash)?;
+        self.number()
+    }
+
+    fn note_length(&self, denominator: u32) -> Result<NoteLength> {
+        NoteLength::ALL
+            .into_iter()
+            .find(|l| denominator > 0 && l.value() == Rational::new(1, denominator))
+            .ok_or_else(|| self.error(format!("Unsupported duration '{denominator}'")))
+    }
+
+    /// Parse the optional duration and return the duration of the symbol.
+    fn duration(&mut self) -> Result<Duration> {
+        if let Some(Token::Number(n)) = self.peek().cloned() {
+            self.pos += 1;
+            let dots = self.dots()?;
+
+            self.state.duration = Duration::new(self.note_length(n)?, dots, None);
+        }
+
+        let mut duration = self.state.duration;
+        duration.tuplet = self.state.tuplet;
+
+        Ok(duration)
+    }
+
+    /// Parse the optional `*n` or `*n/m` multiplier of the duration.
+    fn multiplier(&mut self) -> Result<Rational> {
+        if !self.accept(&Token::Star) {
+            return Ok(Rational::from(1));
+        }
+
+        let numer = self.number()?;
+        let denom = match self.accept(&Token::Slash) {
+            true => self.number()?,
+            false => 1,
+        };
+        if denom == 0 {
+            Err(self.error("Duration multiplier divides by zero"))?;
+        }
+
+        Ok(Rational::new(numer, denom))
+    }
+
+    /// Apply the multiplier shorter than one as the tuplet of the duration, like in
+    /// `c8*2/3`.
+    fn scaled(&self, duration: Duration, multiplier: Rational) -> Result<Duration> {
+        if multiplier == Rational::from(1) {
+            return Ok(duration);
+        }
+
+        let tuplet = (duration.tuplet.is_none() && multiplier < Rational::from(1))
+            .then(|| {
+                let actual = u8::try_from(*multiplier.denom()).ok()?;
+                let normal = u8::try_from(*multiplier.numer()).ok()?;
+                Tuplet::new(actual, normal)
+            })
+            .flatten()
+            .ok_or_else(|| self.error("Duration multipliers of notes are not supported"))?;
+
+        Ok(Duration {
+            tuplet: Some(tuplet),
+            ..duration
+        })
+    }
+
+    /// Skip articulations, dynamics and slurs attached to the symbol and return, whether
+    /// the symbol is tied to the next one.
+    fn post_events(&mut self) -> Result<bool> {
+        let mut tie = false;
+
+        loop {
+            match self.peek() {
+                Some(Token::Tie) => tie = true,
+                Some(Token::Dash | Token::Up | Token::Down) => {
+                    self.pos += 1;
+                }
+                Some(Token::Other('(' | ')' | '[' | ']')) => {}
+                Some(Token::Command(c)) if c.len() == 1 && "()<>!".contains(c.as_str()) => {}
+                _ => return Ok(tie),
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// Position of the pitch counted in note names.
+    fn position(pitch: &Pitch) -> i32 {
+        pitch.octave.value() as i32 * 7 + pitch.ext.note_name.value() as i32
+    }
+
+    fn pitch_at(&self, name: LilyNoteName, position: i32) -> Result<Pitch> {
+        let octave = u8::try_from(position.div_euclid(7))
+            .ok()
+            .and_then(Octave::from_value)
+            .ok_or_else(|| self.error("Note is out of the supported octaves"))?;
+
+        Ok(Pitch {
+            ext: name.into(),
+            octave,
+        })
+    }
+
+    /// Parse the note name and octave marks.
+    fn note_name(&mut self, word: &str) -> Result<(LilyNoteName, i32)> {
+        let name = *self
+            .note_names
+            .get(word)
+            .ok_or_else(|| self.error(format!("Unknown note name '{word}'")))?;
+        let marks = match self.peek() {
+            Some(Token::Marks(marks)) => {
+                let marks = *marks;
+                self.pos += 1;
+                marks
+            }
+            _ => 0,
+        };
+        // Forced and cautionary accidentals.
+        while matches!(self.peek(), Some(Token::Other('!' | '?'))) {
+            self.pos += 1;
+        }
+
+        Ok((name, marks))
+    }
+
+    /// Parse a pitch, which is always absolute, e.g. the key tonic.
+    fn absolute_pitch(&mut self) -> Result<Pitch> {
+        let word = self.string()?;
+        let (name, marks) = self.note_name(&word)?;
+
+        self.pitch_at(name, (3 + marks) * 7 + name.letter() as i32)
+    }
+
+    /// Parse a pitch of a note, resolving the octave in the current mode.
+    fn pitch(&mut self, word: &str, previous: Option<i32>) -> Result<Pitch> {
+        let (name, marks) = self.note_name(word)?;
+        let letter = name.letter() as i32;
+
+        let position = match previous {
+            Some(previous) => {
+                // Without marks, the note is placed at most a fourth from the previous one.
+                let step = (letter - previous).rem_euclid(7);
+                previous + if step > 3 { step - 7 } else { step } + marks * 7
+            }
+            None => (3 + self.state.fixed + marks) * 7 + letter,
+        };
+
+        // Octave check, e.g. `c='`, only verifies the octave.
+        if self.accept(&Token::Equals) {
+            if let Some(Token::Marks(_)) = self.peek() {
+                self.pos += 1;
+            }
+        }
+
+        self.pitch_at(name, position)
+    }
+
+    fn note(&mut self, word: &str, symbols: &mut Vec<Symbol>) -> Result<()> {
+        match word {
+            // Rests, full bar rests and invisible rests.
+            "r" | "R" | "s" => {
+                let duration = self.duration()?;
+                let multiplier = self.multiplier()?;
+                self.post_events()?;
+
+                // Whole multipliers repeat the rest, like in `R1*4`.
+                match multiplier.is_integer() {
+                    true => {
+                        symbols.extend((0..multiplier.to_integer()).map(|_| Symbol::Rest(duration)))
+                    }
+                    false => symbols.push(Symbol::Rest(self.scaled(duration, multiplier)?)),
+                }
+            }
+            // Repetition of the previous chord.
+            "q" => {
+                let duration = self.duration()?;
+                let tie = self.post_events()?;
+
+                symbols.push(Symbol::Chord(Chord {
+                    pitches: self.state.chord.clone(),
+                    duration,
+                    tie,
+                }));
+            }
+            _ => {
+                let pitch = self.pitch(word, self.state.relative)?;
+                if self.state.relative.is_some() {
+                    self.state.relative = Some(Self::position(&pitch));
+                }
+                let duration = self.duration()?;
+                let multiplier = self.multiplier()?;
+                let duration = self.scaled(duration, multiplier)?;
+                let tie = self.post_events()?;
+
+                symbols.push(Symbol::Note(Note {
+                    pitch,
+                    duration,
+                    tie,
+                }));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn chord(&mut self, symbols: &mut Vec<Symbol>) -> Result<()> {
+        let mut pitches: Vec<Pitch> = vec![];
+
+        while !self.accept(&Token::CloseChord) {
+            match self.next()? {
+                Token::Word(word) => {
+                    // Chord notes are relative to each other, the next symbol to the first one.
+                    let previous = match pitches.last() {
+                        Some(last) if self.state.relative.is_some() => Some(Self::position(last)),
+                        _ => self.state.relative,
+                    };
+                    pitches.push(self.pitch(&word, previous)?);
+                    self.post_events()?;
+                }
+                t => Err(self.error(format!("Unexpected {t:?} in chord")))?,
+            }
+        }
+
+        if let (Some(_), Some(first)) = (self.state.relative, pitches.first()) {
+            self.state.relative = Some(Self::position(first));
+        }
+
+        let duration = self.duration()?;
+        let tie = self.post_events()?;
+        self.state.chord = pitches.clone();
+
+        symbols.push(Symbol::Chord(Chord {
+            pitches,
+            duration,
+            tie,
+        }));
+
+        Ok(())
+    }
+
+    fn tie_last(symbols: &mut [Symbol]) {
+        match symbols.last_mut() {
+            Some(Symbol::Note(note)) => note.tie = true,
+            Some(Symbol::Chord(chord)) => chord.tie = true,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lily::Lilypond, notation::NoteName};
+
+    fn pitches(stave: &Stave) -> Vec<String> {
+        stave
+            .symbols
+            .iter()
+            .filter_map(|s| match s {
+                Symbol::Note(n) => Some(format!("{}{}", n.pitch.ext, n.pitch.octave.value())),
+                Symbol::Chord(c) => Some(
+                    c.pitches
+                        .iter()
+                        .map(|p| format!("{}{}", p.ext, p.octave.value()))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
+                Symbol::Rest(_) => Some("r".to_owned()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_relative() {
+        // Arrange
+        let source = "\\relative c' { c4 f b, a'' <c, e c'>2 g,,4 }";
+
+        // Act
+        let score = LilyParser::parse(source).unwrap();
+
+        // Assert
+        assert_eq!(
+            pitches(&score.staves[0]),
+            ["C4", "F4", "B3", "A5", "C5 E5 C6", "G2"]
+        );
+    }
+
+    #[test]
+    fn parse_durations_ties_and_rests() {
+        // Arrange
+        let source = "{ c'4. d'8~ d'4 r R1*2 \\tuplet 3/2 { e'8 f' g' } }";
+
+        // Act
+        let score = LilyParser::parse(source).unwrap();
+
+        // Assert
+        let symbols = &score.staves[0].symbols[2..];
+        assert_eq!(symbols.len(), 9);
+        assert_eq!(
+            symbols[0].duration(),
+            Some(&Duration::new(NoteLength::L4, 1, None))
+        );
+        assert!(matches!(&symbols[1], Symbol::Note(n) if n.tie));
+        assert!(matches!(&symbols[2], Symbol::Note(n) if !n.tie));
+        assert_eq!(symbols[3].duration(), Some(&NoteLength::L4.into()));
+        assert_eq!(symbols[5].duration(), Some(&NoteLength::L1.into()));
+        assert_eq!(
+            symbols[8].duration(),
+            Some(&Duration::new(NoteLength::L8, 0, Some(Tuplet::triplet())))
+        );
+    }
+
+    #[test]
+    fn parse_scaled_durations() {
+        // Arrange
+        let source = "{ c'8*2/3 r4*4/5 d'2*1 }";
+
+        // Act
+        let score = LilyParser::parse(source).unwrap();
+
+        // Assert
+        let symbols = &score.staves[0].symbols[2..];
+        assert_eq!(
+            symbols[0].duration(),
+            Some(&Duration::new(NoteLength::L8, 0, Some(Tuplet::triplet())))
+        );
+        assert_eq!(
+            symbols[1].duration(),
+            Some(&Duration::new(NoteLength::L4, 0, Tuplet::new(5, 4)))
+        );
+        assert_eq!(symbols[2].duration(), Some(&NoteLength::L2.into()));
+    }
+
+    #[test]
+    fn parse_staves_variables_and_header() {
+        // Arrange
+        let source = "\\version \"2.24.0\"
+            \\language \"english\"
+            \\header { title = \"Song\" composer = \"Me\" tagline = ##f }
+            % Melody of the song
+            melody = \\relative { \\clef treble \\key d \\major \\time 3/4 d'4 fs a }
+            bass = { \\clef \"bass\" d2. }
+            \\score {
+              \\new PianoStaff <<
+                \\new Staff = \"up\" \\melody
+                \\new Staff \\with { midiInstrument = \"piano\" } \\bass
+              >>
+              \\layout { }
+              \\midi { \\tempo 4 = 120 }
+            }";
+
+        // Act
+        let score = LilyParser::parse(source).unwrap();
+
+        // Assert
+        assert_eq!(score.info.name.as_deref(), Some("Song"));
+        assert_eq!(score.info.author.as_deref(), Some("Me"));
+        assert_eq!(score.tempo, 120);
+        assert_eq!(score.staves.len(), 2);
+        assert_eq!(pitches(&score.staves[0]), ["D4", "F#4", "A4"]);
+        assert_eq!(pitches(&score.staves[1]), ["D3"]);
+        assert!(matches!(
+            score.staves[0].symbols[1],
+            Symbol::KeySignature(KeySignature {
+                signature_type: KeySignatureType::Maj,
+                ..
+            })
+        ));
+        assert!(matches!(
+            score.staves[1].symbols[0],
+            Symbol::Clef(Clef::Bass)
+        ));
+    }
+
+    #[test]
+    fn parse_written_lilypond() {
+        // Arrange
+        let note = |note_name, octave, tie| {
+            Symbol::Note(Note {
+                pitch: Pitch::new(note_name, octave, None),
+                duration: NoteLength::L4.into(),
+                tie,
+            })
+        };
+        let score = Score {
+            staves: vec![Stave {
+                symbols: vec![
+                    Symbol::Clef(Clef::Treble),
+                    Symbol::TimeSignature(TimeSignature::c()),
+                    note(NoteName::C, Octave::O4, true),
+                    note(NoteName::C, Octave::O4, false),
+                    note(NoteName::B, Octave::O3, false),
+                    note(NoteName::A, Octave::O5, false),
+                ],
+            }],
+            ..Default::default()
+        };
+
+        for (language, relative) in LilyLanguage::ALL
+            .into_iter()
+            .zip([true, false].into_iter().cycle())
+        {
+            let lilypond = Lilypond {
+                language,
+                relative,
+                ..Lilypond::from(score.clone())
+            };
+
+            // Act
+            let parsed = LilyParser::parse(&lilypond.to_string()).unwrap();
+
+            // Assert
+            assert_eq!(pitches(&parsed.staves[0]), ["C4", "C4", "B3", "A5"]);
+            // C major key is inserted before the first note.
+            assert!(matches!(&parsed.staves[0].symbols[3], Symbol::Note(n) if n.tie));
+        }
+    }
+
+    #[test]
+    fn parse_without_key() {
+        for source in ["{ c'4 d'8 e'8 f'2 }", "\\relative { c'4 d e f }"] {
+            // Act
+            let score = LilyParser::parse(source).unwrap().sanitized();
+
+            // Assert
+            let score = score.unwrap();
+            assert!(matches!(
+                score.staves[0].symbols[0],
+                Symbol::KeySignature(KeySignature {
+                    signature_type: KeySignatureType::Maj,
+                    ..
+                })
+            ));
+            assert_eq!(pitches(&score.staves[0]), ["C4", "D4", "E4", "F4"]);
+        }
+    }
+
+    #[test]
+    fn unsupported_constructs() {
+        assert!(LilyParser::parse("{ c4 d e").is_err());
+        assert!(LilyParser::parse("{ << { c4 } \\\\ { e4 } >> }").is_err());
+        assert!(LilyParser::parse("{ x4 }").is_err());
+        assert!(LilyParser::parse("{ c'4.... }").is_err());
+        assert!(LilyParser::parse("{ c'4*3/2 }").is_err());
+        assert!(LilyParser::parse("{ c'4*1/0 }").is_err());
+        assert!(LilyParser::parse("{ c'0 }").is_err());
+        assert!(LilyParser::parse("{ \\tuplet 0/2 { c'4 d' e' } }").is_err());
+        assert!(LilyParser::parse("{ \\tuplet 3/0 { c'4 d' e' } }").is_err());
+        assert!(LilyParser::parse("{ \\times 2/0 { c'4 d' e' } }").is_err());
+        assert!(LilyParser::parse("{ \\time 0/4 c'4 }").is_err());
+        assert!(LilyParser::parse("{ \\time 4/0 c'4 }").is_err());
+        assert!(LilyParser::parse("\\include \"other.ly\"").is_err());
+        assert!(LilyParser::parse("\\version \"2.24.0\"").is_err());
+    }
+}
@@ -3,32 +3,177 @@
 //! ### Author
 //! Jakub Kloub (xkloub03), VUT FIT
 
-use std::fmt::Display;
+use std::{fmt::Display, ops::Range};
 
-use crate::{lily::lily_symbol::LilySymbol, notation::Stave};
+use crate::{
+    lily::{
+        lily_symbol::LilySymbol, LilyLanguage, LilyNoteName, LilyTuplet, LilyWrite, OctaveRelative,
+    },
+    notation::{Stave, Tuplet},
+};
 
 #[derive(Debug, Clone)]
 pub struct LilyStave {
     pub symbols: Vec<LilySymbol>,
+    /// Whether the octave marks are relative to the previous note, starting from `c'`.
+    pub relative: bool,
 }
 
-impl Display for LilyStave {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let symbols = self
-            .symbols
+impl LilyStave {
+    /// Copy of the stave written in the `\relative c'` mode.
+    ///
+    /// Octave marks of the stave must be absolute.
+    pub fn to_relative(&self) -> Self {
+        let mut stave = self.clone();
+        let mut previous = Self::position(LilyNoteName::C, &OctaveRelative::Up(1));
+
+        Self::make_relative(&mut stave.symbols, &mut previous);
+        stave.relative = true;
+
+        stave
+    }
+
+    /// Position of the note counted in note names.
+    fn position(note_name: LilyNoteName, octave: &OctaveRelative) -> i32 {
+        octave.offset() * 7 + note_name.letter() as i32
+    }
+
+    /// Minimal octave marks, which move the note from the previous position to the given one.
+    fn relative_marks(previous: i32, position: i32) -> OctaveRelative {
+        // Without marks, the note is placed at most a fourth from the previous one.
+        let step = (position - previous).rem_euclid(7);
+        let nearest = previous + if step > 3 { step - 7 } else { step };
+
+        OctaveRelative::from_offset((position - nearest) / 7)
+    }
+
+    /// Append the stave to the output and return byte ranges of the written symbols.
+    pub fn write_mapped(&self, language: LilyLanguage, out: &mut String) -> Vec<Range<usize>> {
+        let relative = match self.relative {
+            true => format!("\\relative {}' ", LilyNoteName::C.in_language(language)),
+            false => String::new(),
+        };
+        out.push_str(&format!("\n\\new Staff {relative}{{ "));
+
+        let mut ranges = vec![];
+        for (i, sym) in self.symbols.iter().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+
+            let start = out.len();
+            out.push_str(&sym.in_language(language).to_string());
+            ranges.push(start..out.len());
+        }
+
+        out.push_str(" }\n");
+        ranges
+    }
+
+    /// Indices of the `Stave` symbols, from which each symbol was converted.
+    ///
+    /// Tuplets group several symbols, breaks are added by the sanitizer.
+    pub fn score_symbols(&self) -> Vec<Range<usize>> {
+        let mut next = 0;
+
+        self.symbols
             .iter()
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>()
-            .join(" ");
+            .map(|sym| {
+                let count = match sym {
+                    LilySymbol::Tuplet(tuplet) => tuplet.symbols.len(),
+                    LilySymbol::Break => 0,
+                    _ => 1,
+                };
+                next += count;
+                next - count..next
+            })
+            .collect()
+    }
+
+    fn make_relative(symbols: &mut [LilySymbol], previous: &mut i32) {
+        for sym in symbols.iter_mut() {
+            match sym {
+                LilySymbol::Note(note) => {
+                    let position = Self::position(note.note_name, &note.octave_relative);
+                    note.octave_relative = Self::relative_marks(*previous, position);
+                    *previous = position;
+                }
+                LilySymbol::Chord(chord) => {
+                    // Chord notes are relative to each other, the next symbol to the first one.
+                    let mut last = *previous;
+                    for (i, (note_name, octave)) in chord.notes.iter_mut().enumerate() {
+                        let position = Self::position(*note_name, octave);
+                        *octave = Self::relative_marks(last, position);
+                        last = position;
 
-        write!(f, "\n\\new Staff {{ {symbols} }}\n")
+                        if i == 0 {
+                            *previous = position;
+                        }
+                    }
+                }
+                LilySymbol::Tuplet(tuplet) => Self::make_relative(&mut tuplet.symbols, previous),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl LilyWrite for LilyStave {
+    fn write_lily(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        language: LilyLanguage,
+    ) -> std::fmt::Result {
+        let mut out = String::new();
+        self.write_mapped(language, &mut out);
+
+        f.write_str(&out)
+    }
+}
+
+impl Display for LilyStave {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_lily(f, LilyLanguage::default())
     }
 }
 
 impl From<Stave> for LilyStave {
+    /// Convert the stave, grouping consecutive symbols of the same tuplet into `LilyTuplet`s.
+    ///
+    /// Symbols of an incomplete group get the tuplet as their duration multiplier instead.
     fn from(stave: Stave) -> Self {
+        let mut symbols = vec![];
+        let mut group: Option<LilyTuplet> = None;
+
+        for sym in stave.symbols {
+            let tuplet = sym.duration().and_then(|d| d.tuplet);
+
+            if let Some(g) = group.take_if(|g| Tuplet::new(g.actual, g.normal) != tuplet) {
+                symbols.extend(g.into_scaled());
+            }
+
+            let Some(tuplet) = tuplet else {
+                symbols.push(sym.into());
+                continue;
+            };
+
+            let g = group.get_or_insert_with(|| LilyTuplet {
+                actual: tuplet.actual(),
+                normal: tuplet.normal(),
+                symbols: vec![],
+            });
+            g.symbols.push(sym.into());
+
+            if g.is_complete() {
+                symbols.push(LilySymbol::Tuplet(group.take().unwrap()));
+            }
+        }
+
+        symbols.extend(group.into_iter().flat_map(LilyTuplet::into_scaled));
+
         Self {
-            symbols: stave.symbols.into_iter().map(Into::into).collect(),
+            symbols,
+            relative: false,
         }
     }
 }
@@ -53,11 +198,13 @@ pub mod tests {
             LilySymbol::Note(LilyNote {
                 note_name: *n,
                 octave_relative: OctaveRelative::Up(1),
-                length: LilyNoteLength::L4,
+                length: LilyNoteLength::L4.into(),
+                tie: false,
             })
         });
         let d_maj = LilyStave {
             symbols: d_maj_def.cloned().chain(d_maj_notes).collect(),
+            relative: false,
         };
 
         (
@@ -74,4 +221,95 @@ pub mod tests {
         // Act && Assert
         assert_eq!(d_maj.to_string().trim(), d_maj_str);
     }
+
+    #[test]
+    fn from_stave_tuplets() {
+        use crate::notation::{Duration, Note, NoteLength, NoteName, Octave, Pitch, Symbol};
+
+        // Arrange
+        let note = |length, tuplet| {
+            Symbol::Note(Note {
+                pitch: Pitch::new(NoteName::C, Octave::O4, None),
+                duration: Duration::new(length, 0, tuplet),
+                tie: false,
+            })
+        };
+        let triplet = Some(Tuplet::triplet());
+        let stave = Stave {
+            symbols: vec![
+                note(NoteLength::L4, None),
+                note(NoteLength::L8, triplet),
+                note(NoteLength::L8, triplet),
+                note(NoteLength::L8, triplet),
+                note(NoteLength::L8, triplet),
+                note(NoteLength::L4, Tuplet::new(5, 4)),
+                note(NoteLength::L2, None),
+            ],
+        };
+
+        // Act
+        let lily = LilyStave::from(stave);
+
+        // Assert
+        assert_eq!(
+            lily.to_string().trim(),
+            "\\new Staff { c'4 \\tuplet 3/2 { c'8 c'8 c'8 } c'8*2/3 c'4*4/5 c'2 }"
+        );
+        assert_eq!(lily.score_symbols(), vec![0..1, 1..4, 4..5, 5..6, 6..7]);
+    }
+
+    #[test]
+    fn to_relative() {
+        use LilyNoteName::*;
+
+        // Arrange
+        let note = |note_name, octave_relative| {
+            LilySymbol::Note(LilyNote {
+                note_name,
+                octave_relative,
+                length: LilyNoteLength::L4.into(),
+                tie: false,
+            })
+        };
+        let stave = LilyStave {
+            symbols: vec![
+                note(C, OctaveRelative::Up(1)),
+                note(F, OctaveRelative::Up(1)),
+                note(H, OctaveRelative::Up(0)),
+                note(A, OctaveRelative::Up(2)),
+                LilySymbol::Chord(LilyChord {
+                    notes: vec![
+                        (C, OctaveRelative::Up(2)),
+                        (E, OctaveRelative::Up(2)),
+                        (C, OctaveRelative::Up(3)),
+                    ],
+                    length: LilyNoteLength::L2.into(),
+                    tie: false,
+                }),
+                note(G, OctaveRelative::Down(1)),
+            ],
+            relative: false,
+        };
+
+        // Act
+        let relative = stave.to_relative();
+
+        // Assert
+        assert_eq!(
+            relative.to_string().trim(),
+            "\\new Staff \\relative c' { c4 f4 h,4 a''4 <c, e c'>2 g,,4 }"
+        );
+    }
+
+    #[test]
+    fn to_string_english() {
+        // Arrange
+        let (d_maj, _) = stave_d_maj();
+
+        // Act && Assert
+        assert_eq!(
+            d_maj.in_language(LilyLanguage::English).to_string().trim(),
+            "\\new Staff { \\clef treble \\key d \\major d'4 e'4 fs'4 g'4 a'4 b'4 c'4 }"
+        );
+    }
 }
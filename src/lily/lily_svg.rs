@@ -0,0 +1,154 @@
+//! Lilypond SVG point-and-click links definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::lily::SourcePosition;
+
+/// Engraved object linked to the position of its event in the Lilypond source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgLink {
+    pub position: SourcePosition,
+    /// Origin of the object in the page coordinates.
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Point-and-click links of a page engraved by the Lilypond SVG backend.
+///
+/// Lilypond wraps the linked objects into `<a xlink:href="textedit://file:line:char:column">`
+/// elements, whose first `translate(x, y)` is the origin of the object.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SvgLinks {
+    /// Left, top, width and height of the page in the page coordinates.
+    pub view_box: [f32; 4],
+    pub links: Vec<SvgLink>,
+}
+
+impl SvgLinks {
+    pub fn parse(svg: &str) -> Self {
+        static VIEW_BOX_REG: Lazy<Regex> = Lazy::new(|| {
+            Regex::new(r#"viewBox="([-\d.]+)\s+([-\d.]+)\s+([-\d.]+)\s+([-\d.]+)""#).unwrap()
+        });
+        static LINK_REG: Lazy<Regex> = Lazy::new(|| {
+            Regex::new(r#"(?s)<a [^>]*?href="textedit://[^"]*?:(\d+):(\d+):\d+"[^>]*>(.*?)</a>"#)
+                .unwrap()
+        });
+        static TRANSLATE_REG: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"translate\(\s*([-\d.]+)\s*,\s*([-\d.]+)\s*\)").unwrap());
+
+        let view_box = VIEW_BOX_REG
+            .captures(svg)
+            .and_then(|c| {
+                let n = |i: usize| c[i].parse::<f32>().ok();
+                Some([n(1)?, n(2)?, n(3)?, n(4)?])
+            })
+            .unwrap_or_default();
+
+        let links = LINK_REG
+            .captures_iter(svg)
+            .filter_map(|c| {
+                let translate = TRANSLATE_REG.captures(c.get(3)?.as_str())?;
+
+                Some(SvgLink {
+                    position: SourcePosition {
+                        line: c[1].parse().ok()?,
+                        column: c[2].parse().ok()?,
+                    },
+                    x: translate[1].parse().ok()?,
+                    y: translate[2].parse().ok()?,
+                })
+            })
+            .collect();
+
+        Self { view_box, links }
+    }
+
+    /// Convert the point given as a fraction of the page size to the page coordinates.
+    pub fn to_page(&self, fraction: (f32, f32)) -> (f32, f32) {
+        let [left, top, width, height] = self.view_box;
+        (left + fraction.0 * width, top + fraction.1 * height)
+    }
+
+    /// Convert the point in the page coordinates to a fraction of the page size.
+    pub fn to_fraction(&self, point: (f32, f32)) -> (f32, f32) {
+        let [left, top, width, height] = self.view_box;
+        ((point.0 - left) / width, (point.1 - top) / height)
+    }
+
+    /// Link nearest to the point in the page coordinates, which is at most `max_distance` away.
+    ///
+    /// Page coordinates of Lilypond are in staff spaces.
+    pub fn nearest(&self, point: (f32, f32), max_distance: f32) -> Option<&SvgLink> {
+        let distance = |link: &SvgLink| (link.x - point.0).hypot(link.y - point.1);
+
+        self.links
+            .iter()
+            .filter(|link| distance(link) <= max_distance)
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" version="1.2" width="210.00mm" height="297.00mm" viewBox="0 0 119.5016 169.0094">
+<line transform="translate(5.6906, 10.5000)" stroke-width="0.1000" x1="0.0500" y1="-0.0000" x2="106.6797" y2="-0.0000"/>
+<a style="color:inherit;" xlink:href="textedit:///tmp/input.ily:7:21:22">
+<path transform="translate(20.0000, 11.0000) scale(0.0040, -0.0040)" d="M218 136c55 0 108 -28 108 -89"/>
+</a>
+<a style="color:inherit;" xlink:href="textedit:///tmp/input.ily:7:25:26">
+<g transform="translate(24.5000, 10.5000)"><path d="M0 0"/></g>
+</a>
+</svg>"#;
+
+    #[test]
+    fn parse_links() {
+        // Act
+        let links = SvgLinks::parse(SVG);
+
+        // Assert
+        assert_eq!(links.view_box, [0.0, 0.0, 119.5016, 169.0094]);
+        assert_eq!(
+            links.links,
+            vec![
+                SvgLink {
+                    position: SourcePosition {
+                        line: 7,
+                        column: 21
+                    },
+                    x: 20.0,
+                    y: 11.0
+                },
+                SvgLink {
+                    position: SourcePosition {
+                        line: 7,
+                        column: 25
+                    },
+                    x: 24.5,
+                    y: 10.5
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn nearest_link() {
+        // Arrange
+        let links = SvgLinks::parse(SVG);
+
+        // Act
+        let point = links.to_page(links.to_fraction((24.0, 10.0)));
+
+        // Assert
+        assert_eq!(
+            links.nearest(point, 1.5).map(|l| l.position.column),
+            Some(25)
+        );
+        assert_eq!(links.nearest((60.0, 60.0), 1.5), None);
+    }
+}
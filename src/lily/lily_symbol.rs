@@ -6,8 +6,11 @@
 use std::fmt::Display;
 
 use crate::{
-    lily::{lily_note::LilyNote, LilyClef, LilyKey, LilyTempo, LilyTime},
-    notation::Symbol,
+    lily::{
+        lily_note::LilyNote, LilyChord, LilyClef, LilyDuration, LilyKey, LilyLanguage, LilyTempo,
+        LilyTime, LilyTuplet, LilyWrite,
+    },
+    notation::{Rational, Symbol},
 };
 
 #[derive(Debug, Clone)]
@@ -16,10 +19,26 @@ pub enum LilySymbol {
     Key(LilyKey),
     Time(LilyTime),
     Note(LilyNote),
+    Chord(LilyChord),
+    Rest(LilyDuration),
     Tempo(LilyTempo),
+    Tuplet(LilyTuplet),
     Break,
 }
 
+impl LilySymbol {
+    /// Fraction of the whole note the symbol takes, if it takes any time in the bar.
+    pub fn value(&self) -> Option<Rational> {
+        match self {
+            LilySymbol::Note(note) => Some(note.length.value()),
+            LilySymbol::Chord(chord) => Some(chord.length.value()),
+            LilySymbol::Rest(length) => Some(length.value()),
+            LilySymbol::Tuplet(tuplet) => Some(tuplet.value()),
+            _ => None,
+        }
+    }
+}
+
 impl From<Symbol> for LilySymbol {
     fn from(sym: Symbol) -> Self {
         match sym {
@@ -27,29 +46,43 @@ impl From<Symbol> for LilySymbol {
             Symbol::TimeSignature(time_signature) => LilySymbol::Time(time_signature.into()),
             Symbol::KeySignature(key_signature) => LilySymbol::Key(key_signature.into()),
             Symbol::Note(note) => LilySymbol::Note(note.into()),
+            Symbol::Chord(chord) => LilySymbol::Chord(chord.into()),
+            Symbol::Rest(length) => LilySymbol::Rest(length.into()),
             Symbol::Tempo(tempo) => LilySymbol::Tempo(tempo.into()),
-            _ => panic!("Invalid symbol for conversion"),
         }
     }
 }
 
-impl Display for LilySymbol {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl LilyWrite for LilySymbol {
+    fn write_lily(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        language: LilyLanguage,
+    ) -> std::fmt::Result {
         match self {
             LilySymbol::Clef(sym) => sym.fmt(f),
-            LilySymbol::Key(sym) => sym.fmt(f),
+            LilySymbol::Key(sym) => sym.write_lily(f, language),
             LilySymbol::Time(sym) => sym.fmt(f),
-            LilySymbol::Note(sym) => sym.fmt(f),
+            LilySymbol::Note(sym) => sym.write_lily(f, language),
+            LilySymbol::Chord(sym) => sym.write_lily(f, language),
+            LilySymbol::Rest(length) => write!(f, "r{length}"),
             LilySymbol::Tempo(sym) => sym.fmt(f),
+            LilySymbol::Tuplet(sym) => sym.write_lily(f, language),
             LilySymbol::Break => write!(f, "\\break"),
         }
     }
 }
 
+impl Display for LilySymbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_lily(f, LilyLanguage::default())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::lily::*;
+    use crate::{lily::*, notation::Tuplet};
 
     #[test]
     fn to_string() {
@@ -61,7 +94,8 @@ mod tests {
         let note_c1_4 = LilyNote {
             note_name: LilyNoteName::C,
             octave_relative: OctaveRelative::Up(1),
-            length: LilyNoteLength::L4,
+            length: LilyNoteLength::L4.into(),
+            tie: false,
         };
 
         // Act && Assert
@@ -78,5 +112,27 @@ mod tests {
             LilySymbol::Note(note_c1_4.clone()).to_string(),
             note_c1_4.to_string()
         );
+        assert_eq!(
+            LilySymbol::Rest(LilyNoteLength::L2.into()).to_string(),
+            "r2"
+        );
+        assert_eq!(
+            LilySymbol::Rest(LilyDuration {
+                length: LilyNoteLength::L4,
+                dots: 2,
+                scale: None,
+            })
+            .to_string(),
+            "r4.."
+        );
+        assert_eq!(
+            LilySymbol::Rest(LilyDuration {
+                length: LilyNoteLength::L8,
+                dots: 0,
+                scale: Some(Tuplet::triplet()),
+            })
+            .to_string(),
+            "r8*2/3"
+        );
     }
 }
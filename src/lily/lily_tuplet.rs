@@ -0,0 +1,118 @@
+//! Lilypond tuplet definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use crate::{
+    lily::{LilyLanguage, LilySymbol, LilyWrite},
+    notation::{Rational, Tuplet},
+};
+
+/// Group of symbols, where `actual` notes are played in the time of `normal` notes.
+#[derive(Debug, Clone)]
+pub struct LilyTuplet {
+    pub actual: u8,
+    pub normal: u8,
+    pub symbols: Vec<LilySymbol>,
+}
+
+impl LilyTuplet {
+    /// Fraction of the whole note the whole group takes.
+    pub fn value(&self) -> Rational {
+        self.written_value() * Rational::new(self.normal as u32, self.actual as u32)
+    }
+
+    /// Whether the group fills the time of `normal` notes of its first symbol.
+    pub fn is_complete(&self) -> bool {
+        match self.symbols.first().and_then(LilySymbol::value) {
+            Some(first) => self.written_value() >= first * Rational::from(self.actual as u32),
+            None => false,
+        }
+    }
+
+    /// Symbols of an incomplete group with the tuplet written as their duration multiplier,
+    /// so they keep their value outside of the `\tuplet` bracket.
+    pub fn into_scaled(self) -> Vec<LilySymbol> {
+        let scale = Tuplet::new(self.actual, self.normal);
+
+        self.symbols
+            .into_iter()
+            .map(|mut sym| {
+                match &mut sym {
+                    LilySymbol::Note(note) => note.length.scale = scale,
+                    LilySymbol::Chord(chord) => chord.length.scale = scale,
+                    LilySymbol::Rest(length) => length.scale = scale,
+                    _ => {}
+                }
+                sym
+            })
+            .collect()
+    }
+
+    /// Sum of the written lengths of the symbols in the group.
+    pub fn written_value(&self) -> Rational {
+        self.symbols.iter().filter_map(LilySymbol::value).sum()
+    }
+}
+
+impl LilyWrite for LilyTuplet {
+    fn write_lily(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        language: LilyLanguage,
+    ) -> std::fmt::Result {
+        let symbols = self
+            .symbols
+            .iter()
+            .map(|s| s.in_language(language).to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        write!(
+            f,
+            "\\tuplet {}/{} {{ {symbols} }}",
+            self.actual, self.normal
+        )
+    }
+}
+
+impl std::fmt::Display for LilyTuplet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_lily(f, LilyLanguage::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lily::{LilyNote, LilyNoteLength, LilyNoteName, OctaveRelative};
+
+    fn triplet() -> LilyTuplet {
+        LilyTuplet {
+            actual: 3,
+            normal: 2,
+            symbols: [LilyNoteName::C, LilyNoteName::D, LilyNoteName::E]
+                .into_iter()
+                .map(|note_name| {
+                    LilySymbol::Note(LilyNote {
+                        note_name,
+                        octave_relative: OctaveRelative::Up(1),
+                        length: LilyNoteLength::L8.into(),
+                        tie: false,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn to_string() {
+        assert_eq!(triplet().to_string(), "\\tuplet 3/2 { c'8 d'8 e'8 }");
+    }
+
+    #[test]
+    fn value() {
+        assert_eq!(triplet().written_value(), Rational::new(3, 8));
+        assert_eq!(triplet().value(), Rational::new(1, 4));
+    }
+}
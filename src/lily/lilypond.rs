@@ -3,17 +3,69 @@
 //! ### Author
 //! Jakub Kloub (xkloub03), VUT FIT
 
-use crate::{lily::lily_stave::LilyStave, notation::Score, sanitizer::Sanitizer};
+use crate::{
+    lily::{
+        lily_stave::LilyStave, LilyHeader, LilyLanguage, LilyLayout, SourceMap, SymbolLocation,
+    },
+    notation::Score,
+    sanitizer::Sanitizer,
+};
 use std::fmt::Display;
 
 #[derive(Debug, Clone)]
 pub struct Lilypond {
     pub version: String,
-    pub language: String,
+    pub language: LilyLanguage,
+    /// Whether the staves are written in the `\relative` mode.
+    pub relative: bool,
+    pub header: LilyHeader,
+    pub layout: LilyLayout,
+    /// Tempo of the MIDI output in quarter notes per minute.
+    pub tempo: u32,
     pub staves: Vec<LilyStave>,
 }
 
 impl Lilypond {
+    /// Lilypond source of the score along with the positions of the stave symbols in it.
+    pub fn source_map(&self) -> SourceMap {
+        let mut source = format!(
+            "\\version \"{}\"\n\\language \"{}\"\n{}\n{}\n{}\n\\score{{<<",
+            self.version,
+            self.language.name(),
+            self.layout.staff_size(),
+            self.layout.paper(),
+            self.header,
+        );
+        let mut symbols = vec![];
+
+        for (i, stave) in self.staves.iter().enumerate() {
+            let ranges = match self.relative {
+                true => stave.to_relative().write_mapped(self.language, &mut source),
+                false => stave.write_mapped(self.language, &mut source),
+            };
+            let locations =
+                stave
+                    .score_symbols()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(j, score_symbols)| SymbolLocation {
+                        stave: i,
+                        symbol: j,
+                        score_symbols,
+                    });
+
+            symbols.extend(ranges.into_iter().zip(locations));
+        }
+
+        source.push_str(&format!(
+            ">>{}\\midi{{\\tempo 4 = {}}}}}",
+            self.layout.layout(),
+            self.tempo
+        ));
+
+        SourceMap { source, symbols }
+    }
+
     pub fn sanitized_with(mut self, sanitizer: impl Sanitizer<Self>) -> crate::error::Result<Self> {
         sanitizer.sanitize(&mut self)?;
         Ok(self)
@@ -24,7 +76,11 @@ impl Default for Lilypond {
     fn default() -> Self {
         Self {
             version: "2.23.20".to_owned(),
-            language: "deutsch".to_owned(),
+            language: LilyLanguage::default(),
+            relative: false,
+            header: Default::default(),
+            layout: Default::default(),
+            tempo: 90,
             staves: Default::default(),
         }
     }
@@ -32,19 +88,15 @@ impl Default for Lilypond {
 
 impl Display for Lilypond {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let staves: String = self.staves.iter().map(|s| s.to_string()).collect();
-
-        write!(
-            f,
-            "\\version \"{}\"\n\\language \"{}\"\n\\score{{{}\\layout{{}}\\midi{{}}}}",
-            self.version, self.language, staves
-        )
+        f.write_str(self.source_map().source())
     }
 }
 
 impl From<Score> for Lilypond {
     fn from(value: Score) -> Self {
         Self {
+            header: value.info.into(),
+            tempo: value.tempo,
             staves: value.staves.into_iter().map(Into::into).collect(),
             ..Default::default()
         }
@@ -62,14 +114,21 @@ mod tests {
         let (d_maj, _) = stave_d_maj();
         let lilypond = Lilypond {
             version: "1.2.3".to_owned(),
-            language: "english".to_owned(),
+            language: LilyLanguage::Deutsch,
+            relative: false,
+            header: LilyHeader {
+                title: Some("Title".to_owned()),
+                ..Default::default()
+            },
+            layout: LilyLayout::default(),
+            tempo: 120,
             staves: vec![d_maj.clone(), d_maj.clone()],
         };
 
         // Act && Assert
         assert_eq!(
             lilypond.to_string().trim(),
-            format!("\\version \"1.2.3\"\n\\language \"english\"\n\\score{{{d_maj}{d_maj}\\layout{{}}\\midi{{}}}}")
+            format!("\\version \"1.2.3\"\n\\language \"deutsch\"\n#(set-global-staff-size 20)\n\\paper{{#(set-paper-size \"a4\")}}\n\\header{{title = \"Title\" tagline = ##f}}\n\\score{{<<{d_maj}{d_maj}>>\\layout{{}}\\midi{{\\tempo 4 = 120}}}}")
         );
     }
 }
@@ -12,6 +12,7 @@ pub mod l_system;
 
 pub use l_rewriter::CSSLRewriter;
 pub use l_rewriter::LRewriter;
+pub use l_rewriter::RuleSpan;
 pub use l_rule::CSSLRule;
 pub use l_rule::LRule;
 pub use l_rule_set::CSSLRuleSet;
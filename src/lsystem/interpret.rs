@@ -4,17 +4,21 @@
 //! Jakub Kloub (xkloub03), VUT FIT
 
 pub struct ControlPanel;
+mod custom_scale;
 mod scale;
 
 use std::rc::Rc;
 
-use crate::{
-    lsystem::interpret::scale::{BasicScale, JazzLikeScale},
-    notation::{Clef, KeySignature, Note, Score, Stave, Symbol, Tempo, TimeSignature},
-};
-use scale::Scale;
+use crate::notation::{Clef, KeySignature, Note, Score, Stave, Symbol, Tempo, TimeSignature};
 
+pub use custom_scale::CustomScale;
+pub use custom_scale::SpelledScale;
+pub use custom_scale::Spelling;
+pub use scale::BasicScale;
+pub use scale::JazzLikeScale;
+pub use scale::Scale;
 pub use scale::ScaleType;
+pub use scale::StepScale;
 
 /// Generic interpret translating any string to T.
 pub trait Interpret<T> {
@@ -42,6 +46,8 @@ pub struct MusicIntInfo {
     pub time_signature: TimeSignature,
     pub tempo: Tempo,
     pub scale_type: ScaleType,
+    #[serde(default)]
+    pub custom_scale: CustomScale,
 }
 
 impl Default for MusicIntInfo {
@@ -63,11 +69,13 @@ impl Default for MusicIntInfo {
                     },
                     octave: crate::notation::Octave::O4,
                 },
-                duration: crate::notation::NoteLength::L1,
+                duration: crate::notation::NoteLength::L1.into(),
+                tie: false,
             },
             time_signature: TimeSignature::c(),
             tempo: Tempo::default(),
             scale_type: ScaleType::Basic,
+            custom_scale: CustomScale::default(),
         }
     }
 }
@@ -90,10 +98,10 @@ impl Interpret<Score> for MusicInterpret {
     fn translate(&self, string: &str) -> Score {
         // Create state.
         let mut context = Context {
-            scale: match self.int_info.scale_type {
-                ScaleType::Basic => Rc::new(BasicScale::new(self.int_info.key_signature)),
-                ScaleType::JazzLike => Rc::new(JazzLikeScale::new(self.int_info.key_signature)),
-            },
+            scale: self
+                .int_info
+                .scale_type
+                .scale(self.int_info.key_signature, &self.int_info.custom_scale),
             note: self.int_info.first_note.clone(),
             stave_notes: Default::default(),
             stack: Default::default(),
@@ -105,16 +113,17 @@ impl Interpret<Score> for MusicInterpret {
         // Compile the resulting score.
         Score {
             staves: vec![Stave {
-                symbols: [
-                    Symbol::Clef(self.int_info.clef),
-                    Symbol::KeySignature(self.int_info.key_signature),
-                    Symbol::Tempo(self.int_info.tempo),
-                    Symbol::TimeSignature(self.int_info.time_signature),
-                ]
-                .into_iter()
-                .chain(context.stave_notes.into_iter().map(Symbol::Note))
-                .collect(),
+                symbols: self
+                    .header()
+                    .into_iter()
+                    .chain(context.stave_notes.into_iter().map(Symbol::Note))
+                    .collect(),
             }],
+            tempo: self.int_info.tempo.quarters_per_minute(),
+            spelling: match self.int_info.scale_type {
+                ScaleType::Custom => self.int_info.custom_scale.spelling.accidental(),
+                _ => None,
+            },
             ..Default::default()
         }
     }
@@ -125,6 +134,30 @@ impl MusicInterpret {
         Self { int_info }
     }
 
+    /// Symbols at the beginning of the translated stave.
+    fn header(&self) -> [Symbol; 4] {
+        [
+            Symbol::Clef(self.int_info.clef),
+            Symbol::KeySignature(self.int_info.key_signature),
+            Symbol::Tempo(self.int_info.tempo),
+            Symbol::TimeSignature(self.int_info.time_signature),
+        ]
+    }
+
+    /// Index of the L-system string character, which wrote each symbol of the translated stave.
+    pub fn word_indices(&self, string: &str) -> Vec<Option<usize>> {
+        let notes = string
+            .chars()
+            .enumerate()
+            .filter(|(_, c)| *c == 'F')
+            .map(|(i, _)| Some(i));
+
+        std::iter::repeat(None)
+            .take(self.header().len())
+            .chain(notes)
+            .collect()
+    }
+
     /// State-modifying action based on the L-system character from its alphabet.
     ///
     /// ## TODO
@@ -137,10 +170,12 @@ impl MusicInterpret {
             '+' => context.scale.advance(&mut context.note.pitch),
             // Change the current note to the previous one in scale.
             '-' => context.scale.recede(&mut context.note.pitch),
-            // Half the length of current note.
-            // FIXME: Handle the case where duration can no longer be halved.
-            //        What do we do then?
-            'd' => context.note.duration.halve(),
+            // Half the length of current note. The shortest note keeps its length.
+            'd' => {
+                if let Err(e) = context.note.duration.halve() {
+                    log::warn!("{e}");
+                }
+            }
             // Save current state onto the stack.
             '[' => context.stack.push(context.note.clone()),
             // Pop current state from the stack.
@@ -0,0 +1,288 @@
+//! Custom scale definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::*,
+    lsystem::interpret::{Scale, StepScale},
+    notation::{Accidental, KeySignature, Octave, Pitch},
+    sanitizer::{preferred_accidental, ToPrefSynonym},
+};
+
+/// Accidentals used for the notes of a custom scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Spelling {
+    Sharps,
+    Flats,
+    /// Use the accidental preferred by the key.
+    Key,
+}
+
+impl Spelling {
+    /// Accidental chosen by the spelling, none for the accidental of the key.
+    pub fn accidental(&self) -> Option<Accidental> {
+        match self {
+            Spelling::Sharps => Some(Accidental::Sharp),
+            Spelling::Flats => Some(Accidental::Flat),
+            Spelling::Key => None,
+        }
+    }
+}
+
+/// Scale defined by the user with halftone steps between its notes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "CustomScaleRepr")]
+pub struct CustomScale {
+    /// Positive halftone steps, which sum up to an octave.
+    steps: Vec<u8>,
+    pub spelling: Spelling,
+}
+
+/// Serialized custom scale, which is not validated yet.
+#[derive(Deserialize)]
+struct CustomScaleRepr {
+    steps: Vec<u8>,
+    spelling: Spelling,
+}
+
+impl TryFrom<CustomScaleRepr> for CustomScale {
+    type Error = String;
+
+    fn try_from(repr: CustomScaleRepr) -> std::result::Result<Self, Self::Error> {
+        CustomScale::new(repr.steps, repr.spelling).map_err(|e| e.to_string())
+    }
+}
+
+impl Default for CustomScale {
+    fn default() -> Self {
+        Self {
+            steps: vec![2, 1, 2, 2, 1, 3, 1],
+            spelling: Spelling::Key,
+        }
+    }
+}
+
+impl CustomScale {
+    /// Create scale with the given steps, which must be positive and sum up to an octave.
+    pub fn new(steps: Vec<u8>, spelling: Spelling) -> Result<Self> {
+        let scale = Self { steps, spelling };
+        StepScale::check_steps(&scale.steps)
+            .map_err(|e| AppError::ScalePattern(scale.pattern(), e))?;
+
+        Ok(scale)
+    }
+
+    /// Parse halftone steps separated by whitespaces.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::lsystem::interpret::CustomScale;
+    ///
+    /// assert_eq!(CustomScale::parse_steps("2 1 2 2 1 3 1").unwrap(), vec![2, 1, 2, 2, 1, 3, 1]);
+    /// assert!(CustomScale::parse_steps("2 2 2").is_err());
+    /// assert!(CustomScale::parse_steps("2 0 2 2 2 2 2").is_err());
+    /// assert!(CustomScale::parse_steps("a b").is_err());
+    /// ```
+    pub fn parse_steps(pattern: &str) -> Result<Vec<u8>> {
+        let steps = pattern
+            .split_whitespace()
+            .map(|s| {
+                s.parse::<u8>()
+                    .map_err(|e| AppError::ScalePattern(pattern.to_string(), e.to_string()))
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        StepScale::check_steps(&steps)
+            .map_err(|e| AppError::ScalePattern(pattern.to_string(), e))?;
+        Ok(steps)
+    }
+
+    /// Positive halftone steps between the notes, which sum up to an octave.
+    pub fn steps(&self) -> &[u8] {
+        &self.steps
+    }
+
+    /// Steps of the scale in the same format as accepted by `parse_steps`.
+    pub fn pattern(&self) -> String {
+        self.steps
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Create scale in the given key.
+    pub fn scale(&self, key: KeySignature) -> SpelledScale {
+        SpelledScale {
+            scale: StepScale::new(key, &self.steps).expect("Steps are validated"),
+            accidental: self
+                .spelling
+                .accidental()
+                .or_else(|| preferred_accidental(&key)),
+        }
+    }
+
+    /// All notes of one octave of the scale starting from the key note.
+    pub fn notes(&self, key: KeySignature, octave: Octave) -> Vec<Pitch> {
+        let scale = self.scale(key);
+        let mut pitch = Pitch {
+            ext: key.ext,
+            octave,
+        };
+
+        let mut notes = vec![pitch.clone()];
+        for _ in self.steps.iter() {
+            scale.advance(&mut pitch);
+            notes.push(pitch.clone());
+        }
+
+        notes
+    }
+}
+
+/// Step scale which spells its notes with the given accidental.
+///
+/// Notes such as E-sharp or C-flat are always spelled as their natural synonyms.
+#[derive(Debug, Clone)]
+pub struct SpelledScale {
+    scale: StepScale,
+    accidental: Option<Accidental>,
+}
+
+impl Scale for SpelledScale {
+    fn advance(&self, pitch: &mut Pitch) {
+        self.scale.advance(pitch);
+        pitch.to_pref_synonym(self.accidental);
+        pitch.to_pref_synonym(None);
+    }
+
+    fn recede(&self, pitch: &mut Pitch) {
+        self.scale.recede(pitch);
+        pitch.to_pref_synonym(self.accidental);
+        pitch.to_pref_synonym(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{
+        Accidental::*, ExtNoteName, KeySignatureType::*, NoteName::*, Octave::*,
+    };
+
+    fn c_min() -> KeySignature {
+        KeySignature {
+            ext: ExtNoteName {
+                note_name: C,
+                accidental: None,
+            },
+            signature_type: Min,
+        }
+    }
+
+    fn spelled(notes: Vec<Pitch>) -> Vec<ExtNoteName> {
+        notes.into_iter().map(|p| p.ext).collect()
+    }
+
+    fn ext(note_name: crate::notation::NoteName, accidental: Option<Accidental>) -> ExtNoteName {
+        ExtNoteName {
+            note_name,
+            accidental,
+        }
+    }
+
+    #[test]
+    fn notes_flats() {
+        // Arrange
+        let scale = CustomScale {
+            steps: vec![2, 1, 2, 2, 1, 3, 1],
+            spelling: Spelling::Flats,
+        };
+
+        // Act
+        let notes = scale.notes(c_min(), O4);
+
+        // Assert
+        assert_eq!(notes.last(), Some(&Pitch::new(C, O5, None)));
+        assert_eq!(
+            spelled(notes),
+            vec![
+                ext(C, None),
+                ext(D, None),
+                ext(E, Some(Flat)),
+                ext(F, None),
+                ext(G, None),
+                ext(A, Some(Flat)),
+                ext(B, None),
+                ext(C, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn notes_sharps() {
+        // Arrange
+        let scale = CustomScale {
+            steps: vec![3, 2, 1, 1, 3, 2],
+            spelling: Spelling::Sharps,
+        };
+
+        // Act
+        let notes = scale.notes(c_min(), O4);
+
+        // Assert
+        assert_eq!(
+            spelled(notes),
+            vec![
+                ext(C, None),
+                ext(D, Some(Sharp)),
+                ext(F, None),
+                ext(F, Some(Sharp)),
+                ext(G, None),
+                ext(A, Some(Sharp)),
+                ext(C, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn recede_spelled() {
+        // Arrange
+        let scale = CustomScale {
+            steps: vec![1, 3, 1, 2, 1, 3, 1],
+            spelling: Spelling::Flats,
+        }
+        .scale(c_min());
+        let mut pitch = Pitch::new(C, O5, None);
+
+        // Act
+        scale.recede(&mut pitch);
+        scale.recede(&mut pitch);
+        scale.recede(&mut pitch);
+
+        // Assert
+        assert_eq!(pitch.ext, ext(G, None));
+        scale.recede(&mut pitch);
+        assert_eq!(pitch.ext, ext(F, None));
+        scale.recede(&mut pitch);
+        scale.recede(&mut pitch);
+        assert_eq!(pitch.ext, ext(D, Some(Flat)));
+    }
+    #[test]
+    fn deserialize_invalid() {
+        // Arrange
+        let empty = "(steps: [], spelling: Key)";
+        let overflow = "(steps: [255, 255, 2], spelling: Key)";
+
+        // Act
+        let empty = ron::from_str::<CustomScale>(empty);
+        let overflow = ron::from_str::<CustomScale>(overflow);
+
+        // Assert
+        assert!(empty.is_err());
+        assert!(overflow.is_err());
+    }
+}
@@ -3,14 +3,136 @@
 //! ### Author
 //! Jakub Kloub (xkloub03), VUT FIT
 
+use std::rc::Rc;
+
 use serde::{Deserialize, Serialize};
 
-use crate::notation::{KeySignature, KeySignatureType, Octave, Pitch};
+use crate::{
+    error::*,
+    lsystem::interpret::CustomScale,
+    notation::{Accidental, Interval, KeySignature, KeySignatureType, Octave, Pitch},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ScaleType {
     Basic,
     JazzLike,
+    Ionian,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Aeolian,
+    Locrian,
+    HarmonicMinor,
+    MelodicMinor,
+    MajorPentatonic,
+    MinorPentatonic,
+    Blues,
+    WholeTone,
+    Chromatic,
+    /// Scale defined by the user, see `CustomScale`.
+    Custom,
+}
+
+impl ScaleType {
+    #[rustfmt::skip]
+    pub const ALL: [ScaleType; 17] = [
+        ScaleType::Basic, ScaleType::JazzLike,
+        ScaleType::Ionian, ScaleType::Dorian, ScaleType::Phrygian, ScaleType::Lydian,
+        ScaleType::Mixolydian, ScaleType::Aeolian, ScaleType::Locrian,
+        ScaleType::HarmonicMinor, ScaleType::MelodicMinor,
+        ScaleType::MajorPentatonic, ScaleType::MinorPentatonic, ScaleType::Blues,
+        ScaleType::WholeTone, ScaleType::Chromatic, ScaleType::Custom,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScaleType::Basic => "Basic",
+            ScaleType::JazzLike => "Jazz-like",
+            ScaleType::Ionian
+            | ScaleType::Dorian
+            | ScaleType::Phrygian
+            | ScaleType::Lydian
+            | ScaleType::Mixolydian
+            | ScaleType::Aeolian
+            | ScaleType::Locrian => self.mode().map_or("", |m| m.mode_name()),
+            ScaleType::HarmonicMinor => "Harmonic minor",
+            ScaleType::MelodicMinor => "Melodic minor",
+            ScaleType::MajorPentatonic => "Major pentatonic",
+            ScaleType::MinorPentatonic => "Minor pentatonic",
+            ScaleType::Blues => "Blues",
+            ScaleType::WholeTone => "Whole-tone",
+            ScaleType::Chromatic => "Chromatic",
+            ScaleType::Custom => "Custom",
+        }
+    }
+
+    /// Church mode of the scale, which is a key signature type as well.
+    pub fn mode(&self) -> Option<KeySignatureType> {
+        match self {
+            ScaleType::Ionian => Some(KeySignatureType::Maj),
+            ScaleType::Dorian => Some(KeySignatureType::Dorian),
+            ScaleType::Phrygian => Some(KeySignatureType::Phrygian),
+            ScaleType::Lydian => Some(KeySignatureType::Lydian),
+            ScaleType::Mixolydian => Some(KeySignatureType::Mixolydian),
+            ScaleType::Aeolian => Some(KeySignatureType::Min),
+            ScaleType::Locrian => Some(KeySignatureType::Locrian),
+            _ => None,
+        }
+    }
+
+    /// Halftone steps between the neighbouring notes of the scale, starting from the key note.
+    /// Modes take the steps of their key signature type.
+    ///
+    /// Returns `None` for scales, which depend on the key signature type or are defined by the user.
+    pub fn steps(&self) -> Option<Vec<u8>> {
+        if let Some(mode) = self.mode() {
+            return Some(mode.steps().to_vec());
+        }
+
+        let steps: &[u8] = match self {
+            ScaleType::HarmonicMinor => &[2, 1, 2, 2, 1, 3, 1],
+            ScaleType::MelodicMinor => &[2, 1, 2, 2, 2, 2, 1],
+            ScaleType::MajorPentatonic => &[2, 2, 3, 2, 3],
+            ScaleType::MinorPentatonic => &[3, 2, 2, 3, 2],
+            ScaleType::Blues => &[3, 2, 1, 1, 3, 2],
+            ScaleType::WholeTone => &[2, 2, 2, 2, 2, 2],
+            ScaleType::Chromatic => &[1; 12],
+            _ => return None,
+        };
+
+        Some(steps.to_vec())
+    }
+
+    /// Create scale of this type in the given key.
+    ///
+    /// The `custom` scale is used only for `ScaleType::Custom`.
+    pub fn scale(&self, key: KeySignature, custom: &CustomScale) -> Rc<dyn Scale> {
+        match (self, self.steps()) {
+            (ScaleType::JazzLike, _) => Rc::new(JazzLikeScale::new(key)),
+            (ScaleType::Custom, _) => Rc::new(custom.scale(key)),
+            (_, Some(steps)) => Rc::new(
+                StepScale::new(key, &steps).expect("Steps of the predefined scales are valid"),
+            ),
+            _ => Rc::new(BasicScale::new(key)),
+        }
+    }
+}
+
+impl From<KeySignatureType> for ScaleType {
+    /// Scale of the church mode.
+    fn from(mode: KeySignatureType) -> Self {
+        match mode {
+            KeySignatureType::Maj => ScaleType::Ionian,
+            KeySignatureType::Min => ScaleType::Aeolian,
+            KeySignatureType::Dorian => ScaleType::Dorian,
+            KeySignatureType::Phrygian => ScaleType::Phrygian,
+            KeySignatureType::Lydian => ScaleType::Lydian,
+            KeySignatureType::Mixolydian => ScaleType::Mixolydian,
+            KeySignatureType::Locrian => ScaleType::Locrian,
+        }
+    }
 }
 
 /// Scale a trait that helps us with moving notes in given key.
@@ -58,18 +180,16 @@ impl Scale for BasicScale {
 
         match self.key.signature_type {
             KeySignatureType::Maj => match rank {
-                4 | 11 => pitch.move_halftone_up(),
-                _ => pitch.move_tone_up(),
+                4 | 11 => *pitch += Interval::MINOR_SECOND,
+                _ => *pitch += Interval::MAJOR_SECOND,
             },
             // Natural minor
             KeySignatureType::Min => match rank {
-                2 | 7 => pitch.move_halftone_up(),
-                8 => {
-                    pitch.move_tone_up();
-                    pitch.move_halftone_up();
-                }
-                _ => pitch.move_tone_up(),
+                2 | 7 => *pitch += Interval::MINOR_SECOND,
+                8 => *pitch += Interval::AUGMENTED_SECOND,
+                _ => *pitch += Interval::MAJOR_SECOND,
             },
+            mode => StepScale::mode(self.key, mode).advance(pitch),
         }
     }
 
@@ -80,21 +200,20 @@ impl Scale for BasicScale {
 
         match self.key.signature_type {
             KeySignatureType::Maj => match rank {
-                0 | 5 => pitch.move_halftone_down(),
-                _ => pitch.move_tone_down(),
+                0 | 5 => *pitch -= Interval::MINOR_SECOND,
+                _ => *pitch -= Interval::MAJOR_SECOND,
             },
             KeySignatureType::Min => match rank {
-                0 | 3 | 8 => pitch.move_halftone_down(),
-                11 => {
-                    pitch.move_tone_down();
-                    pitch.move_halftone_down();
-                }
-                _ => pitch.move_tone_down(),
+                0 | 3 | 8 => *pitch -= Interval::MINOR_SECOND,
+                11 => *pitch -= Interval::AUGMENTED_SECOND,
+                _ => *pitch -= Interval::MAJOR_SECOND,
             },
+            mode => StepScale::mode(self.key, mode).recede(pitch),
         }
     }
 }
 
+/// Scale with jazz flavour. Minor keys and modes use the minor bebop scale.
 #[derive(Debug, Clone)]
 pub struct JazzLikeScale {
     pub key: KeySignature,
@@ -111,16 +230,20 @@ impl Scale for JazzLikeScale {
         let ht = Octave::halftone_count();
         let rank = (pitch.value_halftone() + ht - self.key.ext.value_halftone()) % ht;
 
-        match self.key.signature_type {
-            KeySignatureType::Maj => match rank {
-                2 | 9 => {
-                    pitch.move_tone_up();
-                    pitch.move_halftone_up();
-                }
-                7 | 8 => pitch.move_halftone_up(),
-                _ => pitch.move_tone_up(),
+        match self.key.signature_type.is_minor() {
+            false => match rank {
+                2 | 9 => *pitch += Interval::MINOR_THIRD,
+                // Passing flat sixth
+                7 => *pitch += Interval::MINOR_SECOND,
+                8 => *pitch += Interval::AUGMENTED_UNISON,
+                _ => *pitch += Interval::MAJOR_SECOND,
+            },
+            // Minor bebop - dorian with passing major third
+            true => match rank {
+                3 => *pitch += Interval::AUGMENTED_UNISON,
+                2 | 4 | 9 => *pitch += Interval::MINOR_SECOND,
+                _ => *pitch += Interval::MAJOR_SECOND,
             },
-            KeySignatureType::Min => todo!(),
         }
     }
 
@@ -128,20 +251,142 @@ impl Scale for JazzLikeScale {
         let ht = Octave::halftone_count();
         let rank = (pitch.value_halftone() + ht - self.key.ext.value_halftone()) % ht;
 
-        match self.key.signature_type {
-            KeySignatureType::Maj => match rank {
-                0 | 5 => {
-                    pitch.move_tone_down();
-                    pitch.move_halftone_down();
-                }
-                9 | 8 => pitch.move_halftone_down(),
-                _ => pitch.move_tone_down(),
+        match self.key.signature_type.is_minor() {
+            false => match rank {
+                0 | 5 => *pitch -= Interval::MINOR_THIRD,
+                9 => *pitch -= Interval::AUGMENTED_UNISON,
+                8 => *pitch -= Interval::MINOR_SECOND,
+                _ => *pitch -= Interval::MAJOR_SECOND,
+            },
+            true => match rank {
+                4 => *pitch -= Interval::AUGMENTED_UNISON,
+                3 | 5 | 10 => *pitch -= Interval::MINOR_SECOND,
+                _ => *pitch -= Interval::MAJOR_SECOND,
             },
-            KeySignatureType::Min => todo!(),
         }
     }
 }
 
+/// Scale given by halftone steps between its notes.
+///
+/// Notes outside of the scale are moved to the nearest scale note in the given direction.
+#[derive(Debug, Clone)]
+pub struct StepScale {
+    pub key: KeySignature,
+    /// Halftone distances of the scale notes from the key note.
+    degrees: Vec<u8>,
+}
+
+impl StepScale {
+    /// Create a new scale with given key and steps, which must be positive and sum up to
+    /// an octave.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::{lsystem::interpret::StepScale, notation::*};
+    ///
+    /// let key = KeySignature::from_fifths(0, KeySignatureType::Maj).unwrap();
+    ///
+    /// assert!(StepScale::new(key, &[2, 2, 3, 2, 3]).is_ok());
+    /// assert!(StepScale::new(key, &[]).is_err());
+    /// assert!(StepScale::new(key, &[255, 255, 2]).is_err());
+    /// ```
+    pub fn new(key: KeySignature, steps: &[u8]) -> Result<Self> {
+        if let Err(e) = Self::check_steps(steps) {
+            let pattern = steps.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+            Err(AppError::ScalePattern(pattern.join(" "), e))?;
+        }
+
+        let degrees = std::iter::once(0)
+            .chain(steps.iter().scan(0, |acc, s| {
+                *acc += s;
+                Some(*acc)
+            }))
+            .take(steps.len())
+            .collect();
+
+        Ok(Self { key, degrees })
+    }
+
+    /// Scale of the church mode.
+    fn mode(key: KeySignature, mode: KeySignatureType) -> Self {
+        Self::new(key, &mode.steps()).expect("Steps of the modes are valid")
+    }
+
+    /// Check that the steps are positive and sum up to an octave.
+    pub fn check_steps(steps: &[u8]) -> std::result::Result<(), String> {
+        if steps.contains(&0) {
+            return Err("steps must be positive".to_string());
+        }
+
+        let sum: u32 = steps.iter().map(|&s| s as u32).sum();
+        if sum != Octave::halftone_count() as u32 {
+            return Err(format!("steps sum up to {sum} instead of 12"));
+        }
+
+        Ok(())
+    }
+
+    fn rank(&self, pitch: &Pitch) -> u8 {
+        let ht = Octave::halftone_count();
+        (pitch.value_halftone() + ht - self.key.ext.value_halftone()) % ht
+    }
+
+    /// Move the pitch by the given number of halftones.
+    ///
+    /// Heptatonic scales move by seconds, so that each note name is used once. Other
+    /// scales and seconds, which can't be spelled, use the nearest simple note name in the
+    /// direction of movement.
+    fn shift(&self, pitch: &mut Pitch, halftones: u8, up: bool) {
+        let second = Interval::from_steps(1, halftones as i32).and_then(|second| match up {
+            true => pitch.checked_add(second),
+            false => pitch.checked_sub(second),
+        });
+
+        match (self.degrees.len(), second) {
+            (7, Some(second)) => *pitch = second,
+            _ => {
+                let (midi, prefer) = match up {
+                    true => (pitch.midi().checked_add(halftones), Accidental::Sharp),
+                    false => (pitch.midi().checked_sub(halftones), Accidental::Flat),
+                };
+
+                match midi.and_then(|m| Pitch::from_midi(m, Some(prefer))) {
+                    Some(p) => *pitch = p,
+                    None => log::warn!("Cannot move {pitch:?} by {halftones} halftones."),
+                }
+            }
+        }
+    }
+}
+
+impl Scale for StepScale {
+    fn advance(&self, pitch: &mut Pitch) {
+        let rank = self.rank(pitch);
+        let next = self
+            .degrees
+            .iter()
+            .find(|&&d| d > rank)
+            .copied()
+            .unwrap_or(Octave::halftone_count());
+
+        self.shift(pitch, next - rank, true);
+    }
+
+    fn recede(&self, pitch: &mut Pitch) {
+        let rank = self.rank(pitch) + Octave::halftone_count();
+        let prev = self
+            .degrees
+            .iter()
+            .rev()
+            .map(|&d| d + Octave::halftone_count())
+            .find(|&d| d < rank)
+            .unwrap_or(*self.degrees.last().unwrap());
+
+        self.shift(pitch, rank - prev, false);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -361,4 +606,266 @@ mod tests {
         assert_eq!(n5, Pitch::new(F, O4, Some(Sharp)));
         assert_eq!(n6, Pitch::new(D, O4, Some(Sharp)));
     }
+
+    #[test]
+    fn advance_jazz_min() {
+        // Arrange
+        let d_jazz = KeySignature {
+            ext: ExtNoteName {
+                note_name: D,
+                accidental: None,
+            },
+            signature_type: Min,
+        };
+        let scale = JazzLikeScale { key: d_jazz };
+        let n1 = Pitch::new(D, O4, None);
+
+        // Act
+        let n2 = scale.next(&n1);
+        let n3 = scale.next(&n2);
+        let n4 = scale.next(&n3);
+        let n5 = scale.next(&n4);
+        let n6 = scale.next(&n5);
+        let n7 = scale.next(&n6);
+        let n8 = scale.next(&n7);
+        let n9 = scale.next(&n8);
+
+        // Assert
+        assert_eq!(n2, Pitch::new(E, O4, None));
+        assert_eq!(n3, Pitch::new(F, O4, None));
+        assert_eq!(n4, Pitch::new(F, O4, Some(Sharp)));
+        assert_eq!(n5, Pitch::new(G, O4, None));
+        assert_eq!(n6, Pitch::new(A, O4, None));
+        assert_eq!(n7, Pitch::new(B, O4, None));
+        assert_eq!(n8, Pitch::new(C, O5, None));
+        assert_eq!(n9, Pitch::new(D, O5, None));
+    }
+
+    #[test]
+    fn recede_jazz_min() {
+        // Arrange
+        let d_jazz = KeySignature {
+            ext: ExtNoteName {
+                note_name: D,
+                accidental: None,
+            },
+            signature_type: Min,
+        };
+        let scale = JazzLikeScale { key: d_jazz };
+        let n1 = Pitch::new(D, O5, None);
+
+        // Act
+        let n2 = scale.prev(&n1);
+        let n3 = scale.prev(&n2);
+        let n4 = scale.prev(&n3);
+        let n5 = scale.prev(&n4);
+        let n6 = scale.prev(&n5);
+        let n7 = scale.prev(&n6);
+        let n8 = scale.prev(&n7);
+        let n9 = scale.prev(&n8);
+
+        // Assert
+        assert_eq!(n2, Pitch::new(C, O5, None));
+        assert_eq!(n3, Pitch::new(B, O4, None));
+        assert_eq!(n4, Pitch::new(A, O4, None));
+        assert_eq!(n5, Pitch::new(G, O4, None));
+        assert_eq!(n6, Pitch::new(F, O4, Some(Sharp)));
+        assert_eq!(n7, Pitch::new(F, O4, None));
+        assert_eq!(n8, Pitch::new(E, O4, None));
+        assert_eq!(n9, Pitch::new(D, O4, None));
+    }
+
+    #[test]
+    fn advance_dorian() {
+        // Arrange
+        let d_dorian = KeySignature {
+            ext: ExtNoteName {
+                note_name: D,
+                accidental: None,
+            },
+            signature_type: Min,
+        };
+        let scale = StepScale::new(d_dorian, &ScaleType::Dorian.steps().unwrap()).unwrap();
+        let n1 = Pitch::new(D, O4, None);
+
+        // Act
+        let n2 = scale.next(&n1);
+        let n3 = scale.next(&n2);
+        let n4 = scale.next(&n3);
+        let n5 = scale.next(&n4);
+        let n6 = scale.next(&n5);
+        let n7 = scale.next(&n6);
+        let n8 = scale.next(&n7);
+
+        // Assert
+        assert_eq!(n2, Pitch::new(E, O4, None));
+        assert_eq!(n3, Pitch::new(F, O4, None));
+        assert_eq!(n4, Pitch::new(G, O4, None));
+        assert_eq!(n5, Pitch::new(A, O4, None));
+        assert_eq!(n6, Pitch::new(B, O4, None));
+        assert_eq!(n7, Pitch::new(C, O5, None));
+        assert_eq!(n8, Pitch::new(D, O5, None));
+    }
+
+    #[test]
+    fn recede_harmonic_minor() {
+        // Arrange
+        let a_min = KeySignature {
+            ext: ExtNoteName {
+                note_name: A,
+                accidental: None,
+            },
+            signature_type: Min,
+        };
+        let scale = StepScale::new(a_min, &ScaleType::HarmonicMinor.steps().unwrap()).unwrap();
+        let n1 = Pitch::new(A, O4, None);
+
+        // Act
+        let n2 = scale.prev(&n1);
+        let n3 = scale.prev(&n2);
+        let n4 = scale.prev(&n3);
+        let n5 = scale.prev(&n4);
+
+        // Assert
+        assert_eq!(n2, Pitch::new(G, O4, Some(Sharp)));
+        assert_eq!(n3, Pitch::new(F, O4, None));
+        assert_eq!(n4, Pitch::new(E, O4, None));
+        assert_eq!(n5, Pitch::new(D, O4, None));
+    }
+
+    #[test]
+    fn advance_melodic_minor() {
+        // Arrange
+        let c_min = KeySignature {
+            ext: ExtNoteName {
+                note_name: C,
+                accidental: None,
+            },
+            signature_type: Min,
+        };
+        let scale = StepScale::new(c_min, &ScaleType::MelodicMinor.steps().unwrap()).unwrap();
+        let n1 = Pitch::new(G, O4, None);
+
+        // Act
+        let n2 = scale.next(&n1);
+        let n3 = scale.next(&n2);
+        let n4 = scale.next(&n3);
+
+        // Assert
+        assert_eq!(n2, Pitch::new(A, O4, None));
+        assert_eq!(n3, Pitch::new(B, O4, None));
+        assert_eq!(n4, Pitch::new(C, O5, None));
+    }
+
+    #[test]
+    fn advance_pentatonic() {
+        // Arrange
+        let g_maj = KeySignature {
+            ext: ExtNoteName {
+                note_name: G,
+                accidental: None,
+            },
+            signature_type: Maj,
+        };
+        let scale = StepScale::new(g_maj, &ScaleType::MajorPentatonic.steps().unwrap()).unwrap();
+        let n1 = Pitch::new(G, O3, None);
+
+        // Act
+        let n2 = scale.next(&n1);
+        let n3 = scale.next(&n2);
+        let n4 = scale.next(&n3);
+        let n5 = scale.next(&n4);
+        let n6 = scale.next(&n5);
+
+        // Assert
+        assert_eq!(n2, Pitch::new(A, O3, None));
+        assert_eq!(n3, Pitch::new(B, O3, None));
+        assert_eq!(n4, Pitch::new(D, O4, None));
+        assert_eq!(n5, Pitch::new(E, O4, None));
+        assert_eq!(n6, Pitch::new(G, O4, None));
+    }
+
+    #[test]
+    fn recede_blues() {
+        // Arrange
+        let e_min = KeySignature {
+            ext: ExtNoteName {
+                note_name: E,
+                accidental: None,
+            },
+            signature_type: Min,
+        };
+        let scale = StepScale::new(e_min, &ScaleType::Blues.steps().unwrap()).unwrap();
+        let n1 = Pitch::new(E, O5, None);
+
+        // Act
+        let n2 = scale.prev(&n1);
+        let n3 = scale.prev(&n2);
+        let n4 = scale.prev(&n3);
+        let n5 = scale.prev(&n4);
+        let n6 = scale.prev(&n5);
+        let n7 = scale.prev(&n6);
+
+        // Assert
+        assert_eq!(n2, Pitch::new(D, O5, None));
+        assert_eq!(n3, Pitch::new(B, O4, None));
+        assert_eq!(n4, Pitch::new(B, O4, Some(Flat)));
+        assert_eq!(n5, Pitch::new(A, O4, None));
+        assert_eq!(n6, Pitch::new(G, O4, None));
+        assert_eq!(n7, Pitch::new(E, O4, None));
+    }
+
+    #[test]
+    fn whole_tone_and_chromatic() {
+        // Arrange
+        let c_maj = KeySignature {
+            ext: ExtNoteName {
+                note_name: C,
+                accidental: None,
+            },
+            signature_type: Maj,
+        };
+        let whole_tone = StepScale::new(c_maj, &ScaleType::WholeTone.steps().unwrap()).unwrap();
+        let chromatic = StepScale::new(c_maj, &ScaleType::Chromatic.steps().unwrap()).unwrap();
+        let e = Pitch::new(E, O4, None);
+
+        // Act && Assert
+        assert_eq!(whole_tone.next(&e), Pitch::new(F, O4, Some(Sharp)));
+        assert_eq!(whole_tone.prev(&e), Pitch::new(D, O4, None));
+        assert_eq!(chromatic.next(&e), Pitch::new(F, O4, None));
+        assert_eq!(chromatic.prev(&e), Pitch::new(E, O4, Some(Flat)));
+    }
+
+    #[test]
+    fn step_scale_outside_note() {
+        // Arrange
+        let c_maj = KeySignature {
+            ext: ExtNoteName {
+                note_name: C,
+                accidental: None,
+            },
+            signature_type: Maj,
+        };
+        let scale = StepScale::new(c_maj, &ScaleType::MajorPentatonic.steps().unwrap()).unwrap();
+        let f = Pitch::new(F, O4, None);
+
+        // Act && Assert
+        assert_eq!(scale.next(&f), Pitch::new(G, O4, None));
+        assert_eq!(scale.prev(&f), Pitch::new(E, O4, None));
+    }
+
+    #[test]
+    fn modes_match_key_signature_types() {
+        for mode in KeySignatureType::ALL {
+            // Act
+            let scale_type = ScaleType::from(mode);
+
+            // Assert
+            assert_eq!(scale_type.mode(), Some(mode));
+            assert_eq!(scale_type.steps(), Some(mode.steps().to_vec()));
+            assert_eq!(scale_type.name(), mode.mode_name());
+        }
+        assert_eq!(ScaleType::Aeolian.name(), "Aeolian");
+        assert_eq!(ScaleType::Blues.mode(), None);
+    }
 }
@@ -4,7 +4,8 @@
 //! Jakub Kloub (xkloub03), VUT FIT
 
 pub struct ControlPanel;
-use std::rc::Rc;
+use rand::Rng;
+use std::{ops::Range, rc::Rc};
 
 use crate::lsystem::{
     l_rule::LRule,
@@ -12,18 +13,27 @@ use crate::lsystem::{
     CSSLRule,
 };
 
+/// Used rule along with the range of the new string written by its right side.
+pub type RuleSpan<R> = (Range<usize>, Rc<R>);
+
 /// Generic rewriter using the given ruleset.
 pub trait LRewriter<R: LRule, S: LRuleSet<R>> {
     fn rules(&self) -> &S;
     fn max_lside_len(&self) -> i32;
 
     /// Rewrite the given L-system string and return the new string and used rules.
-    fn rewrite(&self, s: &str) -> (String, Vec<Rc<R>>) {
+    fn rewrite(&self, s: &str, rng: &mut impl Rng) -> (String, Vec<Rc<R>>) {
+        let (word, used_rules) = self.rewrite_traced(s, rng);
+        (word, used_rules.into_iter().map(|(_, r)| r).collect())
+    }
+
+    /// Rewrite the given L-system string and return the new string and used rules
+    /// along with the ranges of the new string written by their right sides.
+    fn rewrite_traced(&self, s: &str, rng: &mut impl Rng) -> (String, Vec<RuleSpan<R>>) {
         // We start from the right (right derivation).
         let mut i = s.len() as i32 - 1;
-        // Store used left sides of rules for future new string construction.
-        let mut res: Vec<&str> = Vec::new();
-        let mut used_rules: Vec<Rc<R>> = vec![];
+        // Store used right sides of rules for future new string construction.
+        let mut res: Vec<(&str, Option<Rc<R>>)> = Vec::new();
 
         loop {
             // Get the left side to search in rules. We move the window rtl.
@@ -32,16 +42,13 @@ pub trait LRewriter<R: LRule, S: LRuleSet<R>> {
             let str_view = &s[j as usize..=i as usize];
 
             // Select rule, which matches the selected left side.
-            let (left, right) = match self.rules().select(str_view) {
-                Some(r) => {
-                    used_rules.push(r.clone());
-                    (r.left(), r.right())
-                }
-                None => ("_", &str_view[str_view.len() - 1..]),
+            let (left, right, rule) = match self.rules().select(str_view, rng) {
+                Some(r) => (r.left(), r.right(), Some(r.clone())),
+                None => ("_", &str_view[str_view.len() - 1..], None),
             };
 
             // Store the right side for future reconstruction.
-            res.push(right);
+            res.push((right, rule));
 
             // Move the window to left. We need to advance by length of the
             // replaced left side, so we don't interlace the replacements.
@@ -51,10 +58,22 @@ pub trait LRewriter<R: LRule, S: LRuleSet<R>> {
             }
         }
 
+        // The right sides were added from the right to left, so their ranges
+        // are counted from the end of the new string.
+        let mut end: usize = res.iter().map(|(right, _)| right.len()).sum();
+        let mut used_rules = vec![];
+        for (right, rule) in res.iter() {
+            let start = end - right.len();
+            if let Some(rule) = rule {
+                used_rules.push((start..end, rule.clone()));
+            }
+            end = start;
+        }
+
         // As the rules were added from the right to left, we need to
         // concat them in reverse order.
-        res.reverse();
-        (res.join(""), used_rules)
+        let word = res.iter().rev().map(|(right, _)| *right).collect();
+        (word, used_rules)
     }
 }
 
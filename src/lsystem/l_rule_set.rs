@@ -4,6 +4,7 @@
 //! Jakub Kloub (xkloub03), VUT FIT
 
 pub struct ControlPanel;
+use rand::Rng;
 use std::{fmt::Display, rc::Rc};
 
 use crate::lsystem::l_rule::{CSSLRule, LRule};
@@ -11,8 +12,8 @@ use crate::{error::Result, lsystem::l_rule::ToCSSLRule};
 
 /// Set of all rules in a given L-System
 pub trait LRuleSet<R: LRule>: Display {
-    /// Select a matching rule from the set.
-    fn select(&self, left: &str) -> Option<&Rc<R>>;
+    /// Select a matching rule from the set by the random generator.
+    fn select(&self, left: &str, rng: &mut impl Rng) -> Option<&Rc<R>>;
 
     fn rules(&self) -> &Vec<Rc<R>>;
 }
@@ -52,11 +53,11 @@ impl LRuleSet<CSSLRule> for CSSLRuleSet {
     /// Select rule by taking all matching rules, scaling the random 0..1 value
     /// to be in range 0..<sum of all rule probabilities> and selecting random
     /// rule from that.
-    fn select(&self, left: &str) -> Option<&Rc<CSSLRule>> {
+    fn select(&self, left: &str, rng: &mut impl Rng) -> Option<&Rc<CSSLRule>> {
         let matching_rules: Vec<_> = self.rules.iter().filter(|r| r.matches(left)).collect();
 
         let total_p: f32 = matching_rules.iter().map(|r| r.p()).sum();
-        let rnd = rand::random::<f32>() * total_p;
+        let rnd = rng.random::<f32>() * total_p;
 
         let mut acc = 0.;
         matching_rules
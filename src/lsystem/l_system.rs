@@ -7,11 +7,13 @@ pub struct ControlPanel;
 use derive_getters::Getters;
 
 use crate::lsystem::{
-    l_rewriter::{CSSLRewriter, LRewriter},
+    l_rewriter::{CSSLRewriter, LRewriter, RuleSpan},
     l_rule::CSSLRule,
     l_rule_set::CSSLRuleSet,
     LRule, LRuleSet,
 };
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
 use std::{fmt::Display, rc::Rc};
 
 /// Holds the L-system state needed for rewrites and statistics.
@@ -19,9 +21,31 @@ use std::{fmt::Display, rc::Rc};
 pub struct LSystemState {
     iter_num: i32,
     word: String,
+    /// Seed of the random generator, which reproduces the selected rules.
+    #[serde(default)]
+    seed: u64,
+    /// Random generator selecting the rules. It is the generator of `StdRng`, which can be
+    /// serialized, so the restored L-system continues with the same random sequence.
+    #[getter(skip)]
+    #[serde(default = "LSystemState::default_rng")]
+    rng: ChaCha12Rng,
 }
 
 impl LSystemState {
+    fn new(word: String, seed: u64) -> Self {
+        Self {
+            iter_num: 0,
+            word,
+            seed,
+            rng: ChaCha12Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Generator of the state saved without it, which has the default seed.
+    fn default_rng() -> ChaCha12Rng {
+        ChaCha12Rng::seed_from_u64(0)
+    }
+
     pub fn set_iter_num(&mut self, new_iter_num: i32) -> &mut Self {
         self.iter_num = new_iter_num;
         self
@@ -43,8 +67,18 @@ impl Display for LSystemState {
 pub trait LSystem<R: LRule, S: LRuleSet<R>, W: LRewriter<R, S>>: Display + std::fmt::Debug {
     /// Advance the L-System by rewriting the stored word.
     fn step(&mut self) -> Vec<Rc<R>> {
-        let rewrite_result = self.rewriter().rewrite(self.state().word.as_ref());
+        self.step_traced().into_iter().map(|(_, r)| r).collect()
+    }
+
+    /// Advance the L-System and return the used rules along with the ranges
+    /// of the new word they wrote.
+    fn step_traced(&mut self) -> Vec<RuleSpan<R>> {
+        let mut rng = self.state().rng.clone();
+        let rewrite_result = self
+            .rewriter()
+            .rewrite_traced(self.state().word.as_ref(), &mut rng);
 
+        self.state_mut().rng = rng;
         self.state_mut().word = rewrite_result.0;
         self.state_mut().iter_num += 1;
 
@@ -71,27 +105,22 @@ impl CSSLSystem {
     /// # Parameters
     /// - **axiom** Small string that will be expanded by rules
     /// - **rules** Stochastic context-sennsitive string rewriting rules
-    pub fn from(axiom: String, rules: &[&str]) -> Self {
+    /// - **seed** Seed of the random generator selecting the rules
+    pub fn from(axiom: String, rules: &[&str], seed: u64) -> Self {
         Self {
             rewriter: CSSLRewriter::new(CSSLRuleSet::new(
                 rules.iter().map(|s| CSSLRule::from(s).unwrap()).collect(),
             )),
             axiom: axiom.clone(),
-            state: LSystemState {
-                word: axiom,
-                iter_num: 0,
-            },
+            state: LSystemState::new(axiom, seed),
         }
     }
 
-    pub fn new(axiom: String, ruleset: CSSLRuleSet) -> Self {
+    pub fn new(axiom: String, ruleset: CSSLRuleSet, seed: u64) -> Self {
         Self {
             rewriter: CSSLRewriter::new(ruleset),
             axiom: axiom.clone(),
-            state: LSystemState {
-                word: axiom,
-                iter_num: 0,
-            },
+            state: LSystemState::new(axiom, seed),
         }
     }
 }
@@ -0,0 +1,827 @@
+//! Standard MIDI File writer and reader definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use std::path::Path;
+
+use crate::{
+    error::{AppError, Result},
+    notation::{KeySignature, Rational, Score, Stave, Symbol, TimeSignature},
+};
+
+/// Layout of the tracks in the written file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum SmfFormat {
+    /// Format 0, all staves are merged into a single track.
+    SingleTrack,
+    /// Format 1, tempo and signatures are in the first track, each stave has its own track.
+    #[default]
+    MultiTrack,
+}
+
+impl SmfFormat {
+    fn value(&self) -> u16 {
+        match self {
+            SmfFormat::SingleTrack => 0,
+            SmfFormat::MultiTrack => 1,
+        }
+    }
+}
+
+/// Channel reserved for percussion by General MIDI.
+const PERCUSSION_CHANNEL: u8 = 9;
+
+/// MIDI event placed at the given tick.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Event {
+    tick: u32,
+    /// Order of the events at the same tick: meta events, note offs, note ons.
+    priority: u8,
+    data: Vec<u8>,
+}
+
+impl Event {
+    fn meta(tick: u32, meta_type: u8, data: &[u8]) -> Self {
+        let mut bytes = vec![0xFF, meta_type];
+        write_var_len(&mut bytes, data.len() as u32);
+        bytes.extend_from_slice(data);
+
+        Self {
+            tick,
+            priority: 0,
+            data: bytes,
+        }
+    }
+
+    fn note_off(tick: u32, channel: u8, key: u8) -> Self {
+        Self {
+            tick,
+            priority: 1,
+            data: vec![0x80 | channel, key, 0],
+        }
+    }
+
+    fn note_on(tick: u32, channel: u8, key: u8, velocity: u8) -> Self {
+        Self {
+            tick,
+            priority: 2,
+            data: vec![0x90 | channel, key, velocity],
+        }
+    }
+}
+
+/// Append the number in the MIDI variable length encoding.
+fn write_var_len(out: &mut Vec<u8>, mut value: u32) {
+    let mut bytes = vec![(value & 0x7F) as u8];
+    value >>= 7;
+    while value > 0 {
+        bytes.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+
+    out.extend(bytes.iter().rev());
+}
+
+/// Writer of the score into a Standard MIDI File, independent of Lilypond.
+///
+/// # EXAMPLE
+/// ```
+/// # use music_sheet_gen::{midi::*, notation::*};
+///
+/// let score = Score {
+///     staves: vec![Stave {
+///         symbols: vec![Symbol::Note(Note {
+///             pitch: Pitch::new(NoteName::C, Octave::O4, None),
+///             duration: NoteLength::L4.into(),
+///             tie: false,
+///         })],
+///     }],
+///     ..Default::default()
+/// };
+///
+/// let smf = MidiWriter::default().write(&score).unwrap();
+///
+/// assert_eq!(&smf[..4], b"MThd");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct MidiWriter {
+    pub format: SmfFormat,
+    /// Resolution of the file in ticks per quarter note.
+    pub ticks_per_quarter: u16,
+    /// Velocity of all notes.
+    pub velocity: u8,
+}
+
+impl Default for MidiWriter {
+    fn default() -> Self {
+        Self {
+            format: SmfFormat::default(),
+            ticks_per_quarter: 480,
+            velocity: 90,
+        }
+    }
+}
+
+impl MidiWriter {
+    /// Write the score into the bytes of a Standard MIDI File.
+    pub fn write(&self, score: &Score) -> Result<Vec<u8>> {
+        if self.ticks_per_quarter == 0 || self.ticks_per_quarter > 0x7FFF {
+            Err(AppError::Midi(format!(
+                "Invalid resolution {} ticks per quarter",
+                self.ticks_per_quarter
+            )))?;
+        }
+
+        let mut conductor = vec![Self::tempo_event(0, score.tempo)?];
+        if let Some(name) = &score.info.name {
+            conductor.push(Event::meta(0, 0x03, name.as_bytes()));
+        }
+
+        let mut tracks = vec![];
+        for (i, stave) in score.staves.iter().enumerate() {
+            let (meta, notes) = self.stave_events(stave, Self::channel(i))?;
+            // Signatures are the same in all staves, the first one is enough.
+            if i == 0 {
+                conductor.extend(meta);
+            }
+            tracks.push(notes);
+        }
+
+        let tracks = match self.format {
+            SmfFormat::SingleTrack => vec![conductor
+                .into_iter()
+                .chain(tracks.into_iter().flatten())
+                .collect()],
+            SmfFormat::MultiTrack => std::iter::once(conductor).chain(tracks).collect(),
+        };
+
+        let mut out = b"MThd".to_vec();
+        out.extend(6u32.to_be_bytes());
+        out.extend(self.format.value().to_be_bytes());
+        out.extend((tracks.len() as u16).to_be_bytes());
+        out.extend(self.ticks_per_quarter.to_be_bytes());
+        for track in tracks {
+            Self::write_track(&mut out, track);
+        }
+
+        Ok(out)
+    }
+
+    /// Write the score into the file at the given path.
+    pub fn save(&self, score: &Score, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.write(score)?).map_err(|e| AppError::build_path(path, &e))?;
+
+        Ok(())
+    }
+
+    /// Channel of the stave, skipping the percussion channel.
+    fn channel(stave: usize) -> u8 {
+        let channel = (stave % 15) as u8;
+        if channel >= PERCUSSION_CHANNEL {
+            channel + 1
+        } else {
+            channel
+        }
+    }
+
+    fn tempo_event(tick: u32, quarters_per_minute: u32) -> Result<Event> {
+        if quarters_per_minute == 0 {
+            Err(AppError::Midi("Tempo must be positive".to_owned()))?;
+        }
+
+        let micros_per_quarter = (60_000_000 / quarters_per_minute).min(0xFF_FFFF);
+        Ok(Event::meta(
+            tick,
+            0x51,
+            &micros_per_quarter.to_be_bytes()[1..],
+        ))
+    }
+
+    fn time_signature_event(tick: u32, time: &TimeSignature) -> Result<Event> {
+        let (numerator, denominator) = (time.numerator(), time.denominator());
+        // Denominator is written as a power of two.
+        let (Ok(nom), true) = (u8::try_from(numerator), denominator.is_power_of_two()) else {
+            Err(AppError::Midi(format!(
+                "Unsupported time signature {numerator}/{denominator}"
+            )))?
+        };
+
+        let denom = denominator.trailing_zeros() as u8;
+        Ok(Event::meta(tick, 0x58, &[nom, denom, 24, 8]))
+    }
+
+    fn key_signature_event(tick: u32, key: &KeySignature) -> Event {
+        // Keys with double accidentals can't be written, their enharmonic key is used.
+        let fifths = match key.fifths() {
+            f if f > 7 => f - 12,
+            f if f < -7 => f + 12,
+            f => f,
+        };
+        let minor = key.signature_type.is_minor() as u8;
+
+        Event::meta(tick, 0x59, &[fifths as u8, minor])
+    }
+
+    /// Tick of the time given as a fraction of the whole note.
+    fn tick(&self, time: Rational) -> u32 {
+        (time * self.ticks_per_quarter as u32 * 4)
+            .round()
+            .to_integer()
+    }
+
+    /// Tempo and signature events and note events of the stave.
+    fn stave_events(&self, stave: &Stave, channel: u8) -> Result<(Vec<Event>, Vec<Event>)> {
+        let (mut meta, mut notes) = (vec![], vec![]);
+        let mut time = Rational::from(0);
+        // Keys of the notes tied from the previous symbol.
+        let mut held: Vec<u8> = vec![];
+
+        for symbol in stave.symbols.iter() {
+            let tick = self.tick(time);
+
+            let keys: Vec<u8> = match symbol {
+                Symbol::Note(note) => vec![note.pitch.midi()],
+                Symbol::Chord(chord) => chord.pitches.iter().map(|p| p.midi()).collect(),
+                Symbol::Rest(_) => vec![],
+                Symbol::Tempo(tempo) => {
+                    meta.push(Self::tempo_event(tick, tempo.quarters_per_minute())?);
+                    continue;
+                }
+                Symbol::TimeSignature(time) => {
+                    meta.push(Self::time_signature_event(tick, time)?);
+                    continue;
+                }
+                Symbol::KeySignature(key) => {
+                    meta.push(Self::key_signature_event(tick, key));
+                    continue;
+                }
+                Symbol::Clef(_) => continue,
+            };
+            let tie = match symbol {
+                Symbol::Note(note) => note.tie,
+                Symbol::Chord(chord) => chord.tie,
+                _ => false,
+            };
+
+            // Tied notes continue sounding, the others are released.
+            for &key in held.iter().filter(|k| !keys.contains(k)) {
+                notes.push(Event::note_off(tick, channel, key));
+            }
+            for &key in keys.iter().filter(|k| !held.contains(k)) {
+                notes.push(Event::note_on(tick, channel, key, self.velocity));
+            }
+
+            if let Some(duration) = symbol.duration() {
+                time += duration.value();
+            }
+
+            held = match tie {
+                true => keys,
+                false => {
+                    let end = self.tick(time);
+                    notes.extend(keys.iter().map(|&key| Event::note_off(end, channel, key)));
+                    vec![]
+                }
+            };
+        }
+
+        // Tie at the end of the stave has nothing to continue to.
+        let end = self.tick(time);
+        notes.extend(held.iter().map(|&key| Event::note_off(end, channel, key)));
+
+        Ok((meta, notes))
+    }
+
+    fn write_track(out: &mut Vec<u8>, mut events: Vec<Event>) {
+        events.sort_by_key(|e| (e.tick, e.priority));
+        let end = events.last().map_or(0, |e| e.tick);
+        events.push(Event::meta(end, 0x2F, &[]));
+
+        let mut data = vec![];
+        let mut last_tick = 0;
+        for event in events {
+            write_var_len(&mut data, event.tick - last_tick);
+            data.extend(event.data);
+            last_tick = event.tick;
+        }
+
+        out.extend(b"MTrk");
+        out.extend((data.len() as u32).to_be_bytes());
+        out.extend(data);
+    }
+}
+
+/// Note read from a MIDI file with its times in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MidiNote {
+    pub channel: u8,
+    pub key: u8,
+    pub velocity: u8,
+    /// Program of the channel when the note started.
+    pub program: u8,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Channel event read from a track.
+#[derive(Debug, Clone, Copy)]
+enum ReadEvent {
+    NoteOn {
+        channel: u8,
+        key: u8,
+        velocity: u8,
+    },
+    NoteOff {
+        channel: u8,
+        key: u8,
+    },
+    Program {
+        channel: u8,
+        program: u8,
+    },
+    /// New tempo in microseconds per quarter note.
+    Tempo(u32),
+}
+
+/// Bytes of a track chunk.
+struct TrackData<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl TrackData<'_> {
+    fn byte(&mut self) -> Result<u8> {
+        let byte = self.data.get(self.pos).copied();
+        self.pos += 1;
+
+        Ok(byte.ok_or(AppError::Midi("Unexpected end of track".to_owned()))?)
+    }
+
+    fn var_len(&mut self) -> Result<u32> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let byte = self.byte()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(AppError::Midi(
+            "Variable length number is too long".to_owned(),
+        ))?
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&[u8]> {
+        let bytes = self.data.get(self.pos..self.pos + len);
+        self.pos += len;
+
+        Ok(bytes.ok_or(AppError::Midi("Unexpected end of track".to_owned()))?)
+    }
+}
+
+/// Reader of the notes of a Standard MIDI File, which are played by the synthesizer.
+///
+/// # EXAMPLE
+/// ```
+/// # use music_sheet_gen::{midi::*, notation::*};
+///
+/// let score = Score {
+///     staves: vec![Stave {
+///         symbols: vec![Symbol::Note(Note {
+///             pitch: Pitch::new(NoteName::A, Octave::O4, None),
+///             duration: NoteLength::L4.into(),
+///             tie: false,
+///         })],
+///     }],
+///     tempo: 60,
+///     ..Default::default()
+/// };
+/// let smf = MidiWriter::default().write(&score).unwrap();
+///
+/// let notes = MidiReader.read(&smf).unwrap();
+///
+/// assert_eq!(notes.len(), 1);
+/// assert_eq!((notes[0].key, notes[0].end), (69, 1.0));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MidiReader;
+
+impl MidiReader {
+    /// Read the notes of the file ordered by their start.
+    pub fn read(&self, smf: &[u8]) -> Result<Vec<MidiNote>> {
+        let invalid = |message: &str| AppError::Midi(message.to_owned());
+
+        let mut chunks = vec![];
+        let mut i = 0;
+        while i + 8 <= smf.len() {
+            let len = u32::from_be_bytes(smf[i + 4..i + 8].try_into().unwrap()) as usize;
+            let data = smf
+                .get(i + 8..i + 8 + len)
+                .ok_or(invalid("Chunk exceeds the file"))?;
+            chunks.push((&smf[i..i + 4], data));
+            i += 8 + len;
+        }
+
+        let Some((b"MThd", header)) = chunks.first() else {
+            Err(invalid("File doesn't start with the MThd chunk"))?
+        };
+        let division = match header.get(4..6) {
+            Some(&[high, low]) => u16::from_be_bytes([high, low]),
+            _ => Err(invalid("Header chunk is too short"))?,
+        };
+        if division == 0 || division & 0x8000 != 0 {
+            Err(invalid(
+                "Only the resolution in ticks per quarter is supported",
+            ))?;
+        }
+
+        let mut events = vec![];
+        for (_, data) in chunks.iter().filter(|(id, _)| *id == b"MTrk") {
+            events.extend(Self::track_events(data)?);
+        }
+        // Stable sort keeps the order of the events at the same tick in a track.
+        events.sort_by_key(|(tick, _)| *tick);
+
+        Ok(Self::notes(&events, division))
+    }
+
+    /// Read the notes of the file at the given path.
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<Vec<MidiNote>> {
+        let path = path.as_ref();
+        let smf = std::fs::read(path).map_err(|e| AppError::build_path(path, &e))?;
+
+        self.read(&smf)
+    }
+
+    /// Events of the track with their absolute ticks.
+    fn track_events(data: &[u8]) -> Result<Vec<(u64, ReadEvent)>> {
+        let mut track = TrackData { data, pos: 0 };
+        let mut events = vec![];
+        let mut tick = 0;
+        let mut running_status = None;
+
+        while track.pos < data.len() {
+            tick += track.var_len()? as u64;
+
+            let mut status = track.byte()?;
+            let first = match status {
+                0x80.. => None,
+                // Running status reuses the status of the previous channel event.
+                _ => {
+                    let first = status;
+                    status = running_status.ok_or(AppError::Midi(
+                        "Running status without a previous event".to_owned(),
+                    ))?;
+                    Some(first)
+                }
+            };
+
+            match status {
+                0xFF => {
+                    let meta_type = track.byte()?;
+                    let len = track.var_len()? as usize;
+                    let meta = track.bytes(len)?;
+                    match (meta_type, meta) {
+                        (0x51, &[a, b, c]) => {
+                            let tempo = u32::from_be_bytes([0, a, b, c]);
+                            events.push((tick, ReadEvent::Tempo(tempo)));
+                        }
+                        (0x2F, _) => break,
+                        _ => {}
+                    }
+                }
+                0xF0 | 0xF7 => {
+                    let len = track.var_len()? as usize;
+                    track.bytes(len)?;
+                }
+                0x80..=0xEF => {
+                    running_status = Some(status);
+                    let channel = status & 0x0F;
+                    let first = match first {
+                        Some(first) => first,
+                        None => track.byte()?,
+                    };
+
+                    let event = match status & 0xF0 {
+                        0xC0 => Some(ReadEvent::Program {
+                            channel,
+                            program: first,
+                        }),
+                        0xD0 => None,
+                        kind => {
+                            let second = track.byte()?;
+                            match (kind, second) {
+                                (0x90, 1..) => Some(ReadEvent::NoteOn {
+                                    channel,
+                                    key: first,
+                                    velocity: second,
+                                }),
+                                (0x80 | 0x90, _) => Some(ReadEvent::NoteOff {
+                                    channel,
+                                    key: first,
+                                }),
+                                _ => None,
+                            }
+                        }
+                    };
+                    events.extend(event.map(|e| (tick, e)));
+                }
+                _ => Err(AppError::Midi(format!(
+                    "Unsupported status byte {status:#X}"
+                )))?,
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Pair the note ons with the note offs and convert their ticks to seconds.
+    fn notes(events: &[(u64, ReadEvent)], division: u16) -> Vec<MidiNote> {
+        let mut notes: Vec<MidiNote> = vec![];
+        let mut programs = [0; 16];
+        // Indices of the sounding notes of each channel and key.
+        let mut sounding: std::collections::HashMap<(u8, u8), Vec<usize>> = Default::default();
+
+        // Default tempo is 120 quarters per minute.
+        let mut micros_per_quarter = 500_000.0;
+        let (mut last_tick, mut seconds) = (0, 0.0);
+
+        for &(tick, event) in events {
+            seconds += (tick - last_tick) as f64 * micros_per_quarter / division as f64 / 1e6;
+            last_tick = tick;
+
+            match event {
+                ReadEvent::Tempo(tempo) => micros_per_quarter = tempo as f64,
+                ReadEvent::Program { channel, program } => {
+                    programs[channel as usize] = program;
+                }
+                ReadEvent::NoteOn {
+                    channel,
+                    key,
+                    velocity,
+                } => {
+                    sounding
+                        .entry((channel, key))
+                        .or_default()
+                        .push(notes.len());
+                    notes.push(MidiNote {
+                        channel,
+                        key,
+                        velocity,
+                        program: programs[channel as usize],
+                        start: seconds,
+                        end: seconds,
+                    });
+                }
+                ReadEvent::NoteOff { channel, key } => {
+                    let started = sounding.get_mut(&(channel, key));
+                    if let Some(index) = started.and_then(|s| (!s.is_empty()).then(|| s.remove(0)))
+                    {
+                        notes[index].end = seconds;
+                    }
+                }
+            }
+        }
+
+        // Notes without a note off sound until the last event.
+        for index in sounding.into_values().flatten() {
+            notes[index].end = seconds;
+        }
+
+        notes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{
+        Chord, Duration, KeySignatureType, Note, NoteLength, NoteName, Octave, Pitch, Tempo, Tuplet,
+    };
+
+    fn note(note_name: NoteName, tie: bool) -> Symbol {
+        Symbol::Note(Note {
+            pitch: Pitch::new(note_name, Octave::O4, None),
+            duration: NoteLength::L4.into(),
+            tie,
+        })
+    }
+
+    /// Split the file into its chunks.
+    fn chunks(smf: &[u8]) -> Vec<(&[u8], &[u8])> {
+        let mut chunks = vec![];
+        let mut i = 0;
+        while i < smf.len() {
+            let len = u32::from_be_bytes(smf[i + 4..i + 8].try_into().unwrap()) as usize;
+            chunks.push((&smf[i..i + 4], &smf[i + 8..i + 8 + len]));
+            i += 8 + len;
+        }
+
+        chunks
+    }
+
+    #[test]
+    fn var_len() {
+        for (value, expected) in [
+            (0, vec![0x00]),
+            (0x7F, vec![0x7F]),
+            (0x80, vec![0x81, 0x00]),
+            (0x3FFF, vec![0xFF, 0x7F]),
+            (0x0FFF_FFFF, vec![0xFF, 0xFF, 0xFF, 0x7F]),
+        ] {
+            // Act
+            let mut out = vec![];
+            write_var_len(&mut out, value);
+
+            // Assert
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn write_multi_track() {
+        // Arrange
+        let d_maj = KeySignature {
+            ext: crate::notation::ExtNoteName {
+                note_name: NoteName::D,
+                accidental: None,
+            },
+            signature_type: KeySignatureType::Maj,
+        };
+        let score = Score {
+            staves: vec![
+                Stave {
+                    symbols: vec![
+                        Symbol::KeySignature(d_maj),
+                        Symbol::TimeSignature(TimeSignature::c()),
+                        note(NoteName::C, true),
+                        note(NoteName::C, false),
+                        Symbol::Rest(NoteLength::L4.into()),
+                        Symbol::Tempo(Tempo {
+                            note_length: NoteLength::L4.into(),
+                            speed: 60,
+                        }),
+                    ],
+                },
+                Stave {
+                    symbols: vec![Symbol::Chord(Chord {
+                        pitches: vec![
+                            Pitch::new(NoteName::C, Octave::O3, None),
+                            Pitch::new(NoteName::G, Octave::O3, None),
+                        ],
+                        duration: Duration::new(NoteLength::L8, 0, Some(Tuplet::triplet())),
+                        tie: false,
+                    })],
+                },
+            ],
+            tempo: 120,
+            ..Default::default()
+        };
+
+        // Act
+        let smf = MidiWriter::default().write(&score).unwrap();
+
+        // Assert
+        let chunks = chunks(&smf);
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[0], (&b"MThd"[..], &[0, 1, 0, 3, 0x01, 0xE0][..]));
+        assert_eq!(
+            chunks[1].1,
+            [
+                0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // 120 BPM
+                0x00, 0xFF, 0x59, 0x02, 0x02, 0x00, // D major
+                0x00, 0xFF, 0x58, 0x04, 0x04, 0x02, 24, 8, // 4/4
+                0x8B, 0x20, 0xFF, 0x51, 0x03, 0x0F, 0x42, 0x40, // 60 BPM after three beats
+                0x00, 0xFF, 0x2F, 0x00,
+            ]
+        );
+        // Tied notes sound as a single half note.
+        assert_eq!(
+            chunks[2].1,
+            [
+                0x00, 0x90, 60, 90, //
+                0x87, 0x40, 0x80, 60, 0, //
+                0x00, 0xFF, 0x2F, 0x00,
+            ]
+        );
+        // Triplet eight takes third of a quarter.
+        assert_eq!(
+            chunks[3].1,
+            [
+                0x00, 0x91, 48, 90, 0x00, 0x91, 55, 90, //
+                0x81, 0x20, 0x81, 48, 0, 0x00, 0x81, 55, 0, //
+                0x00, 0xFF, 0x2F, 0x00,
+            ]
+        );
+    }
+
+    #[test]
+    fn read_written_notes() {
+        // Arrange
+        let score = Score {
+            staves: vec![
+                Stave {
+                    symbols: vec![
+                        note(NoteName::C, true),
+                        note(NoteName::C, false),
+                        Symbol::Tempo(Tempo {
+                            note_length: NoteLength::L4.into(),
+                            speed: 60,
+                        }),
+                        note(NoteName::D, false),
+                    ],
+                },
+                Stave {
+                    symbols: vec![
+                        Symbol::Rest(NoteLength::L4.into()),
+                        note(NoteName::E, false),
+                    ],
+                },
+            ],
+            tempo: 120,
+            ..Default::default()
+        };
+
+        for format in [SmfFormat::SingleTrack, SmfFormat::MultiTrack] {
+            let smf = MidiWriter {
+                format,
+                ..Default::default()
+            }
+            .write(&score)
+            .unwrap();
+
+            // Act
+            let notes = MidiReader.read(&smf).unwrap();
+
+            // Assert
+            let keys: Vec<_> = notes.iter().map(|n| (n.channel, n.key)).collect();
+            assert_eq!(keys, [(0, 60), (1, 64), (0, 62)]);
+            assert_eq!((notes[0].start, notes[0].end), (0.0, 1.0));
+            assert_eq!((notes[1].start, notes[1].end), (0.5, 1.0));
+            assert_eq!((notes[2].start, notes[2].end), (1.0, 2.0));
+            assert!(notes.iter().all(|n| n.velocity == 90));
+        }
+    }
+
+    #[test]
+    fn read_running_status() {
+        // Arrange
+        let mut smf = b"MThd".to_vec();
+        smf.extend([0, 0, 0, 6, 0, 0, 0, 1, 0, 96]);
+        let track = [
+            0x00, 0xC3, 5, // program 5
+            0x00, 0x93, 60, 100, 0x00, 64, 100, // two notes with running status
+            0x60, 60, 0, 0x00, 0x83, 64, 0, // note on with zero velocity and note off
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        smf.extend(b"MTrk");
+        smf.extend((track.len() as u32).to_be_bytes());
+        smf.extend(track);
+
+        // Act
+        let notes = MidiReader.read(&smf).unwrap();
+
+        // Assert
+        assert_eq!(notes.len(), 2);
+        assert!(notes.iter().all(|n| n.program == 5 && n.end == 0.5));
+    }
+
+    #[test]
+    fn write_single_track() {
+        // Arrange
+        let score = Score {
+            staves: vec![
+                Stave {
+                    symbols: vec![note(NoteName::C, false)],
+                },
+                Stave {
+                    symbols: vec![note(NoteName::E, false)],
+                },
+            ],
+            ..Default::default()
+        };
+        let writer = MidiWriter {
+            format: SmfFormat::SingleTrack,
+            ..Default::default()
+        };
+
+        // Act
+        let smf = writer.write(&score).unwrap();
+
+        // Assert
+        let chunks = chunks(&smf);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].1, [0, 0, 0, 1, 0x01, 0xE0]);
+        assert_eq!(
+            chunks[1].1[7..],
+            [
+                0x00, 0x90, 60, 90, 0x00, 0x91, 64, 90, //
+                0x83, 0x60, 0x80, 60, 0, 0x00, 0x81, 64, 0, //
+                0x00, 0xFF, 0x2F, 0x00,
+            ]
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! Module with the MusicXML import and export of the score
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+mod musicxml_reader;
+mod musicxml_writer;
+
+pub use musicxml_reader::MusicXmlReader;
+pub use musicxml_writer::MusicXmlWriter;
+
+use crate::notation::{Clef, KeySignatureType, NoteLength};
+
+/// Note type names of MusicXML.
+fn note_type(length: NoteLength) -> &'static str {
+    match length {
+        NoteLength::L1 => "whole",
+        NoteLength::L2 => "half",
+        NoteLength::L4 => "quarter",
+        NoteLength::L8 => "eighth",
+        NoteLength::L16 => "16th",
+        NoteLength::L32 => "32nd",
+        NoteLength::L64 => "64th",
+        NoteLength::L128 => "128th",
+    }
+}
+
+/// Mode names of MusicXML.
+fn mode(signature_type: KeySignatureType) -> &'static str {
+    match signature_type {
+        KeySignatureType::Maj => "major",
+        KeySignatureType::Min => "minor",
+        KeySignatureType::Dorian => "dorian",
+        KeySignatureType::Phrygian => "phrygian",
+        KeySignatureType::Lydian => "lydian",
+        KeySignatureType::Mixolydian => "mixolydian",
+        KeySignatureType::Locrian => "locrian",
+    }
+}
+
+/// Sign and staff line of the clef.
+fn clef_sign(clef: Clef) -> (&'static str, u8) {
+    match clef {
+        Clef::Treble => ("G", 2),
+        Clef::Bass => ("F", 4),
+    }
+}
+
+/// Escape the text, so that it can be written into an element or an attribute.
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_owned(),
+            '<' => "&lt;".to_owned(),
+            '>' => "&gt;".to_owned(),
+            '"' => "&quot;".to_owned(),
+            '\'' => "&apos;".to_owned(),
+            c => c.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,743 @@
+//! MusicXML reader definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use std::path::Path;
+
+use roxmltree::{Document, Node};
+
+use crate::{
+    error::{AppError, Result},
+    musicxml::{clef_sign, mode, note_type},
+    notation::{
+        Accidental, Chord, Clef, Duration, ExtNoteName, KeySignature, KeySignatureType, Note,
+        NoteLength, NoteName, Octave, Pitch, Rational, Score, ScoreInfo, Stave, Symbol, Tempo,
+        TimeSignature, Tuplet,
+    },
+};
+
+/// Error at the position of the node.
+fn error(node: Node, message: impl Into<String>) -> Box<dyn std::error::Error> {
+    let position = node.document().text_pos_at(node.range().start);
+    AppError::MusicXml(format!("line {}: {}", position.row, message.into())).into()
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name).and_then(|n| n.text()).map(str::trim)
+}
+
+/// Parse the text of the child element.
+fn parse_child<T: std::str::FromStr>(node: Node, name: &str) -> Result<Option<T>> {
+    child_text(node, name)
+        .map(|text| {
+            text.parse()
+                .map_err(|_| error(node, format!("Invalid <{name}> value '{text}'")))
+        })
+        .transpose()
+}
+
+/// Reader of partwise MusicXML documents. Each staff of each part becomes a stave.
+///
+/// # EXAMPLE
+/// ```
+/// # use music_sheet_gen::{musicxml::*, notation::*};
+///
+/// let xml = r#"<score-partwise version="4.0">
+///   <part-list><score-part id="P1"><part-name>Piano</part-name></score-part></part-list>
+///   <part id="P1"><measure number="1">
+///     <attributes><divisions>1</divisions></attributes>
+///     <note><pitch><step>C</step><octave>4</octave></pitch><duration>1</duration><type>quarter</type></note>
+///   </measure></part>
+/// </score-partwise>"#;
+///
+/// let score = MusicXmlReader.read(xml).unwrap();
+///
+/// assert_eq!(score.staves.len(), 1);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MusicXmlReader;
+
+impl MusicXmlReader {
+    /// Read the score from the MusicXML document.
+    pub fn read(&self, source: &str) -> Result<Score> {
+        let document = Document::parse_with_options(
+            source,
+            roxmltree::ParsingOptions {
+                allow_dtd: true,
+                ..Default::default()
+            },
+        )
+        .map_err(|e| AppError::MusicXml(e.to_string()))?;
+        let root = document.root_element();
+
+        match root.tag_name().name() {
+            "score-partwise" => {}
+            "score-timewise" => Err(error(root, "Timewise documents are not supported"))?,
+            name => Err(error(root, format!("Unknown root element <{name}>")))?,
+        }
+
+        let mut staves = vec![];
+        let mut tempo = None;
+        for part in root.children().filter(|n| n.has_tag_name("part")) {
+            let mut reader = PartReader::default();
+            reader.part(part)?;
+
+            tempo = tempo.or(reader.tempo);
+            staves.extend(reader.staves);
+        }
+
+        if staves.is_empty() {
+            Err(error(root, "Document contains no parts"))?;
+        }
+
+        Ok(Score {
+            staves,
+            info: Self::info(root),
+            tempo: tempo.unwrap_or(Score::default().tempo),
+            spelling: None,
+        })
+    }
+
+    /// Read the score from the file at the given path.
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<Score> {
+        let path = path.as_ref();
+        if path.extension().is_some_and(|e| e == "mxl") {
+            Err(AppError::MusicXml(
+                "Compressed MusicXML is not supported, export it uncompressed".to_owned(),
+            ))?;
+        }
+
+        let source = std::fs::read_to_string(path).map_err(|e| AppError::build_path(path, &e))?;
+        self.read(&source)
+    }
+
+    fn info(root: Node) -> ScoreInfo {
+        let creator = |creator_type: &str| {
+            child(root, "identification")?
+                .children()
+                .find(|n| n.has_tag_name("creator") && n.attribute("type") == Some(creator_type))
+                .and_then(|n| n.text())
+                .map(|t| t.trim().to_owned())
+        };
+        let name = child(root, "work")
+            .and_then(|w| child_text(w, "work-title"))
+            .or(child_text(root, "movement-title"))
+            .map(str::to_owned);
+
+        ScoreInfo {
+            name,
+            author: creator("composer"),
+            transcriber: creator("arranger"),
+        }
+    }
+}
+
+/// Reader of a single part, which may have several staves.
+#[derive(Debug)]
+struct PartReader {
+    /// Divisions of the quarter note.
+    divisions: u32,
+    /// Current time in the measure, moved by notes, `<backup>` and `<forward>`.
+    cursor: Rational,
+    /// Start of the current measure.
+    measure_start: Rational,
+    staves: Vec<Stave>,
+    /// Time at which each stave ends.
+    stave_ends: Vec<Rational>,
+    /// Tempo of the first `<sound>` element in quarters per minute.
+    tempo: Option<u32>,
+}
+
+impl Default for PartReader {
+    fn default() -> Self {
+        Self {
+            divisions: 1,
+            cursor: Rational::from(0),
+            measure_start: Rational::from(0),
+            staves: vec![Stave::default()],
+            stave_ends: vec![Rational::from(0)],
+            tempo: None,
+        }
+    }
+}
+
+impl PartReader {
+    fn part(&mut self, part: Node) -> Result<()> {
+        for measure in part.children().filter(|n| n.has_tag_name("measure")) {
+            self.measure_start = self.cursor;
+
+            let mut measure_end = self.cursor;
+            for element in measure.children().filter(Node::is_element) {
+                self.element(element)?;
+                measure_end = measure_end.max(self.cursor);
+            }
+
+            self.cursor = measure_end;
+        }
+
+        // Rests of the staves, which end earlier.
+        for i in 0..self.staves.len() {
+            self.fill_to(i, self.cursor);
+        }
+
+        // Both `<key>` and `<time>` are optional, C major and 4/4 are assumed without them.
+        self.staves
+            .iter_mut()
+            .for_each(Stave::insert_missing_signatures);
+
+        Ok(())
+    }
+
+    fn element(&mut self, element: Node) -> Result<()> {
+        match element.tag_name().name() {
+            "attributes" => self.attributes(element)?,
+            "note" => self.note(element)?,
+            "backup" => {
+                let duration = self.duration(element)?;
+                if duration > self.cursor - self.measure_start {
+                    Err(error(
+                        element,
+                        "Backup moves before the start of the measure",
+                    ))?;
+                }
+                self.cursor -= duration;
+            }
+            "forward" => self.cursor += self.duration(element)?,
+            "direction" => self.direction(element)?,
+            "sound" => self.sound(element)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Value of the `<duration>` child as a fraction of the whole note.
+    fn duration(&self, node: Node) -> Result<Rational> {
+        let duration: u32 =
+            parse_child(node, "duration")?.ok_or_else(|| error(node, "Missing <duration>"))?;
+
+        Ok(Rational::new(duration, 4 * self.divisions))
+    }
+
+    /// Index of the stave given by the `number` attribute or the `<staff>` child.
+    fn stave_index(&self, node: Node, number: Option<&str>) -> Result<usize> {
+        let number: usize = match number {
+            Some(number) => number
+                .parse()
+                .map_err(|_| error(node, format!("Invalid staff number '{number}'")))?,
+            None => 1,
+        };
+        if number == 0 || number > self.staves.len() {
+            Err(error(node, format!("Staff {number} is not declared")))?;
+        }
+
+        Ok(number - 1)
+    }
+
+    /// Add rests to the stave up to the given time.
+    fn fill_to(&mut self, stave: usize, time: Rational) {
+        if self.stave_ends[stave] < time {
+            let gap = time - self.stave_ends[stave];
+            self.staves[stave]
+                .symbols
+                .extend(Duration::split(gap).into_iter().map(Symbol::Rest));
+            self.stave_ends[stave] = time;
+        }
+    }
+
+    /// Push the symbol, which takes no time, to the stave at the cursor.
+    fn push_at_cursor(&mut self, node: Node, stave: usize, symbol: Symbol) -> Result<()> {
+        if self.stave_ends[stave] > self.cursor {
+            Err(error(
+                node,
+                "Multiple voices in one staff are not supported",
+            ))?;
+        }
+
+        self.fill_to(stave, self.cursor);
+        self.staves[stave].symbols.push(symbol);
+
+        Ok(())
+    }
+
+    /// Indices of the staves, to which the attribute with the optional `number` applies.
+    fn attribute_staves(&self, node: Node) -> Result<Vec<usize>> {
+        match node.attribute("number") {
+            Some(number) => Ok(vec![self.stave_index(node, Some(number))?]),
+            None => Ok((0..self.staves.len()).collect()),
+        }
+    }
+
+    fn attributes(&mut self, attributes: Node) -> Result<()> {
+        if let Some(divisions) = parse_child::<u32>(attributes, "divisions")? {
+            if divisions == 0 {
+                Err(error(attributes, "Divisions must be positive"))?;
+            }
+            self.divisions = divisions;
+        }
+
+        if let Some(count) = parse_child::<usize>(attributes, "staves")? {
+            if count == 0
+                || self.stave_ends.iter().any(|&e| e > Rational::from(0))
+                    && count != self.staves.len()
+            {
+                Err(error(
+                    attributes,
+                    "Number of staves can't change within the part",
+                ))?;
+            }
+            self.staves.resize_with(count, Stave::default);
+            self.stave_ends.resize(count, Rational::from(0));
+        }
+
+        // Attributes are written in the order of the score, i.e. key, time and clef.
+        for element in attributes.children().filter(Node::is_element) {
+            let symbol = match element.tag_name().name() {
+                "key" => Symbol::KeySignature(Self::key(element)?),
+                "time" => Symbol::TimeSignature(Self::time(element)?),
+                "clef" => Symbol::Clef(Self::clef(element)?),
+                _ => continue,
+            };
+
+            for stave in self.attribute_staves(element)? {
+                self.push_at_cursor(element, stave, symbol.clone())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn key(key: Node) -> Result<KeySignature> {
+        let fifths: i8 = parse_child(key, "fifths")?
+            .ok_or_else(|| error(key, "Only traditional keys with <fifths> are supported"))?;
+        let signature_type = match child_text(key, "mode").unwrap_or("major") {
+            "ionian" => KeySignatureType::Maj,
+            "aeolian" => KeySignatureType::Min,
+            name => KeySignatureType::ALL
+                .into_iter()
+                .find(|t| mode(*t) == name)
+                .ok_or_else(|| error(key, format!("Unsupported key mode '{name}'")))?,
+        };
+
+        KeySignature::from_fifths(fifths, signature_type)
+            .ok_or_else(|| error(key, format!("Unsupported key with {fifths} fifths")))
+    }
+
+    fn time(time: Node) -> Result<TimeSignature> {
+        if child(time, "senza-misura").is_some() {
+            Err(error(time, "Unmeasured time is not supported"))?;
+        }
+        let text =
+            |name| child_text(time, name).ok_or_else(|| error(time, format!("Missing <{name}>")));
+        let (beats, beat_type) = (text("beats")?, text("beat-type")?);
+
+        let beat_count =
+            beats.parse().ok().filter(|b| *b > 0).ok_or_else(|| {
+                error(time, format!("Unsupported time signature beats '{beats}'"))
+            })?;
+        let single_beat_note = beat_type
+            .parse()
+            .ok()
+            .and_then(|n: u32| {
+                NoteLength::ALL
+                    .into_iter()
+                    .find(|l| n > 0 && l.value() == Rational::new(1, n))
+            })
+            .ok_or_else(|| error(time, format!("Unsupported beat type '{beat_type}'")))?
+            .into();
+
+        Ok(TimeSignature {
+            beat_count,
+            single_beat_note,
+        })
+    }
+
+    fn clef(clef: Node) -> Result<Clef> {
+        let sign = child_text(clef, "sign").unwrap_or_default();
+        let line: Option<u8> = parse_child(clef, "line")?;
+
+        [Clef::Treble, Clef::Bass]
+            .into_iter()
+            .find(|c| {
+                let (c_sign, c_line) = clef_sign(*c);
+                c_sign == sign && line.unwrap_or(c_line) == c_line
+            })
+            .ok_or_else(|| error(clef, format!("Unsupported clef '{sign}'")))
+    }
+
+    fn sound(&mut self, sound: Node) -> Result<()> {
+        if let Some(tempo) = sound.attribute("tempo") {
+            let tempo: f32 = tempo
+                .parse()
+                .map_err(|_| error(sound, format!("Invalid tempo '{tempo}'")))?;
+            self.tempo = self.tempo.or(Some(tempo.round() as u32));
+        }
+
+        Ok(())
+    }
+
+    fn direction(&mut self, direction: Node) -> Result<()> {
+        if let Some(sound) = child(direction, "sound") {
+            self.sound(sound)?;
+        }
+
+        let Some(metronome) = direction
+            .descendants()
+            .find(|n| n.has_tag_name("metronome"))
+        else {
+            return Ok(());
+        };
+        let (Some(unit), Some(speed)) = (
+            child_text(metronome, "beat-unit"),
+            parse_child::<f32>(metronome, "per-minute").ok().flatten(),
+        ) else {
+            // Metric modulations and textual tempos are only printed.
+            return Ok(());
+        };
+
+        let tempo = Tempo {
+            note_length: Duration::new(
+                Self::length(metronome, unit)?,
+                metronome
+                    .children()
+                    .filter(|n| n.has_tag_name("beat-unit-dot"))
+                    .count() as u8,
+                None,
+            ),
+            speed: speed.round() as u8,
+        };
+        let stave = self.stave_index(direction, child_text(direction, "staff"))?;
+
+        self.push_at_cursor(direction, stave, Symbol::Tempo(tempo))
+    }
+
+    fn length(node: Node, name: &str) -> Result<NoteLength> {
+        NoteLength::ALL
+            .into_iter()
+            .find(|l| note_type(*l) == name)
+            .ok_or_else(|| error(node, format!("Unsupported note type '{name}'")))
+    }
+
+    fn pitch(pitch: Node) -> Result<Pitch> {
+        let step = child_text(pitch, "step").unwrap_or_default();
+        let note_name = ["C", "D", "E", "F", "G", "A", "B"]
+            .iter()
+            .position(|s| *s == step)
+            .and_then(|i| NoteName::from_value(i as u8))
+            .ok_or_else(|| error(pitch, format!("Invalid step '{step}'")))?;
+
+        let alter: f32 = parse_child(pitch, "alter")?.unwrap_or(0.0);
+        let accidental = (alter.fract() == 0.0)
+            .then(|| Accidental::from_alteration(alter as i8))
+            .flatten()
+            .ok_or_else(|| error(pitch, format!("Unsupported alteration {alter}")))?;
+
+        let octave = parse_child::<u8>(pitch, "octave")?
+            .and_then(Octave::from_value)
+            .ok_or_else(|| error(pitch, "Missing or unsupported <octave>"))?;
+
+        Ok(Pitch {
+            ext: ExtNoteName {
+                note_name,
+                accidental,
+            },
+            octave,
+        })
+    }
+
+    /// Written duration of the note, or `None`, if it has to be derived from its length.
+    fn note_duration(note: Node) -> Result<Option<Duration>> {
+        let Some(name) = child_text(note, "type") else {
+            return Ok(None);
+        };
+
+        let tuplet = match child(note, "time-modification") {
+            Some(modification) => {
+                let actual = parse_child(modification, "actual-notes")?;
+                let normal = parse_child(modification, "normal-notes")?;
+                match (actual, normal) {
+                    (Some(actual), Some(normal)) => Some(
+                        Tuplet::new(actual, normal)
+                            .ok_or_else(|| error(modification, "Invalid time modification"))?,
+                    ),
+                    _ => Err(error(modification, "Incomplete time modification"))?,
+                }
+            }
+            None => None,
+        };
+
+        Ok(Some(Duration::new(
+            Self::length(note, name)?,
+            note.children().filter(|n| n.has_tag_name("dot")).count() as u8,
+            tuplet,
+        )))
+    }
+
+    fn note(&mut self, note: Node) -> Result<()> {
+        // Grace and cue notes take no time in the played music.
+        if child(note, "grace").is_some() || child(note, "cue").is_some() {
+            return Ok(());
+        }
+        if child(note, "unpitched").is_some() {
+            Err(error(note, "Unpitched notes are not supported"))?;
+        }
+
+        let stave = self.stave_index(note, child_text(note, "staff"))?;
+        let length = self.duration(note)?;
+        let tie = note
+            .children()
+            .any(|n| n.has_tag_name("tie") && n.attribute("type") == Some("start"));
+        let pitch = match child(note, "pitch") {
+            Some(pitch) => Some(Self::pitch(pitch)?),
+            None if child(note, "rest").is_some() => None,
+            None => Err(error(note, "Note has no pitch nor rest"))?,
+        };
+
+        // Chord notes start with the previous note, whose symbol becomes a chord.
+        if child(note, "chord").is_some() {
+            let previous = self.staves[stave].symbols.last_mut();
+            let (Some(pitch), Some(previous)) = (pitch, previous) else {
+                Err(error(note, "Chord note doesn't follow a note"))?
+            };
+
+            *previous = match previous.clone() {
+                Symbol::Note(n) => Symbol::Chord(Chord {
+                    pitches: vec![n.pitch, pitch],
+                    duration: n.duration,
+                    tie: n.tie || tie,
+                }),
+                Symbol::Chord(mut c) => {
+                    c.pitches.push(pitch);
+                    c.tie |= tie;
+                    Symbol::Chord(c)
+                }
+                _ => Err(error(note, "Chord note doesn't follow a note"))?,
+            };
+
+            return Ok(());
+        }
+
+        if self.stave_ends[stave] > self.cursor {
+            Err(error(
+                note,
+                "Multiple voices in one staff are not supported",
+            ))?;
+        }
+        self.fill_to(stave, self.cursor);
+
+        let durations = match Self::note_duration(note)? {
+            Some(duration) => vec![duration],
+            None => Duration::split(length),
+        };
+        match pitch {
+            Some(pitch) => {
+                let duration = match durations[..] {
+                    [duration] => duration,
+                    _ => Err(error(note, "Note without <type> has no simple duration"))?,
+                };
+                self.staves[stave].symbols.push(Symbol::Note(Note {
+                    pitch,
+                    duration,
+                    tie,
+                }));
+            }
+            None => {
+                let rests = durations.into_iter().map(Symbol::Rest);
+                self.staves[stave].symbols.extend(rests);
+            }
+        }
+
+        self.cursor += length;
+        self.stave_ends[stave] = self.cursor;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::musicxml::MusicXmlWriter;
+
+    fn symbols_kinds(stave: &Stave) -> String {
+        stave
+            .symbols
+            .iter()
+            .map(|s| match s {
+                Symbol::Clef(_) => 'c',
+                Symbol::TimeSignature(_) => 't',
+                Symbol::KeySignature(_) => 'k',
+                Symbol::Chord(_) => 'C',
+                Symbol::Note(_) => 'n',
+                Symbol::Rest(_) => 'r',
+                Symbol::Tempo(_) => 'T',
+            })
+            .collect()
+    }
+
+    #[test]
+    fn read_part_without_key() {
+        // Arrange
+        let xml = r#"<score-partwise version="4.0">
+  <part-list><score-part id="P1"><part-name>Flute</part-name></score-part></part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes><divisions>1</divisions></attributes>
+      <note><pitch><step>E</step><alter>-1</alter><octave>5</octave></pitch><duration>4</duration><type>whole</type></note>
+    </measure>
+  </part>
+</score-partwise>"#;
+
+        // Act
+        let score = MusicXmlReader.read(xml).unwrap().sanitized();
+
+        // Assert
+        let score = score.unwrap();
+        assert_eq!(symbols_kinds(&score.staves[0]), "ktn");
+        assert!(matches!(
+            &score.staves[0].symbols[0],
+            Symbol::KeySignature(k) if k.fifths() == 0
+        ));
+    }
+
+    #[test]
+    fn read_multi_staff_part() {
+        // Arrange
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE score-partwise PUBLIC "-//Recordare//DTD MusicXML 4.0 Partwise//EN" "http://www.musicxml.org/dtds/partwise.dtd">
+<score-partwise version="4.0">
+  <work><work-title>Minuet</work-title></work>
+  <identification><creator type="composer">Bach</creator></identification>
+  <part-list><score-part id="P1"><part-name>Piano</part-name></score-part></part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>2</divisions>
+        <key><fifths>1</fifths><mode>minor</mode></key>
+        <time><beats>3</beats><beat-type>4</beat-type></time>
+        <staves>2</staves>
+        <clef number="1"><sign>G</sign><line>2</line></clef>
+        <clef number="2"><sign>F</sign><line>4</line></clef>
+      </attributes>
+      <sound tempo="96"/>
+      <note><pitch><step>D</step><octave>5</octave></pitch><duration>2</duration><type>quarter</type><staff>1</staff></note>
+      <note><pitch><step>G</step><octave>4</octave></pitch><duration>1</duration><type>eighth</type><staff>1</staff></note>
+      <note><pitch><step>A</step><octave>4</octave></pitch><duration>1</duration><type>eighth</type><staff>1</staff></note>
+      <note><pitch><step>B</step><octave>4</octave></pitch><duration>2</duration><tie type="start"/><type>quarter</type><staff>1</staff></note>
+      <backup><duration>6</duration></backup>
+      <note><pitch><step>G</step><octave>3</octave></pitch><duration>4</duration><type>half</type><staff>2</staff></note>
+      <note><chord/><pitch><step>F</step><alter>1</alter><octave>3</octave></pitch><duration>4</duration><type>half</type><staff>2</staff></note>
+    </measure>
+    <measure number="2">
+      <note><pitch><step>B</step><octave>4</octave></pitch><duration>2</duration><tie type="stop"/><type>quarter</type><staff>1</staff></note>
+      <note><rest measure="yes"/><duration>6</duration><staff>2</staff></note>
+    </measure>
+  </part>
+</score-partwise>"#;
+
+        // Act
+        let score = MusicXmlReader.read(xml).unwrap();
+
+        // Assert
+        assert_eq!(score.info.name.as_deref(), Some("Minuet"));
+        assert_eq!(score.info.author.as_deref(), Some("Bach"));
+        assert_eq!(score.tempo, 96);
+        assert_eq!(score.staves.len(), 2);
+        assert_eq!(symbols_kinds(&score.staves[0]), "ktcnnnnnrr");
+        assert_eq!(symbols_kinds(&score.staves[1]), "ktcCrrr");
+        assert!(matches!(&score.staves[0].symbols[6], Symbol::Note(n) if n.tie));
+        assert!(matches!(
+            &score.staves[0].symbols[0],
+            Symbol::KeySignature(k) if k.ext.note_name == NoteName::E
+        ));
+        assert!(matches!(
+            &score.staves[1].symbols[2],
+            Symbol::Clef(Clef::Bass)
+        ));
+        assert!(matches!(
+            &score.staves[1].symbols[3],
+            Symbol::Chord(c) if c.pitches[1].accidental() == Some(Accidental::Sharp)
+        ));
+    }
+
+    #[test]
+    fn read_written_score() {
+        // Arrange
+        let note = |note_name, duration: Duration, tie| {
+            Symbol::Note(Note {
+                pitch: Pitch::new(note_name, Octave::O4, Some(Accidental::Flat)),
+                duration,
+                tie,
+            })
+        };
+        let score = Score {
+            staves: vec![Stave {
+                symbols: vec![
+                    Symbol::KeySignature(
+                        KeySignature::from_fifths(-3, KeySignatureType::Maj).unwrap(),
+                    ),
+                    Symbol::TimeSignature(TimeSignature::c()),
+                    Symbol::Clef(Clef::Treble),
+                    note(NoteName::E, Duration::new(NoteLength::L2, 1, None), true),
+                    note(NoteName::E, NoteLength::L4.into(), false),
+                    note(
+                        NoteName::A,
+                        Duration::new(NoteLength::L8, 0, Some(Tuplet::triplet())),
+                        false,
+                    ),
+                ],
+            }],
+            tempo: 72,
+            ..Default::default()
+        };
+
+        // Act
+        let xml = MusicXmlWriter.write(&score).unwrap();
+        let read = MusicXmlReader.read(&xml).unwrap();
+
+        // Assert
+        assert_eq!(read.tempo, 72);
+        assert_eq!(symbols_kinds(&read.staves[0]), "ktcnnn");
+        for (read, written) in read.staves[0].symbols.iter().zip(&score.staves[0].symbols) {
+            match (read, written) {
+                (Symbol::Note(r), Symbol::Note(w)) => {
+                    assert_eq!(r.pitch, w.pitch);
+                    assert_eq!(r.duration, w.duration);
+                    assert_eq!(r.tie, w.tie);
+                }
+                (r, w) => assert_eq!(format!("{r:?}"), format!("{w:?}")),
+            }
+        }
+    }
+
+    #[test]
+    fn unsupported_elements() {
+        let part = |content: &str| {
+            format!(
+                "<score-partwise><part id=\"P1\"><measure number=\"1\">{content}</measure></part></score-partwise>"
+            )
+        };
+        let note = "<note><pitch><step>C</step><octave>4</octave></pitch><duration>1</duration><type>quarter</type></note>";
+
+        for xml in [
+            "<score-timewise/>".to_owned(),
+            "<score-partwise/>".to_owned(),
+            part("<attributes><clef><sign>C</sign><line>3</line></clef></attributes>"),
+            part("<attributes><time><beats>3+2</beats><beat-type>8</beat-type></time></attributes>"),
+            part("<attributes><time><beats>0</beats><beat-type>4</beat-type></time></attributes>"),
+            part("<attributes><key><fifths>127</fifths></key></attributes>"),
+            part("<attributes><time><beats>4</beats><beat-type>0</beat-type></time></attributes>"),
+            part(&format!("{note}<backup><duration>1</duration></backup>{note}")),
+            part("<note><unpitched/><duration>1</duration></note>"),
+            part("<note><pitch><step>C</step><alter>0.5</alter><octave>4</octave></pitch><duration>1</duration><type>quarter</type></note>"),
+            "<score-partwise><part".to_owned(),
+        ] {
+            assert!(MusicXmlReader.read(&xml).is_err(), "{xml}");
+        }
+    }
+}
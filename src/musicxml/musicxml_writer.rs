@@ -0,0 +1,547 @@
+//! MusicXML writer definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use std::{fmt::Write, path::Path};
+
+use crate::{
+    error::{AppError, Result},
+    musicxml::{clef_sign, escape, mode, note_type},
+    notation::{
+        Accidental, Clef, Duration, KeySignature, Pitch, Rational, Score, Stave, Symbol, Tempo,
+        TimeSignature,
+    },
+};
+
+/// Attributes waiting to be written before the next note.
+#[derive(Debug, Default)]
+struct Attributes {
+    divisions: Option<u32>,
+    key: Option<KeySignature>,
+    time: Option<TimeSignature>,
+    clef: Option<Clef>,
+}
+
+impl Attributes {
+    fn is_empty(&self) -> bool {
+        self.divisions.is_none() && self.key.is_none() && self.time.is_none() && self.clef.is_none()
+    }
+}
+
+/// Writer of a single part, which splits the stave into measures.
+struct PartWriter {
+    out: String,
+    /// Divisions of the quarter note.
+    divisions: u32,
+    bar_length: Rational,
+    /// Position in the current measure.
+    position: Rational,
+    measure: u32,
+    attributes: Attributes,
+    /// Pitches tied from the previous symbol.
+    tied: Vec<Pitch>,
+}
+
+impl PartWriter {
+    fn new(divisions: u32) -> Self {
+        let mut writer = Self {
+            out: String::new(),
+            divisions,
+            bar_length: TimeSignature::c().bar_length(),
+            position: Rational::from(0),
+            measure: 1,
+            attributes: Attributes {
+                divisions: Some(divisions),
+                ..Default::default()
+            },
+            tied: vec![],
+        };
+        writer.out.push_str("    <measure number=\"1\">\n");
+
+        writer
+    }
+
+    fn finish(mut self) -> String {
+        self.flush_attributes();
+        self.out.push_str("    </measure>\n");
+
+        self.out
+    }
+
+    /// Start the next measure, if the current one is full.
+    fn next_measure(&mut self) {
+        while self.position >= self.bar_length {
+            self.flush_attributes();
+            self.position -= self.bar_length;
+            self.measure += 1;
+            let _ = write!(
+                self.out,
+                "    </measure>\n    <measure number=\"{}\">\n",
+                self.measure
+            );
+        }
+    }
+
+    fn flush_attributes(&mut self) {
+        if self.attributes.is_empty() {
+            return;
+        }
+
+        let attributes = std::mem::take(&mut self.attributes);
+        self.out.push_str("      <attributes>\n");
+        if let Some(divisions) = attributes.divisions {
+            let _ = writeln!(self.out, "        <divisions>{divisions}</divisions>");
+        }
+        if let Some(key) = attributes.key {
+            let _ = writeln!(
+                self.out,
+                "        <key><fifths>{}</fifths><mode>{}</mode></key>",
+                key.fifths(),
+                mode(key.signature_type)
+            );
+        }
+        if let Some(time) = attributes.time {
+            let _ = writeln!(
+                self.out,
+                "        <time><beats>{}</beats><beat-type>{}</beat-type></time>",
+                time.numerator(),
+                time.denominator()
+            );
+        }
+        if let Some(clef) = attributes.clef {
+            let (sign, line) = clef_sign(clef);
+            let _ = writeln!(
+                self.out,
+                "        <clef><sign>{sign}</sign><line>{line}</line></clef>"
+            );
+        }
+        self.out.push_str("      </attributes>\n");
+    }
+
+    fn tempo(&mut self, quarters_per_minute: u32, tempo: Option<&Tempo>) {
+        self.flush_attributes();
+
+        self.out.push_str("      <direction placement=\"above\">\n");
+        if let Some(tempo) = tempo {
+            let dots = "<beat-unit-dot/>".repeat(tempo.note_length.dots as usize);
+            let _ = writeln!(
+                self.out,
+                "        <direction-type><metronome><beat-unit>{}</beat-unit>{dots}<per-minute>{}</per-minute></metronome></direction-type>",
+                note_type(tempo.note_length.base),
+                tempo.speed
+            );
+        }
+        let _ = writeln!(self.out, "        <sound tempo=\"{quarters_per_minute}\"/>");
+        self.out.push_str("      </direction>\n");
+    }
+
+    /// Write the note, chord or rest (no pitches).
+    fn notes(&mut self, pitches: &[Pitch], duration: Duration, tie: bool) {
+        self.flush_attributes();
+
+        let pieces = duration.split_bars(self.position, self.bar_length);
+        for (i, piece) in pieces.iter().enumerate() {
+            self.next_measure();
+            self.flush_attributes();
+
+            let last = i + 1 == pieces.len();
+            if pitches.is_empty() {
+                self.note(None, false, piece, (false, false));
+            }
+            for (j, pitch) in pitches.iter().enumerate() {
+                let stop = i > 0 || self.tied.contains(pitch);
+                let start = !last || tie;
+                self.note(Some(pitch), j > 0, piece, (stop, start));
+            }
+
+            self.position += piece.value();
+        }
+
+        self.tied = match tie {
+            true => pitches.to_vec(),
+            false => vec![],
+        };
+    }
+
+    fn note(&mut self, pitch: Option<&Pitch>, chord: bool, duration: &Duration, tie: (bool, bool)) {
+        let (stop, start) = tie;
+        let ticks = (duration.value() * 4 * self.divisions).round().to_integer();
+
+        self.out.push_str("      <note>\n");
+        if chord {
+            self.out.push_str("        <chord/>\n");
+        }
+        match pitch {
+            Some(pitch) => {
+                let alter = Accidental::alteration(pitch.accidental());
+                let alter = match alter {
+                    0 => String::new(),
+                    a => format!("<alter>{a}</alter>"),
+                };
+                let _ = writeln!(
+                    self.out,
+                    "        <pitch><step>{}</step>{alter}<octave>{}</octave></pitch>",
+                    ["C", "D", "E", "F", "G", "A", "B"][pitch.note_name().value() as usize],
+                    pitch.octave.value()
+                );
+            }
+            None => self.out.push_str("        <rest/>\n"),
+        }
+        let _ = writeln!(self.out, "        <duration>{ticks}</duration>");
+        if stop {
+            self.out.push_str("        <tie type=\"stop\"/>\n");
+        }
+        if start {
+            self.out.push_str("        <tie type=\"start\"/>\n");
+        }
+        let _ = writeln!(
+            self.out,
+            "        <type>{}</type>",
+            note_type(duration.base)
+        );
+        for _ in 0..duration.dots {
+            self.out.push_str("        <dot/>\n");
+        }
+        if let Some(tuplet) = duration.tuplet {
+            let _ = writeln!(
+                self.out,
+                "        <time-modification><actual-notes>{}</actual-notes><normal-notes>{}</normal-notes></time-modification>",
+                tuplet.actual(), tuplet.normal()
+            );
+        }
+        if stop || start {
+            self.out.push_str("        <notations>");
+            if stop {
+                self.out.push_str("<tied type=\"stop\"/>");
+            }
+            if start {
+                self.out.push_str("<tied type=\"start\"/>");
+            }
+            self.out.push_str("</notations>\n");
+        }
+        self.out.push_str("      </note>\n");
+    }
+
+    fn stave(mut self, stave: &Stave, tempo: Option<u32>) -> String {
+        let mut tempo = tempo;
+
+        for symbol in stave.symbols.iter() {
+            match symbol {
+                Symbol::Clef(clef) => self.attributes.clef = Some(*clef),
+                Symbol::KeySignature(key) => self.attributes.key = Some(*key),
+                Symbol::TimeSignature(time) => {
+                    self.attributes.time = Some(*time);
+                    self.bar_length = time.bar_length();
+                }
+                Symbol::Tempo(t) => {
+                    self.next_measure();
+                    self.tempo(t.quarters_per_minute(), Some(t));
+                    tempo = None;
+                }
+                Symbol::Note(note) => {
+                    if let Some(t) = tempo.take() {
+                        self.tempo(t, None);
+                    }
+                    self.notes(std::slice::from_ref(&note.pitch), note.duration, note.tie);
+                }
+                Symbol::Chord(chord) => {
+                    if let Some(t) = tempo.take() {
+                        self.tempo(t, None);
+                    }
+                    self.notes(&chord.pitches, chord.duration, chord.tie);
+                }
+                Symbol::Rest(duration) => {
+                    if let Some(t) = tempo.take() {
+                        self.tempo(t, None);
+                    }
+                    self.notes(&[], *duration, false);
+                }
+            }
+        }
+
+        self.finish()
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    match b {
+        0 => a,
+        b => gcd(b, a % b),
+    }
+}
+
+/// Writer of the score into a partwise MusicXML document. Each stave is written as a part.
+///
+/// # EXAMPLE
+/// ```
+/// # use music_sheet_gen::{musicxml::*, notation::*};
+///
+/// let score = Score {
+///     staves: vec![Stave {
+///         symbols: vec![
+///             Symbol::Clef(Clef::Treble),
+///             Symbol::Note(Note {
+///                 pitch: Pitch::new(NoteName::C, Octave::O4, None),
+///                 duration: NoteLength::L4.into(),
+///                 tie: false,
+///             }),
+///         ],
+///     }],
+///     ..Default::default()
+/// };
+///
+/// let xml = MusicXmlWriter.write(&score).unwrap();
+///
+/// assert!(xml.contains("<pitch><step>C</step><octave>4</octave></pitch>"));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MusicXmlWriter;
+
+impl MusicXmlWriter {
+    /// Write the score into the MusicXML document.
+    pub fn write(&self, score: &Score) -> Result<String> {
+        if score.staves.is_empty() {
+            Err(AppError::MusicXml("Score has no staves".to_owned()))?;
+        }
+        // Bars of no length would never be filled.
+        let empty_bars =
+            score.staves.iter().flat_map(|s| &s.symbols).any(
+                |s| matches!(s, Symbol::TimeSignature(t) if t.bar_length() == Rational::from(0)),
+            );
+        if empty_bars {
+            Err(AppError::MusicXml("Time signature has no beats".to_owned()))?;
+        }
+
+        let divisions = Self::divisions(score);
+        let mut out = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n",
+            "<!DOCTYPE score-partwise PUBLIC \"-//Recordare//DTD MusicXML 4.0 Partwise//EN\" ",
+            "\"http://www.musicxml.org/dtds/partwise.dtd\">\n",
+            "<score-partwise version=\"4.0\">\n"
+        ));
+
+        if let Some(name) = &score.info.name {
+            let _ = writeln!(
+                out,
+                "  <work><work-title>{}</work-title></work>",
+                escape(name)
+            );
+        }
+        out.push_str("  <identification>\n");
+        for (creator, name) in [
+            ("composer", &score.info.author),
+            ("arranger", &score.info.transcriber),
+        ] {
+            if let Some(name) = name {
+                let _ = writeln!(
+                    out,
+                    "    <creator type=\"{creator}\">{}</creator>",
+                    escape(name)
+                );
+            }
+        }
+        let _ = writeln!(
+            out,
+            "    <encoding><software>{} {}</software></encoding>",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION")
+        );
+        out.push_str("  </identification>\n");
+
+        out.push_str("  <part-list>\n");
+        for i in 1..=score.staves.len() {
+            let _ = writeln!(
+                out,
+                "    <score-part id=\"P{i}\"><part-name>Stave {i}</part-name></score-part>"
+            );
+        }
+        out.push_str("  </part-list>\n");
+
+        for (i, stave) in score.staves.iter().enumerate() {
+            let _ = writeln!(out, "  <part id=\"P{}\">", i + 1);
+            let tempo = (i == 0).then_some(score.tempo);
+            out.push_str(&PartWriter::new(divisions).stave(stave, tempo));
+            out.push_str("  </part>\n");
+        }
+        out.push_str("</score-partwise>\n");
+
+        Ok(out)
+    }
+
+    /// Write the score into the file at the given path.
+    pub fn save(&self, score: &Score, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.write(score)?).map_err(|e| AppError::build_path(path, &e))?;
+
+        Ok(())
+    }
+
+    /// Divisions of the quarter note, in which all durations and bar lengths are whole.
+    fn divisions(score: &Score) -> u32 {
+        let values = score
+            .staves
+            .iter()
+            .flat_map(|s| s.symbols.iter())
+            .filter_map(|s| match s {
+                Symbol::TimeSignature(time) => Some(time.bar_length()),
+                s => s.duration().map(|d| d.value()),
+            });
+
+        values.fold(1, |divisions, value| {
+            let denom = *(value * 4).denom();
+            divisions * denom / gcd(denom, divisions)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{
+        Chord, ExtNoteName, KeySignatureType, Note, NoteLength, NoteName, Octave, Tuplet,
+    };
+
+    fn note(note_name: NoteName, duration: Duration, tie: bool) -> Symbol {
+        Symbol::Note(Note {
+            pitch: Pitch::new(note_name, Octave::O4, None),
+            duration,
+            tie,
+        })
+    }
+
+    /// Text of all elements with the given name.
+    fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+        let (open, close) = (format!("<{name}>"), format!("</{name}>"));
+        xml.match_indices(&open)
+            .map(|(i, _)| {
+                let start = i + open.len();
+                &xml[start..start + xml[start..].find(&close).unwrap()]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn write_attributes_and_measures() {
+        // Arrange
+        let score = Score {
+            staves: vec![Stave {
+                symbols: vec![
+                    Symbol::Clef(Clef::Treble),
+                    Symbol::KeySignature(KeySignature {
+                        ext: ExtNoteName {
+                            note_name: NoteName::E,
+                            accidental: Some(Accidental::Flat),
+                        },
+                        signature_type: KeySignatureType::Maj,
+                    }),
+                    Symbol::TimeSignature(TimeSignature {
+                        beat_count: 3,
+                        single_beat_note: NoteLength::L4.into(),
+                    }),
+                    note(NoteName::C, NoteLength::L2.into(), false),
+                    // Crosses the bar line, so it is split and tied.
+                    note(NoteName::D, NoteLength::L2.into(), false),
+                    note(
+                        NoteName::E,
+                        Duration::new(NoteLength::L8, 0, Some(Tuplet::triplet())),
+                        false,
+                    ),
+                ],
+            }],
+            tempo: 100,
+            ..Default::default()
+        };
+
+        // Act
+        let xml = MusicXmlWriter.write(&score).unwrap();
+
+        // Assert
+        assert_eq!(elements(&xml, "divisions"), ["3"]);
+        assert_eq!(elements(&xml, "fifths"), ["-3"]);
+        assert_eq!(elements(&xml, "beats"), ["3"]);
+        assert_eq!(elements(&xml, "sign"), ["G"]);
+        assert!(xml.contains("<sound tempo=\"100\"/>"));
+        assert_eq!(elements(&xml, "duration"), ["6", "3", "3", "1"]);
+        assert_eq!(
+            elements(&xml, "type"),
+            ["half", "quarter", "quarter", "eighth"]
+        );
+        assert_eq!(xml.matches("<measure number=").count(), 2);
+        assert_eq!(xml.matches("<tie type=\"start\"/>").count(), 1);
+        assert_eq!(xml.matches("<tie type=\"stop\"/>").count(), 1);
+        assert_eq!(elements(&xml, "actual-notes"), ["3"]);
+    }
+
+    #[test]
+    fn write_chords_ties_and_parts() {
+        // Arrange
+        let chord = |tie| {
+            Symbol::Chord(Chord {
+                pitches: vec![
+                    Pitch::new(NoteName::C, Octave::O3, None),
+                    Pitch::new(NoteName::F, Octave::O3, Some(Accidental::Sharp)),
+                ],
+                duration: NoteLength::L4.into(),
+                tie,
+            })
+        };
+        let score = Score {
+            staves: vec![
+                Stave {
+                    symbols: vec![note(NoteName::C, NoteLength::L1.into(), false)],
+                },
+                Stave {
+                    symbols: vec![
+                        Symbol::Clef(Clef::Bass),
+                        chord(true),
+                        chord(false),
+                        Symbol::Rest(NoteLength::L2.into()),
+                    ],
+                },
+            ],
+            info: crate::notation::ScoreInfo {
+                name: Some("Fish & Chips".to_owned()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // Act
+        let xml = MusicXmlWriter.write(&score).unwrap();
+
+        // Assert
+        assert_eq!(elements(&xml, "work-title"), ["Fish &amp; Chips"]);
+        assert_eq!(elements(&xml, "part-name"), ["Stave 1", "Stave 2"]);
+        assert_eq!(xml.matches("<part id=").count(), 2);
+        assert_eq!(elements(&xml, "sign"), ["F"]);
+        assert_eq!(xml.matches("<chord/>").count(), 2);
+        assert_eq!(elements(&xml, "alter"), ["1", "1"]);
+        assert_eq!(xml.matches("<tied type=\"start\"/>").count(), 2);
+        assert_eq!(xml.matches("<tied type=\"stop\"/>").count(), 2);
+        assert_eq!(xml.matches("<rest/>").count(), 1);
+    }
+
+    #[test]
+    fn write_zero_beats() {
+        // Arrange
+        let score = Score {
+            staves: vec![Stave {
+                symbols: vec![
+                    Symbol::TimeSignature(TimeSignature {
+                        beat_count: 0,
+                        single_beat_note: NoteLength::L4.into(),
+                    }),
+                    note(NoteName::C, NoteLength::L4.into(), false),
+                ],
+            }],
+            ..Default::default()
+        };
+
+        // Act
+        let written = MusicXmlWriter.write(&score);
+
+        // Assert
+        assert!(written.is_err());
+    }
+}
@@ -3,12 +3,21 @@
 //! ### Author
 //! Jakub Kloub (xkloub03), VUT FIT
 
+mod duration;
+mod interval;
 mod score;
 mod stave;
 mod symbol;
 
+pub use duration::Duration;
+pub use duration::Rational;
+pub use duration::Tuplet;
+pub use duration::MAX_DOTS;
+pub use interval::Interval;
+pub use interval::IntervalQuality;
 pub use score::Score;
 pub use score::ScoreInfo;
+pub use score::Transposition;
 pub use stave::Stave;
 pub use symbol::Chord;
 pub use symbol::Note;
@@ -19,16 +28,16 @@ pub use symbol::Symbol;
 pub enum Clef { Treble, Bass }
 
 #[rustfmt::skip] #[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
-pub enum KeySignatureType { Maj, Min }
+pub enum KeySignatureType { Maj, Min, Dorian, Phrygian, Lydian, Mixolydian, Locrian }
 
-#[rustfmt::skip] #[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[rustfmt::skip] #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, serde::Deserialize, serde::Serialize)]
 pub enum NoteName { C, D, E, F, G, A, B }
 
-#[rustfmt::skip] #[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[rustfmt::skip] #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, serde::Deserialize, serde::Serialize)]
 pub enum Octave { O0, O1, O2, O3, O4, O5, O6, O7, O8, O9 }
 
 #[rustfmt::skip] #[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
-pub enum Accidental { Sharp, Flat }
+pub enum Accidental { Sharp, Flat, DoubleSharp, DoubleFlat }
 
 #[rustfmt::skip] #[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum NoteLength { L1, L2, L4, L8, L16, L32, L64, L128 }
@@ -48,24 +57,43 @@ pub struct KeySignature {
 #[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize)]
 pub struct TimeSignature {
     pub beat_count: u8,
-    pub single_beat_note: NoteLength,
+    pub single_beat_note: Duration,
 }
 
 #[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize)]
 pub struct Tempo {
-    pub note_length: NoteLength,
+    pub note_length: Duration,
     pub speed: u8,
 }
 
 impl Default for Tempo {
     fn default() -> Self {
         Self {
-            note_length: NoteLength::L4,
+            note_length: NoteLength::L4.into(),
             speed: 100,
         }
     }
 }
 
+impl Tempo {
+    /// Speed of the tempo counted in quarter notes per minute.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// let dotted = Tempo { note_length: Duration::new(NoteLength::L4, 1, None), speed: 60 };
+    ///
+    /// assert_eq!(dotted.quarters_per_minute(), 90);
+    /// assert_eq!(Tempo::default().quarters_per_minute(), 100);
+    /// ```
+    pub fn quarters_per_minute(&self) -> u32 {
+        (Rational::from(self.speed as u32) * self.note_length.value() * 4)
+            .round()
+            .to_integer()
+    }
+}
+
 impl NoteName {
     pub fn next(&self) -> Self {
         match self {
@@ -103,6 +131,19 @@ impl NoteName {
         }
     }
 
+    pub fn from_value(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(NoteName::C),
+            1 => Some(NoteName::D),
+            2 => Some(NoteName::E),
+            3 => Some(NoteName::F),
+            4 => Some(NoteName::G),
+            5 => Some(NoteName::A),
+            6 => Some(NoteName::B),
+            _ => None,
+        }
+    }
+
     pub fn value_halftone(&self) -> u8 {
         match self {
             NoteName::C => 0,
@@ -147,6 +188,26 @@ impl Octave {
         }
     }
 
+    pub fn value(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn from_value(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Octave::O0),
+            1 => Some(Octave::O1),
+            2 => Some(Octave::O2),
+            3 => Some(Octave::O3),
+            4 => Some(Octave::O4),
+            5 => Some(Octave::O5),
+            6 => Some(Octave::O6),
+            7 => Some(Octave::O7),
+            8 => Some(Octave::O8),
+            9 => Some(Octave::O9),
+            _ => None,
+        }
+    }
+
     /// Number of tones in one octave
     pub fn tone_count() -> u8 {
         7
@@ -159,21 +220,6 @@ impl Octave {
 }
 
 impl NoteLength {
-    pub fn halve(&mut self) {
-        match self {
-            NoteLength::L1 => *self = NoteLength::L2,
-            NoteLength::L2 => *self = NoteLength::L4,
-            NoteLength::L4 => *self = NoteLength::L8,
-            NoteLength::L8 => *self = NoteLength::L16,
-            NoteLength::L16 => *self = NoteLength::L32,
-            NoteLength::L32 => *self = NoteLength::L64,
-            NoteLength::L64 => *self = NoteLength::L128,
-            NoteLength::L128 => {
-                log::warn!("Trying to halve 1/128 note. Ignoring.");
-            }
-        }
-    }
-
     pub fn half(&self) -> Option<Self> {
         match self {
             NoteLength::L1 => Some(NoteLength::L2),
@@ -192,9 +238,219 @@ impl TimeSignature {
     pub fn c() -> Self {
         Self {
             beat_count: 4,
-            single_beat_note: NoteLength::L4,
+            single_beat_note: NoteLength::L4.into(),
         }
     }
+
+    /// Written numerator of the signature. Dotted beats are written in their compound meter,
+    /// so the written signature always matches the bar length.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// let time = TimeSignature {
+    ///     beat_count: 2,
+    ///     single_beat_note: Duration::new(NoteLength::L4, 1, None),
+    /// };
+    ///
+    /// assert_eq!((time.numerator(), time.denominator()), (6, 8));
+    /// assert_eq!(time.bar_length(), Rational::new(3, 4));
+    /// ```
+    pub fn numerator(&self) -> u32 {
+        self.beat_count as u32 * *self.single_beat_note.value().numer()
+    }
+
+    /// Written denominator of the signature.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// let time = TimeSignature {
+    ///     beat_count: 6,
+    ///     single_beat_note: NoteLength::L8.into(),
+    /// };
+    ///
+    /// assert_eq!(time.denominator(), 8);
+    /// assert_eq!(time.bar_length(), Rational::new(3, 4));
+    /// ```
+    pub fn denominator(&self) -> u32 {
+        *self.single_beat_note.value().denom()
+    }
+
+    /// Length of a single bar as a fraction of the whole note.
+    pub fn bar_length(&self) -> Rational {
+        Rational::from(self.beat_count as u32) * self.single_beat_note.value()
+    }
+}
+
+impl KeySignatureType {
+    pub const ALL: [KeySignatureType; 7] = [
+        KeySignatureType::Maj,
+        KeySignatureType::Min,
+        KeySignatureType::Dorian,
+        KeySignatureType::Phrygian,
+        KeySignatureType::Lydian,
+        KeySignatureType::Mixolydian,
+        KeySignatureType::Locrian,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeySignatureType::Maj => "Major",
+            KeySignatureType::Min => "Minor",
+            KeySignatureType::Dorian => "Dorian",
+            KeySignatureType::Phrygian => "Phrygian",
+            KeySignatureType::Lydian => "Lydian",
+            KeySignatureType::Mixolydian => "Mixolydian",
+            KeySignatureType::Locrian => "Locrian",
+        }
+    }
+
+    /// Name of the church mode, which is the same as the name of the key for the modes other
+    /// than major and minor.
+    pub fn mode_name(&self) -> &'static str {
+        match self {
+            KeySignatureType::Maj => "Ionian",
+            KeySignatureType::Min => "Aeolian",
+            _ => self.name(),
+        }
+    }
+
+    /// Position of the mode in the circle of fifths relative to the major key with the
+    /// same tonic.
+    pub fn fifths_offset(&self) -> i8 {
+        match self {
+            KeySignatureType::Lydian => 1,
+            KeySignatureType::Maj => 0,
+            KeySignatureType::Mixolydian => -1,
+            KeySignatureType::Dorian => -2,
+            KeySignatureType::Min => -3,
+            KeySignatureType::Phrygian => -4,
+            KeySignatureType::Locrian => -5,
+        }
+    }
+
+    /// Whether the mode has a minor third.
+    pub fn is_minor(&self) -> bool {
+        self.fifths_offset() < -1
+    }
+
+    /// Halftone steps between the degrees of the mode.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// assert_eq!(KeySignatureType::Maj.steps(), [2, 2, 1, 2, 2, 2, 1]);
+    /// assert_eq!(KeySignatureType::Dorian.steps(), [2, 1, 2, 2, 2, 1, 2]);
+    /// ```
+    pub fn steps(&self) -> [u8; 7] {
+        const MAJOR: [u8; 7] = [2, 2, 1, 2, 2, 2, 1];
+
+        // Each fifth up starts the major steps a fourth higher
+        let start = (self.fifths_offset() * 3).rem_euclid(7) as usize;
+        std::array::from_fn(|i| MAJOR[(start + i) % MAJOR.len()])
+    }
+}
+
+impl KeySignature {
+    /// Number of sharps (positive) or flats (negative) of the key signature.
+    ///
+    /// Keys beyond seven accidentals (e.g. D-sharp major) use double accidentals.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// let fis_min = KeySignature {
+    ///     ext: ExtNoteName { note_name: NoteName::F, accidental: Some(Accidental::Sharp) },
+    ///     signature_type: KeySignatureType::Min,
+    /// };
+    ///
+    /// assert_eq!(fis_min.fifths(), 3);
+    /// ```
+    pub fn fifths(&self) -> i8 {
+        let letter = match self.ext.note_name {
+            NoteName::F => -1,
+            NoteName::C => 0,
+            NoteName::G => 1,
+            NoteName::D => 2,
+            NoteName::A => 3,
+            NoteName::E => 4,
+            NoteName::B => 5,
+        };
+
+        letter
+            + 7 * Accidental::alteration(self.ext.accidental)
+            + self.signature_type.fifths_offset()
+    }
+
+    /// Key of the given type with the given number of sharps (positive) or flats (negative).
+    ///
+    /// Returns `None` if the tonic would need more than a double accidental.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// let es_maj = KeySignature::from_fifths(-3, KeySignatureType::Maj).unwrap();
+    ///
+    /// assert_eq!(es_maj.ext.to_string(), "Eb");
+    /// assert!(KeySignature::from_fifths(127, KeySignatureType::Maj).is_none());
+    /// ```
+    pub fn from_fifths(fifths: i8, signature_type: KeySignatureType) -> Option<Self> {
+        const LETTERS: [NoteName; 7] = [
+            NoteName::F,
+            NoteName::C,
+            NoteName::G,
+            NoteName::D,
+            NoteName::A,
+            NoteName::E,
+            NoteName::B,
+        ];
+
+        let tonic = fifths
+            .checked_sub(signature_type.fifths_offset())
+            .and_then(|t| t.checked_add(1))?;
+
+        Some(Self {
+            ext: ExtNoteName {
+                note_name: LETTERS[tonic.rem_euclid(7) as usize],
+                accidental: Accidental::from_alteration(tonic.div_euclid(7))?,
+            },
+            signature_type,
+        })
+    }
+
+    /// Number of halftones by which the key signature alters the note name.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// let d_maj = KeySignature::from_fifths(2, KeySignatureType::Maj).unwrap();
+    /// let es_maj = KeySignature::from_fifths(-3, KeySignatureType::Maj).unwrap();
+    ///
+    /// assert_eq!(d_maj.alteration(NoteName::C), 1);
+    /// assert_eq!(d_maj.alteration(NoteName::G), 0);
+    /// assert_eq!(es_maj.alteration(NoteName::A), -1);
+    /// ```
+    pub fn alteration(&self, note_name: NoteName) -> i8 {
+        // Order, in which the sharps are added to the key signature
+        let position = match note_name {
+            NoteName::F => 0,
+            NoteName::C => 1,
+            NoteName::G => 2,
+            NoteName::D => 3,
+            NoteName::A => 4,
+            NoteName::E => 5,
+            NoteName::B => 6,
+        };
+
+        (self.fifths() - position + 6).div_euclid(7)
+    }
 }
 
 impl ExtNoteName {
@@ -202,12 +458,66 @@ impl ExtNoteName {
         // Add total number of halftones to get rid of negative values (case of C flat)
         let v = self.note_name.value_halftone() + Octave::halftone_count();
 
-        let h = match self.accidental {
-            Some(Accidental::Sharp) => v + 1,
-            Some(Accidental::Flat) => v - 1,
-            None => v,
+        let h = v as i8 + Accidental::alteration(self.accidental);
+
+        h as u8 % Octave::halftone_count()
+    }
+}
+
+impl Accidental {
+    /// Number of halftones by which the accidental alters the note.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// assert_eq!(Accidental::alteration(Some(Accidental::DoubleFlat)), -2);
+    /// assert_eq!(Accidental::alteration(None), 0);
+    /// ```
+    pub fn alteration(accidental: Option<Accidental>) -> i8 {
+        match accidental {
+            Some(Accidental::DoubleSharp) => 2,
+            Some(Accidental::Sharp) => 1,
+            None => 0,
+            Some(Accidental::Flat) => -1,
+            Some(Accidental::DoubleFlat) => -2,
+        }
+    }
+
+    /// Accidental altering the note by the given number of halftones.
+    ///
+    /// Returns `None` if no accidental alters the note that much.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// assert_eq!(Accidental::from_alteration(2), Some(Some(Accidental::DoubleSharp)));
+    /// assert_eq!(Accidental::from_alteration(0), Some(None));
+    /// assert_eq!(Accidental::from_alteration(3), None);
+    /// ```
+    pub fn from_alteration(alteration: i8) -> Option<Option<Accidental>> {
+        match alteration {
+            2 => Some(Some(Accidental::DoubleSharp)),
+            1 => Some(Some(Accidental::Sharp)),
+            0 => Some(None),
+            -1 => Some(Some(Accidental::Flat)),
+            -2 => Some(Some(Accidental::DoubleFlat)),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ExtNoteName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let accidental = match self.accidental {
+            Some(Accidental::Sharp) => "#",
+            Some(Accidental::Flat) => "b",
+            Some(Accidental::DoubleSharp) => "##",
+            Some(Accidental::DoubleFlat) => "bb",
+            None => "",
         };
 
-        h % Octave::halftone_count()
+        write!(f, "{:?}{accidental}", self.note_name)
     }
 }
@@ -0,0 +1,341 @@
+//! Duration definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use crate::{error::*, notation::NoteLength};
+
+/// Exact fraction of a whole note.
+pub type Rational = num_rational::Ratio<u32>;
+
+/// Maximal number of dots, which a duration can have.
+pub const MAX_DOTS: u8 = 3;
+
+/// Group of `actual` notes played in the time of `normal` notes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "TupletRepr")]
+pub struct Tuplet {
+    actual: u8,
+    normal: u8,
+}
+
+/// Serialized tuplet, which is not validated yet.
+#[derive(serde::Deserialize)]
+struct TupletRepr {
+    actual: u8,
+    normal: u8,
+}
+
+impl TryFrom<TupletRepr> for Tuplet {
+    type Error = String;
+
+    fn try_from(repr: TupletRepr) -> std::result::Result<Self, Self::Error> {
+        Tuplet::new(repr.actual, repr.normal)
+            .ok_or_else(|| format!("Invalid tuplet {}:{}", repr.actual, repr.normal))
+    }
+}
+
+impl Tuplet {
+    /// Tuplet of `actual` notes in the time of `normal` notes, or `None` if any of them is zero.
+    ///
+    /// ```
+    /// use music_sheet_gen::notation::Tuplet;
+    ///
+    /// assert_eq!(Tuplet::new(3, 2), Some(Tuplet::triplet()));
+    /// assert_eq!(Tuplet::new(0, 2), None);
+    /// ```
+    pub fn new(actual: u8, normal: u8) -> Option<Self> {
+        (actual > 0 && normal > 0).then_some(Self { actual, normal })
+    }
+
+    /// Three notes in the time of two.
+    pub fn triplet() -> Self {
+        Self {
+            actual: 3,
+            normal: 2,
+        }
+    }
+
+    /// Number of notes played in the group.
+    pub fn actual(&self) -> u8 {
+        self.actual
+    }
+
+    /// Number of notes, in whose time the group is played.
+    pub fn normal(&self) -> u8 {
+        self.normal
+    }
+
+    /// Ratio by which the tuplet changes the note values.
+    pub fn ratio(&self) -> Rational {
+        Rational::new(self.normal as u32, self.actual as u32)
+    }
+}
+
+/// Duration of a note, chord or rest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(from = "DurationRepr")]
+pub struct Duration {
+    /// Written length of the note head.
+    pub base: NoteLength,
+    /// Each dot adds half of the previous value.
+    pub dots: u8,
+    /// Tuplet the note is part of.
+    pub tuplet: Option<Tuplet>,
+}
+
+/// Serialized duration. Durations used to be stored as a plain `NoteLength`.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum DurationRepr {
+    Length(NoteLength),
+    Full {
+        base: NoteLength,
+        #[serde(default)]
+        dots: u8,
+        #[serde(default)]
+        tuplet: Option<Tuplet>,
+    },
+}
+
+impl From<DurationRepr> for Duration {
+    fn from(repr: DurationRepr) -> Self {
+        match repr {
+            DurationRepr::Length(base) => base.into(),
+            DurationRepr::Full { base, dots, tuplet } => Self { base, dots, tuplet },
+        }
+    }
+}
+
+impl From<NoteLength> for Duration {
+    fn from(base: NoteLength) -> Self {
+        Self {
+            base,
+            dots: 0,
+            tuplet: None,
+        }
+    }
+}
+
+impl Duration {
+    pub fn new(base: NoteLength, dots: u8, tuplet: Option<Tuplet>) -> Self {
+        Self { base, dots, tuplet }
+    }
+
+    /// Fraction of the whole note this duration takes.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// let dotted = Duration::new(NoteLength::L4, 1, None);
+    /// let triplet = Duration::new(NoteLength::L8, 0, Some(Tuplet::triplet()));
+    ///
+    /// assert_eq!(dotted.value(), Rational::new(3, 8));
+    /// assert_eq!(triplet.value(), Rational::new(1, 12));
+    /// assert_eq!(Duration::from(NoteLength::L2).value(), Rational::new(1, 2));
+    /// ```
+    pub fn value(&self) -> Rational {
+        let value = Self::dotted(self.base.value(), self.dots);
+
+        match self.tuplet {
+            Some(tuplet) => value * tuplet.ratio(),
+            None => value,
+        }
+    }
+
+    /// Value of a note with the given base value and number of dots.
+    ///
+    /// Dots above `MAX_DOTS` are ignored, as their value doesn't fit into the rational.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// assert_eq!(Duration::dotted(Rational::new(1, 4), 2), Rational::new(7, 16));
+    /// assert_eq!(
+    ///     Duration::dotted(Rational::new(1, 4), 40),
+    ///     Duration::dotted(Rational::new(1, 4), MAX_DOTS)
+    /// );
+    /// ```
+    pub fn dotted(value: Rational, dots: u8) -> Rational {
+        value * (Rational::from(2) - Rational::new(1, 1 << dots.min(MAX_DOTS)))
+    }
+
+    /// Halve the duration, keeping the dots and the tuplet.
+    pub fn halve(&mut self) -> Result<()> {
+        self.base = self.base.half().ok_or(AppError::DurationTooShort)?;
+
+        Ok(())
+    }
+
+    /// Find a plain or dotted duration with exactly the given value.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// assert_eq!(
+    ///     Duration::from_value(Rational::new(3, 8)),
+    ///     Some(Duration::new(NoteLength::L4, 1, None))
+    /// );
+    /// assert_eq!(Duration::from_value(Rational::new(5, 8)), None);
+    /// ```
+    pub fn from_value(value: Rational) -> Option<Self> {
+        NoteLength::ALL.iter().find_map(|&base| {
+            (0..=MAX_DOTS)
+                .map(|dots| Self::new(base, dots, None))
+                .find(|d| d.value() == value)
+        })
+    }
+
+    /// Split the given value into the longest possible plain durations.
+    ///
+    /// Remainder shorter than 1/128 note (e.g. from tuplets) is dropped.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// assert_eq!(
+    ///     Duration::split(Rational::new(3, 8)),
+    ///     vec![NoteLength::L4.into(), NoteLength::L8.into()]
+    /// );
+    /// assert_eq!(Duration::split(Rational::new(1, 1)), vec![NoteLength::L1.into()]);
+    /// assert_eq!(Duration::split(Rational::new(0, 1)), vec![]);
+    /// ```
+    pub fn split(mut value: Rational) -> Vec<Self> {
+        let mut durations = vec![];
+        for l in NoteLength::ALL {
+            while value >= l.value() {
+                value -= l.value();
+                durations.push(l.into());
+            }
+        }
+
+        durations
+    }
+
+    /// Split the duration at the bar lines into plain durations, which are meant to be tied.
+    ///
+    /// Tuplets and durations, which can't be split exactly, are kept whole and overflow
+    /// the bar.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// let half = Duration::from(NoteLength::L2);
+    ///
+    /// assert_eq!(
+    ///     half.split_bars(Rational::new(3, 4), Rational::from(1)),
+    ///     vec![NoteLength::L4.into(), NoteLength::L4.into()]
+    /// );
+    /// assert_eq!(half.split_bars(Rational::from(0), Rational::from(1)), vec![half]);
+    /// ```
+    pub fn split_bars(&self, position: Rational, bar_length: Rational) -> Vec<Self> {
+        let mut value = self.value();
+        let mut room = bar_length - position;
+        if value <= room || self.tuplet.is_some() {
+            return vec![*self];
+        }
+
+        let mut pieces = vec![];
+        while value > room {
+            let split = Self::split(room);
+            if split.iter().map(|d| d.value()).sum::<Rational>() != room {
+                return vec![*self];
+            }
+
+            pieces.extend(split);
+            value -= room;
+            room = bar_length;
+        }
+
+        let rest = Self::split(value);
+        if rest.iter().map(|d| d.value()).sum::<Rational>() != value {
+            return vec![*self];
+        }
+        pieces.extend(rest);
+
+        pieces
+    }
+}
+
+impl NoteLength {
+    pub const ALL: [NoteLength; 8] = [
+        NoteLength::L1,
+        NoteLength::L2,
+        NoteLength::L4,
+        NoteLength::L8,
+        NoteLength::L16,
+        NoteLength::L32,
+        NoteLength::L64,
+        NoteLength::L128,
+    ];
+
+    /// Fraction of the whole note.
+    pub fn value(&self) -> Rational {
+        let exp = NoteLength::ALL.iter().position(|l| l == self).unwrap();
+        Rational::new(1, 1 << exp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_double_dotted_tuplet() {
+        // Arrange
+        let duration = Duration::new(NoteLength::L4, 2, Tuplet::new(5, 4));
+
+        // Act
+        let value = duration.value();
+
+        // Assert
+        assert_eq!(value, Rational::new(7, 16) * Rational::new(4, 5));
+    }
+
+    #[test]
+    fn halve_shortest() {
+        // Arrange
+        let mut duration = Duration::new(NoteLength::L64, 1, Some(Tuplet::triplet()));
+
+        // Act && Assert
+        assert!(duration.halve().is_ok());
+        assert_eq!(
+            duration,
+            Duration::new(NoteLength::L128, 1, Some(Tuplet::triplet()))
+        );
+        assert!(duration.halve().is_err());
+        assert_eq!(duration.base, NoteLength::L128);
+    }
+
+    #[test]
+    fn deserialize_note_length() {
+        use serde::{de::IntoDeserializer, Deserialize};
+
+        // Arrange
+        let old = "L8".into_deserializer();
+
+        // Act
+        let duration: std::result::Result<Duration, serde::de::value::Error> =
+            Duration::deserialize(old);
+
+        // Assert
+        assert_eq!(duration.unwrap(), NoteLength::L8.into());
+    }
+
+    #[test]
+    fn deserialize_invalid_tuplet() {
+        // Arrange
+        let text = "(base: L8, tuplet: Some((actual: 0, normal: 2)))";
+
+        // Act
+        let duration = ron::from_str::<Duration>(text);
+
+        // Assert
+        assert!(duration.is_err());
+    }
+}
@@ -0,0 +1,492 @@
+//! Interval definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+use crate::{
+    error::*,
+    notation::{Accidental, NoteName, Octave, Pitch},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum IntervalQuality {
+    Diminished,
+    Minor,
+    Perfect,
+    Major,
+    Augmented,
+}
+
+/// Distance between two pitches given by its quality and number (1 is unison, 8 is octave).
+///
+/// The fields are private, so an interval is always valid, and the deserialized intervals
+/// are checked by `Interval::new` as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "IntervalRepr")]
+pub struct Interval {
+    quality: IntervalQuality,
+    number: u8,
+}
+
+/// Serialized interval, which is not validated yet.
+#[derive(serde::Deserialize)]
+struct IntervalRepr {
+    quality: IntervalQuality,
+    number: u8,
+}
+
+impl TryFrom<IntervalRepr> for Interval {
+    type Error = String;
+
+    fn try_from(repr: IntervalRepr) -> std::result::Result<Self, Self::Error> {
+        Interval::new(repr.quality, repr.number).map_err(|e| e.to_string())
+    }
+}
+
+/// Halftones of perfect and major simple intervals.
+const SIMPLE_HALFTONES: [i32; 7] = [0, 2, 4, 5, 7, 9, 11];
+
+macro_rules! interval {
+    ($quality:ident, $number:expr) => {
+        Interval {
+            quality: IntervalQuality::$quality,
+            number: $number,
+        }
+    };
+}
+
+impl Interval {
+    pub const PERFECT_UNISON: Interval = interval!(Perfect, 1);
+    pub const AUGMENTED_UNISON: Interval = interval!(Augmented, 1);
+    pub const DIMINISHED_SECOND: Interval = interval!(Diminished, 2);
+    pub const MINOR_SECOND: Interval = interval!(Minor, 2);
+    pub const MAJOR_SECOND: Interval = interval!(Major, 2);
+    pub const AUGMENTED_SECOND: Interval = interval!(Augmented, 2);
+    pub const MINOR_THIRD: Interval = interval!(Minor, 3);
+    pub const MAJOR_THIRD: Interval = interval!(Major, 3);
+    pub const PERFECT_FOURTH: Interval = interval!(Perfect, 4);
+    pub const AUGMENTED_FOURTH: Interval = interval!(Augmented, 4);
+    pub const DIMINISHED_FIFTH: Interval = interval!(Diminished, 5);
+    pub const PERFECT_FIFTH: Interval = interval!(Perfect, 5);
+    pub const MINOR_SIXTH: Interval = interval!(Minor, 6);
+    pub const MAJOR_SIXTH: Interval = interval!(Major, 6);
+    pub const MINOR_SEVENTH: Interval = interval!(Minor, 7);
+    pub const MAJOR_SEVENTH: Interval = interval!(Major, 7);
+    pub const PERFECT_OCTAVE: Interval = interval!(Perfect, 8);
+
+    /// Create an interval, checking that the quality is valid for the number.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// assert!(Interval::new(IntervalQuality::Perfect, 12).is_ok());
+    /// assert!(Interval::new(IntervalQuality::Major, 5).is_err());
+    /// assert!(Interval::new(IntervalQuality::Perfect, 3).is_err());
+    /// assert!(Interval::new(IntervalQuality::Minor, 0).is_err());
+    /// ```
+    pub fn new(quality: IntervalQuality, number: u8) -> Result<Self> {
+        use IntervalQuality::*;
+
+        let valid = match quality {
+            _ if number == 0 => false,
+            Perfect => Self::is_perfect_class(number),
+            Minor | Major => !Self::is_perfect_class(number),
+            Diminished | Augmented => true,
+        };
+
+        if !valid {
+            Err(AppError::Interval(format!("{quality:?} {number}")))?;
+        }
+
+        Ok(Self { quality, number })
+    }
+
+    pub fn quality(&self) -> IntervalQuality {
+        self.quality
+    }
+
+    /// Number of the interval, 1 is unison and 8 is octave.
+    pub fn number(&self) -> u8 {
+        self.number
+    }
+
+    /// Whether unisons, fourths, fifths and their compounds can be perfect.
+    fn is_perfect_class(number: u8) -> bool {
+        matches!((number - 1) % 7, 0 | 3 | 4)
+    }
+
+    /// Number of note names the interval spans.
+    pub fn steps(&self) -> u8 {
+        self.number - 1
+    }
+
+    /// Number of halftones the interval spans.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// assert_eq!(Interval::PERFECT_FIFTH.halftones(), 7);
+    /// assert_eq!(Interval::DIMINISHED_SECOND.halftones(), 0);
+    /// assert_eq!(Interval::new(IntervalQuality::Minor, 10).unwrap().halftones(), 15);
+    /// ```
+    pub fn halftones(&self) -> i32 {
+        use IntervalQuality::*;
+
+        let steps = self.steps() as usize;
+        let base = SIMPLE_HALFTONES[steps % 7] + 12 * (steps / 7) as i32;
+
+        base + match (self.quality, Self::is_perfect_class(self.number)) {
+            (Diminished, true) | (Minor, _) => -1,
+            (Diminished, false) => -2,
+            (Perfect, _) | (Major, _) => 0,
+            (Augmented, _) => 1,
+        }
+    }
+
+    /// Interval spanning the given number of note names and halftones, if any quality fits.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// assert_eq!(Interval::from_steps(1, 3), Some(Interval::AUGMENTED_SECOND));
+    /// assert_eq!(Interval::from_steps(2, 3), Some(Interval::MINOR_THIRD));
+    /// assert_eq!(Interval::from_steps(2, 7), None);
+    /// ```
+    pub fn from_steps(steps: u8, halftones: i32) -> Option<Self> {
+        use IntervalQuality::*;
+
+        let number = steps.checked_add(1)?;
+        let base = SIMPLE_HALFTONES[steps as usize % 7] + 12 * (steps as i32 / 7);
+
+        let quality = match (halftones - base, Self::is_perfect_class(number)) {
+            (-1, true) | (-2, false) => Diminished,
+            (-1, false) => Minor,
+            (0, true) => Perfect,
+            (0, false) => Major,
+            (1, _) => Augmented,
+            _ => return None,
+        };
+
+        Some(Self { quality, number })
+    }
+
+    /// The simplest interval spanning the given number of halftones.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// assert_eq!(Interval::from_halftones(3), Interval::MINOR_THIRD);
+    /// assert_eq!(Interval::from_halftones(6), Interval::AUGMENTED_FOURTH);
+    /// assert_eq!(Interval::from_halftones(12), Interval::PERFECT_OCTAVE);
+    /// ```
+    pub fn from_halftones(halftones: u8) -> Self {
+        use IntervalQuality::*;
+
+        let (steps, quality) = match halftones % 12 {
+            0 => (0, Perfect),
+            1 => (1, Minor),
+            2 => (1, Major),
+            3 => (2, Minor),
+            4 => (2, Major),
+            5 => (3, Perfect),
+            6 => (3, Augmented),
+            7 => (4, Perfect),
+            8 => (5, Minor),
+            9 => (5, Major),
+            10 => (6, Minor),
+            _ => (6, Major),
+        };
+
+        Self {
+            quality,
+            number: steps + 7 * (halftones / 12) + 1,
+        }
+    }
+}
+
+impl std::fmt::Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let quality = match self.quality {
+            IntervalQuality::Diminished => "d",
+            IntervalQuality::Minor => "m",
+            IntervalQuality::Perfect => "P",
+            IntervalQuality::Major => "M",
+            IntervalQuality::Augmented => "A",
+        };
+
+        write!(f, "{quality}{}", self.number)
+    }
+}
+
+/// Position of the pitch counted in note names from C0.
+fn diatonic_index(pitch: &Pitch) -> i32 {
+    pitch.octave.value() as i32 * Octave::tone_count() as i32 + pitch.note_name().value() as i32
+}
+
+/// Pitch with the given note name position and MIDI number.
+///
+/// When the note name cannot be reached with the available accidentals, the nearest
+/// enharmonic synonym is used instead.
+fn spell(diatonic: i32, midi: i32) -> Option<Pitch> {
+    let tones = Octave::tone_count() as i32;
+    let note_name = NoteName::from_value(diatonic.rem_euclid(tones) as u8)?;
+    let octave = Octave::from_value(u8::try_from(diatonic.div_euclid(tones)).ok()?)?;
+    let natural = Pitch::new(note_name, octave, None).midi() as i32;
+
+    match i8::try_from(midi - natural)
+        .ok()
+        .and_then(Accidental::from_alteration)
+    {
+        Some(accidental) => Some(Pitch::new(note_name, octave, accidental)),
+        None => Pitch::from_midi(
+            u8::try_from(midi).ok()?,
+            Some(match midi > natural {
+                true => Accidental::Sharp,
+                false => Accidental::Flat,
+            }),
+        ),
+    }
+}
+
+impl Pitch {
+    /// Move the pitch up by the interval, or `None` if the result can't be spelled or is out
+    /// of range.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// let e = Pitch::new(NoteName::E, Octave::O4, None);
+    /// let cis = Pitch::new(NoteName::C, Octave::O5, Some(Accidental::Sharp));
+    /// let highest = Pitch::new(NoteName::B, Octave::O9, None);
+    ///
+    /// assert_eq!(e.checked_add(Interval::MAJOR_SIXTH), Some(cis));
+    /// assert_eq!(highest.checked_add(Interval::MAJOR_SECOND), None);
+    /// ```
+    pub fn checked_add(&self, interval: Interval) -> Option<Pitch> {
+        spell(
+            diatonic_index(self) + interval.steps() as i32,
+            self.midi() as i32 + interval.halftones(),
+        )
+    }
+
+    /// Move the pitch down by the interval, or `None` if the result can't be spelled or is out
+    /// of range.
+    pub fn checked_sub(&self, interval: Interval) -> Option<Pitch> {
+        spell(
+            diatonic_index(self) - interval.steps() as i32,
+            self.midi() as i32 - interval.halftones(),
+        )
+    }
+}
+
+impl Add<Interval> for Pitch {
+    type Output = Pitch;
+
+    /// Move the pitch up by the interval.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// let e = Pitch::new(NoteName::E, Octave::O4, None);
+    /// let cis = Pitch::new(NoteName::C, Octave::O5, Some(Accidental::Sharp));
+    ///
+    /// assert_eq!((e.clone() + Interval::MAJOR_SIXTH).ext, cis.ext);
+    /// assert_eq!(e + Interval::MAJOR_SIXTH, cis);
+    /// ```
+    fn add(self, interval: Interval) -> Self::Output {
+        self.checked_add(interval).unwrap_or_else(|| {
+            log::warn!("Cannot move {self:?} up by {interval}.");
+            self
+        })
+    }
+}
+
+impl Sub<Interval> for Pitch {
+    type Output = Pitch;
+
+    /// Move the pitch down by the interval.
+    fn sub(self, interval: Interval) -> Self::Output {
+        self.checked_sub(interval).unwrap_or_else(|| {
+            log::warn!("Cannot move {self:?} down by {interval}.");
+            self
+        })
+    }
+}
+
+impl AddAssign<Interval> for Pitch {
+    fn add_assign(&mut self, interval: Interval) {
+        *self = self.clone() + interval;
+    }
+}
+
+impl SubAssign<Interval> for Pitch {
+    fn sub_assign(&mut self, interval: Interval) {
+        *self = self.clone() - interval;
+    }
+}
+
+impl Sub for Pitch {
+    type Output = Interval;
+
+    /// Interval between the two pitches regardless of their order.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// let c = Pitch::new(NoteName::C, Octave::O4, None);
+    /// let es = Pitch::new(NoteName::E, Octave::O4, Some(Accidental::Flat));
+    /// let dis = Pitch::new(NoteName::D, Octave::O4, Some(Accidental::Sharp));
+    ///
+    /// assert_eq!(es.clone() - c.clone(), Interval::MINOR_THIRD);
+    /// assert_eq!(c.clone() - dis.clone(), Interval::AUGMENTED_SECOND);
+    /// assert_eq!(es - dis, Interval::DIMINISHED_SECOND);
+    /// ```
+    fn sub(self, other: Pitch) -> Self::Output {
+        let key = |p: &Pitch| (p.midi(), diatonic_index(p));
+        let (low, high) = match key(&self) <= key(&other) {
+            true => (self, other),
+            false => (other, self),
+        };
+
+        let steps = diatonic_index(&high) - diatonic_index(&low);
+        let halftones = high.midi() - low.midi();
+
+        u8::try_from(steps)
+            .ok()
+            .and_then(|steps| Interval::from_steps(steps, halftones as i32))
+            .unwrap_or(Interval::from_halftones(halftones))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{Accidental::*, NoteName::*, Octave::*};
+
+    #[test]
+    fn add_spelling() {
+        // Arrange
+        let fis = Pitch::new(F, O4, Some(Sharp));
+        let es = Pitch::new(E, O4, Some(Flat));
+        let h = Pitch::new(B, O3, None);
+
+        // Act && Assert
+        assert_eq!(
+            (fis.clone() + Interval::MAJOR_THIRD).ext,
+            Pitch::new(A, O4, Some(Sharp)).ext
+        );
+        assert_eq!(
+            (fis + Interval::MINOR_SEVENTH).ext,
+            Pitch::new(E, O5, None).ext
+        );
+        assert_eq!(
+            (es.clone() + Interval::PERFECT_FIFTH).ext,
+            Pitch::new(B, O4, Some(Flat)).ext
+        );
+        assert_eq!(
+            (es - Interval::AUGMENTED_FOURTH).ext,
+            Pitch::new(B, O3, Some(DoubleFlat)).ext
+        );
+        assert_eq!(h.clone() + Interval::MINOR_SECOND, Pitch::new(C, O4, None));
+        assert_eq!((h + Interval::MINOR_SECOND).octave, O4);
+    }
+
+    #[test]
+    fn add_unreachable_spelling() {
+        // Arrange
+        let bisis = Pitch::new(B, O4, Some(DoubleSharp));
+
+        // Act
+        let e = bisis + Interval::AUGMENTED_SECOND;
+
+        // Assert
+        assert_eq!(e.ext.note_name, E);
+        assert_eq!(e, Pitch::new(E, O5, None));
+    }
+
+    #[test]
+    fn sub_double_sharp() {
+        // Arrange
+        let gis = Pitch::new(G, O4, Some(Sharp));
+
+        // Act
+        let fisis = gis - Interval::MINOR_SECOND;
+
+        // Assert
+        assert_eq!(fisis.ext.note_name, F);
+        assert_eq!(fisis.accidental(), Some(DoubleSharp));
+        assert_eq!(fisis, Pitch::new(G, O4, None));
+    }
+
+    #[test]
+    fn add_out_of_range() {
+        // Arrange
+        let c = Pitch::new(C, O0, None);
+
+        // Act
+        let lower = c.clone() - Interval::MINOR_SECOND;
+
+        // Assert
+        assert_eq!(lower, c);
+    }
+
+    #[test]
+    fn difference_compound() {
+        // Arrange
+        let c = Pitch::new(C, O3, None);
+        let e = Pitch::new(E, O4, None);
+
+        // Act
+        let interval = c - e;
+
+        // Assert
+        assert_eq!(interval, Interval::new(IntervalQuality::Major, 10).unwrap());
+        assert_eq!(interval.to_string(), "M10");
+    }
+
+    #[test]
+    fn deserialize_invalid() {
+        // Arrange
+        let valid = "(quality: Minor, number: 10)";
+        let zero = "(quality: Perfect, number: 0)";
+        let perfect_third = "(quality: Perfect, number: 3)";
+
+        // Act
+        let valid: std::result::Result<Interval, _> = ron::from_str(valid);
+        let zero: std::result::Result<Interval, _> = ron::from_str(zero);
+        let perfect_third: std::result::Result<Interval, _> = ron::from_str(perfect_third);
+
+        // Assert
+        assert_eq!(
+            valid.unwrap(),
+            Interval::new(IntervalQuality::Minor, 10).unwrap()
+        );
+        assert!(zero.is_err());
+        assert!(perfect_third.is_err());
+    }
+
+    #[test]
+    fn round_trip_all_intervals() {
+        // Arrange
+        let start = Pitch::new(D, O4, None);
+
+        for halftones in 0..=24 {
+            // Act
+            let interval = Interval::from_halftones(halftones);
+            let end = start.clone() + interval;
+
+            // Assert
+            assert_eq!(end.midi() - start.midi(), halftones);
+            assert_eq!(end - start.clone(), interval);
+        }
+    }
+}
@@ -4,13 +4,14 @@
 //! Jakub Kloub (xkloub03), VUT FIT
 
 use crate::{
-    notation::stave::Stave,
-    sanitizer::{Sanitizer, ScoreSanitizer},
+    error::*,
+    notation::{stave::Stave, Accidental, Interval, KeySignature, Octave, Pitch, Symbol},
+    sanitizer::{preferred_accidental, Sanitizer, ScoreSanitizer, ToPrefSynonym},
 };
 
 type Tempo = u32;
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 pub struct ScoreInfo {
     pub name: Option<String>,
     pub author: Option<String>,
@@ -22,6 +23,20 @@ pub struct Score {
     pub staves: Vec<Stave>,
     pub info: ScoreInfo,
     pub tempo: Tempo,
+    /// Accidental spelling all altered notes instead of their scale degree in the key, as
+    /// chosen by a custom scale.
+    pub spelling: Option<Accidental>,
+}
+
+/// Change of the score pitch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum Transposition {
+    #[default]
+    None,
+    Up(Interval),
+    Down(Interval),
+    /// Move the score to the key with the given tonic, keeping the key type.
+    To(KeySignature),
 }
 
 impl Default for Score {
@@ -30,13 +45,260 @@ impl Default for Score {
             staves: Default::default(),
             info: Default::default(),
             tempo: 90,
+            spelling: None,
         }
     }
 }
 
 impl Score {
-    pub fn sanitized(mut self) -> crate::error::Result<Self> {
+    pub fn sanitized(mut self) -> Result<Self> {
         ScoreSanitizer {}.sanitize(&mut self)?;
         Ok(self)
     }
+
+    /// Transpose the score and respell its notes according to the new keys.
+    pub fn transpose(&mut self, transposition: &Transposition) -> Result<()> {
+        match transposition {
+            Transposition::None => return Ok(()),
+            Transposition::Up(interval) => self.move_pitches(|p| p.checked_add(*interval))?,
+            Transposition::Down(interval) => self.move_pitches(|p| p.checked_sub(*interval))?,
+            Transposition::To(key) => self.transpose_to(key)?,
+        }
+
+        ScoreSanitizer {}.sanitize(self)
+    }
+
+    /// Transpose the score by the smallest interval to the key with the given tonic.
+    fn transpose_to(&mut self, key: &KeySignature) -> Result<()> {
+        let current = self
+            .staves
+            .iter()
+            .flat_map(|s| s.symbols.iter())
+            .find_map(|s| match s {
+                Symbol::KeySignature(k) => Some(*k),
+                _ => None,
+            })
+            .ok_or(AppError::StaveKeyNotFound)?;
+
+        let from = Pitch {
+            ext: current.ext,
+            octave: Octave::O4,
+        };
+        let mut to = Pitch {
+            ext: key.ext,
+            octave: Octave::O4,
+        };
+        if to.midi() < from.midi() {
+            to.octave = Octave::O5;
+        }
+
+        let up = to.clone() - from.clone();
+        if up.halftones() <= 6 {
+            self.move_pitches(|p| p.checked_add(up))
+        } else {
+            to.octave = to.octave.try_prev().unwrap();
+            let down = from - to;
+            self.move_pitches(|p| p.checked_sub(down))
+        }
+    }
+
+    /// Apply the movement to all pitches and key signatures of the score.
+    ///
+    /// The score is left unchanged, when any of its pitches can't be moved.
+    fn move_pitches(&mut self, movement: impl Fn(&Pitch) -> Option<Pitch>) -> Result<()> {
+        let move_pitch = |pitch: &mut Pitch| -> Result<()> {
+            *pitch = movement(pitch).ok_or_else(|| {
+                AppError::Transposition(format!("{}{}", pitch.ext, pitch.octave.value()))
+            })?;
+            Ok(())
+        };
+
+        let mut staves = self.staves.clone();
+        for sym in staves.iter_mut().flat_map(|s| s.symbols.iter_mut()) {
+            match sym {
+                Symbol::Note(note) => move_pitch(&mut note.pitch)?,
+                Symbol::Chord(chord) => chord.pitches.iter_mut().try_for_each(move_pitch)?,
+                Symbol::KeySignature(key) => {
+                    let mut tonic = Pitch {
+                        ext: key.ext,
+                        octave: Octave::O4,
+                    };
+                    move_pitch(&mut tonic)?;
+                    key.ext = tonic.ext;
+                    Self::respell_key(key);
+                }
+                _ => {}
+            }
+        }
+
+        self.staves = staves;
+        Ok(())
+    }
+
+    /// Use the enharmonic synonym of the key tonic, if the key prefers the other accidental.
+    fn respell_key(key: &mut KeySignature) {
+        let (Some(accidental), Some(pref)) = (key.ext.accidental, preferred_accidental(key)) else {
+            return;
+        };
+
+        if accidental != pref {
+            let mut tonic = Pitch {
+                ext: key.ext,
+                octave: Octave::O4,
+            };
+            tonic.to_pref_synonym(Some(pref));
+            key.ext = tonic.ext;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{
+        Accidental, Accidental::*, ExtNoteName, KeySignatureType, Note, NoteLength, NoteName,
+        NoteName::*, Octave::*,
+    };
+
+    fn key(note_name: NoteName, accidental: Option<Accidental>) -> KeySignature {
+        KeySignature {
+            ext: ExtNoteName {
+                note_name,
+                accidental,
+            },
+            signature_type: KeySignatureType::Maj,
+        }
+    }
+
+    fn score(key: KeySignature, pitches: &[Pitch]) -> Score {
+        Score {
+            staves: vec![Stave {
+                symbols: std::iter::once(Symbol::KeySignature(key))
+                    .chain(pitches.iter().map(|p| {
+                        Symbol::Note(Note {
+                            pitch: p.clone(),
+                            duration: NoteLength::L4.into(),
+                            tie: false,
+                        })
+                    }))
+                    .collect(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn spelled(score: &Score) -> (KeySignature, Vec<(ExtNoteName, Octave)>) {
+        let mut key = None;
+        let mut notes = vec![];
+        for sym in score.staves[0].symbols.iter() {
+            match sym {
+                Symbol::KeySignature(k) => key = Some(*k),
+                Symbol::Note(n) => notes.push((n.pitch.ext, n.pitch.octave)),
+                _ => {}
+            }
+        }
+
+        (key.unwrap(), notes)
+    }
+
+    #[test]
+    fn transpose_up() {
+        // Arrange
+        let mut score = score(
+            key(C, None),
+            &[Pitch::new(C, O4, None), Pitch::new(F, O4, None)],
+        );
+
+        // Act
+        score
+            .transpose(&Transposition::Up(Interval::MAJOR_SECOND))
+            .unwrap();
+
+        // Assert
+        let (key_sig, notes) = spelled(&score);
+        assert_eq!(key_sig, key(D, None));
+        assert_eq!(
+            notes,
+            vec![
+                (Pitch::new(D, O4, None).ext, O4),
+                (Pitch::new(G, O4, None).ext, O4),
+            ]
+        );
+    }
+
+    #[test]
+    fn transpose_respells_key() {
+        // Arrange
+        let mut score = score(key(D, None), &[Pitch::new(F, O4, Some(Sharp))]);
+
+        // Act
+        score
+            .transpose(&Transposition::Up(Interval::AUGMENTED_UNISON))
+            .unwrap();
+
+        // Assert
+        let (key_sig, notes) = spelled(&score);
+        assert_eq!(key_sig, key(E, Some(Flat)));
+        assert_eq!(notes, vec![(Pitch::new(G, O4, None).ext, O4)]);
+    }
+
+    #[test]
+    fn transpose_to_key() {
+        // Arrange
+        let mut score = score(
+            key(C, None),
+            &[Pitch::new(E, O4, None), Pitch::new(G, O4, None)],
+        );
+
+        // Act
+        score
+            .transpose(&Transposition::To(key(E, Some(Flat))))
+            .unwrap();
+
+        // Assert
+        let (key_sig, notes) = spelled(&score);
+        assert_eq!(key_sig, key(E, Some(Flat)));
+        assert_eq!(
+            notes,
+            vec![
+                (Pitch::new(G, O4, None).ext, O4),
+                (Pitch::new(B, O4, Some(Flat)).ext, O4),
+            ]
+        );
+    }
+
+    #[test]
+    fn transpose_to_key_down() {
+        // Arrange
+        let mut score = score(key(C, None), &[Pitch::new(C, O4, None)]);
+
+        // Act
+        score.transpose(&Transposition::To(key(A, None))).unwrap();
+
+        // Assert
+        let (key_sig, notes) = spelled(&score);
+        assert_eq!(key_sig, key(A, None));
+        assert_eq!(notes, vec![(Pitch::new(A, O3, None).ext, O3)]);
+    }
+
+    #[test]
+    fn transpose_out_of_range() {
+        // Arrange
+        let mut score = score(
+            key(C, None),
+            &[Pitch::new(C, O4, None), Pitch::new(B, O9, None)],
+        );
+
+        // Act
+        let transposed = score.transpose(&Transposition::Up(Interval::MAJOR_SECOND));
+
+        // Assert
+        assert_eq!(
+            transposed.unwrap_err().to_string(),
+            "Pitch B9 cannot be transposed."
+        );
+        let (key_sig, notes) = spelled(&score);
+        assert_eq!(key_sig, key(C, None));
+        assert_eq!(notes[0], (Pitch::new(C, O4, None).ext, O4));
+    }
 }
@@ -3,9 +3,43 @@
 //! ### Author
 //! Jakub Kloub (xkloub03), VUT FIT
 
-use crate::notation::symbol::Symbol;
+use crate::notation::{
+    symbol::Symbol, ExtNoteName, KeySignature, KeySignatureType, NoteName, TimeSignature,
+};
 
 #[derive(Debug, Default, Clone)]
 pub struct Stave {
     pub symbols: Vec<Symbol>,
 }
+
+impl Stave {
+    /// Insert C major key and 4/4 time before the first note, if the stave doesn't set them
+    /// there. Imported staves may omit them, but the sanitizers require both.
+    pub fn insert_missing_signatures(&mut self) {
+        let first_note = self
+            .symbols
+            .iter()
+            .position(|s| s.duration().is_some())
+            .unwrap_or(self.symbols.len());
+        let before = &self.symbols[..first_note];
+        let has_time = before.iter().any(|s| matches!(s, Symbol::TimeSignature(_)));
+        let has_key = before.iter().any(|s| matches!(s, Symbol::KeySignature(_)));
+
+        if !has_time {
+            self.symbols
+                .insert(first_note, Symbol::TimeSignature(TimeSignature::c()));
+        }
+        if !has_key {
+            self.symbols.insert(
+                first_note,
+                Symbol::KeySignature(KeySignature {
+                    ext: ExtNoteName {
+                        note_name: NoteName::C,
+                        accidental: None,
+                    },
+                    signature_type: KeySignatureType::Maj,
+                }),
+            );
+        }
+    }
+}
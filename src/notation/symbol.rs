@@ -69,16 +69,105 @@ impl Pitch {
     /// assert_eq!(ais.real_octave(), Octave::O3);
     /// ```
     pub fn real_octave(&self) -> Octave {
-        use Accidental::*;
-        use NoteName::*;
-
-        if self.note_name() == C && self.accidental() == Some(Flat) {
-            self.octave.try_prev().unwrap()
-        } else if self.note_name() == B && self.accidental() == Some(Sharp) {
-            self.octave.try_next().unwrap()
-        } else {
-            self.octave
-        }
+        let raw = self.octave.value() as i32 * Octave::halftone_count() as i32
+            + self.note_name().value_halftone() as i32
+            + Accidental::alteration(self.accidental()) as i32;
+
+        u8::try_from(raw.div_euclid(Octave::halftone_count() as i32))
+            .ok()
+            .and_then(Octave::from_value)
+            .unwrap_or(self.octave)
+    }
+
+    /// MIDI note number, where C4 is 60.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// assert_eq!(Pitch::new(NoteName::C, Octave::O4, None).midi(), 60);
+    /// assert_eq!(Pitch::new(NoteName::A, Octave::O4, None).midi(), 69);
+    /// assert_eq!(Pitch::new(NoteName::C, Octave::O4, Some(Accidental::Flat)).midi(), 59);
+    /// assert_eq!(Pitch::new(NoteName::B, Octave::O3, Some(Accidental::Sharp)).midi(), 60);
+    /// ```
+    pub fn midi(&self) -> u8 {
+        (self.real_octave().value() + 1) * Octave::halftone_count() + self.value_halftone()
+    }
+
+    /// Pitch with the given MIDI note number. Black keys are spelled with the preferred
+    /// accidental, sharp by default.
+    ///
+    /// Returns `None` if the pitch is out of the supported octaves.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// let bes = Pitch::from_midi(70, Some(Accidental::Flat)).unwrap();
+    ///
+    /// assert_eq!(bes.ext.note_name, NoteName::B);
+    /// assert_eq!(Pitch::from_midi(61, None).unwrap().accidental(), Some(Accidental::Sharp));
+    /// assert_eq!(Pitch::from_midi(60, None).unwrap().octave, Octave::O4);
+    /// assert_eq!(Pitch::from_midi(5, None), None);
+    /// ```
+    pub fn from_midi(midi: u8, prefer: Option<Accidental>) -> Option<Self> {
+        let ht = Octave::halftone_count();
+        let octave = Octave::from_value((midi / ht).checked_sub(1)?)?;
+
+        let ext = match (midi % ht, prefer) {
+            (1, Some(Accidental::Flat)) => (NoteName::D, Some(Accidental::Flat)),
+            (3, Some(Accidental::Flat)) => (NoteName::E, Some(Accidental::Flat)),
+            (6, Some(Accidental::Flat)) => (NoteName::G, Some(Accidental::Flat)),
+            (8, Some(Accidental::Flat)) => (NoteName::A, Some(Accidental::Flat)),
+            (10, Some(Accidental::Flat)) => (NoteName::B, Some(Accidental::Flat)),
+            (1, _) => (NoteName::C, Some(Accidental::Sharp)),
+            (3, _) => (NoteName::D, Some(Accidental::Sharp)),
+            (6, _) => (NoteName::F, Some(Accidental::Sharp)),
+            (8, _) => (NoteName::G, Some(Accidental::Sharp)),
+            (10, _) => (NoteName::A, Some(Accidental::Sharp)),
+            (h, _) => (
+                (0..Octave::tone_count())
+                    .filter_map(NoteName::from_value)
+                    .find(|n| n.value_halftone() == h)?,
+                None,
+            ),
+        };
+
+        Some(Self::new(ext.0, octave, ext.1))
+    }
+
+    /// The same pitch spelled with the given note name.
+    ///
+    /// Returns `None` if the note name cannot be reached with at most a double accidental.
+    ///
+    /// # EXAMPLE
+    /// ```
+    /// # use music_sheet_gen::notation::*;
+    ///
+    /// let g = Pitch::new(NoteName::G, Octave::O4, None);
+    /// let fisis = g.respelled(NoteName::F).unwrap();
+    ///
+    /// assert_eq!(fisis.accidental(), Some(Accidental::DoubleSharp));
+    /// assert_eq!(fisis.octave, Octave::O4);
+    /// assert_eq!(g.respelled(NoteName::C), None);
+    /// ```
+    pub fn respelled(&self, note_name: NoteName) -> Option<Self> {
+        let midi = self.midi() as i32;
+        let octave = self.real_octave();
+
+        [octave.try_prev(), Some(octave), octave.try_next()]
+            .into_iter()
+            .flatten()
+            .find_map(|octave| {
+                let natural = Self::new(note_name, octave, None).midi() as i32;
+                let alteration = i8::try_from(midi - natural).ok()?;
+
+                Some(Self::new(
+                    note_name,
+                    octave,
+                    Accidental::from_alteration(alteration)?,
+                ))
+            })
     }
 
     /// Move the pitch by a halftone up
@@ -116,6 +205,13 @@ impl Pitch {
                 self.ext.note_name = self.note_name().next();
             }
             Some(Accidental::Flat) => self.ext.accidental = None,
+            Some(Accidental::DoubleFlat) => self.ext.accidental = Some(Accidental::Flat),
+            Some(Accidental::DoubleSharp) => {
+                match Self::from_midi(self.midi() + 1, Some(Accidental::Sharp)) {
+                    Some(pitch) => *self = pitch,
+                    None => log::warn!("Cannot move {self:?} halftone up."),
+                }
+            }
             None => self.ext.accidental = Some(Accidental::Sharp),
         }
     }
@@ -141,6 +237,13 @@ impl Pitch {
     pub fn move_halftone_down(&mut self) {
         match self.accidental() {
             Some(Accidental::Sharp) => self.ext.accidental = None,
+            Some(Accidental::DoubleSharp) => self.ext.accidental = Some(Accidental::Sharp),
+            Some(Accidental::DoubleFlat) => {
+                match Self::from_midi(self.midi().saturating_sub(1), Some(Accidental::Flat)) {
+                    Some(pitch) => *self = pitch,
+                    None => log::warn!("Cannot move {self:?} halftone down."),
+                }
+            }
             Some(Accidental::Flat) => {
                 if self.note_name() == NoteName::C {
                     self.octave = match self.octave.try_prev() {
@@ -181,14 +284,19 @@ impl PartialEq for Pitch {
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct Note {
     pub pitch: Pitch,
-    pub duration: NoteLength,
+    pub duration: Duration,
+    /// Whether the note is tied to the following one.
+    #[serde(default)]
+    pub tie: bool,
 }
 
 /// Represents a chord of notes that have the same length.
 #[derive(Debug, Clone)]
 pub struct Chord {
     pub pitches: Vec<Pitch>,
-    pub duration: NoteLength,
+    pub duration: Duration,
+    /// Whether the chord is tied to the following one.
+    pub tie: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -198,6 +306,18 @@ pub enum Symbol {
     KeySignature(KeySignature),
     Chord(Chord),
     Note(Note),
-    Rest(NoteLength),
+    Rest(Duration),
     Tempo(Tempo),
 }
+
+impl Symbol {
+    /// Duration of the symbol, if it takes any time in the bar.
+    pub fn duration(&self) -> Option<&Duration> {
+        match self {
+            Symbol::Note(note) => Some(&note.duration),
+            Symbol::Chord(chord) => Some(&chord.duration),
+            Symbol::Rest(duration) => Some(duration),
+            _ => None,
+        }
+    }
+}
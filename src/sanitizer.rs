@@ -11,40 +11,99 @@ pub use lily_sanitizer::LilySanitizer;
 pub use score_sanitizer::ScoreSanitizer;
 pub use to_pref_synonym::ToPrefSynonym;
 
-use crate::notation::KeySignature;
-use crate::notation::{Accidental::*, KeySignatureType::*, NoteName::*};
-use crate::{error::Result, notation::ExtNoteName};
-
-#[rustfmt::skip]
-const SHARP_KEYS: [KeySignature; 10] = [
-    KeySignature { ext: ExtNoteName { note_name: C, accidental: None }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: G, accidental: None }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: D, accidental: None }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: A, accidental: None }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: E, accidental: None }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: B, accidental: None }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: E, accidental: Some(Sharp) }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: B, accidental: Some(Sharp) }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: F, accidental: Some(Flat) }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: C, accidental: Some(Sharp) }, signature_type: Maj },
-];
-
-#[rustfmt::skip]
-const FLAT_KEYS: [KeySignature; 11] = [
-    KeySignature { ext: ExtNoteName { note_name: F, accidental: None }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: B, accidental: Some(Flat) }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: E, accidental: Some(Flat) }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: A, accidental: Some(Flat) }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: D, accidental: Some(Flat) }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: G, accidental: Some(Flat) }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: C, accidental: Some(Flat) }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: A, accidental: Some(Sharp) }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: D, accidental: Some(Sharp) }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: G, accidental: Some(Sharp) }, signature_type: Maj },
-    KeySignature { ext: ExtNoteName { note_name: F, accidental: Some(Sharp) }, signature_type: Maj },
-];
+use crate::error::Result;
+use crate::notation::{Accidental, KeySignature};
 
 /// Generic sanitizer trait used by all sanitizers.
 pub trait Sanitizer<T> {
     fn sanitize(&self, score: &mut T) -> Result<()>;
 }
+
+/// Get preffered accidental for given key.
+///
+/// Keys with sharps in the signature prefer sharps, keys with flats prefer flats. Keys
+/// beyond seven accidentals prefer the accidental of their enharmonic key with fewer
+/// accidentals (e.g. D-sharp major prefers flats of E-flat major).
+pub fn preferred_accidental(key: &KeySignature) -> Option<Accidental> {
+    match key.fifths() {
+        -7..=-1 | 8.. => Some(Accidental::Flat),
+        _ => Some(Accidental::Sharp),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{Accidental::*, ExtNoteName, KeySignatureType::*, NoteName, NoteName::*};
+
+    #[rustfmt::skip]
+    const KEYS: [(NoteName, Option<Accidental>, NoteName, Option<Accidental>); 15] = [
+        (C, Some(Flat), A, Some(Flat)),
+        (G, Some(Flat), E, Some(Flat)),
+        (D, Some(Flat), B, Some(Flat)),
+        (A, Some(Flat), F, None),
+        (E, Some(Flat), C, None),
+        (B, Some(Flat), G, None),
+        (F, None, D, None),
+        (C, None, A, None),
+        (G, None, E, None),
+        (D, None, B, None),
+        (A, None, F, Some(Sharp)),
+        (E, None, C, Some(Sharp)),
+        (B, None, G, Some(Sharp)),
+        (F, Some(Sharp), D, Some(Sharp)),
+        (C, Some(Sharp), A, Some(Sharp)),
+    ];
+
+    fn key(note_name: NoteName, accidental: Option<Accidental>, minor: bool) -> KeySignature {
+        KeySignature {
+            ext: ExtNoteName {
+                note_name,
+                accidental,
+            },
+            signature_type: if minor { Min } else { Maj },
+        }
+    }
+
+    #[test]
+    fn all_key_signatures() {
+        for (fifths, (maj, maj_acc, min, min_acc)) in (-7..=7).zip(KEYS) {
+            // Arrange
+            let major = key(maj, maj_acc, false);
+            let minor = key(min, min_acc, true);
+            let expected = match fifths < 0 {
+                true => Some(Flat),
+                false => Some(Sharp),
+            };
+
+            // Act && Assert
+            assert_eq!(major.fifths(), fifths);
+            assert_eq!(minor.fifths(), fifths);
+            assert_eq!(KeySignature::from_fifths(fifths, Maj), Some(major));
+            assert_eq!(KeySignature::from_fifths(fifths, Min), Some(minor));
+            assert_eq!(preferred_accidental(&major), expected);
+            assert_eq!(preferred_accidental(&minor), expected);
+        }
+    }
+
+    #[test]
+    fn modes_and_theoretical_keys() {
+        // Arrange
+        let e_dorian = KeySignature {
+            signature_type: Dorian,
+            ..key(E, None, false)
+        };
+        let f_lydian = KeySignature {
+            signature_type: Lydian,
+            ..key(F, None, false)
+        };
+        let dis_maj = key(D, Some(Sharp), false);
+
+        // Act && Assert
+        assert_eq!(e_dorian.fifths(), 2);
+        assert_eq!(f_lydian.fifths(), 0);
+        assert_eq!(dis_maj.fifths(), 9);
+        assert_eq!(preferred_accidental(&e_dorian), Some(Sharp));
+        assert_eq!(preferred_accidental(&dis_maj), Some(Flat));
+    }
+}
@@ -6,6 +6,7 @@
 use crate::{
     error::*,
     lily::{LilyStave, LilySymbol, LilyTime, Lilypond},
+    notation::Rational,
     sanitizer::Sanitizer,
 };
 
@@ -27,7 +28,9 @@ impl Default for LilySanitizer {
 
 impl Sanitizer<Lilypond> for LilySanitizer {
     fn sanitize(&self, score: &mut Lilypond) -> Result<()> {
-        for stave in score.staves.iter_mut() {
+        // Line breaks apply to the whole system, so it is enough to place them
+        // into the first stave. Other staves would only add breaks of their own.
+        if let Some(stave) = score.staves.first_mut() {
             self.sanitize_stave(stave)?;
         }
 
@@ -42,24 +45,21 @@ impl LilySanitizer {
             .find_time_sig(stave)
             .ok_or(AppError::MissingTimeSignature)?;
 
-        let total_bar_len = (time.nom as u16) * time.denom.value_128();
+        let total_bar_len = time.bar_length();
         let mut line_notes = 0;
         let mut line_bars = 0;
-        let mut current_bar_len = 0;
+        let mut current_bar_len = Rational::from(0);
 
         let mut breaks: Vec<usize> = vec![];
 
-        for (i, note) in stave
+        for (i, length) in stave
             .symbols
             .iter()
             .enumerate()
-            .filter_map(|(i, n)| match n {
-                LilySymbol::Note(n) => Some((i, n)),
-                _ => None,
-            })
+            .filter_map(|(i, s)| s.value().map(|l| (i, l)))
         {
             line_notes += 1;
-            current_bar_len += note.length.value_128();
+            current_bar_len += length;
 
             if current_bar_len >= total_bar_len {
                 line_bars += 1;
@@ -5,20 +5,20 @@
 
 use crate::{
     error::*,
-    notation::{Accidental, KeySignature, NoteName, Score, Stave, Symbol},
-    sanitizer::{Sanitizer, ToPrefSynonym, FLAT_KEYS, SHARP_KEYS},
+    notation::{
+        Accidental, Interval, KeySignature, KeySignatureType, NoteName, Octave, Pitch, Score,
+        Stave, Symbol,
+    },
+    sanitizer::{preferred_accidental, Sanitizer},
 };
 
-use Accidental::*;
-use NoteName::*;
-
 pub struct ScoreSanitizer;
 
 /// Score internal structure sanitizer.
 impl Sanitizer<Score> for ScoreSanitizer {
     fn sanitize(&self, score: &mut Score) -> Result<()> {
         for stave in score.staves.iter_mut() {
-            self.sanitize_stave(stave)?;
+            self.sanitize_stave(stave, score.spelling)?;
         }
 
         Ok(())
@@ -26,38 +26,96 @@ impl Sanitizer<Score> for ScoreSanitizer {
 }
 
 impl ScoreSanitizer {
-    /// Convert pitch to its preffered accidental.
-    fn pitch_to_pref(&self, pref: Option<Accidental>, pitch: &mut crate::notation::Pitch) {
-        match pref {
-            Some(Sharp) => match (pitch.note_name(), pitch.accidental()) {
-                (C, None) | (F, None) => {}
-                (B, Some(Sharp)) | (E, Some(Sharp)) => pitch.to_pref_synonym(None),
-                _ => pitch.to_pref_synonym(pref),
-            },
-            Some(Flat) => match (pitch.note_name(), pitch.accidental()) {
-                (E, None) | (B, None) => {}
-                (F, Some(Flat)) | (C, Some(Flat)) => pitch.to_pref_synonym(None),
-                _ => pitch.to_pref_synonym(pref),
-            },
-            _ => pitch.to_pref_synonym(pref),
+    /// Note names of the scale degrees of the key with their distance from the key note
+    /// in halftones. Minor keys also contain the raised sixth and seventh degrees.
+    fn degrees(key: &KeySignature) -> Vec<(NoteName, u8)> {
+        let tonic = key.ext.note_name.value();
+        let mut halftones = 0;
+        let mut degrees: Vec<(NoteName, u8)> = key
+            .signature_type
+            .steps()
+            .iter()
+            .zip(0..Octave::tone_count())
+            .filter_map(|(step, i)| {
+                let degree = (
+                    NoteName::from_value((tonic + i) % Octave::tone_count())?,
+                    halftones,
+                );
+                halftones += step;
+                Some(degree)
+            })
+            .collect();
+
+        if key.signature_type == KeySignatureType::Min {
+            degrees.push((degrees[5].0, Interval::MAJOR_SIXTH.halftones() as u8));
+            degrees.push((degrees[6].0, Interval::MAJOR_SEVENTH.halftones() as u8));
+        }
+
+        degrees
+    }
+
+    /// Spell the pitch by its scale degree in the key.
+    ///
+    /// Notes outside of the scale are spelled either as the raised lower degree or as the
+    /// lowered upper degree, whichever needs the simpler accidental. Ties are decided by
+    /// the accidental preferred by the key.
+    fn spell_in_key(&self, key: &KeySignature, pitch: &mut Pitch) {
+        let degrees = Self::degrees(key);
+        let ht = Octave::halftone_count();
+        let offset = (pitch.value_halftone() + ht - key.ext.value_halftone()) % ht;
+        let respell = |offset: u8| {
+            degrees
+                .iter()
+                .find(|(_, h)| *h == offset % ht)
+                .and_then(|(note_name, _)| pitch.respelled(*note_name))
+        };
+
+        let spelled = respell(offset).or_else(|| {
+            let raised = respell(offset + ht - 1);
+            let lowered = respell(offset + 1);
+            let simplicity = |p: &Option<Pitch>| {
+                p.as_ref()
+                    .map(|p| Accidental::alteration(p.accidental()).abs())
+                    .unwrap_or(i8::MAX)
+            };
+
+            match simplicity(&raised).cmp(&simplicity(&lowered)) {
+                std::cmp::Ordering::Less => raised,
+                std::cmp::Ordering::Greater => lowered,
+                std::cmp::Ordering::Equal => match preferred_accidental(key) {
+                    Some(Accidental::Flat) => lowered,
+                    _ => raised,
+                },
+            }
+        });
+
+        if let Some(spelled) = spelled {
+            *pitch = spelled;
         }
     }
 
-    /// Sanitize all stave symbols.
-    fn sanitize_stave(&self, stave: &mut Stave) -> Result<()> {
+    /// Spell the pitch as a natural note or with the given accidental.
+    fn spell_with(&self, accidental: Accidental, pitch: &mut Pitch) {
+        if let Some(spelled) = Pitch::from_midi(pitch.midi(), Some(accidental)) {
+            *pitch = spelled;
+        }
+    }
+
+    /// Sanitize all stave symbols, spelling the notes by the key or with the given
+    /// accidental.
+    fn sanitize_stave(&self, stave: &mut Stave, spelling: Option<Accidental>) -> Result<()> {
         let (key, key_pos) = self.find_first_key(stave)?;
-        let mut preffered_accidental = self.get_pref_accidental(key);
+        let mut key = *key;
+        let spell = |key: &KeySignature, pitch: &mut Pitch| match spelling {
+            Some(accidental) => self.spell_with(accidental, pitch),
+            None => self.spell_in_key(key, pitch),
+        };
 
         for sym in stave.symbols.iter_mut().skip(key_pos + 1) {
             match sym {
-                Symbol::KeySignature(sig) => preffered_accidental = self.get_pref_accidental(sig),
-                Symbol::Note(note) => self.pitch_to_pref(preffered_accidental, &mut note.pitch),
-                Symbol::Chord(chord) => {
-                    chord
-                        .pitches
-                        .iter_mut()
-                        .for_each(|p| self.pitch_to_pref(preffered_accidental, p));
-                }
+                Symbol::KeySignature(sig) => key = *sig,
+                Symbol::Note(note) => spell(&key, &mut note.pitch),
+                Symbol::Chord(chord) => chord.pitches.iter_mut().for_each(|p| spell(&key, p)),
                 _ => {}
             }
         }
@@ -77,15 +135,120 @@ impl ScoreSanitizer {
 
         Err(AppError::StaveKeyNotFound)?
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{Accidental::*, ExtNoteName, Note, NoteLength, NoteName::*, Octave::*};
+
+    fn sanitized(key: KeySignature, pitches: &[Pitch]) -> Vec<ExtNoteName> {
+        sanitized_with(key, pitches, None)
+    }
+
+    fn sanitized_with(
+        key: KeySignature,
+        pitches: &[Pitch],
+        spelling: Option<Accidental>,
+    ) -> Vec<ExtNoteName> {
+        let mut score = Score {
+            staves: vec![Stave {
+                symbols: std::iter::once(Symbol::KeySignature(key))
+                    .chain(pitches.iter().map(|p| {
+                        Symbol::Note(Note {
+                            pitch: p.clone(),
+                            duration: NoteLength::L4.into(),
+                            tie: false,
+                        })
+                    }))
+                    .collect(),
+            }],
+            spelling,
+            ..Default::default()
+        };
+
+        ScoreSanitizer.sanitize(&mut score).unwrap();
+
+        score.staves[0]
+            .symbols
+            .iter()
+            .filter_map(|sym| match sym {
+                Symbol::Note(note) => Some(note.pitch.ext),
+                _ => None,
+            })
+            .collect()
+    }
 
-    /// Get preffered accidental for given key.
-    fn get_pref_accidental(&self, key: &KeySignature) -> Option<Accidental> {
-        if SHARP_KEYS.iter().any(|x| x == key) {
-            Some(Accidental::Sharp)
-        } else if FLAT_KEYS.iter().any(|x| x == key) {
-            Some(Accidental::Flat)
-        } else {
-            None
+    fn key(
+        note_name: NoteName,
+        accidental: Option<Accidental>,
+        signature_type: KeySignatureType,
+    ) -> KeySignature {
+        KeySignature {
+            ext: ExtNoteName {
+                note_name,
+                accidental,
+            },
+            signature_type,
         }
     }
+
+    #[test]
+    fn spell_leading_tone() {
+        // Arrange
+        let gis_min = key(G, Some(Sharp), KeySignatureType::Min);
+        let pitches = [
+            Pitch::new(G, O4, None),
+            Pitch::new(A, O4, Some(Flat)),
+            Pitch::new(F, O4, None),
+        ];
+
+        // Act
+        let notes = sanitized(gis_min, &pitches);
+
+        // Assert
+        assert_eq!(notes[0], Pitch::new(F, O4, Some(DoubleSharp)).ext);
+        assert_eq!(notes[1], Pitch::new(G, O4, Some(Sharp)).ext);
+        assert_eq!(notes[2], Pitch::new(E, O4, Some(Sharp)).ext);
+    }
+
+    #[test]
+    fn spell_chromatic() {
+        // Arrange
+        let f_maj = key(F, None, KeySignatureType::Maj);
+        let c_maj = key(C, None, KeySignatureType::Maj);
+        let pitches = [
+            Pitch::new(C, O5, Some(Flat)),
+            Pitch::new(G, O4, Some(Sharp)),
+        ];
+
+        // Act
+        let f_notes = sanitized(f_maj, &pitches);
+        let c_notes = sanitized(c_maj, &pitches);
+
+        // Assert
+        assert_eq!(f_notes[0], Pitch::new(B, O4, None).ext);
+        assert_eq!(f_notes[1], Pitch::new(A, O4, Some(Flat)).ext);
+        assert_eq!(c_notes[0], Pitch::new(B, O4, None).ext);
+        assert_eq!(c_notes[1], Pitch::new(G, O4, Some(Sharp)).ext);
+    }
+
+    #[test]
+    fn spell_with_accidental() {
+        // Arrange
+        let d_maj = key(D, None, KeySignatureType::Maj);
+        let pitches = [
+            Pitch::new(F, O4, Some(Sharp)),
+            Pitch::new(A, O4, Some(Sharp)),
+            Pitch::new(G, O4, None),
+        ];
+
+        // Act
+        let notes = sanitized_with(d_maj, &pitches, Some(Flat));
+
+        // Assert
+        assert_eq!(notes[0], Pitch::new(G, O4, Some(Flat)).ext);
+        assert_eq!(notes[1], Pitch::new(B, O4, Some(Flat)).ext);
+        assert_eq!(notes[2], Pitch::new(G, O4, None).ext);
+    }
 }
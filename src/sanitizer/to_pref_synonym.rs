@@ -3,7 +3,7 @@
 //! ### Author
 //! Jakub Kloub
 
-use crate::notation::{Accidental, NoteName, Pitch};
+use crate::notation::{Accidental, Interval, NoteName, Pitch};
 
 pub trait ToPrefSynonym {
     /// Convert to preffered equivalent note with given accidental.
@@ -13,33 +13,28 @@ pub trait ToPrefSynonym {
 impl ToPrefSynonym for Pitch {
     /// Convert to preffered equivalent note with given accidental.
     ///
+    /// Synonyms differ by a diminished second, which keeps the pitch and moves the note name.
+    ///
     /// ## Example
     /// D-flat.to_pref_synonym(Sharp) gives C-sharp
     fn to_pref_synonym(&mut self, preffer: Option<Accidental>) {
         use Accidental::*;
         use NoteName::*;
 
-        match preffer {
-            Some(Sharp) => match (self.note_name(), self.accidental()) {
-                (C, Some(Flat)) => *self = Pitch::new(B, self.octave.try_prev().unwrap(), None),
-                (F, Some(Flat)) => *self = Pitch::new(E, self.octave, None),
-                (n, Some(Flat)) => *self = Pitch::new(n.prev(), self.octave, Some(Sharp)),
-                _ => {}
-            },
-            Some(Flat) => match (self.note_name(), self.accidental()) {
-                (B, Some(Sharp)) => *self = Pitch::new(C, self.octave.try_next().unwrap(), None),
-                (E, Some(Sharp)) => *self = Pitch::new(F, self.octave, None),
-                (n, Some(Sharp)) => *self = Pitch::new(n.next(), self.octave, Some(Flat)),
-                _ => {}
-            },
-            None => match (self.note_name(), self.accidental()) {
-                (E, Some(Sharp)) => *self = Pitch::new(F, self.octave, None),
-                (F, Some(Flat)) => *self = Pitch::new(E, self.octave, None),
-                (B, Some(Sharp)) => *self = Pitch::new(C, self.octave.try_next().unwrap(), None),
-                (C, Some(Flat)) => *self = Pitch::new(B, self.octave.try_prev().unwrap(), None),
-                _ => {}
-            },
+        let synonym = match (preffer, self.note_name(), self.accidental()) {
+            (_, _, Some(DoubleFlat)) | (Some(Sharp), _, Some(Flat)) | (None, C | F, Some(Flat)) => {
+                self.checked_sub(Interval::DIMINISHED_SECOND)
+            }
+            (_, _, Some(DoubleSharp))
+            | (Some(Flat), _, Some(Sharp))
+            | (None, B | E, Some(Sharp)) => self.checked_add(Interval::DIMINISHED_SECOND),
+            _ => None,
         };
+
+        // Pitch is kept, when its synonym is out of range.
+        if let Some(synonym) = synonym {
+            *self = synonym;
+        }
     }
 }
 
@@ -127,4 +122,22 @@ mod tests {
         assert!(pitch_eq(eis, (F, O4, None)));
         assert!(pitch_eq(f, (F, O4, None)));
     }
+
+    #[test]
+    fn double_accidentals() {
+        // Arrange
+        let mut fisis = Pitch::new(F, O4, Some(DoubleSharp));
+        let mut bisis = Pitch::new(B, O4, Some(DoubleSharp));
+        let mut eses = Pitch::new(E, O4, Some(DoubleFlat));
+
+        // Act
+        fisis.to_pref_synonym(Some(Sharp));
+        bisis.to_pref_synonym(None);
+        eses.to_pref_synonym(Some(Flat));
+
+        // Assert
+        assert!(pitch_eq(fisis, (G, O4, None)));
+        assert!(pitch_eq(bisis, (C, O5, Some(Sharp))));
+        assert!(pitch_eq(eses, (D, O4, None)));
+    }
 }
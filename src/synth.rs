@@ -0,0 +1,16 @@
+//! Module with the built-in SoundFont and additive synthesizer
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+mod additive_voice;
+mod audio_writer;
+mod effects;
+mod flac_encoder;
+mod sound_font;
+mod synthesizer;
+mod vorbis_encoder;
+
+pub use audio_writer::{AudioFormat, AudioWriter, BitDepth};
+pub use sound_font::{Preset, Region, SoundFont};
+pub use synthesizer::{Instrument, RenderSettings, Synthesizer, SAMPLE_RATE};
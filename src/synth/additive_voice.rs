@@ -0,0 +1,108 @@
+//! Additive voice definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use crate::midi::MidiNote;
+
+/// Number of the summed harmonics of the tone.
+const HARMONICS: usize = 8;
+
+/// Length of the linear attack in seconds.
+const ATTACK: f32 = 0.003;
+
+/// Time constant of the fade after the release in seconds.
+const RELEASE: f32 = 0.04;
+
+/// Level, below which the voice is inaudible and finishes.
+const SILENCE: f32 = 1e-4;
+
+/// Gain of a voice with the maximal velocity.
+const GAIN: f32 = 0.4;
+
+/// Piano-like tone made of decaying harmonics, which needs no samples.
+///
+/// Higher harmonics decay faster than the fundamental, so the tone gets softer over time,
+/// and lower keys ring longer than higher ones.
+#[derive(Debug, Clone)]
+pub(super) struct AdditiveVoice {
+    /// Phase and phase step of each harmonic per an output frame.
+    phases: [f32; HARMONICS],
+    steps: [f32; HARMONICS],
+    /// Current amplitude and its decay per an output frame of each harmonic.
+    amplitudes: [f32; HARMONICS],
+    decays: [f32; HARMONICS],
+    attack_frames: f32,
+    /// Decay of the level per an output frame after the release.
+    release_decay: f32,
+    /// Level of the release fade.
+    release_level: f32,
+    start: u64,
+    release: u64,
+    finished: bool,
+}
+
+impl AdditiveVoice {
+    pub(super) fn new(note: &MidiNote, sample_rate: u32) -> Self {
+        let sample_rate = sample_rate as f32;
+        let frequency = 440.0 * 2f32.powf((note.key as f32 - 69.0) / 12.0);
+        let velocity = note.velocity as f32 / 127.0;
+        // Middle C rings for about three seconds.
+        let ring = (3.0 * (261.6 / frequency).sqrt()).clamp(0.3, 8.0);
+
+        let harmonic_gain: f32 = (1..=HARMONICS).map(|n| (n as f32).powf(-1.5)).sum();
+        let mut voice = Self {
+            phases: [0.0; HARMONICS],
+            steps: [0.0; HARMONICS],
+            amplitudes: [0.0; HARMONICS],
+            decays: [0.0; HARMONICS],
+            attack_frames: ATTACK * sample_rate,
+            release_decay: (-1.0 / (RELEASE * sample_rate)).exp(),
+            release_level: 1.0,
+            start: (note.start * sample_rate as f64).round() as u64,
+            release: 0,
+            finished: false,
+        };
+        voice.release = ((note.end * sample_rate as f64).round() as u64).max(voice.start + 1);
+
+        for n in 0..HARMONICS {
+            let harmonic = (n + 1) as f32;
+            // Harmonics above the Nyquist frequency would alias.
+            if frequency * harmonic >= sample_rate / 2.0 {
+                break;
+            }
+
+            voice.steps[n] = frequency * harmonic / sample_rate * std::f32::consts::TAU;
+            voice.amplitudes[n] = harmonic.powf(-1.5) / harmonic_gain * velocity * velocity * GAIN;
+            voice.decays[n] = (-harmonic / (ring * sample_rate)).exp();
+        }
+
+        voice
+    }
+
+    pub(super) fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Mono sample of the voice at the frame, which advances the voice.
+    pub(super) fn sample(&mut self, frame: u64) -> f32 {
+        let elapsed = (frame - self.start) as f32;
+        let attack = (elapsed / self.attack_frames).min(1.0);
+        if frame >= self.release {
+            self.release_level *= self.release_decay;
+        }
+
+        let mut value = 0.0;
+        for n in 0..HARMONICS {
+            value += self.phases[n].sin() * self.amplitudes[n];
+            self.phases[n] = (self.phases[n] + self.steps[n]) % std::f32::consts::TAU;
+            self.amplitudes[n] *= self.decays[n];
+        }
+
+        if self.amplitudes[0] * self.release_level < SILENCE {
+            self.finished = true;
+        }
+
+        value * attack * self.release_level
+    }
+}
@@ -0,0 +1,242 @@
+//! Audio file writer definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use std::path::Path;
+
+use super::{flac_encoder::FlacEncoder, vorbis_encoder::VorbisEncoder};
+use crate::error::{AppError, Result};
+
+/// Peak level of the normalized audio, which is 1 dB below the full scale.
+const NORMALIZED_PEAK: f32 = 0.891;
+
+/// Format of the written audio file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum AudioFormat {
+    #[default]
+    Wav,
+    /// Lossless compressed audio.
+    Flac,
+    /// Lossy compressed audio.
+    OggVorbis,
+}
+
+impl AudioFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "wav",
+            AudioFormat::Flac => "flac",
+            AudioFormat::OggVorbis => "ogg",
+        }
+    }
+}
+
+/// Sample format of the written audio. OGG Vorbis ignores it and FLAC can't store the
+/// floating point samples, so it writes them as 24-bit integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum BitDepth {
+    #[default]
+    Int16,
+    Int24,
+    Float32,
+}
+
+impl BitDepth {
+    pub fn bits(&self) -> u32 {
+        match self {
+            BitDepth::Int16 => 16,
+            BitDepth::Int24 => 24,
+            BitDepth::Float32 => 32,
+        }
+    }
+}
+
+/// Writer of the stereo audio frames into WAV, FLAC and OGG Vorbis files.
+///
+/// ```
+/// use music_sheet_gen::synth::{AudioFormat, AudioWriter, BitDepth};
+///
+/// let writer = AudioWriter {
+///     format: AudioFormat::Flac,
+///     bit_depth: BitDepth::Int24,
+///     normalize: true,
+/// };
+/// let frames = vec![[0.25, -0.25]; 100];
+/// let flac = writer.write(&frames, 48000).unwrap();
+/// assert_eq!(&flac[..4], b"fLaC");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub struct AudioWriter {
+    pub format: AudioFormat,
+    pub bit_depth: BitDepth,
+    /// Whether to scale the audio, so its peak is just below the full scale.
+    pub normalize: bool,
+}
+
+impl AudioWriter {
+    /// Write the frames with the sample rate into the bytes of the audio file.
+    pub fn write(&self, frames: &[[f32; 2]], sample_rate: u32) -> Result<Vec<u8>> {
+        let gain = match self.normalize {
+            true => {
+                let peak = frames
+                    .iter()
+                    .flatten()
+                    .fold(0.0, |p: f32, s| p.max(s.abs()));
+                if peak > 0.0 {
+                    NORMALIZED_PEAK / peak
+                } else {
+                    1.0
+                }
+            }
+            false => 1.0,
+        };
+        let frames: Vec<[f32; 2]> = frames.iter().map(|f| f.map(|s| s * gain)).collect();
+
+        match self.format {
+            AudioFormat::Wav => Ok(self.wav(&frames, sample_rate)),
+            AudioFormat::Flac => {
+                let bits = self.bit_depth.bits().min(24);
+                let encoder = FlacEncoder { sample_rate, bits };
+                Ok(encoder.encode(&integers(&frames, bits)))
+            }
+            AudioFormat::OggVorbis => Ok(VorbisEncoder::new(sample_rate).encode(&frames)),
+        }
+    }
+
+    /// Write the frames into the audio file at the given path, which gets the extension of
+    /// the format, if it has none.
+    pub fn save(
+        &self,
+        frames: &[[f32; 2]],
+        sample_rate: u32,
+        path: impl AsRef<Path>,
+    ) -> Result<()> {
+        let path = path.as_ref();
+        let path = match path.extension() {
+            Some(_) => path.to_path_buf(),
+            None => path.with_extension(self.format.extension()),
+        };
+        let data = self.write(frames, sample_rate)?;
+        std::fs::write(&path, data).map_err(|e| AppError::build_path(&path, &e))?;
+
+        Ok(())
+    }
+
+    /// WAV file of the frames, which are not normalized.
+    pub(super) fn wav(&self, frames: &[[f32; 2]], sample_rate: u32) -> Vec<u8> {
+        let bits = self.bit_depth.bits();
+
+        let data: Vec<u8> = match self.bit_depth {
+            BitDepth::Float32 => frames
+                .iter()
+                .flatten()
+                .flat_map(|s| s.to_le_bytes())
+                .collect(),
+            _ => integers(frames, bits)
+                .iter()
+                .flatten()
+                .flat_map(|s| s.to_le_bytes()[..bits as usize / 8].to_vec())
+                .collect(),
+        };
+        let block_align = 2 * bits / 8;
+        // Integer PCM or IEEE floating point format.
+        let format: u16 = match self.bit_depth {
+            BitDepth::Float32 => 3,
+            _ => 1,
+        };
+
+        let mut wav = b"RIFF".to_vec();
+        wav.extend((36 + data.len() as u32).to_le_bytes());
+        wav.extend(b"WAVEfmt ");
+        wav.extend(16u32.to_le_bytes());
+        wav.extend(format.to_le_bytes());
+        wav.extend(2u16.to_le_bytes());
+        wav.extend(sample_rate.to_le_bytes());
+        wav.extend((sample_rate * block_align).to_le_bytes());
+        wav.extend((block_align as u16).to_le_bytes());
+        wav.extend((bits as u16).to_le_bytes());
+        wav.extend(b"data");
+        wav.extend((data.len() as u32).to_le_bytes());
+        wav.extend(data);
+
+        wav
+    }
+}
+
+/// Integer samples of the frames with the number of bits, which clip the samples outside
+/// of the full scale.
+fn integers(frames: &[[f32; 2]], bits: u32) -> Vec<[i32; 2]> {
+    let max = ((1 << (bits - 1)) - 1) as f32;
+    frames
+        .iter()
+        .map(|f| f.map(|s| (s.clamp(-1.0, 1.0) * max).round() as i32))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn writer(format: AudioFormat, bit_depth: BitDepth, normalize: bool) -> AudioWriter {
+        AudioWriter {
+            format,
+            bit_depth,
+            normalize,
+        }
+    }
+
+    #[test]
+    fn write_wav_bit_depths() {
+        // Arrange
+        let frames = vec![[0.5, -0.5]; 10];
+
+        // Act
+        let int16 = writer(AudioFormat::Wav, BitDepth::Int16, false).write(&frames, 22050);
+        let int24 = writer(AudioFormat::Wav, BitDepth::Int24, true).write(&frames, 96000);
+        let float = writer(AudioFormat::Wav, BitDepth::Float32, false).write(&frames, 48000);
+
+        // Assert
+        let (int16, int24, float) = (int16.unwrap(), int24.unwrap(), float.unwrap());
+        assert_eq!((int16.len(), int24.len(), float.len()), (84, 104, 124));
+        assert_eq!(u32::from_le_bytes(int24[24..28].try_into().unwrap()), 96000);
+        assert_eq!((int24[34], float[20], float[34]), (24, 3, 32));
+        assert_eq!(i16::from_le_bytes([int16[44], int16[45]]), 16384);
+        // Normalized peak is 1 dB below the full scale.
+        assert_eq!(&int24[44..47], &[0x49, 0x0C, 0x72]);
+        assert_eq!(f32::from_le_bytes(float[48..52].try_into().unwrap()), -0.5);
+    }
+
+    #[test]
+    fn write_flac() {
+        // Arrange
+        let frames: Vec<[f32; 2]> = (0..5000)
+            .map(|i| [(i as f32 / 10.0).sin() * 0.5, 0.0])
+            .collect();
+
+        // Act
+        let flac = writer(AudioFormat::Flac, BitDepth::Float32, false).write(&frames, 44100);
+
+        // Assert
+        let flac = flac.unwrap();
+        let mut reader = claxon::FlacReader::new(&flac[..]).unwrap();
+        assert_eq!(reader.streaminfo().bits_per_sample, 24);
+        let samples: Vec<i32> = reader.samples().map(|s| s.unwrap()).collect();
+        assert_eq!(samples.len(), 10000);
+        assert_eq!(samples[2], (0.1f32.sin() * 0.5 * 8388607.0).round() as i32);
+    }
+
+    #[test]
+    fn write_ogg_vorbis() {
+        // Arrange
+        let frames: Vec<[f32; 2]> = (0..5000).map(|i| [(i as f32 / 10.0).sin(), 0.0]).collect();
+
+        // Act
+        let ogg = writer(AudioFormat::OggVorbis, BitDepth::Int16, true).write(&frames, 22050);
+
+        // Assert
+        let ogg = ogg.unwrap();
+        let reader = lewton::inside_ogg::OggStreamReader::new(std::io::Cursor::new(ogg)).unwrap();
+        assert_eq!(reader.ident_hdr.audio_sample_rate, 22050);
+    }
+}
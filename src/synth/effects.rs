@@ -0,0 +1,218 @@
+//! Audio effects definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+/// Sample rate, for which the delays of the reverb are tuned.
+const TUNING_RATE: f32 = 44100.0;
+
+/// Delays of the comb filters of the reverb in frames.
+const COMB_DELAYS: [usize; 4] = [1116, 1188, 1277, 1356];
+const COMB_FEEDBACK: f32 = 0.84;
+const COMB_DAMPING: f32 = 0.2;
+
+/// Delays of the all-pass filters of the reverb in frames.
+const ALLPASS_DELAYS: [usize; 2] = [556, 441];
+const ALLPASS_FEEDBACK: f32 = 0.5;
+
+/// Delay of the right channel filters, which widens the reverb.
+const STEREO_SPREAD: usize = 23;
+const REVERB_WET: f32 = 0.05;
+
+/// Base delay, depth and rate of the modulated chorus delay.
+const CHORUS_DELAY: f32 = 0.02;
+const CHORUS_DEPTH: f32 = 0.003;
+const CHORUS_RATE: f32 = 0.8;
+const CHORUS_WET: f32 = 0.5;
+
+/// Length of the reverb tail after the last note in seconds.
+const REVERB_TAIL: f32 = 2.0;
+
+/// Delay line of a filter.
+#[derive(Debug, Clone)]
+struct DelayLine {
+    buffer: Vec<f32>,
+    position: usize,
+}
+
+impl DelayLine {
+    fn new(len: usize) -> Self {
+        Self {
+            buffer: vec![0.0; len.max(1)],
+            position: 0,
+        }
+    }
+
+    /// Delayed value, which is replaced by the input.
+    fn shift(&mut self, input: f32) -> f32 {
+        let output = self.buffer[self.position];
+        self.buffer[self.position] = input;
+        self.position = (self.position + 1) % self.buffer.len();
+        output
+    }
+}
+
+/// Low-pass feedback comb filter.
+#[derive(Debug, Clone)]
+struct Comb {
+    line: DelayLine,
+    filtered: f32,
+}
+
+impl Comb {
+    fn process(&mut self, input: f32) -> f32 {
+        let output = self.line.buffer[self.line.position];
+        self.filtered = output * (1.0 - COMB_DAMPING) + self.filtered * COMB_DAMPING;
+        self.line.shift(input + self.filtered * COMB_FEEDBACK);
+        output
+    }
+}
+
+/// Schroeder all-pass filter.
+#[derive(Debug, Clone)]
+struct AllPass {
+    line: DelayLine,
+}
+
+impl AllPass {
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.line.buffer[self.line.position];
+        self.line.shift(input + delayed * ALLPASS_FEEDBACK);
+        delayed - input
+    }
+}
+
+/// Reverb of one channel made of parallel comb filters and serial all-pass filters.
+#[derive(Debug, Clone)]
+struct Reverb {
+    combs: Vec<Comb>,
+    all_passes: Vec<AllPass>,
+}
+
+impl Reverb {
+    fn new(sample_rate: u32, spread: usize) -> Self {
+        let scaled =
+            |delay: usize| ((delay + spread) as f32 * sample_rate as f32 / TUNING_RATE) as usize;
+
+        Self {
+            combs: COMB_DELAYS
+                .iter()
+                .map(|d| Comb {
+                    line: DelayLine::new(scaled(*d)),
+                    filtered: 0.0,
+                })
+                .collect(),
+            all_passes: ALLPASS_DELAYS
+                .iter()
+                .map(|d| AllPass {
+                    line: DelayLine::new(scaled(*d)),
+                })
+                .collect(),
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let combed = self.combs.iter_mut().map(|c| c.process(input)).sum();
+        self.all_passes
+            .iter_mut()
+            .fold(combed, |value, all_pass| all_pass.process(value))
+    }
+}
+
+/// Chorus of one channel mixing the input with its delay modulated by a sine.
+#[derive(Debug, Clone)]
+struct Chorus {
+    buffer: Vec<f32>,
+    position: usize,
+    phase: f32,
+    step: f32,
+    sample_rate: f32,
+}
+
+impl Chorus {
+    fn new(sample_rate: u32, phase: f32) -> Self {
+        let sample_rate = sample_rate as f32;
+        let len = ((CHORUS_DELAY + CHORUS_DEPTH) * sample_rate) as usize + 2;
+
+        Self {
+            buffer: vec![0.0; len],
+            position: 0,
+            phase,
+            step: CHORUS_RATE / sample_rate * std::f32::consts::TAU,
+            sample_rate,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        self.buffer[self.position] = input;
+
+        let delay = (CHORUS_DELAY + CHORUS_DEPTH * self.phase.sin()) * self.sample_rate;
+        let read = self.position as f32 - delay + self.buffer.len() as f32;
+        let index = read as usize;
+        let fraction = read - index as f32;
+        let len = self.buffer.len();
+        let delayed =
+            self.buffer[index % len] * (1.0 - fraction) + self.buffer[(index + 1) % len] * fraction;
+
+        self.position = (self.position + 1) % len;
+        self.phase = (self.phase + self.step) % std::f32::consts::TAU;
+
+        input * (1.0 - CHORUS_WET) + delayed * CHORUS_WET
+    }
+}
+
+/// Stereo effects applied to the rendered audio.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Effects {
+    reverb: Option<[Reverb; 2]>,
+    chorus: Option<[Chorus; 2]>,
+}
+
+impl Effects {
+    pub fn new(reverb: bool, chorus: bool, sample_rate: u32) -> Self {
+        Self {
+            reverb: reverb.then(|| {
+                [
+                    Reverb::new(sample_rate, 0),
+                    Reverb::new(sample_rate, STEREO_SPREAD),
+                ]
+            }),
+            chorus: chorus.then(|| {
+                [
+                    Chorus::new(sample_rate, 0.0),
+                    Chorus::new(sample_rate, std::f32::consts::FRAC_PI_2),
+                ]
+            }),
+        }
+    }
+
+    /// Number of frames, which the effects sound after the input ends.
+    pub fn tail(&self, sample_rate: u32) -> u64 {
+        let reverb = match self.reverb {
+            Some(_) => REVERB_TAIL,
+            None => 0.0,
+        };
+        let chorus = match self.chorus {
+            Some(_) => CHORUS_DELAY + CHORUS_DEPTH,
+            None => 0.0,
+        };
+
+        ((reverb + chorus) * sample_rate as f32) as u64
+    }
+
+    pub fn process(&mut self, frame: [f32; 2]) -> [f32; 2] {
+        let mut frame = frame;
+        if let Some(chorus) = self.chorus.as_mut() {
+            frame = [chorus[0].process(frame[0]), chorus[1].process(frame[1])];
+        }
+        if let Some(reverb) = self.reverb.as_mut() {
+            let input = (frame[0] + frame[1]) / 2.0;
+            frame = [
+                frame[0] + reverb[0].process(input) * REVERB_WET,
+                frame[1] + reverb[1].process(input) * REVERB_WET,
+            ];
+        }
+
+        frame
+    }
+}
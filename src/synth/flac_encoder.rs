@@ -0,0 +1,308 @@
+//! FLAC encoder definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+/// Number of the frames of a channel encoded in a FLAC frame.
+const BLOCK_SIZE: usize = 4096;
+
+/// Highest order of the fixed predictors.
+const MAX_ORDER: usize = 4;
+
+/// Highest Rice parameter, which is written in 4 bits.
+const MAX_RICE_PARAMETER: u32 = 14;
+
+/// Writer of the values with any number of bits, the most significant bit first.
+#[derive(Debug, Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    /// Bits, which don't make a whole byte yet.
+    pending: u64,
+    pending_len: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u64, len: u32) {
+        for i in (0..len).rev() {
+            self.pending = (self.pending << 1) | ((value >> i) & 1);
+            self.pending_len += 1;
+            if self.pending_len == 8 {
+                self.bytes.push(self.pending as u8);
+                self.pending = 0;
+                self.pending_len = 0;
+            }
+        }
+    }
+
+    fn write_signed(&mut self, value: i64, len: u32) {
+        self.write(value as u64 & ((1 << len) - 1), len);
+    }
+
+    /// Write the zeros of the unary value ended by a one.
+    fn write_unary(&mut self, value: u64) {
+        for _ in 0..value {
+            self.write(0, 1);
+        }
+        self.write(1, 1);
+    }
+
+    /// Pad the bits with zeros to a whole byte.
+    fn align(&mut self) {
+        if self.pending_len > 0 {
+            self.write(0, 8 - self.pending_len);
+        }
+    }
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |crc, byte| {
+        (0..8).fold(crc ^ byte, |crc, _| match crc & 0x80 {
+            0 => crc << 1,
+            _ => (crc << 1) ^ 0x07,
+        })
+    })
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, byte| {
+        (0..8).fold(crc ^ ((*byte as u16) << 8), |crc, _| match crc & 0x8000 {
+            0 => crc << 1,
+            _ => (crc << 1) ^ 0x8005,
+        })
+    })
+}
+
+/// Frame number coded like a UTF-8 character.
+fn utf8_number(number: u64) -> Vec<u8> {
+    if number < 0x80 {
+        return vec![number as u8];
+    }
+
+    let mut continuation = vec![];
+    let mut rest = number;
+    // Bits of the value, which fit into the first byte with the given number of bytes.
+    while rest >= 1 << (6 - continuation.len()) {
+        continuation.push(0x80 | (rest & 0x3F) as u8);
+        rest >>= 6;
+    }
+    let len = continuation.len() + 1;
+    let first = (0xFF00u16 >> len) as u8 | rest as u8;
+
+    std::iter::once(first)
+        .chain(continuation.into_iter().rev())
+        .collect()
+}
+
+/// Residual of the fixed predictor with the order.
+fn residual(samples: &[i64], order: usize) -> Vec<i64> {
+    samples[order..]
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| {
+            let s = &samples[i..i + order];
+            sample
+                - match order {
+                    0 => 0,
+                    1 => s[0],
+                    2 => 2 * s[1] - s[0],
+                    3 => 3 * s[2] - 3 * s[1] + s[0],
+                    _ => 4 * s[3] - 6 * s[2] + 4 * s[1] - s[0],
+                }
+        })
+        .collect()
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Best Rice parameter and the number of bits of the residual coded with it.
+fn rice_parameter(residual: &[i64]) -> (u32, u64) {
+    (0..=MAX_RICE_PARAMETER)
+        .map(|k| {
+            let bits = residual
+                .iter()
+                .map(|r| (zigzag(*r) >> k) + 1 + k as u64)
+                .sum();
+            (k, bits)
+        })
+        .min_by_key(|(_, bits)| *bits)
+        .unwrap_or((0, 0))
+}
+
+/// Encoder of the stereo audio into a FLAC stream with the fixed predictors.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct FlacEncoder {
+    pub sample_rate: u32,
+    /// Bits per sample, 16 or 24.
+    pub bits: u32,
+}
+
+impl FlacEncoder {
+    /// Encode the interleaved integer samples of the two channels.
+    pub fn encode(&self, frames: &[[i32; 2]]) -> Vec<u8> {
+        let mut out = b"fLaC".to_vec();
+
+        // Last metadata block is the stream info.
+        let mut info = BitWriter::default();
+        info.write(1, 1);
+        info.write(0, 7);
+        info.write(34, 24);
+        info.write(BLOCK_SIZE as u64, 16);
+        info.write(BLOCK_SIZE as u64, 16);
+        info.write(0, 24);
+        info.write(0, 24);
+        info.write(self.sample_rate as u64, 20);
+        info.write(2 - 1, 3);
+        info.write(self.bits as u64 - 1, 5);
+        info.write(frames.len() as u64, 36);
+        // Unknown MD5 signature of the audio.
+        info.write(0, 64);
+        info.write(0, 64);
+        out.extend(info.bytes);
+
+        for (number, block) in frames.chunks(BLOCK_SIZE).enumerate() {
+            out.extend(self.frame(number as u64, block));
+        }
+
+        out
+    }
+
+    /// Code of the sample rate in the frame header, which refers to the stream info for
+    /// the uncommon sample rates.
+    fn sample_rate_code(&self) -> u64 {
+        match self.sample_rate {
+            88200 => 0b0001,
+            176400 => 0b0010,
+            192000 => 0b0011,
+            8000 => 0b0100,
+            16000 => 0b0101,
+            22050 => 0b0110,
+            24000 => 0b0111,
+            32000 => 0b1000,
+            44100 => 0b1001,
+            48000 => 0b1010,
+            96000 => 0b1011,
+            _ => 0b0000,
+        }
+    }
+
+    fn frame(&self, number: u64, block: &[[i32; 2]]) -> Vec<u8> {
+        let mut header = BitWriter::default();
+        header.write(0b11111111111110, 14);
+        // Reserved bit and the fixed block size.
+        header.write(0, 2);
+        // Block size stored at the end of the header.
+        header.write(0b0111, 4);
+        header.write(self.sample_rate_code(), 4);
+        // Independent left and right channel.
+        header.write(0b0001, 4);
+        header.write(
+            match self.bits {
+                16 => 0b100,
+                _ => 0b110,
+            },
+            3,
+        );
+        header.write(0, 1);
+        for byte in utf8_number(number) {
+            header.write(byte as u64, 8);
+        }
+        header.write(block.len() as u64 - 1, 16);
+        let crc = crc8(&header.bytes);
+        header.write(crc as u64, 8);
+
+        let mut frame = header;
+        for channel in 0..2 {
+            let samples: Vec<i64> = block.iter().map(|f| f[channel] as i64).collect();
+            self.subframe(&mut frame, &samples);
+        }
+        frame.align();
+        let crc = crc16(&frame.bytes);
+        frame.write(crc as u64, 16);
+
+        frame.bytes
+    }
+
+    /// Write the subframe with the fixed predictor, which codes the samples in the least
+    /// bits, or the verbatim samples, if no predictor saves any bits.
+    fn subframe(&self, out: &mut BitWriter, samples: &[i64]) {
+        let verbatim_bits = samples.len() as u64 * self.bits as u64;
+        let best = (0..=MAX_ORDER.min(samples.len().saturating_sub(1)))
+            .map(|order| {
+                let residual = residual(samples, order);
+                let (parameter, bits) = rice_parameter(&residual);
+                let bits = bits + order as u64 * self.bits as u64 + 10;
+                (order, residual, parameter, bits)
+            })
+            .min_by_key(|(.., bits)| *bits)
+            .filter(|(.., bits)| *bits < verbatim_bits);
+
+        // Zero padding bit and no wasted bits surround the subframe type.
+        out.write(0, 1);
+        match best {
+            Some((order, residual, parameter, _)) => {
+                out.write(0b001000 | order as u64, 6);
+                out.write(0, 1);
+                for sample in &samples[..order] {
+                    out.write_signed(*sample, self.bits);
+                }
+                // Rice coding with 4-bit parameters in a single partition.
+                out.write(0, 2);
+                out.write(0, 4);
+                out.write(parameter as u64, 4);
+                for value in residual {
+                    let value = zigzag(value);
+                    out.write_unary(value >> parameter);
+                    out.write(value, parameter);
+                }
+            }
+            None => {
+                out.write(0b000001, 6);
+                out.write(0, 1);
+                for sample in samples {
+                    out.write_signed(*sample, self.bits);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_numbers() {
+        assert_eq!(utf8_number(0x45), vec![0x45]);
+        assert_eq!(utf8_number(0xE9), vec![0xC3, 0xA9]);
+        assert_eq!(utf8_number(0x20AC), vec![0xE2, 0x82, 0xAC]);
+    }
+
+    #[test]
+    fn encode_decodable_stream() {
+        // Arrange
+        let frames: Vec<[i32; 2]> = (0..10000)
+            .map(|i| {
+                let sine = (i as f32 / 20.0).sin() * 30000.0;
+                [sine as i32, if i % 7 == 0 { -32768 } else { i % 500 }]
+            })
+            .collect();
+        let encoder = FlacEncoder {
+            sample_rate: 48000,
+            bits: 16,
+        };
+
+        // Act
+        let flac = encoder.encode(&frames);
+
+        // Assert
+        let mut reader = claxon::FlacReader::new(&flac[..]).unwrap();
+        let info = reader.streaminfo();
+        assert_eq!((info.sample_rate, info.channels), (48000, 2));
+        assert_eq!(info.samples, Some(10000));
+        let samples: Vec<i32> = reader.samples().map(|s| s.unwrap()).collect();
+        assert_eq!(samples, frames.concat());
+        assert!(flac.len() < frames.len() * 4);
+    }
+}
@@ -0,0 +1,615 @@
+//! SoundFont definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use std::path::Path;
+
+use crate::error::{AppError, Result};
+
+/// Generators of SoundFont 2.04, which are used by the synthesizer.
+mod generator {
+    pub const START_OFFSET: usize = 0;
+    pub const END_OFFSET: usize = 1;
+    pub const LOOP_START_OFFSET: usize = 2;
+    pub const LOOP_END_OFFSET: usize = 3;
+    pub const START_COARSE_OFFSET: usize = 4;
+    pub const END_COARSE_OFFSET: usize = 12;
+    pub const PAN: usize = 17;
+    pub const DELAY_VOL_ENV: usize = 33;
+    pub const ATTACK_VOL_ENV: usize = 34;
+    pub const HOLD_VOL_ENV: usize = 35;
+    pub const DECAY_VOL_ENV: usize = 36;
+    pub const SUSTAIN_VOL_ENV: usize = 37;
+    pub const RELEASE_VOL_ENV: usize = 38;
+    pub const INSTRUMENT: usize = 41;
+    pub const KEY_RANGE: usize = 43;
+    pub const VEL_RANGE: usize = 44;
+    pub const LOOP_START_COARSE_OFFSET: usize = 45;
+    pub const KEYNUM: usize = 46;
+    pub const VELOCITY: usize = 47;
+    pub const INITIAL_ATTENUATION: usize = 48;
+    pub const LOOP_END_COARSE_OFFSET: usize = 50;
+    pub const COARSE_TUNE: usize = 51;
+    pub const FINE_TUNE: usize = 52;
+    pub const SAMPLE_ID: usize = 53;
+    pub const SAMPLE_MODES: usize = 54;
+    pub const SCALE_TUNING: usize = 56;
+    pub const OVERRIDING_ROOT_KEY: usize = 58;
+
+    /// Number of the generators defined by the specification.
+    pub const COUNT: usize = 61;
+
+    /// Generators, which the preset zones add to the instrument zones.
+    pub const ADDITIVE: [usize; 11] = [
+        PAN,
+        DELAY_VOL_ENV,
+        ATTACK_VOL_ENV,
+        HOLD_VOL_ENV,
+        DECAY_VOL_ENV,
+        SUSTAIN_VOL_ENV,
+        RELEASE_VOL_ENV,
+        INITIAL_ATTENUATION,
+        COARSE_TUNE,
+        FINE_TUNE,
+        SCALE_TUNING,
+    ];
+}
+
+use generator as gen;
+
+/// Generator values of a zone. Unset generators keep their default value.
+#[derive(Debug, Clone)]
+struct Zone([Option<i16>; gen::COUNT]);
+
+impl Default for Zone {
+    fn default() -> Self {
+        Self([None; gen::COUNT])
+    }
+}
+
+impl Zone {
+    fn get(&self, generator: usize) -> Option<i16> {
+        self.0[generator]
+    }
+
+    /// Low and high value of the range generator.
+    fn range(&self, generator: usize) -> (u8, u8) {
+        match self.get(generator) {
+            Some(range) => {
+                let [low, high] = range.to_le_bytes();
+                (low, high)
+            }
+            None => (0, 127),
+        }
+    }
+
+    fn contains(&self, key: u8, velocity: u8) -> bool {
+        let (key_low, key_high) = self.range(gen::KEY_RANGE);
+        let (vel_low, vel_high) = self.range(gen::VEL_RANGE);
+
+        (key_low..=key_high).contains(&key) && (vel_low..=vel_high).contains(&velocity)
+    }
+
+    /// Values of the zone, which override the values of the global zone.
+    fn merged(&self, global: &Zone) -> Zone {
+        Zone(std::array::from_fn(|i| self.0[i].or(global.0[i])))
+    }
+}
+
+/// Header of a sample in the sample data.
+#[derive(Debug, Clone, Copy)]
+struct SampleHeader {
+    start: u32,
+    end: u32,
+    loop_start: u32,
+    loop_end: u32,
+    sample_rate: u32,
+    original_key: u8,
+    /// Pitch correction in cents.
+    correction: i8,
+}
+
+/// Preset selected by the bank and the program of a MIDI channel.
+#[derive(Debug, Clone)]
+pub struct Preset {
+    pub name: String,
+    pub bank: u16,
+    pub program: u16,
+    zones: Vec<Zone>,
+}
+
+/// Sample with the generator values, which plays a note.
+#[derive(Debug, Clone, Copy)]
+pub struct Region {
+    /// Played sample range in the sample data.
+    pub start: usize,
+    pub end: usize,
+    /// Loop of the sample, if it loops.
+    pub sample_loop: Option<(usize, usize)>,
+    /// Whether the sample plays to its end after the release.
+    pub loop_until_release: bool,
+    pub sample_rate: u32,
+    /// Key, in which the sample sounds at its original pitch.
+    pub root_key: u8,
+    /// Tuning in cents.
+    pub tune: i32,
+    /// Change of the pitch in cents by a key.
+    pub scale_tuning: i32,
+    /// Attenuation in centibels.
+    pub attenuation: f32,
+    /// Pan from -500 (left) to 500 (right).
+    pub pan: f32,
+    /// Volume envelope delay, attack, hold, decay and release in seconds.
+    pub delay: f32,
+    pub attack: f32,
+    pub hold: f32,
+    pub decay: f32,
+    pub release: f32,
+    /// Sustain level attenuation in centibels.
+    pub sustain: f32,
+    /// Fixed key and velocity of the region.
+    pub key: Option<u8>,
+    pub velocity: Option<u8>,
+}
+
+/// Seconds of the time in timecents.
+fn seconds(timecents: i32) -> f32 {
+    2f32.powf(timecents as f32 / 1200.0)
+}
+
+/// Parsed SoundFont 2 file with its samples.
+///
+/// Modulators and filters are ignored, notes are played by the samples with the volume
+/// envelope.
+#[derive(Debug, Clone)]
+pub struct SoundFont {
+    samples: Vec<i16>,
+    sample_headers: Vec<SampleHeader>,
+    presets: Vec<Preset>,
+    instruments: Vec<Vec<Zone>>,
+}
+
+/// Chunks of the RIFF data as their identifier and content.
+fn chunks(mut data: &[u8]) -> Result<Vec<(&[u8], &[u8])>> {
+    let mut chunks = vec![];
+    while data.len() >= 8 {
+        let len = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+        let content = data
+            .get(8..8 + len)
+            .ok_or(AppError::SoundFont("Chunk exceeds the file".to_owned()))?;
+        chunks.push((&data[..4], content));
+
+        // Chunks are padded to even length.
+        data = data.get(8 + len + len % 2..).unwrap_or_default();
+    }
+
+    Ok(chunks)
+}
+
+/// Content of the chunk with the identifier.
+fn chunk<'a>(chunks: &[(&[u8], &'a [u8])], id: &[u8]) -> Result<&'a [u8]> {
+    let chunk = chunks.iter().find(|(i, _)| *i == id).map(|(_, c)| *c);
+
+    Ok(chunk.ok_or(AppError::SoundFont(format!(
+        "Missing chunk '{}'",
+        String::from_utf8_lossy(id)
+    )))?)
+}
+
+/// Content of the list with the type.
+fn list<'a>(chunks: &[(&[u8], &'a [u8])], list_type: &[u8]) -> Result<&'a [u8]> {
+    let list = chunks
+        .iter()
+        .find(|(id, c)| *id == b"LIST" && c.get(..4) == Some(list_type))
+        .map(|(_, c)| &c[4..]);
+
+    Ok(list.ok_or(AppError::SoundFont(format!(
+        "Missing list '{}'",
+        String::from_utf8_lossy(list_type)
+    )))?)
+}
+
+fn u16_at(record: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([record[offset], record[offset + 1]])
+}
+
+fn u32_at(record: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(record[offset..offset + 4].try_into().unwrap())
+}
+
+fn name(record: &[u8]) -> String {
+    let name = &record[..20];
+    let len = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+
+    String::from_utf8_lossy(&name[..len]).trim().to_owned()
+}
+
+/// Records of the chunk with the given size. The last terminal record is kept.
+fn records<'a>(data: &'a [u8], size: usize, id: &str) -> Result<Vec<&'a [u8]>> {
+    if data.len() % size != 0 || data.len() < size {
+        Err(AppError::SoundFont(format!(
+            "Chunk '{id}' has invalid size"
+        )))?;
+    }
+
+    Ok(data.chunks_exact(size).collect())
+}
+
+/// Zones of the headers with bag indices. Each header ends where the next one starts.
+fn zones(bag_starts: &[u16], bags: &[&[u8]], generators: &[&[u8]]) -> Result<Vec<Vec<Zone>>> {
+    let invalid = || AppError::SoundFont("Zone index exceeds the list".to_owned());
+
+    let mut headers = vec![];
+    for window in bag_starts.windows(2) {
+        let (first, last) = (window[0] as usize, window[1] as usize);
+        let mut zones = vec![];
+        for bag in first..last {
+            let start = u16_at(bags.get(bag).ok_or_else(invalid)?, 0) as usize;
+            let end = u16_at(bags.get(bag + 1).ok_or_else(invalid)?, 0) as usize;
+
+            let mut zone = Zone::default();
+            for generator in generators.get(start..end).ok_or_else(invalid)? {
+                let operator = u16_at(generator, 0) as usize;
+                if operator < gen::COUNT {
+                    zone.0[operator] = Some(i16::from_le_bytes([generator[2], generator[3]]));
+                }
+            }
+            zones.push(zone);
+        }
+        headers.push(zones);
+    }
+
+    Ok(headers)
+}
+
+/// Local zones with the values of the global zone, which is the first zone without the
+/// terminal generator.
+fn merge_global(zones: Vec<Zone>, terminal: usize) -> Vec<Zone> {
+    let global = match zones.first() {
+        Some(first) if first.get(terminal).is_none() => first.clone(),
+        _ => Zone::default(),
+    };
+
+    zones
+        .iter()
+        .filter(|z| z.get(terminal).is_some())
+        .map(|z| z.merged(&global))
+        .collect()
+}
+
+impl SoundFont {
+    /// Parse the SoundFont from the bytes of a SF2 file.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.get(..4) != Some(b"RIFF") || data.get(8..12) != Some(b"sfbk") {
+            Err(AppError::SoundFont("File is not a SoundFont 2".to_owned()))?;
+        }
+        let riff = chunks(&data[12..])?;
+
+        let smpl = chunk(&chunks(list(&riff, b"sdta")?)?, b"smpl")?;
+        let samples = smpl
+            .chunks_exact(2)
+            .map(|s| i16::from_le_bytes([s[0], s[1]]))
+            .collect();
+
+        let pdta = chunks(list(&riff, b"pdta")?)?;
+        let phdr = records(chunk(&pdta, b"phdr")?, 38, "phdr")?;
+        let pbag = records(chunk(&pdta, b"pbag")?, 4, "pbag")?;
+        let pgen = records(chunk(&pdta, b"pgen")?, 4, "pgen")?;
+        let inst = records(chunk(&pdta, b"inst")?, 22, "inst")?;
+        let ibag = records(chunk(&pdta, b"ibag")?, 4, "ibag")?;
+        let igen = records(chunk(&pdta, b"igen")?, 4, "igen")?;
+        let shdr = records(chunk(&pdta, b"shdr")?, 46, "shdr")?;
+
+        let preset_bags: Vec<u16> = phdr.iter().map(|r| u16_at(r, 24)).collect();
+        let preset_zones = zones(&preset_bags, &pbag, &pgen)?;
+        let instrument_bags: Vec<u16> = inst.iter().map(|r| u16_at(r, 20)).collect();
+        let instrument_zones = zones(&instrument_bags, &ibag, &igen)?;
+
+        // Last headers are the terminal records.
+        let sample_headers: Vec<SampleHeader> = shdr[..shdr.len() - 1]
+            .iter()
+            .map(|r| SampleHeader {
+                start: u32_at(r, 20),
+                end: u32_at(r, 24),
+                loop_start: u32_at(r, 28),
+                loop_end: u32_at(r, 32),
+                sample_rate: u32_at(r, 36),
+                original_key: r[40],
+                correction: r[41] as i8,
+            })
+            .collect();
+
+        let instruments: Vec<Vec<Zone>> = instrument_zones
+            .into_iter()
+            .map(|z| merge_global(z, gen::SAMPLE_ID))
+            .collect();
+        let presets: Vec<Preset> = phdr
+            .iter()
+            .zip(preset_zones)
+            .map(|(record, zones)| Preset {
+                name: name(record),
+                program: u16_at(record, 20),
+                bank: u16_at(record, 22),
+                zones: merge_global(zones, gen::INSTRUMENT),
+            })
+            .collect();
+
+        let instrument_missing = presets.iter().flat_map(|p| p.zones.iter()).any(|z| {
+            z.get(gen::INSTRUMENT).unwrap_or_default() as u16 as usize >= instruments.len()
+        });
+        let sample_missing = instruments.iter().flatten().any(|z| {
+            z.get(gen::SAMPLE_ID).unwrap_or_default() as u16 as usize >= sample_headers.len()
+        });
+        if instrument_missing || sample_missing {
+            Err(AppError::SoundFont(
+                "Zone refers to a missing instrument or sample".to_owned(),
+            ))?;
+        }
+        if presets.is_empty() {
+            Err(AppError::SoundFont("SoundFont has no presets".to_owned()))?;
+        }
+
+        Ok(Self {
+            samples,
+            sample_headers,
+            presets,
+            instruments,
+        })
+    }
+
+    /// Load the SoundFont from the SF2 file at the given path.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|e| AppError::build_path(path, &e))?;
+
+        Self::parse(&data)
+    }
+
+    pub fn presets(&self) -> &[Preset] {
+        &self.presets
+    }
+
+    /// Sample data of all the samples.
+    pub fn samples(&self) -> &[i16] {
+        &self.samples
+    }
+
+    /// Preset of the bank and the program. Missing programs fall back to the first
+    /// program of the bank and then to the first preset.
+    pub fn preset(&self, bank: u16, program: u16) -> &Preset {
+        let find = |predicate: &dyn Fn(&Preset) -> bool| self.presets.iter().find(|p| predicate(p));
+
+        find(&|p| p.bank == bank && p.program == program)
+            .or_else(|| find(&|p| p.bank == bank))
+            .unwrap_or(&self.presets[0])
+    }
+
+    /// Regions of the preset, which play the key with the velocity.
+    pub fn regions(&self, preset: &Preset, key: u8, velocity: u8) -> Vec<Region> {
+        let mut regions = vec![];
+        for preset_zone in preset.zones.iter().filter(|z| z.contains(key, velocity)) {
+            let instrument = preset_zone.get(gen::INSTRUMENT).unwrap_or_default() as u16;
+            let instrument_zones = &self.instruments[instrument as usize];
+
+            for zone in instrument_zones
+                .iter()
+                .filter(|z| z.contains(key, velocity))
+            {
+                if let Some(region) = self.region(zone, preset_zone) {
+                    regions.push(region);
+                }
+            }
+        }
+
+        regions
+    }
+
+    /// Region of the instrument zone with the additive values of the preset zone.
+    fn region(&self, zone: &Zone, preset_zone: &Zone) -> Option<Region> {
+        let value = |generator: usize, default: i16| {
+            let preset = match gen::ADDITIVE.contains(&generator) {
+                true => preset_zone.get(generator).unwrap_or_default() as i32,
+                false => 0,
+            };
+            zone.get(generator).unwrap_or(default) as i32 + preset
+        };
+        let offset = |fine: usize, coarse: usize| value(fine, 0) + value(coarse, 0) * 32768;
+
+        let header = self.sample_headers[zone.get(gen::SAMPLE_ID)? as u16 as usize];
+        let position = |base: u32, offset: i32| {
+            (base as i64 + offset as i64).clamp(0, self.samples.len() as i64) as usize
+        };
+        let start = position(
+            header.start,
+            offset(gen::START_OFFSET, gen::START_COARSE_OFFSET),
+        );
+        let end = position(header.end, offset(gen::END_OFFSET, gen::END_COARSE_OFFSET));
+        let loop_start = position(
+            header.loop_start,
+            offset(gen::LOOP_START_OFFSET, gen::LOOP_START_COARSE_OFFSET),
+        );
+        let loop_end = position(
+            header.loop_end,
+            offset(gen::LOOP_END_OFFSET, gen::LOOP_END_COARSE_OFFSET),
+        );
+        if start >= end || header.sample_rate == 0 {
+            return None;
+        }
+
+        let modes = value(gen::SAMPLE_MODES, 0) & 3;
+        let sample_loop = (matches!(modes, 1 | 3) && start <= loop_start && loop_start < loop_end)
+            .then_some((loop_start, loop_end.min(end)));
+        let root_key = match value(gen::OVERRIDING_ROOT_KEY, -1) {
+            key @ 0..=127 => key as u8,
+            _ => header.original_key.min(127),
+        };
+        let fixed = |generator| {
+            u8::try_from(value(generator, -1))
+                .ok()
+                .filter(|v| *v <= 127)
+        };
+
+        Some(Region {
+            start,
+            end,
+            sample_loop,
+            loop_until_release: modes == 3,
+            sample_rate: header.sample_rate,
+            root_key,
+            tune: value(gen::COARSE_TUNE, 0) * 100
+                + value(gen::FINE_TUNE, 0)
+                + header.correction as i32,
+            scale_tuning: value(gen::SCALE_TUNING, 100),
+            attenuation: value(gen::INITIAL_ATTENUATION, 0).clamp(0, 1440) as f32,
+            pan: value(gen::PAN, 0).clamp(-500, 500) as f32,
+            delay: seconds(value(gen::DELAY_VOL_ENV, -12000)),
+            attack: seconds(value(gen::ATTACK_VOL_ENV, -12000)),
+            hold: seconds(value(gen::HOLD_VOL_ENV, -12000)),
+            decay: seconds(value(gen::DECAY_VOL_ENV, -12000)),
+            release: seconds(value(gen::RELEASE_VOL_ENV, -12000)),
+            sustain: value(gen::SUSTAIN_VOL_ENV, 0).clamp(0, 1440) as f32,
+            key: fixed(gen::KEYNUM),
+            velocity: fixed(gen::VELOCITY),
+        })
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// Sample rate of the test sine, in which its period is exactly 100 samples.
+    pub const SINE_RATE: u32 = 44000;
+
+    fn chunk(id: &[u8], content: &[u8]) -> Vec<u8> {
+        let mut out = id.to_vec();
+        out.extend((content.len() as u32).to_le_bytes());
+        out.extend(content);
+        if content.len() % 2 == 1 {
+            out.push(0);
+        }
+        out
+    }
+
+    fn list(list_type: &[u8], chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut content = list_type.to_vec();
+        content.extend(chunks.concat());
+        chunk(b"LIST", &content)
+    }
+
+    fn name(name: &str) -> Vec<u8> {
+        let mut out = name.as_bytes().to_vec();
+        out.resize(20, 0);
+        out
+    }
+
+    fn generator(operator: usize, amount: i16) -> Vec<u8> {
+        let mut out = (operator as u16).to_le_bytes().to_vec();
+        out.extend(amount.to_le_bytes());
+        out
+    }
+
+    /// SoundFont with a looped 440 Hz sine played by the piano of the first bank.
+    pub fn sine_sound_font() -> Vec<u8> {
+        let samples: Vec<u8> = (0..2000)
+            .flat_map(|i| {
+                let phase = i as f32 / 100.0 * std::f32::consts::TAU;
+                ((phase.sin() * 16000.0) as i16).to_le_bytes()
+            })
+            .chain([0; 92])
+            .collect();
+
+        let phdr = [
+            [name("Sine"), vec![0, 0, 0, 0, 0, 0], vec![0; 12]].concat(),
+            [name("EOP"), vec![0, 0, 0, 0, 2, 0], vec![0; 12]].concat(),
+        ];
+        let pbag = [vec![0, 0, 0, 0], vec![1, 0, 0, 0], vec![2, 0, 0, 0]];
+        // Global zone lowers the volume of the instrument.
+        let pgen = [
+            generator(gen::INITIAL_ATTENUATION, 60),
+            generator(gen::INSTRUMENT, 0),
+            generator(0, 0),
+        ];
+        let inst = [
+            [name("Sine"), vec![0, 0]].concat(),
+            [name("EOI"), vec![1, 0]].concat(),
+        ];
+        let ibag = [vec![0, 0, 0, 0], vec![3, 0, 0, 0]];
+        let igen = [
+            generator(gen::KEY_RANGE, i16::from_le_bytes([0, 127])),
+            generator(gen::SAMPLE_MODES, 1),
+            generator(gen::SAMPLE_ID, 0),
+            generator(0, 0),
+        ];
+        let mut sample = name("Sine");
+        for value in [0, 2000, 1000, 1900, SINE_RATE] {
+            sample.extend(value.to_le_bytes());
+        }
+        sample.extend([69, 0, 0, 0, 1, 0]);
+        let shdr = [sample, [name("EOS"), vec![0; 26]].concat()];
+
+        let pdta = list(
+            b"pdta",
+            &[
+                chunk(b"phdr", &phdr.concat()),
+                chunk(b"pbag", &pbag.concat()),
+                chunk(b"pmod", &[0; 10]),
+                chunk(b"pgen", &pgen.concat()),
+                chunk(b"inst", &inst.concat()),
+                chunk(b"ibag", &ibag.concat()),
+                chunk(b"imod", &[0; 10]),
+                chunk(b"igen", &igen.concat()),
+                chunk(b"shdr", &shdr.concat()),
+            ],
+        );
+        let content = [
+            b"sfbk".to_vec(),
+            list(b"INFO", &[chunk(b"ifil", &[2, 0, 4, 0])]),
+            list(b"sdta", &[chunk(b"smpl", &samples)]),
+            pdta,
+        ]
+        .concat();
+
+        chunk(b"RIFF", &content)
+    }
+
+    #[test]
+    fn parse_presets_and_regions() {
+        // Arrange
+        let data = sine_sound_font();
+
+        // Act
+        let sound_font = SoundFont::parse(&data).unwrap();
+
+        // Assert
+        let preset = sound_font.preset(128, 5);
+        assert_eq!(sound_font.presets().len(), 1);
+        assert_eq!(
+            (preset.name.as_str(), preset.bank, preset.program),
+            ("Sine", 0, 0)
+        );
+        let regions = sound_font.regions(preset, 81, 100);
+        assert_eq!(regions.len(), 1);
+        assert_eq!((regions[0].start, regions[0].end), (0, 2000));
+        assert_eq!(regions[0].sample_loop, Some((1000, 1900)));
+        assert_eq!((regions[0].root_key, regions[0].attenuation), (69, 60.0));
+        assert!(regions[0].release < 0.001);
+    }
+
+    #[test]
+    fn invalid_sound_fonts() {
+        let data = sine_sound_font();
+        let mut wrong_sample = data.clone();
+        // Sample identifier of the instrument zone points behind the samples.
+        let position = wrong_sample
+            .windows(4)
+            .position(|w| w == generator(gen::SAMPLE_ID, 0))
+            .unwrap();
+        wrong_sample[position + 2] = 5;
+
+        for data in [&data[..100], b"RIFF\0\0\0\0WAVE", &wrong_sample[..]] {
+            assert!(SoundFont::parse(data).is_err());
+        }
+    }
+}
@@ -0,0 +1,548 @@
+//! Synthesizer definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use std::{path::Path, sync::Arc, time::Duration};
+
+use super::{
+    additive_voice::AdditiveVoice,
+    audio_writer::AudioWriter,
+    effects::Effects,
+    sound_font::{Region, SoundFont},
+};
+use crate::{
+    error::{AppError, Result},
+    midi::MidiNote,
+};
+
+/// Sample rate of the rendered audio.
+pub const SAMPLE_RATE: u32 = 44100;
+
+/// Number of frames rendered at once by the iterator.
+const BLOCK_FRAMES: usize = 1024;
+
+/// Gain of the mixed voices, which leaves headroom for chords.
+const MASTER_GAIN: f32 = 0.5;
+
+/// MIDI channel of the percussion, which uses the percussion bank.
+const PERCUSSION_CHANNEL: u8 = 9;
+const PERCUSSION_BANK: u16 = 128;
+
+/// Settings of the rendered audio, which are also passed to FluidSynth.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct RenderSettings {
+    pub sample_rate: u32,
+    /// Gain of the audio in decibels.
+    pub gain: f32,
+    pub reverb: bool,
+    pub chorus: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            sample_rate: SAMPLE_RATE,
+            gain: 0.0,
+            reverb: false,
+            chorus: false,
+        }
+    }
+}
+
+/// Instrument playing the notes of the synthesizer.
+#[derive(Debug, Clone)]
+pub enum Instrument {
+    /// Presets of the SoundFont selected by the programs of the notes.
+    SoundFont(Arc<SoundFont>),
+    /// Piano-like additive tone, which is used when no SoundFont is available.
+    Additive,
+}
+
+impl From<Arc<SoundFont>> for Instrument {
+    fn from(sound_font: Arc<SoundFont>) -> Self {
+        Instrument::SoundFont(sound_font)
+    }
+}
+
+/// Sound of a note.
+#[derive(Debug, Clone)]
+enum Voice {
+    Sample(SampleVoice),
+    Additive(AdditiveVoice),
+}
+
+impl Voice {
+    fn is_finished(&self) -> bool {
+        match self {
+            Voice::Sample(voice) => voice.finished,
+            Voice::Additive(voice) => voice.is_finished(),
+        }
+    }
+
+    /// Left and right sample of the voice at the frame, which advances the voice.
+    fn frame(&mut self, frame: u64, sample_rate: u32, samples: &[i16]) -> [f32; 2] {
+        match self {
+            Voice::Sample(voice) => {
+                let volume = voice.volume(frame, sample_rate) * MASTER_GAIN;
+                let value = voice.sample(samples, frame >= voice.release) * volume;
+                [value * voice.gain[0], value * voice.gain[1]]
+            }
+            Voice::Additive(voice) => [voice.sample(frame) * std::f32::consts::FRAC_1_SQRT_2; 2],
+        }
+    }
+}
+
+/// Sample of a region played by a note.
+#[derive(Debug, Clone)]
+struct SampleVoice {
+    region: Region,
+    /// Position in the sample data.
+    position: f64,
+    /// Sample data step per an output frame.
+    step: f64,
+    /// Left and right gain.
+    gain: [f32; 2],
+    start: u64,
+    release: u64,
+    finished: bool,
+}
+
+impl SampleVoice {
+    fn new(region: Region, note: &MidiNote, sample_rate: u32) -> Self {
+        let key = region.key.unwrap_or(note.key) as i32;
+        let velocity = region.velocity.unwrap_or(note.velocity) as f32 / 127.0;
+
+        let cents = (key - region.root_key as i32) * region.scale_tuning + region.tune;
+        let step =
+            2f64.powf(cents as f64 / 1200.0) * region.sample_rate as f64 / sample_rate as f64;
+
+        let gain = 10f32.powf(-region.attenuation / 200.0) * velocity * velocity;
+        let angle = (region.pan / 500.0 + 1.0) * std::f32::consts::FRAC_PI_4;
+
+        let start = (note.start * sample_rate as f64).round() as u64;
+        let release = ((note.end * sample_rate as f64).round() as u64).max(start + 1);
+
+        Self {
+            region,
+            position: region.start as f64,
+            step,
+            gain: [gain * angle.cos(), gain * angle.sin()],
+            start,
+            release,
+            finished: false,
+        }
+    }
+
+    /// Volume envelope before the release at the time from the start in seconds.
+    fn envelope(&self, time: f32) -> f32 {
+        let region = &self.region;
+        let attack_end = region.delay + region.attack;
+        let hold_end = attack_end + region.hold;
+
+        if time < region.delay {
+            0.0
+        } else if time < attack_end {
+            (time - region.delay) / region.attack
+        } else {
+            // Decay time is the time of the fall by 100 dB.
+            let decibels = -100.0 * (time - hold_end).max(0.0) / region.decay;
+            10f32.powf(decibels.max(-region.sustain / 10.0) / 20.0)
+        }
+    }
+
+    /// Volume of the voice at the frame, which finishes the voice after its release.
+    fn volume(&mut self, frame: u64, sample_rate: u32) -> f32 {
+        let time = |frame: u64| (frame - self.start) as f32 / sample_rate as f32;
+        if frame < self.release {
+            return self.envelope(time(frame));
+        }
+
+        // Release falls by 100 dB from the level at the release.
+        let released = (time(frame) - time(self.release)) / self.region.release;
+        if released >= 1.0 {
+            self.finished = true;
+        }
+        self.envelope(time(self.release)) * 10f32.powf(-5.0 * released)
+    }
+
+    /// Interpolated sample at the current position, which advances the position.
+    fn sample(&mut self, samples: &[i16], released: bool) -> f32 {
+        let region = &self.region;
+        let sample_loop = region
+            .sample_loop
+            .filter(|_| !(released && region.loop_until_release));
+
+        let index = self.position as usize;
+        let next = match sample_loop {
+            Some((loop_start, loop_end)) if index + 1 >= loop_end => loop_start,
+            _ => index + 1,
+        };
+        let value = |index: usize| match index < region.end {
+            true => samples[index] as f32 / 32768.0,
+            false => 0.0,
+        };
+        let fraction = (self.position - index as f64) as f32;
+        let value = value(index) + (value(next) - value(index)) * fraction;
+
+        self.position += self.step;
+        match sample_loop {
+            Some((loop_start, loop_end)) => {
+                while self.position >= loop_end as f64 {
+                    self.position -= (loop_end - loop_start) as f64;
+                }
+            }
+            None if self.position >= region.end as f64 => self.finished = true,
+            None => {}
+        }
+
+        value
+    }
+}
+
+/// Synthesizer rendering MIDI notes through the SoundFont or the additive instrument.
+///
+/// The audio is rendered progressively, so it can be played while it is synthesized. The
+/// synthesizer is an iterator of interleaved stereo samples and can be played by `rodio`.
+///
+/// ```
+/// use std::sync::Arc;
+/// use music_sheet_gen::{midi::MidiNote, synth::{Instrument, SoundFont, Synthesizer}};
+///
+/// # fn play(sound_font: SoundFont) {
+/// let note = MidiNote { channel: 0, key: 60, velocity: 90, program: 0, start: 0.0, end: 1.0 };
+/// let synthesizer = Synthesizer::new(Arc::new(sound_font), vec![note]);
+/// let samples: Vec<f32> = synthesizer.collect();
+///
+/// let preview = Synthesizer::new(Instrument::Additive, vec![note]);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Synthesizer {
+    instrument: Instrument,
+    /// Notes sorted by their start.
+    notes: Vec<MidiNote>,
+    next_note: usize,
+    voices: Vec<Voice>,
+    /// Next rendered frame.
+    frame: u64,
+    sample_rate: u32,
+    /// Linear gain of the audio.
+    gain: f32,
+    effects: Effects,
+    /// Frames of the effects rendered after the last voice finished.
+    tail: u64,
+    /// Block of the iterator and position of its next sample.
+    block: Vec<[f32; 2]>,
+    block_position: usize,
+}
+
+impl Synthesizer {
+    pub fn new(instrument: impl Into<Instrument>, notes: Vec<MidiNote>) -> Self {
+        Self::with_settings(instrument, notes, RenderSettings::default())
+    }
+
+    pub fn with_settings(
+        instrument: impl Into<Instrument>,
+        mut notes: Vec<MidiNote>,
+        settings: RenderSettings,
+    ) -> Self {
+        notes.sort_by(|a, b| a.start.total_cmp(&b.start));
+        let sample_rate = settings.sample_rate;
+
+        Self {
+            instrument: instrument.into(),
+            notes,
+            next_note: 0,
+            voices: vec![],
+            frame: 0,
+            sample_rate,
+            gain: 10f32.powf(settings.gain / 20.0),
+            effects: Effects::new(settings.reverb, settings.chorus, sample_rate),
+            tail: 0,
+            block: vec![],
+            block_position: 0,
+        }
+    }
+
+    /// Whether all the notes were started, all the voices finished and the effects faded
+    /// out.
+    pub fn is_finished(&self) -> bool {
+        self.voices_finished() && self.tail >= self.effects.tail(self.sample_rate)
+    }
+
+    fn voices_finished(&self) -> bool {
+        self.next_note >= self.notes.len() && self.voices.is_empty()
+    }
+
+    /// End of the last note in seconds. The audio is longer by the release of the notes.
+    pub fn duration(&self) -> f64 {
+        self.notes.iter().map(|n| n.end).fold(0.0, f64::max)
+    }
+
+    /// Rendered part of the notes from zero to one.
+    pub fn progress(&self) -> f32 {
+        let duration = self.duration();
+        if duration <= 0.0 {
+            return 1.0;
+        }
+
+        (self.frame as f64 / self.sample_rate as f64 / duration).min(1.0) as f32
+    }
+
+    /// Start voices of the notes, which start at the current frame.
+    fn start_notes(&mut self) {
+        while let Some(note) = self.notes.get(self.next_note) {
+            if ((note.start * self.sample_rate as f64).round() as u64) > self.frame {
+                break;
+            }
+
+            match &self.instrument {
+                Instrument::SoundFont(sound_font) => {
+                    let bank = match note.channel {
+                        PERCUSSION_CHANNEL => PERCUSSION_BANK,
+                        _ => 0,
+                    };
+                    let preset = sound_font.preset(bank, note.program as u16);
+                    let regions = sound_font.regions(preset, note.key, note.velocity);
+                    self.voices.extend(
+                        regions
+                            .into_iter()
+                            .map(|r| Voice::Sample(SampleVoice::new(r, note, self.sample_rate))),
+                    );
+                }
+                Instrument::Additive => self
+                    .voices
+                    .push(Voice::Additive(AdditiveVoice::new(note, self.sample_rate))),
+            }
+            self.next_note += 1;
+        }
+    }
+
+    /// Render the next frames into the buffer. Returns the number of rendered frames, which
+    /// is smaller than the buffer only at the end of the audio.
+    pub fn render(&mut self, buffer: &mut [[f32; 2]]) -> usize {
+        let instrument = self.instrument.clone();
+        let samples = match &instrument {
+            Instrument::SoundFont(sound_font) => sound_font.samples(),
+            Instrument::Additive => &[],
+        };
+
+        for (rendered, output) in buffer.iter_mut().enumerate() {
+            self.start_notes();
+            if self.is_finished() {
+                return rendered;
+            }
+
+            *output = [0.0; 2];
+            for voice in self.voices.iter_mut() {
+                let [left, right] = voice.frame(self.frame, self.sample_rate, samples);
+                output[0] += left;
+                output[1] += right;
+            }
+            self.voices.retain(|v| !v.is_finished());
+            *output = self.effects.process(output.map(|s| s * self.gain));
+            if self.voices_finished() {
+                self.tail += 1;
+            }
+            self.frame += 1;
+        }
+
+        buffer.len()
+    }
+
+    /// Render the rest of the audio.
+    pub fn frames(&mut self) -> Vec<[f32; 2]> {
+        let mut frames = vec![];
+        let mut block = [[0.0; 2]; BLOCK_FRAMES];
+        loop {
+            let rendered = self.render(&mut block);
+            frames.extend_from_slice(&block[..rendered]);
+            if rendered < block.len() {
+                return frames;
+            }
+        }
+    }
+
+    /// Render the rest of the audio as a 16-bit stereo WAV file.
+    pub fn wav(&mut self) -> Vec<u8> {
+        let frames = self.frames();
+        AudioWriter::default().wav(&frames, self.sample_rate)
+    }
+
+    /// Render the rest of the audio into the WAV file at the given path.
+    pub fn save_wav(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.wav()).map_err(|e| AppError::build_path(path, &e))?;
+
+        Ok(())
+    }
+}
+
+impl Iterator for Synthesizer {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.block_position >= self.block.len() * 2 {
+            self.block.resize(BLOCK_FRAMES, [0.0; 2]);
+            let mut block = std::mem::take(&mut self.block);
+            let rendered = self.render(&mut block);
+            block.truncate(rendered);
+            self.block = block;
+            self.block_position = 0;
+        }
+
+        let sample = self.block.get(self.block_position / 2)?[self.block_position % 2];
+        self.block_position += 1;
+
+        Some(sample)
+    }
+}
+
+impl rodio::Source for Synthesizer {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synth::sound_font::tests::sine_sound_font;
+
+    fn note(key: u8, start: f64, end: f64) -> MidiNote {
+        MidiNote {
+            channel: 0,
+            key,
+            velocity: 127,
+            program: 0,
+            start,
+            end,
+        }
+    }
+
+    fn synthesizer(notes: Vec<MidiNote>) -> Synthesizer {
+        let sound_font = SoundFont::parse(&sine_sound_font()).unwrap();
+        Synthesizer::new(Arc::new(sound_font), notes)
+    }
+
+    /// Number of the sign changes of the left channel.
+    fn crossings(frames: &[[f32; 2]]) -> usize {
+        frames
+            .windows(2)
+            .filter(|w| (w[0][0] < 0.0) != (w[1][0] < 0.0))
+            .count()
+    }
+
+    #[test]
+    fn render_pitch_of_notes() {
+        // Arrange
+        let mut a4 = synthesizer(vec![note(69, 0.0, 0.5)]);
+        let mut a5 = synthesizer(vec![note(81, 0.0, 0.5)]);
+        let mut a4_buffer = vec![[0.0; 2]; SAMPLE_RATE as usize / 10];
+        let mut a5_buffer = a4_buffer.clone();
+
+        // Act
+        a4.render(&mut a4_buffer);
+        a5.render(&mut a5_buffer);
+
+        // Assert
+        // Sine of 440 Hz changes its sign 88 times in 100 ms.
+        assert!((86..=90).contains(&crossings(&a4_buffer)));
+        assert!((174..=178).contains(&crossings(&a5_buffer)));
+        let peak = a4_buffer.iter().map(|f| f[0].abs()).fold(0.0, f32::max);
+        assert!(peak > 0.05 && peak < 0.5);
+    }
+
+    #[test]
+    fn render_until_release() {
+        // Arrange
+        let notes = vec![note(69, 0.0, 0.25), note(72, 0.1, 0.5), note(64, 0.2, 0.3)];
+        let mut synthesizer = synthesizer(notes);
+        let mut buffer = vec![[0.0; 2]; SAMPLE_RATE as usize];
+
+        // Act
+        let rendered = synthesizer.render(&mut buffer);
+
+        // Assert
+        // Default release is 1 ms long.
+        assert!((22050..22200).contains(&rendered));
+        assert!(synthesizer.is_finished());
+        assert_eq!(synthesizer.progress(), 1.0);
+        assert!(buffer[rendered - 1000..rendered]
+            .iter()
+            .any(|f| f[0] != 0.0));
+    }
+
+    #[test]
+    fn render_additive_notes() {
+        // Arrange
+        let notes = vec![note(69, 0.0, 0.1), note(57, 0.0, 0.1)];
+        let mut synthesizer = Synthesizer::new(Instrument::Additive, notes);
+        let mut buffer = vec![[0.0; 2]; SAMPLE_RATE as usize];
+
+        // Act
+        let rendered = synthesizer.render(&mut buffer);
+
+        // Assert
+        // Voices fade out after their release instead of ringing for seconds.
+        assert!((4410..SAMPLE_RATE as usize / 2).contains(&rendered));
+        assert!(synthesizer.is_finished());
+        let peak = buffer.iter().map(|f| f[0].abs()).fold(0.0, f32::max);
+        assert!(peak > 0.1 && peak < 1.0);
+        assert!(buffer[..rendered].iter().all(|f| f[0] == f[1]));
+        // Fundamental of A3 and A4 together changes its sign 44 times in 100 ms.
+        assert!(crossings(&buffer[..4410]) >= 44);
+    }
+
+    #[test]
+    fn render_with_settings() {
+        // Arrange
+        let notes = vec![note(69, 0.0, 0.1)];
+        let settings = RenderSettings {
+            sample_rate: 22050,
+            gain: -6.0,
+            reverb: true,
+            chorus: true,
+        };
+
+        // Act
+        let dry = Synthesizer::new(Instrument::Additive, notes.clone()).frames();
+        let wet = Synthesizer::with_settings(Instrument::Additive, notes, settings).frames();
+
+        // Assert
+        // Reverb rings for two seconds after the last voice.
+        assert!(wet.len() > dry.len() / 2 + 2 * 22050);
+        assert!(wet.iter().any(|f| f[0] != f[1]));
+        let peak = |frames: &[[f32; 2]]| frames.iter().map(|f| f[0].abs()).fold(0.0, f32::max);
+        assert!(peak(&wet[..2205]) < peak(&dry[..4410]) * 0.6);
+    }
+
+    #[test]
+    fn iterate_wav_samples() {
+        // Arrange
+        let notes = vec![note(69, 0.05, 0.1)];
+
+        // Act
+        let samples: Vec<f32> = synthesizer(notes.clone()).collect();
+        let wav = synthesizer(notes).wav();
+
+        // Assert
+        assert_eq!(samples.len() % 2, 0);
+        assert!(samples[..2 * 2205].iter().all(|s| *s == 0.0));
+        assert_eq!((&wav[..4], &wav[8..16]), (&b"RIFF"[..], &b"WAVEfmt "[..]));
+        assert_eq!(wav.len(), 44 + samples.len() * 2);
+    }
+}
@@ -0,0 +1,798 @@
+//! OGG Vorbis encoder definition
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use std::f64::consts::PI;
+
+/// Number of the samples of a channel transformed in a block. All blocks are short ones
+/// of the same size, so the encoder never switches the windows.
+const BLOCK_SIZE: usize = 2048;
+const HALF_BLOCK: usize = BLOCK_SIZE / 2;
+
+/// Multiplier of the floor values, which gives them the range of 0 to 127.
+const FLOOR_MULTIPLIER: u32 = 2;
+const FLOOR_RANGE: u32 = 256 / FLOOR_MULTIPLIER;
+/// Positions of the floor posts between the first post at 0 and the last one at the end
+/// of the spectrum. They are coded in partitions of eight.
+const FLOOR_POSTS: [u32; 32] = [
+    1, 2, 3, 4, 5, 6, 7, 8, 10, 12, 14, 16, 19, 22, 26, 30, 35, 41, 48, 56, 65, 76, 89, 104, 122,
+    143, 168, 197, 231, 271, 318, 373,
+];
+const FLOOR_PARTITION: usize = 8;
+/// Floor value 0 in decibels as the natural logarithm of the amplitude, the last value 255
+/// is the full scale.
+const FLOOR_MIN_LN: f64 = -16.055_2;
+
+/// Highest quantized residue, so the floor is the step of the quantization.
+const MAX_RESIDUE: i32 = 31;
+/// Values of the residue coded in a partition with a single classification.
+const RESIDUE_PARTITION: usize = 32;
+/// Partitions, whose classifications share a codeword.
+const CLASS_DIMENSIONS: usize = 2;
+/// Quantization step below the loudest coefficient of the block in decibels, under which
+/// the coefficients are dropped.
+const DYNAMIC_RANGE: f64 = 70.0;
+
+const FLOOR_BOOK: usize = 0;
+const CLASS_BOOK: usize = 1;
+const SMALL_BOOK: usize = 2;
+const LARGE_BOOK: usize = 3;
+
+/// Writer of the values with any number of bits, the least significant bit first.
+#[derive(Debug, Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    /// Number of the bits used in the last byte.
+    used: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u64, len: u32) {
+        for i in 0..len {
+            if self.used % 8 == 0 {
+                self.bytes.push(0);
+                self.used = 0;
+            }
+            let bit = ((value >> i) & 1) as u8;
+            *self.bytes.last_mut().unwrap() |= bit << self.used;
+            self.used += 1;
+        }
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        bytes.iter().for_each(|b| self.write(*b as u64, 8));
+    }
+}
+
+/// Number of the bits of the value.
+fn ilog(value: u32) -> u32 {
+    u32::BITS - value.leading_zeros()
+}
+
+/// Integer value in the float format of the codebooks.
+fn float32(value: i32) -> u64 {
+    let sign = if value < 0 { 0x8000_0000 } else { 0 };
+    (sign | (788 << 21) | value.unsigned_abs()) as u64
+}
+
+/// Lengths of the Huffman codewords of the entries with the weights.
+fn huffman_lengths(weights: &[f64]) -> Vec<u32> {
+    let max = weights.iter().fold(0.0, |m: f64, w| m.max(*w));
+    // Rare entries are weighted up, so the codewords aren't longer than 32 bits.
+    let mut nodes: Vec<(u64, Option<usize>)> = weights
+        .iter()
+        .map(|w| ((w / max * 1e6).max(1.0) as u64, None))
+        .collect();
+    let mut heap: std::collections::BinaryHeap<_> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, (w, _))| std::cmp::Reverse((*w, i)))
+        .collect();
+    while heap.len() > 1 {
+        let std::cmp::Reverse((w1, a)) = heap.pop().unwrap();
+        let std::cmp::Reverse((w2, b)) = heap.pop().unwrap();
+        let parent = nodes.len();
+        nodes.push((w1 + w2, None));
+        nodes[a].1 = Some(parent);
+        nodes[b].1 = Some(parent);
+        heap.push(std::cmp::Reverse((w1 + w2, parent)));
+    }
+
+    (0..weights.len())
+        .map(|mut i| {
+            let mut len = 0;
+            while let Some(parent) = nodes[i].1 {
+                i = parent;
+                len += 1;
+            }
+            len
+        })
+        .collect()
+}
+
+/// Codewords of the lengths assigned in the order of the entries, as the decoder does.
+fn codewords(lengths: &[u32]) -> Vec<u32> {
+    let mut marker = [0u32; 33];
+    lengths
+        .iter()
+        .map(|&len| {
+            let len = len as usize;
+            let entry = marker[len];
+            for j in (1..=len).rev() {
+                if marker[j] & 1 == 1 {
+                    marker[j] = match j {
+                        1 => marker[1] + 1,
+                        _ => marker[j - 1] << 1,
+                    };
+                    break;
+                }
+                marker[j] += 1;
+            }
+            let mut pruned = entry;
+            for j in len + 1..33 {
+                if marker[j] >> 1 != pruned {
+                    break;
+                }
+                pruned = marker[j];
+                marker[j] = marker[j - 1] << 1;
+            }
+            entry
+        })
+        .collect()
+}
+
+/// Codebook with the Huffman codes of its entries and the vector values of the entries.
+#[derive(Debug)]
+struct Codebook {
+    dimensions: usize,
+    lengths: Vec<u32>,
+    codewords: Vec<u32>,
+    /// Lowest value and the number of the values of a dimension of the vectors.
+    lattice: Option<(i32, usize)>,
+}
+
+impl Codebook {
+    /// Scalar codebook of the entries with the weights.
+    fn scalar(weights: &[f64]) -> Self {
+        let lengths = huffman_lengths(weights);
+        Self {
+            dimensions: 1,
+            codewords: codewords(&lengths),
+            lengths,
+            lattice: None,
+        }
+    }
+
+    /// Codebook of all vectors with the dimensions of the values from `min` to `-min`,
+    /// whose values have the weights.
+    fn lattice(dimensions: usize, min: i32, weight: impl Fn(i32) -> f64) -> Self {
+        let values = (-2 * min + 1) as usize;
+        let weights: Vec<f64> = (0..values.pow(dimensions as u32))
+            .map(|entry| {
+                (0..dimensions)
+                    .map(|d| weight((entry / values.pow(d as u32) % values) as i32 + min))
+                    .product()
+            })
+            .collect();
+        Self {
+            dimensions,
+            lattice: Some((min, values)),
+            ..Self::scalar(&weights)
+        }
+    }
+
+    fn write_header(&self, out: &mut BitWriter) {
+        out.write(0x564342, 24);
+        out.write(self.dimensions as u64, 16);
+        out.write(self.lengths.len() as u64, 24);
+        // Neither ordered nor sparse.
+        out.write(0, 2);
+        for len in &self.lengths {
+            out.write(*len as u64 - 1, 5);
+        }
+        match self.lattice {
+            None => out.write(0, 4),
+            Some((min, values)) => {
+                let bits = ilog(values as u32 - 1);
+                out.write(1, 4);
+                out.write(float32(min), 32);
+                out.write(float32(1), 32);
+                out.write(bits as u64 - 1, 4);
+                out.write(0, 1);
+                for value in 0..values {
+                    out.write(value as u64, bits);
+                }
+            }
+        }
+    }
+
+    /// Write the codeword of the entry, the first bit of the code first.
+    fn write(&self, out: &mut BitWriter, entry: usize) {
+        let (codeword, len) = (self.codewords[entry], self.lengths[entry]);
+        for i in (0..len).rev() {
+            out.write((codeword >> i) as u64 & 1, 1);
+        }
+    }
+
+    /// Write the entry of the vector of the values.
+    fn write_vector(&self, out: &mut BitWriter, vector: &[i32]) {
+        let (min, values) = self.lattice.unwrap();
+        let entry = vector
+            .iter()
+            .rev()
+            .fold(0, |entry, v| entry * values + (v - min) as usize);
+        self.write(out, entry);
+    }
+}
+
+/// Complex number as the real and imaginary part.
+type Complex = (f64, f64);
+
+fn mul(a: Complex, b: Complex) -> Complex {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn cis(angle: f64) -> Complex {
+    (angle.cos(), angle.sin())
+}
+
+/// In-place radix-2 FFT of the values, whose count is a power of two.
+fn fft(values: &mut [Complex]) {
+    let n = values.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let step = cis(-2.0 * PI / len as f64);
+        for start in (0..n).step_by(len) {
+            let mut twiddle = (1.0, 0.0);
+            for k in 0..len / 2 {
+                let even = values[start + k];
+                let odd = mul(values[start + k + len / 2], twiddle);
+                values[start + k] = (even.0 + odd.0, even.1 + odd.1);
+                values[start + k + len / 2] = (even.0 - odd.0, even.1 - odd.1);
+                twiddle = mul(twiddle, step);
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// MDCT of the block with the length of two times the number of the coefficients, which
+/// is computed as a DCT-IV of the folded block.
+fn mdct(block: &[f64]) -> Vec<f64> {
+    let m = block.len() / 2;
+    let (a, b) = (&block[..m / 2], &block[m / 2..m]);
+    let (c, d) = (&block[m..3 * m / 2], &block[3 * m / 2..]);
+    let folded: Vec<f64> = (0..m / 2)
+        .map(|n| -c[m / 2 - 1 - n] - d[n])
+        .chain((0..m / 2).map(|n| a[n] - b[m / 2 - 1 - n]))
+        .collect();
+
+    let mut values: Vec<Complex> = (0..m / 2)
+        .map(|n| {
+            let value = (folded[2 * n], folded[m - 1 - 2 * n]);
+            mul(value, cis(-PI * n as f64 / m as f64))
+        })
+        .collect();
+    fft(&mut values);
+
+    let mut coefficients = vec![0.0; m];
+    for (k, value) in values.into_iter().enumerate() {
+        let value = mul(value, cis(-PI * (k as f64 + 0.25) / m as f64));
+        coefficients[2 * k] = value.0;
+        coefficients[m - 1 - 2 * k] = -value.1;
+    }
+    coefficients
+}
+
+/// Floor value of the line between the points at the position, as the decoder computes it.
+fn render_point(x0: u32, y0: i32, x1: u32, y1: i32, x: u32) -> i32 {
+    let dy = y1 - y0;
+    let offset = dy.abs() * (x - x0) as i32 / (x1 - x0) as i32;
+    match dy < 0 {
+        true => y0 - offset,
+        false => y0 + offset,
+    }
+}
+
+/// Draw the line between the points into the floor without its end.
+fn render_line(x0: u32, y0: i32, x1: u32, y1: i32, floor: &mut [i32]) {
+    let dy = y1 - y0;
+    let adx = (x1 - x0) as i32;
+    let base = dy / adx;
+    let sy = if dy < 0 { base - 1 } else { base + 1 };
+    let ady = dy.abs() - base.abs() * adx;
+    let (mut y, mut err) = (y0, 0);
+    for x in x0..x1.min(floor.len() as u32) {
+        if x > x0 {
+            err += ady;
+            if err >= adx {
+                err -= adx;
+                y += sy;
+            } else {
+                y += base;
+            }
+        }
+        floor[x as usize] = y;
+    }
+}
+
+/// Amplitude of the floor value multiplied by the floor multiplier.
+fn floor_amplitude(value: i32) -> f64 {
+    (FLOOR_MIN_LN * (255 - value) as f64 / 255.0).exp()
+}
+
+/// Smallest floor value, whose amplitude is at least the given one.
+fn floor_value(amplitude: f64) -> i32 {
+    let index = 255.0 - 255.0 * amplitude.ln() / FLOOR_MIN_LN;
+    (index / FLOOR_MULTIPLIER as f64)
+        .ceil()
+        .clamp(0.0, FLOOR_RANGE as f64 - 1.0) as i32
+}
+
+/// Encoder of the stereo audio into an OGG Vorbis stream.
+#[derive(Debug)]
+pub(super) struct VorbisEncoder {
+    pub sample_rate: u32,
+    books: [Codebook; 4],
+}
+
+impl VorbisEncoder {
+    pub fn new(sample_rate: u32) -> Self {
+        let floor_weights: Vec<f64> = (0..FLOOR_RANGE)
+            .map(|v| (-(v as f64) / 8.0).exp())
+            .collect();
+        // Zero, small and large partition.
+        let class_weight = |class: i32| [0.5, 0.2, 0.3][class as usize];
+        let class_weights: Vec<f64> = (0..9)
+            .map(|entry| class_weight(entry / 3) * class_weight(entry % 3))
+            .collect();
+
+        Self {
+            sample_rate,
+            books: [
+                Codebook::scalar(&floor_weights),
+                Codebook {
+                    dimensions: CLASS_DIMENSIONS,
+                    ..Codebook::scalar(&class_weights)
+                },
+                Codebook::lattice(4, -1, |v| if v == 0 { 0.6 } else { 0.2 }),
+                Codebook::lattice(2, -MAX_RESIDUE, |v| (-v.abs() as f64 / 3.0).exp()),
+            ],
+        }
+    }
+
+    /// Encode the frames of the two channels.
+    pub fn encode(&self, frames: &[[f32; 2]]) -> Vec<u8> {
+        let mut ogg = OggWriter::default();
+        ogg.write(self.identification(), 0, false);
+        ogg.flush(0);
+        ogg.write(self.comment(), 0, false);
+        ogg.write(self.setup(), 0, false);
+        ogg.flush(0);
+
+        // Output starts in the middle of the first block, so it is preceded by the zeros.
+        let blocks = 1 + frames.len().div_ceil(HALF_BLOCK).max(1);
+        let mut channels = vec![vec![0.0; (blocks + 1) * HALF_BLOCK]; 2];
+        for (i, frame) in frames.iter().enumerate() {
+            for (channel, sample) in frame.iter().enumerate() {
+                channels[channel][HALF_BLOCK + i] = *sample as f64;
+            }
+        }
+
+        let window: Vec<f64> = (0..BLOCK_SIZE)
+            .map(|n| {
+                let x = ((n as f64 + 0.5) / BLOCK_SIZE as f64 * PI).sin();
+                (PI / 2.0 * x * x).sin()
+            })
+            .collect();
+        for block in 0..blocks {
+            let spectra: Vec<Vec<f64>> = channels
+                .iter()
+                .map(|samples| {
+                    let start = block * HALF_BLOCK;
+                    let windowed: Vec<f64> = samples[start..start + BLOCK_SIZE]
+                        .iter()
+                        .zip(&window)
+                        .map(|(s, w)| s * w)
+                        .collect();
+                    // Scaled, so the inverse transform of the decoder restores the samples.
+                    mdct(&windowed)
+                        .into_iter()
+                        .map(|c| c * 2.0 / HALF_BLOCK as f64)
+                        .collect()
+                })
+                .collect();
+
+            let last = block + 1 == blocks;
+            let granule = match last {
+                true => frames.len(),
+                false => block * HALF_BLOCK,
+            };
+            ogg.write(self.audio(&spectra), granule as u64, last);
+        }
+        ogg.flush(frames.len() as u64);
+
+        ogg.bytes
+    }
+
+    fn identification(&self) -> Vec<u8> {
+        let mut out = BitWriter::default();
+        out.write_bytes(b"\x01vorbis");
+        out.write(0, 32);
+        out.write(2, 8);
+        out.write(self.sample_rate as u64, 32);
+        // Unknown maximum, nominal and minimum bitrate.
+        (0..3).for_each(|_| out.write(0, 32));
+        let exponent = ilog(BLOCK_SIZE as u32 - 1) as u64;
+        out.write(exponent, 4);
+        out.write(exponent, 4);
+        out.write(1, 8);
+        out.bytes
+    }
+
+    fn comment(&self) -> Vec<u8> {
+        let vendor = env!("CARGO_PKG_NAME").as_bytes();
+        let mut out = BitWriter::default();
+        out.write_bytes(b"\x03vorbis");
+        out.write(vendor.len() as u64, 32);
+        out.write_bytes(vendor);
+        out.write(0, 32);
+        out.write(1, 8);
+        out.bytes
+    }
+
+    fn setup(&self) -> Vec<u8> {
+        let mut out = BitWriter::default();
+        out.write_bytes(b"\x05vorbis");
+        out.write(self.books.len() as u64 - 1, 8);
+        self.books.iter().for_each(|b| b.write_header(&mut out));
+        // Single unused time domain transform.
+        out.write(0, 6);
+        out.write(0, 16);
+
+        // Floor of the type 1, whose partitions share a class without subclasses.
+        out.write(0, 6);
+        out.write(1, 16);
+        out.write((FLOOR_POSTS.len() / FLOOR_PARTITION) as u64, 5);
+        for _ in 0..FLOOR_POSTS.len() / FLOOR_PARTITION {
+            out.write(0, 4);
+        }
+        out.write(FLOOR_PARTITION as u64 - 1, 3);
+        out.write(0, 2);
+        out.write(FLOOR_BOOK as u64 + 1, 8);
+        out.write(FLOOR_MULTIPLIER as u64 - 1, 2);
+        let range_bits = ilog(HALF_BLOCK as u32 - 1);
+        out.write(range_bits as u64, 4);
+        for x in FLOOR_POSTS {
+            out.write(x as u64, range_bits);
+        }
+
+        // Residue of the type 1 with the zero, small and large partitions.
+        out.write(0, 6);
+        out.write(1, 16);
+        out.write(0, 24);
+        out.write(HALF_BLOCK as u64, 24);
+        out.write(RESIDUE_PARTITION as u64 - 1, 24);
+        out.write(3 - 1, 6);
+        out.write(CLASS_BOOK as u64, 8);
+        for cascade in [0, 1, 1] {
+            out.write(cascade, 3);
+            out.write(0, 1);
+        }
+        out.write(SMALL_BOOK as u64, 8);
+        out.write(LARGE_BOOK as u64, 8);
+
+        // Mapping of both channels to the floor and residue without coupling.
+        out.write(0, 6);
+        out.write(0, 16);
+        out.write(0, 4);
+        out.write(0, 24);
+
+        // Single mode of the short blocks.
+        out.write(0, 6);
+        out.write(0, 1);
+        out.write(0, 32);
+        out.write(0, 8);
+        out.write(1, 1);
+        out.bytes
+    }
+
+    /// Audio packet of the block with the spectra of the channels.
+    fn audio(&self, spectra: &[Vec<f64>]) -> Vec<u8> {
+        let mut out = BitWriter::default();
+        out.write(0, 1);
+
+        let peak = spectra
+            .iter()
+            .flatten()
+            .fold(0.0, |p: f64, c| p.max(c.abs()));
+        let lowest = peak * 10f64.powf(-DYNAMIC_RANGE / 20.0);
+        let residues: Vec<Option<Vec<i32>>> = spectra
+            .iter()
+            .map(|spectrum| {
+                let floor = self.floor(&mut out, spectrum, lowest)?;
+                let residue = spectrum
+                    .iter()
+                    .zip(floor)
+                    .map(|(c, f)| ((c / f).round() as i32).clamp(-MAX_RESIDUE, MAX_RESIDUE))
+                    .collect();
+                Some(residue)
+            })
+            .collect();
+        self.residue(&mut out, &residues);
+
+        out.bytes
+    }
+
+    /// Write the floor of the spectrum, whose amplitudes are at least the lowest one, and
+    /// return the floor amplitudes decoded from it. An unused floor of the silent channel
+    /// has none.
+    fn floor(&self, out: &mut BitWriter, spectrum: &[f64], lowest: f64) -> Option<Vec<f64>> {
+        let peak = spectrum.iter().fold(0.0, |p: f64, c| p.max(c.abs()));
+        if peak <= f64::MIN_POSITIVE {
+            out.write(0, 1);
+            return None;
+        }
+        out.write(1, 1);
+
+        // Posts in the decoder order with the floor, which covers the neighbouring posts.
+        let xs: Vec<u32> = [0, HALF_BLOCK as u32]
+            .into_iter()
+            .chain(FLOOR_POSTS)
+            .collect();
+        let mut sorted = xs.clone();
+        sorted.sort();
+        let ys: Vec<i32> = xs
+            .iter()
+            .map(|x| {
+                let i = sorted.binary_search(x).unwrap();
+                let start = sorted[i.saturating_sub(1)] as usize;
+                let end = sorted[(i + 1).min(sorted.len() - 1)].min(HALF_BLOCK as u32 - 1);
+                let amplitude = spectrum[start..=end as usize]
+                    .iter()
+                    .fold(lowest, |a, c| a.max(c.abs() / MAX_RESIDUE as f64));
+                floor_value(amplitude)
+            })
+            .collect();
+
+        let value_bits = ilog(FLOOR_RANGE - 1);
+        out.write(ys[0] as u64, value_bits);
+        out.write(ys[1] as u64, value_bits);
+        let mut used = vec![true; xs.len()];
+        for i in 2..xs.len() {
+            // Closest posts before and after this one among the previous posts.
+            let low = (0..i)
+                .filter(|j| xs[*j] < xs[i])
+                .max_by_key(|j| xs[*j])
+                .unwrap();
+            let high = (0..i)
+                .filter(|j| xs[*j] > xs[i])
+                .min_by_key(|j| xs[*j])
+                .unwrap();
+            let predicted = render_point(xs[low], ys[low], xs[high], ys[high], xs[i]);
+            let room = (FLOOR_RANGE as i32 - predicted).min(predicted);
+            let delta = ys[i] - predicted;
+            let value = match delta {
+                d if d < -room => room - d - 1,
+                d if d < 0 => -1 - 2 * d,
+                d if d >= room => d + room,
+                d => 2 * d,
+            };
+            match value {
+                0 => used[i] = false,
+                _ => (used[low], used[high], used[i]) = (true, true, true),
+            }
+            self.books[FLOOR_BOOK].write(out, value as usize);
+        }
+
+        let mut points: Vec<(u32, i32)> = (0..xs.len())
+            .filter(|i| used[*i])
+            .map(|i| (xs[i], ys[i] * FLOOR_MULTIPLIER as i32))
+            .collect();
+        points.sort();
+        let mut floor = vec![0; HALF_BLOCK];
+        for pair in points.windows(2) {
+            let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+            render_line(x0, y0, x1, y1, &mut floor);
+        }
+        Some(floor.into_iter().map(floor_amplitude).collect())
+    }
+
+    /// Write the residues of the channels with used floors in a single pass.
+    fn residue(&self, out: &mut BitWriter, residues: &[Option<Vec<i32>>]) {
+        let classified: Vec<(&[i32], Vec<usize>)> = residues
+            .iter()
+            .flatten()
+            .map(|residue| {
+                let classes = residue
+                    .chunks(RESIDUE_PARTITION)
+                    .map(|p| match p.iter().map(|v| v.abs()).max() {
+                        Some(0) => 0,
+                        Some(1) => 1,
+                        _ => 2,
+                    })
+                    .collect();
+                (residue.as_slice(), classes)
+            })
+            .collect();
+
+        let partitions = HALF_BLOCK / RESIDUE_PARTITION;
+        for start in (0..partitions).step_by(CLASS_DIMENSIONS) {
+            for (_, classes) in &classified {
+                let entry = classes[start..start + CLASS_DIMENSIONS]
+                    .iter()
+                    .fold(0, |entry, class| entry * 3 + class);
+                self.books[CLASS_BOOK].write(out, entry);
+            }
+            for partition in start..start + CLASS_DIMENSIONS {
+                for (residue, classes) in &classified {
+                    let book = match classes[partition] {
+                        0 => continue,
+                        1 => &self.books[SMALL_BOOK],
+                        _ => &self.books[LARGE_BOOK],
+                    };
+                    let offset = partition * RESIDUE_PARTITION;
+                    residue[offset..offset + RESIDUE_PARTITION]
+                        .chunks(book.dimensions)
+                        .for_each(|vector| book.write_vector(out, vector));
+                }
+            }
+        }
+    }
+}
+
+fn ogg_crc(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |crc, byte| {
+        (0..8).fold(crc ^ ((*byte as u32) << 24), |crc, _| {
+            match crc & 0x8000_0000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ 0x04C1_1DB7,
+            }
+        })
+    })
+}
+
+/// Writer of the packets into the pages of a single logical OGG stream.
+#[derive(Debug, Default)]
+struct OggWriter {
+    bytes: Vec<u8>,
+    sequence: u32,
+    /// Lacing values and data of the packets waiting for their page.
+    lacing: Vec<u8>,
+    data: Vec<u8>,
+    /// Whether the last page ends the stream.
+    last: bool,
+}
+
+impl OggWriter {
+    /// Add the packet, which ends at the granule position, and write the page, when it is
+    /// full enough or the packet is the last one.
+    fn write(&mut self, packet: Vec<u8>, granule: u64, last: bool) {
+        self.lacing.extend(vec![255; packet.len() / 255]);
+        self.lacing.push((packet.len() % 255) as u8);
+        self.data.extend(packet);
+        self.last = last;
+        if last || self.data.len() >= 4096 || self.lacing.len() > 200 {
+            self.flush(granule);
+        }
+    }
+
+    /// Write the waiting packets into a page ending at the granule position.
+    fn flush(&mut self, granule: u64) {
+        if self.lacing.is_empty() {
+            return;
+        }
+
+        let mut page = b"OggS\0".to_vec();
+        let mut flags = 0;
+        if self.sequence == 0 {
+            flags |= 0x02;
+        }
+        if self.last {
+            flags |= 0x04;
+        }
+        page.push(flags);
+        page.extend(granule.to_le_bytes());
+        page.extend(1u32.to_le_bytes());
+        page.extend(self.sequence.to_le_bytes());
+        page.extend(0u32.to_le_bytes());
+        page.push(self.lacing.len() as u8);
+        page.append(&mut self.lacing);
+        page.append(&mut self.data);
+        let crc = ogg_crc(&page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+
+        self.bytes.extend(page);
+        self.sequence += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fast_mdct() {
+        // Arrange
+        let block: Vec<f64> = (0..64).map(|n| ((n * n) % 17) as f64 - 8.0).collect();
+
+        // Act
+        let fast = mdct(&block);
+
+        // Assert
+        for (k, coefficient) in fast.iter().enumerate() {
+            let direct: f64 = block
+                .iter()
+                .enumerate()
+                .map(|(n, x)| {
+                    x * (2.0 * PI / 64.0 * (n as f64 + 0.5 + 16.0) * (k as f64 + 0.5)).cos()
+                })
+                .sum();
+            assert!(
+                (coefficient - direct).abs() < 1e-9,
+                "{k}: {coefficient} {direct}"
+            );
+        }
+    }
+
+    #[test]
+    fn complete_codebooks() {
+        let encoder = VorbisEncoder::new(44100);
+        for book in &encoder.books {
+            let kraft: f64 = book.lengths.iter().map(|l| 0.5f64.powi(*l as i32)).sum();
+            assert!((kraft - 1.0).abs() < 1e-9);
+            assert!(book.lengths.iter().all(|l| (1..=32).contains(l)));
+        }
+    }
+
+    #[test]
+    fn encode_decodable_stream() {
+        // Arrange
+        let frames: Vec<[f32; 2]> = (0..20000)
+            .map(|i| {
+                let t = i as f32 / 44100.0;
+                [
+                    (t * 440.0 * std::f32::consts::TAU).sin() * 0.5,
+                    (t * 1000.0 * std::f32::consts::TAU).sin() * 0.25,
+                ]
+            })
+            .collect();
+
+        // Act
+        let ogg = VorbisEncoder::new(44100).encode(&frames);
+
+        // Assert
+        let mut reader =
+            lewton::inside_ogg::OggStreamReader::new(std::io::Cursor::new(&ogg)).unwrap();
+        assert_eq!(reader.ident_hdr.audio_channels, 2);
+        assert_eq!(reader.ident_hdr.audio_sample_rate, 44100);
+        let mut samples = vec![];
+        while let Some(packet) = reader.read_dec_packet_itl().unwrap() {
+            samples.extend(packet);
+        }
+        assert!(samples.len() >= frames.len() * 2);
+        let error: f64 = frames
+            .concat()
+            .iter()
+            .zip(&samples)
+            .map(|(s, d)| (*s as f64 - *d as f64 / 32768.0).powi(2))
+            .sum::<f64>()
+            / (frames.len() * 2) as f64;
+        assert!(error.sqrt() < 0.01, "{}", error.sqrt());
+        assert!(ogg.len() < frames.len() * 2);
+    }
+}
@@ -3,7 +3,7 @@
 //! ### Author
 //! Jakub Kloub (xkloub03), VUT FIT
 
-use crate::error::*;
+use crate::{error::*, synth::Synthesizer};
 use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
 use std::{
     io::{BufReader, Cursor, Read},
@@ -33,12 +33,15 @@ pub struct AudioController {
     sink: Sink,
     total_duration: Option<Duration>,
     audio_data: Option<Arc<[u8]>>,
+    /// Synthesizer streamed, until the audio it renders is loaded.
+    stream: Option<Synthesizer>,
 }
 
 impl std::fmt::Debug for AudioController {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("AudioController")
             .field("total_duration", &self.total_duration)
+            .field("stream", &self.stream.is_some())
             .field(
                 "audio_data",
                 &match &self.audio_data {
@@ -60,16 +63,18 @@ impl AudioController {
             _output_stream: output_stream,
             total_duration: None,
             audio_data: None,
+            stream: None,
         })
     }
 
     pub fn is_loaded(&self) -> bool {
-        self.audio_data.is_some()
+        self.audio_data.is_some() || self.stream.is_some()
     }
 
     pub fn reset(&mut self) {
         self.sink.clear();
         self.audio_data = None;
+        self.stream = None;
         self.total_duration = None;
     }
 
@@ -84,14 +89,44 @@ impl AudioController {
         Ok(())
     }
 
+    /// Load the audio data. Streamed audio is replaced by the data at the same position, so
+    /// the data should be the audio it rendered.
     pub fn load_from_data(&mut self, data: AudioData) -> Result<()> {
+        let resume = match self.stream.take() {
+            Some(_) if !self.sink.empty() => Some((self.position(), self.sink.is_paused())),
+            _ => None,
+        };
         self.audio_data = Some(data.data);
         self.renew_source()?;
 
+        if let Some((position, paused)) = resume {
+            self.try_seek(position)?;
+            if !paused {
+                self.sink.play();
+            }
+        }
+
         Ok(())
     }
 
+    /// Play the audio of the synthesizer while it is rendered. The stream can't be seeked
+    /// until its rendered audio is loaded by [`Self::load_from_data`].
+    pub fn load_stream(&mut self, synthesizer: Synthesizer) -> Result<()> {
+        self.audio_data = None;
+        self.stream = Some(synthesizer);
+        self.renew_source()
+    }
+
     pub fn renew_source(&mut self) -> Result<()> {
+        if let Some(stream) = self.stream.clone() {
+            // Audio is longer by the release of the last notes.
+            self.total_duration = Some(Duration::from_secs_f64(stream.duration()));
+            self.sink.clear();
+            self.sink.append(stream);
+
+            return Ok(());
+        }
+
         let data = match self.audio_data.clone() {
             Some(d) => d,
             None => {
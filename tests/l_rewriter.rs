@@ -36,7 +36,7 @@ fn rewrite_simple() {
     let re = create_rewriter_simple();
 
     // Act
-    let res = re.rewrite("abcdef").0;
+    let res = re.rewrite("abcdef", &mut rand::rng()).0;
 
     // Assert
     assert_eq!("12c3ef", res);
@@ -48,7 +48,7 @@ fn rewrite_complex() {
     let re = create_rewriter_complex();
 
     // Act
-    let res = re.rewrite("abcdef").0;
+    let res = re.rewrite("abcdef", &mut rand::rng()).0;
 
     // Assert
     assert_eq!("553311", res);
@@ -70,8 +70,27 @@ fn rewrite_doc_example() {
     ));
 
     // Act
-    let res = re.rewrite("abcdefgh").0;
+    let res = re.rewrite("abcdefgh", &mut rand::rng()).0;
 
     // Assert
     assert_eq!(res, "a42h");
 }
+
+#[test]
+fn rewrite_traced_ranges() {
+    // Arrange
+    let re = create_rewriter_complex();
+
+    // Act
+    let (res, used_rules) = re.rewrite_traced("abcdef", &mut rand::rng());
+
+    // Assert
+    let written: Vec<_> = used_rules
+        .iter()
+        .map(|(range, rule)| (range.clone(), rule.right().as_str()))
+        .collect();
+    assert_eq!(written, vec![(4..6, "11"), (2..4, "33"), (0..2, "55")]);
+    for (range, rule) in used_rules {
+        assert_eq!(&res[range], rule.right());
+    }
+}
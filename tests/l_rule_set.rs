@@ -48,11 +48,11 @@ pub fn select_simple1() {
     let set = create_basic_set();
 
     // Act
-    let rule1 = set.select("a");
-    let rule2 = set.select("b");
-    let rule3 = set.select("ab");
-    let rule4 = set.select("ba");
-    let rule5 = set.select("abc");
+    let rule1 = set.select("a", &mut rand::rng());
+    let rule2 = set.select("b", &mut rand::rng());
+    let rule3 = set.select("ab", &mut rand::rng());
+    let rule4 = set.select("ba", &mut rand::rng());
+    let rule5 = set.select("abc", &mut rand::rng());
 
     // Assert
     assert_many_times(5, || {
@@ -77,11 +77,11 @@ fn select_simple2() {
     );
 
     // Act
-    let res1 = set.select("bac");
-    let res2 = set.select("aslkdbca");
-    let res3 = set.select("ca");
-    let res4 = set.select("a");
-    let res5 = set.select("bad");
+    let res1 = set.select("bac", &mut rand::rng());
+    let res2 = set.select("aslkdbca", &mut rand::rng());
+    let res3 = set.select("ca", &mut rand::rng());
+    let res4 = set.select("a", &mut rand::rng());
+    let res5 = set.select("bad", &mut rand::rng());
 
     // Assert
     assert!(res1.is_none());
@@ -97,20 +97,28 @@ pub fn select_context() {
     let set = create_context_set();
 
     // Act && Assert
-    assert_many_times(10, || assert_rule_eq(set.select("FFF").unwrap(), "F", "AA"));
+    assert_many_times(10, || {
+        assert_rule_eq(set.select("FFF", &mut rand::rng()).unwrap(), "F", "AA")
+    });
     assert_many_times(10, || {
         assert_rule_any(
-            set.select("F-F+F").unwrap(),
+            set.select("F-F+F", &mut rand::rng()).unwrap(),
             &[("F", "AA"), ("+F", "BB"), ("F+F", "CC")],
         )
     });
     assert_many_times(10, || {
-        assert_rule_any(set.select("F-F").unwrap(), &[("F", "AA"), ("F-F", "DD")])
+        assert_rule_any(
+            set.select("F-F", &mut rand::rng()).unwrap(),
+            &[("F", "AA"), ("F-F", "DD")],
+        )
     });
     assert_many_times(10, || {
-        assert_rule_any(set.select("--F").unwrap(), &[("F", "AA")])
+        assert_rule_any(set.select("--F", &mut rand::rng()).unwrap(), &[("F", "AA")])
     });
     assert_many_times(10, || {
-        assert_rule_any(set.select("++F").unwrap(), &[("F", "AA"), ("+F", "BB")])
+        assert_rule_any(
+            set.select("++F", &mut rand::rng()).unwrap(),
+            &[("F", "AA"), ("+F", "BB")],
+        )
     });
 }
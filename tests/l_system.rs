@@ -0,0 +1,51 @@
+//! CSSLSystem integration tests
+//!
+//! ### Author
+//! Jakub Kloub (xkloub03), VUT FIT
+
+use music_sheet_gen::lsystem::*;
+
+fn create_system(seed: u64) -> CSSLSystem {
+    CSSLSystem::from(
+        "F+F".to_owned(),
+        &["F -> F % 1/2", "F -> FF % 1/4", "F -> F-F % 1/4"],
+        seed,
+    )
+}
+
+fn run(system: &mut CSSLSystem, steps: usize) -> String {
+    (0..steps).for_each(|_| {
+        system.step();
+    });
+    system.state().word().clone()
+}
+
+#[test]
+fn same_seed_same_word() {
+    // Arrange
+    let mut first = create_system(7);
+    let mut second = create_system(7);
+
+    // Act
+    let first_word = run(&mut first, 5);
+    let second_word = run(&mut second, 5);
+
+    // Assert
+    assert_eq!(first_word, second_word);
+    assert_eq!(*first.state().seed(), 7);
+}
+
+#[test]
+fn restored_system_continues() {
+    // Arrange
+    let mut system = create_system(11);
+    run(&mut system, 2);
+    let mut restored: CSSLSystem = ron::from_str(&ron::to_string(&system).unwrap()).unwrap();
+
+    // Act
+    let word = run(&mut system, 3);
+    let restored_word = run(&mut restored, 3);
+
+    // Assert
+    assert_eq!(word, restored_word);
+}